@@ -0,0 +1,251 @@
+//! Benchmarks of the hot paths of the actor system, without an external
+//! benchmark framework. Run them in release mode with:
+//!
+//!     $ cargo bench --bench actors [filter] [--save-baseline FILE] [--baseline FILE] [--threshold PERCENT]
+//!
+//! Each benchmark reports the median time per operation over a number of
+//! samples. `--save-baseline` writes the results to a file, and `--baseline`
+//! compares them to the results in such a file, failing when a benchmark got
+//! slower by more than the threshold (10% by default), so the benchmarks can
+//! guard against performance regressions.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use tiny_tokio_actor::*;
+
+const SAMPLES: usize = 10;
+
+#[derive(Clone, Debug)]
+struct BenchEvent;
+
+impl SystemEvent for BenchEvent {}
+
+#[derive(Default)]
+struct CounterActor {
+    counter: u64,
+}
+
+impl Actor<BenchEvent> for CounterActor {}
+
+#[derive(Clone, Debug)]
+struct Increment;
+
+impl Message for Increment {
+    type Response = u64;
+}
+
+#[async_trait]
+impl Handler<BenchEvent, Increment> for CounterActor {
+    async fn handle(&mut self, _msg: Increment, _ctx: &mut ActorContext<BenchEvent>) -> u64 {
+        self.counter += 1;
+        self.counter
+    }
+}
+
+/// Round trips of asks to a single actor.
+async fn ping_pong(system: &ActorSystem<BenchEvent>, count: u64) -> Duration {
+    let actor_ref = system
+        .create_anonymous_actor(CounterActor::default())
+        .await
+        .unwrap();
+    let started = Instant::now();
+    for _ in 0..count {
+        actor_ref.ask(Increment).await.unwrap();
+    }
+    let elapsed = started.elapsed();
+    system.stop_subtree(actor_ref.path()).await;
+    elapsed
+}
+
+/// Tells to 100 actors, until all of them handled their messages.
+async fn fan_out(system: &ActorSystem<BenchEvent>, count: u64) -> Duration {
+    let mut actor_refs = Vec::new();
+    for _ in 0..100 {
+        let actor_ref = system
+            .create_anonymous_actor(CounterActor::default())
+            .await
+            .unwrap();
+        actor_refs.push(actor_ref);
+    }
+    let started = Instant::now();
+    for actor_ref in &actor_refs {
+        for _ in 0..count / 100 {
+            actor_ref.ordered_tell(Increment).await.unwrap();
+        }
+    }
+    for actor_ref in &actor_refs {
+        actor_ref.ask(Increment).await.unwrap();
+    }
+    let elapsed = started.elapsed();
+    for actor_ref in &actor_refs {
+        system.stop_subtree(actor_ref.path()).await;
+    }
+    elapsed
+}
+
+/// Lookups in the registry by 16 tasks at once, each of which created an
+/// actor.
+async fn registry(system: &ActorSystem<BenchEvent>, count: u64) -> Duration {
+    let started = Instant::now();
+    let mut handles = Vec::new();
+    for _ in 0..16 {
+        let system = system.clone();
+        handles.push(tokio::spawn(async move {
+            let actor_ref = system
+                .create_anonymous_actor(CounterActor::default())
+                .await
+                .unwrap();
+            for _ in 0..count / 16 {
+                system
+                    .get_actor::<CounterActor>(actor_ref.path())
+                    .await
+                    .unwrap();
+            }
+            actor_ref
+        }));
+    }
+    let mut actor_refs = Vec::new();
+    for handle in handles {
+        actor_refs.push(handle.await.unwrap());
+    }
+    let elapsed = started.elapsed();
+    for actor_ref in &actor_refs {
+        system.stop_subtree(actor_ref.path()).await;
+    }
+    elapsed
+}
+
+/// Events published to 16 subscribers, until all of them received them.
+async fn bus_fan_out(_system: &ActorSystem<BenchEvent>, count: u64) -> Duration {
+    let bus = EventBus::<BenchEvent>::new(1024);
+    let mut handles = Vec::new();
+    for _ in 0..16 {
+        let mut events = bus.subscribe();
+        handles.push(tokio::spawn(async move {
+            let mut received = 0;
+            while received < count {
+                match events.recv().await {
+                    Ok(_) => received += 1,
+                    Err(EventRecvError::Lagged(dropped)) => received += dropped,
+                    Err(EventRecvError::Closed) => break,
+                }
+            }
+        }));
+    }
+    let started = Instant::now();
+    for _ in 0..count {
+        bus.send_lossless(BenchEvent).await.unwrap();
+    }
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    started.elapsed()
+}
+
+struct Options {
+    filter: Option<String>,
+    save_baseline: Option<String>,
+    baseline: Option<String>,
+    threshold: f64,
+}
+
+impl Options {
+    fn parse() -> Self {
+        let mut options = Options {
+            filter: None,
+            save_baseline: None,
+            baseline: None,
+            threshold: 10.0,
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--save-baseline" => options.save_baseline = args.next(),
+                "--baseline" => options.baseline = args.next(),
+                "--threshold" => {
+                    options.threshold = args
+                        .next()
+                        .and_then(|threshold| threshold.parse().ok())
+                        .expect("--threshold takes a percentage")
+                }
+                // Passed by cargo bench
+                "--bench" => {}
+                filter if !filter.starts_with("--") => options.filter = Some(filter.to_string()),
+                unknown => panic!("Unknown option {}", unknown),
+            }
+        }
+        options
+    }
+}
+
+/// Reads the nanoseconds per operation of each benchmark from a baseline.
+fn read_baseline(path: &str) -> HashMap<String, f64> {
+    let baseline = std::fs::read_to_string(path).expect("baseline cannot be read");
+    baseline
+        .lines()
+        .filter_map(|line| {
+            let (name, nanos) = line.split_once(' ')?;
+            Some((name.to_string(), nanos.parse().ok()?))
+        })
+        .collect()
+}
+
+#[tokio::main]
+async fn main() {
+    let options = Options::parse();
+    let baseline = options.baseline.as_deref().map(read_baseline);
+    let system = ActorSystem::new("bench", EventBus::<BenchEvent>::new(1024));
+
+    let mut results = Vec::new();
+    let mut regressed = Vec::new();
+    macro_rules! bench {
+        ($name:ident, $count:expr) => {
+            let name = stringify!($name);
+            if options
+                .filter
+                .as_deref()
+                .map_or(true, |filter| name.contains(filter))
+            {
+                // The first run warms up
+                $name(&system, $count).await;
+                let mut samples = Vec::with_capacity(SAMPLES);
+                for _ in 0..SAMPLES {
+                    let elapsed = $name(&system, $count).await;
+                    samples.push(elapsed.as_nanos() as f64 / $count as f64);
+                }
+                samples.sort_by(f64::total_cmp);
+                let nanos = samples[SAMPLES / 2];
+                match baseline.as_ref().and_then(|baseline| baseline.get(name)) {
+                    Some(before) => {
+                        let change = (nanos - before) / before * 100.0;
+                        println!("{:<12} {:>12.1} ns/op ({:+.1}%)", name, nanos, change);
+                        if change > options.threshold {
+                            regressed.push(name);
+                        }
+                    }
+                    None => println!("{:<12} {:>12.1} ns/op", name, nanos),
+                }
+                results.push(format!("{} {}", name, nanos));
+            }
+        };
+    }
+    bench!(ping_pong, 10_000);
+    bench!(fan_out, 10_000);
+    bench!(registry, 16_000);
+    bench!(bus_fan_out, 10_000);
+
+    if let Some(path) = &options.save_baseline {
+        std::fs::write(path, results.join("\n") + "\n").expect("baseline cannot be written");
+    }
+    if !regressed.is_empty() {
+        eprintln!(
+            "Slower than the baseline by more than {}%: {}",
+            options.threshold,
+            regressed.join(", ")
+        );
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,167 @@
+use std::time::{Duration, Instant};
+
+use tiny_tokio_actor::*;
+
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+struct StressEvent(u64);
+
+impl SystemEvent for StressEvent {}
+
+#[derive(Default)]
+struct CounterActor {
+    counter: u64,
+}
+
+impl Actor<StressEvent> for CounterActor {}
+
+#[derive(Clone, Debug)]
+struct Increment;
+
+impl Message for Increment {
+    type Response = u64;
+}
+
+#[async_trait]
+impl Handler<StressEvent, Increment> for CounterActor {
+    async fn handle(&mut self, _msg: Increment, _ctx: &mut ActorContext<StressEvent>) -> u64 {
+        self.counter += 1;
+        self.counter
+    }
+}
+
+/// Prints the rate of a scenario that handled `count` operations.
+fn report(scenario: &str, count: u64, elapsed: Duration) {
+    println!(
+        "{:<20} {:>12.0} ops/sec ({} ops in {:?})",
+        scenario,
+        count as f64 / elapsed.as_secs_f64(),
+        count,
+        elapsed
+    );
+}
+
+fn percentile(sorted: &[Duration], percentile: f64) -> Duration {
+    let index = ((sorted.len() as f64 - 1.0) * percentile).round() as usize;
+    sorted[index]
+}
+
+/// Round trips of asks to a single actor.
+async fn ping_pong(system: &ActorSystem<StressEvent>, count: u64) {
+    let actor_ref = system
+        .create_actor("ping-pong", CounterActor::default())
+        .await
+        .unwrap();
+    let requester = actor_ref.requester::<Increment>();
+    let mut latencies = Vec::with_capacity(count as usize);
+    let started = Instant::now();
+    for _ in 0..count {
+        let sent = Instant::now();
+        requester.ask(Increment).await.unwrap();
+        latencies.push(sent.elapsed());
+    }
+    report("ping-pong", count, started.elapsed());
+    latencies.sort_unstable();
+    println!(
+        "{:<20} p50 {:?}, p99 {:?}, max {:?}",
+        "",
+        percentile(&latencies, 0.5),
+        percentile(&latencies, 0.99),
+        latencies[latencies.len() - 1]
+    );
+    system.stop_actor(actor_ref.path()).await;
+}
+
+/// Tells to many actors, waiting until all of them handled their messages.
+async fn fan_out(system: &ActorSystem<StressEvent>, actors: u64, count: u64) {
+    let mut actor_refs = Vec::new();
+    for i in 0..actors {
+        let name = format!("fan-out-{}", i);
+        let actor_ref = system
+            .create_actor(&name, CounterActor::default())
+            .await
+            .unwrap();
+        actor_refs.push(actor_ref);
+    }
+    let per_actor = count / actors;
+    let started = Instant::now();
+    for actor_ref in &actor_refs {
+        for _ in 0..per_actor {
+            actor_ref.ordered_tell(Increment).await.unwrap();
+        }
+    }
+    for actor_ref in &actor_refs {
+        actor_ref.ask(Increment).await.unwrap();
+    }
+    report("fan-out", per_actor * actors, started.elapsed());
+    for actor_ref in &actor_refs {
+        system.stop_actor(actor_ref.path()).await;
+    }
+}
+
+/// Concurrent lookups in the registry while actors are being created.
+async fn registry(system: &ActorSystem<StressEvent>, tasks: u64, count: u64) {
+    let started = Instant::now();
+    let mut handles = Vec::new();
+    for task in 0..tasks {
+        let system = system.clone();
+        handles.push(tokio::spawn(async move {
+            let name = format!("registry-{}", task);
+            let actor_ref = system
+                .create_actor(&name, CounterActor::default())
+                .await
+                .unwrap();
+            for _ in 0..count / tasks {
+                system
+                    .get_actor::<CounterActor>(actor_ref.path())
+                    .await
+                    .unwrap();
+            }
+        }));
+    }
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    report("registry", count / tasks * tasks, started.elapsed());
+}
+
+/// Events published to many subscribers.
+async fn bus_fan_out(subscribers: u64, count: u64) {
+    let bus = EventBus::<StressEvent>::new(1024);
+    let mut handles = Vec::new();
+    for _ in 0..subscribers {
+        let mut events = bus.subscribe();
+        handles.push(tokio::spawn(async move {
+            let mut received = 0;
+            while received < count {
+                match events.recv().await {
+                    Ok(_) => received += 1,
+                    Err(EventRecvError::Lagged(dropped)) => received += dropped,
+                    Err(EventRecvError::Closed) => break,
+                }
+            }
+        }));
+    }
+    let started = Instant::now();
+    for i in 0..count {
+        bus.send_lossless(StressEvent(i)).await.unwrap();
+    }
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    report("bus fan-out", count * subscribers, started.elapsed());
+}
+
+#[tokio::main]
+async fn main() {
+    let count = std::env::args()
+        .nth(1)
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(100_000);
+
+    let system = ActorSystem::new("stress", EventBus::<StressEvent>::new(1024));
+    ping_pong(&system, count).await;
+    fan_out(&system, 100, count).await;
+    registry(&system, 16, count).await;
+    bus_fan_out(16, count).await;
+}
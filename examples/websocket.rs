@@ -12,6 +12,7 @@ use warp::*;
 
 use tiny_tokio_actor::*;
 
+#[allow(dead_code)]
 #[derive(Clone, Debug)]
 struct ServerEvent(String);
 
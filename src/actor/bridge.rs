@@ -0,0 +1,115 @@
+use crate::system::SystemEvent;
+
+use super::{handler::HandlerRef, Actor, ActorError, ActorPath, ActorRef, Handler, Message};
+
+/// A handle to send messages to an actor from threads that are not managed by
+/// tokio, such as audio or FFI callbacks. It is cheap to clone and can be moved
+/// to any thread. Get one through [`ActorRef::sync_bridge()`]. For example:
+/// ```
+/// use tiny_tokio_actor::*;
+///
+/// #[derive(Clone, Debug)]
+/// struct TestEvent(String);
+///
+/// impl SystemEvent for TestEvent {}
+///
+/// #[derive(Default)]
+/// struct Counter(usize);
+///
+/// impl Actor<TestEvent> for Counter {}
+///
+/// #[derive(Clone, Debug)]
+/// struct Increment;
+///
+/// impl Message for Increment {
+///     type Response = usize;
+/// }
+///
+/// #[async_trait]
+/// impl Handler<TestEvent, Increment> for Counter {
+///     async fn handle(&mut self, _msg: Increment, _ctx: &mut ActorContext<TestEvent>) -> usize {
+///         self.0 += 1;
+///         self.0
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+///     let counter = system.create_actor("counter", Counter::default()).await.unwrap();
+///
+///     let bridge = counter.sync_bridge();
+///     let count = std::thread::spawn(move || {
+///         bridge.blocking_tell(Increment).unwrap();
+///         bridge.blocking_ask(Increment).unwrap()
+///     });
+///     assert_eq!(count.join().unwrap(), 2);
+/// }
+/// ```
+pub struct SyncBridge<E: SystemEvent, A: Actor<E>> {
+    path: ActorPath,
+    sender: HandlerRef<E, A>,
+}
+
+impl<E: SystemEvent, A: Actor<E>> Clone for SyncBridge<E, A> {
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<E: SystemEvent, A: Actor<E>> SyncBridge<E, A> {
+    pub(crate) fn new(actor_ref: &ActorRef<E, A>) -> Self {
+        SyncBridge {
+            path: actor_ref.path.clone(),
+            sender: actor_ref.sender.clone(),
+        }
+    }
+
+    /// Get the path of the actor
+    pub fn path(&self) -> &ActorPath {
+        &self.path
+    }
+
+    /// Sends the message without blocking, failing with
+    /// `Err(ActorError::MailboxFull)` if a bounded mailbox is full. This is
+    /// the same as [`ActorRef::tell()`].
+    pub fn tell<M>(&self, msg: M) -> Result<(), ActorError>
+    where
+        M: Message,
+        A: Handler<E, M>,
+    {
+        self.sender.tell(msg)
+    }
+
+    /// Sends the message, blocking the current thread until there is room if
+    /// a bounded mailbox is full.
+    ///
+    /// This panics when called from within an asynchronous execution context.
+    pub fn blocking_tell<M>(&self, msg: M) -> Result<(), ActorError>
+    where
+        M: Message,
+        A: Handler<E, M>,
+    {
+        self.sender.blocking_tell(msg)
+    }
+
+    /// Sends the message and blocks the current thread until the actor
+    /// responded. The ask timeout of the actor does not apply.
+    ///
+    /// This panics when called from within an asynchronous execution context.
+    pub fn blocking_ask<M>(&self, msg: M) -> Result<M::Response, ActorError>
+    where
+        M: Message,
+        A: Handler<E, M>,
+    {
+        self.sender.blocking_ask(msg)
+    }
+
+    /// Checks if the actor is closed, i.e. it is not running anymore.
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+}
@@ -0,0 +1,221 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use log::LevelFilter;
+
+use super::{supervision::SupervisionPolicy, ActorPath};
+
+/// Configuration to apply when creating an actor. For example:
+/// ```
+/// use tiny_tokio_actor::*;
+///
+/// let config = ActorConfig::default()
+///     .with_label("tenant", "acme")
+///     .with_label("role", "ingest")
+///     .with_mailbox_capacity(100)
+///     .with_supervision(SupervisionPolicy::NoInterval { max_retries: 5 });
+///
+/// assert_eq!(config.label("tenant"), Some("acme"));
+/// ```
+///
+/// Settings that are not set fall back to the defaults of the actor system. An
+/// actor system can also override settings for actors matching a path pattern,
+/// see [`crate::SystemConfig::with_override()`].
+#[derive(Clone, Debug, Default)]
+pub struct ActorConfig {
+    pub(crate) labels: BTreeMap<String, String>,
+    pub(crate) mailbox_capacity: Option<usize>,
+    pub(crate) mailbox_bytes: Option<usize>,
+    pub(crate) throughput: Option<usize>,
+    pub(crate) time_slice: Option<Duration>,
+    pub(crate) supervision: Option<SupervisionPolicy>,
+    pub(crate) ask_timeout: Option<Duration>,
+    pub(crate) log_level: Option<LevelFilter>,
+    pub(crate) dedicated_thread: Option<bool>,
+    pub(crate) stop_when_unreferenced: Option<bool>,
+    pub(crate) dependencies: Vec<ActorPath>,
+}
+
+impl ActorConfig {
+    /// Attach a key/value label to the actor. Labels can be used to look up
+    /// actors through [`crate::ActorSystem::actors_with_label()`].
+    pub fn with_label(mut self, key: &str, value: &str) -> Self {
+        self.labels.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Bound the mailbox of the actor to the given number of messages.
+    pub fn with_mailbox_capacity(mut self, capacity: usize) -> Self {
+        self.mailbox_capacity = Some(capacity);
+        self
+    }
+
+    /// Bound the mailbox of the actor to the given number of bytes, as
+    /// estimated by [`crate::Message::size_hint()`]. A message that does not
+    /// fit is treated the same as when the mailbox is bounded by a number of
+    /// messages and full: `tell` fails with `Err(ActorError::MailboxFull)`
+    /// while `ask` waits for room. A single message larger than the limit is
+    /// still accepted by an empty mailbox.
+    pub fn with_mailbox_bytes(mut self, bytes: usize) -> Self {
+        self.mailbox_bytes = Some(bytes);
+        self
+    }
+
+    /// Yield back to the runtime after handling the given number of messages
+    /// in a row, so a busy actor does not hold on to its worker thread.
+    pub fn with_throughput(mut self, messages: usize) -> Self {
+        self.throughput = Some(messages);
+        self
+    }
+
+    /// Yield back to the runtime once the actor handled messages in a row for
+    /// the given time, so an actor with a deep mailbox and slow handlers does
+    /// not starve the actors sharing its worker thread. Unlike
+    /// [`ActorConfig::with_throughput()`] this bounds the time rather than
+    /// the number of messages. The time slice starts over whenever the
+    /// actor yields or its mailbox runs empty. Each yield is reported to the
+    /// instrumentation, see
+    /// [`crate::MailboxInstrumentation::yielded()`].
+    pub fn with_time_slice(mut self, slice: Duration) -> Self {
+        self.time_slice = Some(slice);
+        self
+    }
+
+    /// Supervise the actor with the given policy instead of the strategy
+    /// defined by [`crate::Actor::supervision_strategy()`].
+    pub fn with_supervision(mut self, policy: SupervisionPolicy) -> Self {
+        self.supervision = Some(policy);
+        self
+    }
+
+    /// Fail asks to the actor that did not receive a response within the given
+    /// duration. See also [`crate::ActorRef::ask_with_timeout()`].
+    pub fn with_ask_timeout(mut self, timeout: Duration) -> Self {
+        self.ask_timeout = Some(timeout);
+        self
+    }
+
+    /// Only log messages about this actor up to the given level, e.g. to quiet
+    /// down a noisy actor without changing the log level of the whole crate.
+    /// The log output of the crate about an actor carries its path as the
+    /// `actor` key/value pair, and, where relevant, the type of the message as
+    /// `message_type`. The lines logged through
+    /// [`crate::ActorContext::log_throttled()`] are filtered by it as well.
+    pub fn with_log_level(mut self, level: LevelFilter) -> Self {
+        self.log_level = Some(level);
+        self
+    }
+
+    /// Run the actor on its own thread instead of on the tokio runtime shared
+    /// by the other actors, e.g. for latency critical work or resources that
+    /// must stay on one thread. The thread runs its own single threaded
+    /// runtime, on which the tasks spawned by the actor also run. Its children
+    /// run on the shared runtime unless they also have a dedicated thread.
+    /// Sending to the actor works the same as for any other actor.
+    pub fn with_dedicated_thread(mut self) -> Self {
+        self.dedicated_thread = Some(true);
+        self
+    }
+
+    /// Stop the actor once the last reference to it that was handed out, by
+    /// creating it or by looking it up through
+    /// [`crate::ActorSystem::get_actor()`], is dropped, e.g. for actors per
+    /// request or per connection. The registry of the system does not keep
+    /// such an actor running, but references held by the actor itself or by
+    /// its subscriptions do.
+    pub fn with_stop_when_unreferenced(mut self) -> Self {
+        self.stop_when_unreferenced = Some(true);
+        self
+    }
+
+    /// Delay starting the actor until the actor at the path has started, i.e.
+    /// its `pre_start` succeeded, e.g. a cache that needs its database
+    /// connection to be up. The actor may depend on several actors, including
+    /// ones that are created later. Messages sent to the actor in the meantime
+    /// wait in its mailbox. Creating the actor fails if its dependencies
+    /// depend on it in turn.
+    pub fn with_dependency(mut self, path: ActorPath) -> Self {
+        if !self.dependencies.contains(&path) {
+            self.dependencies.push(path);
+        }
+        self
+    }
+
+    /// Get the value of a label
+    pub fn label(&self, key: &str) -> Option<&str> {
+        self.labels.get(key).map(|value| value.as_str())
+    }
+
+    /// All labels set on this configuration
+    pub fn labels(&self) -> &BTreeMap<String, String> {
+        &self.labels
+    }
+
+    /// The mailbox capacity, if bounded
+    pub fn mailbox_capacity(&self) -> Option<usize> {
+        self.mailbox_capacity
+    }
+
+    /// The mailbox size in bytes, if bounded
+    pub fn mailbox_bytes(&self) -> Option<usize> {
+        self.mailbox_bytes
+    }
+
+    /// The number of messages handled before yielding, if set
+    pub fn throughput(&self) -> Option<usize> {
+        self.throughput
+    }
+
+    /// How long the actor handles messages before yielding, if set
+    pub fn time_slice(&self) -> Option<Duration> {
+        self.time_slice
+    }
+
+    /// The supervision policy, if set
+    pub fn supervision(&self) -> Option<&SupervisionPolicy> {
+        self.supervision.as_ref()
+    }
+
+    /// The ask timeout, if any
+    pub fn ask_timeout(&self) -> Option<Duration> {
+        self.ask_timeout
+    }
+
+    /// The maximum log level, if set
+    pub fn log_level(&self) -> Option<LevelFilter> {
+        self.log_level
+    }
+
+    /// True if the actor runs on its own thread
+    pub fn dedicated_thread(&self) -> bool {
+        self.dedicated_thread.unwrap_or_default()
+    }
+
+    /// True if the actor stops once it is no longer referenced
+    pub fn stop_when_unreferenced(&self) -> bool {
+        self.stop_when_unreferenced.unwrap_or_default()
+    }
+
+    /// The actors that must have started before this actor starts
+    pub fn dependencies(&self) -> &[ActorPath] {
+        &self.dependencies
+    }
+
+    /// Applies the settings of `other` on top of this configuration. Settings
+    /// and labels set in `other` take precedence.
+    pub(crate) fn merge(mut self, other: &ActorConfig) -> Self {
+        self.labels.extend(other.labels.clone());
+        self.mailbox_capacity = other.mailbox_capacity.or(self.mailbox_capacity);
+        self.mailbox_bytes = other.mailbox_bytes.or(self.mailbox_bytes);
+        self.throughput = other.throughput.or(self.throughput);
+        self.time_slice = other.time_slice.or(self.time_slice);
+        self.supervision = other.supervision.clone().or(self.supervision);
+        self.ask_timeout = other.ask_timeout.or(self.ask_timeout);
+        self.log_level = other.log_level.or(self.log_level);
+        self.dedicated_thread = other.dedicated_thread.or(self.dedicated_thread);
+        self.stop_when_unreferenced = other.stop_when_unreferenced.or(self.stop_when_unreferenced);
+        for path in &other.dependencies {
+            self = self.with_dependency(path.clone());
+        }
+        self
+    }
+}
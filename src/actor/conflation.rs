@@ -0,0 +1,111 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    hash::Hash,
+    sync::{Arc, Mutex},
+};
+
+use crate::{deadline::Deadline, policy::Caller};
+
+use super::Message;
+
+/// A message of which only the latest one per key matters, such as sensor
+/// readings or price ticks. Sent through [`super::ActorRef::tell_latest()`],
+/// a message replaces the message with the same key that is still waiting
+/// in the mailbox, instead of queueing up behind it. For example:
+/// ```
+/// # use tiny_tokio_actor::*;
+/// #[derive(Clone, Debug)]
+/// struct Reading {
+///     sensor: u32,
+///     value: f64,
+/// }
+///
+/// impl Message for Reading {
+///     type Response = ();
+/// }
+///
+/// impl ConflationKey for Reading {
+///     type Key = u32;
+///
+///     fn conflation_key(&self) -> u32 {
+///         self.sensor
+///     }
+/// }
+/// ```
+pub trait ConflationKey: Message {
+    /// The key that messages are conflated by.
+    type Key: Hash + Eq + Clone + Send + Sync + 'static;
+
+    /// The key of this message.
+    fn conflation_key(&self) -> Self::Key;
+}
+
+/// The latest message for a key, with the caller it was sent on behalf of,
+/// the deadline it was sent within and its size.
+pub(crate) struct Slot<M> {
+    pub msg: M,
+    pub caller: Option<Arc<Caller>>,
+    pub deadline: Option<Deadline>,
+    pub size: usize,
+}
+
+/// The latest message per key.
+type Latest<M> = HashMap<<M as ConflationKey>::Key, Slot<M>>;
+
+/// The latest message per key of the conflated messages waiting in the
+/// mailbox of an actor, by message type. The mailbox itself only holds a
+/// placeholder per key, which takes the latest message when it leaves the
+/// mailbox.
+#[derive(Default)]
+pub(crate) struct ConflationSlots {
+    latest: Mutex<HashMap<TypeId, Box<dyn Any + Send>>>,
+}
+
+impl ConflationSlots {
+    /// Stores the message as the latest one for its key, returning `true` if
+    /// it replaced a message that is still waiting in the mailbox. Otherwise
+    /// a placeholder for the key must be put in the mailbox. On a
+    /// replacement, `resized` is called with the size of the replaced message
+    /// before the slot can be taken, so the room taken in the mailbox can be
+    /// adjusted first.
+    pub fn replace<M: ConflationKey>(
+        &self,
+        key: &M::Key,
+        slot: Slot<M>,
+        resized: impl FnOnce(usize),
+    ) -> bool {
+        let mut latest = self.latest.lock().unwrap();
+        let latest = latest
+            .entry(TypeId::of::<M>())
+            .or_insert_with(|| Box::new(Latest::<M>::new()))
+            .downcast_mut::<Latest<M>>()
+            .expect("conflated messages are stored by their type");
+        match latest.insert(key.clone(), slot) {
+            Some(replaced) => {
+                resized(replaced.size);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The size of the latest message for the key, if it is still waiting.
+    pub fn size<M: ConflationKey>(&self, key: &M::Key) -> Option<usize> {
+        let latest = self.latest.lock().unwrap();
+        latest
+            .get(&TypeId::of::<M>())?
+            .downcast_ref::<Latest<M>>()?
+            .get(key)
+            .map(|slot| slot.size)
+    }
+
+    /// Takes the latest message for the key out of its slot.
+    pub fn take<M: ConflationKey>(&self, key: &M::Key) -> Option<Slot<M>> {
+        let mut latest = self.latest.lock().unwrap();
+        latest
+            .get_mut(&TypeId::of::<M>())?
+            .downcast_mut::<Latest<M>>()?
+            .remove(key)
+    }
+}
@@ -0,0 +1,82 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
+
+/// A map holding at most one value of each type. Each running actor has one,
+/// see [`crate::ActorContext::extensions()`], so handlers and code shared
+/// between actors can keep per-actor state, such as rate limiter buckets,
+/// without it being part of the actor itself. For example:
+/// ```
+/// use tiny_tokio_actor::Extensions;
+///
+/// struct Budget(u32);
+///
+/// let mut extensions = Extensions::default();
+/// extensions.insert(Budget(10));
+/// extensions.get_mut::<Budget>().unwrap().0 -= 1;
+/// assert_eq!(extensions.get::<Budget>().unwrap().0, 9);
+/// ```
+#[derive(Default)]
+pub struct Extensions {
+    values: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    /// Inserts the value, returning the previous value of the same type.
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.values
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|previous| previous.downcast().ok())
+            .map(|previous| *previous)
+    }
+
+    /// Get the value of type `T`, if any
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.values
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    /// Get the value of type `T` for modification, if any
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.values
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_mut())
+    }
+
+    /// Get the value of type `T`, inserting the one returned by `f` if there is
+    /// none yet.
+    pub fn get_or_insert_with<T, F>(&mut self, f: F) -> &mut T
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce() -> T,
+    {
+        self.values
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(f()))
+            .downcast_mut()
+            .expect("extension stored under the id of another type")
+    }
+
+    /// Removes the value of type `T`, returning it.
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.values
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok())
+            .map(|value| *value)
+    }
+
+    /// Removes all values.
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+}
+
+impl std::fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.values.len())
+            .finish()
+    }
+}
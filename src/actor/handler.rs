@@ -1,18 +1,57 @@
-use std::marker::PhantomData;
+use std::{
+    any::Any,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 
 use async_trait::async_trait;
+use log::Level;
 use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
 
 use crate::{
     actor::{ActorContext, Handler, Message},
+    dead_letters::DeadLetterReason,
+    deadline::{self, Deadline},
+    instrumentation::{actor_log, MailboxMonitor},
+    policy::{self, Caller},
     system::SystemEvent,
 };
 
-use super::{Actor, ActorError};
+use super::{
+    conflation::{ConflationKey, ConflationSlots, Slot},
+    requester::Rsvp,
+    supervision::ChildFailed,
+    Actor, ActorError,
+};
 
 #[async_trait]
 pub trait MessageHandler<E: SystemEvent, A: Actor<E>>: Send + Sync {
     async fn handle(&mut self, actor: &mut A, ctx: &mut ActorContext<E>);
+
+    /// The type name of the message.
+    fn message_type(&self) -> &'static str;
+
+    /// When the message was put in the mailbox.
+    fn enqueued_at(&self) -> Instant;
+
+    /// Identifies the message while it is in the mailbox, see
+    /// [`MailboxMonitor::track()`].
+    fn id(&self) -> u64;
+
+    /// The estimated size of the message, see [`Message::size_hint()`].
+    fn size(&self) -> usize;
+
+    /// Called once the message is taken from the mailbox, before the room it
+    /// took is given back.
+    fn dequeued(&mut self) {}
+
+    /// Unwraps the message, e.g. to keep it as a dead letter.
+    fn into_payload(self: Box<Self>) -> Arc<dyn Any + Send + Sync>;
 }
 
 struct ActorMessage<M, E, A>
@@ -21,8 +60,14 @@ where
     E: SystemEvent,
     A: Actor<E> + Handler<E, M>,
 {
+    id: u64,
+    size: usize,
     payload: M,
-    rsvp: Option<oneshot::Sender<M::Response>>,
+    rsvp: Option<Rsvp<M::Response>>,
+    cancelled: Option<Arc<AtomicBool>>,
+    caller: Option<Arc<Caller>>,
+    deadline: Option<Deadline>,
+    enqueued_at: Instant,
     _phantom_actor: PhantomData<A>,
     _phantom_event: PhantomData<E>,
 }
@@ -37,6 +82,26 @@ where
     async fn handle(&mut self, actor: &mut A, ctx: &mut ActorContext<E>) {
         self.process(actor, ctx).await
     }
+
+    fn message_type(&self) -> &'static str {
+        std::any::type_name::<M>()
+    }
+
+    fn enqueued_at(&self) -> Instant {
+        self.enqueued_at
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn into_payload(self: Box<Self>) -> Arc<dyn Any + Send + Sync> {
+        Arc::new(self.payload)
+    }
 }
 
 impl<M, E, A> ActorMessage<M, E, A>
@@ -46,27 +111,299 @@ where
     A: Actor<E> + Handler<E, M>,
 {
     async fn process(&mut self, actor: &mut A, ctx: &mut ActorContext<E>) {
-        let result = actor.handle(self.payload.clone(), ctx).await;
-
-        if let Some(rsvp) = std::mem::replace(&mut self.rsvp, None) {
+        if self.deadline.is_some_and(|deadline| deadline.is_expired()) {
+            log::debug!(
+                actor:% = ctx.path,
+                message_type = std::any::type_name::<M>();
+                "Dropping message to actor '{}' as its deadline passed",
+                &ctx.path
+            );
+            ctx.system.report_expired(&ctx.path, self.payload.clone());
+            return;
+        }
+        ctx.cancelled = self.cancelled.take();
+        ctx.caller = self.caller.take();
+        ctx.deadline = self.deadline;
+        let caller = ctx.caller.clone();
+        let handle = deadline::within(self.deadline, actor.handle(self.payload.clone(), ctx));
+        let result = policy::calling_as(caller, handle).await;
+        ctx.cancelled = None;
+        ctx.caller = None;
+        ctx.deadline = None;
+
+        if let Some(rsvp) = self.rsvp.take() {
             rsvp.send(result).unwrap_or_else(|_failed| {
-                log::error!("Failed to send back response!");
+                log::error!(
+                    actor:% = ctx.path,
+                    message_type = std::any::type_name::<M>();
+                    "Failed to send back response!"
+                );
+                ctx.system
+                    .report_orphaned_response(&ctx.path, self.payload.clone());
             })
         }
     }
 
-    pub fn new(msg: M, rsvp: Option<oneshot::Sender<M::Response>>) -> Self {
+    pub fn new(
+        id: u64,
+        msg: M,
+        rsvp: Option<Rsvp<M::Response>>,
+        cancelled: Option<Arc<AtomicBool>>,
+    ) -> Self {
         ActorMessage {
+            id,
+            size: msg.size_hint(),
             payload: msg,
             rsvp,
+            cancelled,
+            caller: policy::current_caller(),
+            deadline: Deadline::current(),
+            enqueued_at: Instant::now(),
             _phantom_actor: PhantomData,
             _phantom_event: PhantomData,
         }
     }
 }
 
-pub type MailboxReceiver<E, A> = mpsc::UnboundedReceiver<BoxedMessageHandler<E, A>>;
-pub type MailboxSender<E, A> = mpsc::UnboundedSender<BoxedMessageHandler<E, A>>;
+/// The placeholder in the mailbox for the latest message with a key, see
+/// [`ConflationSlots`].
+struct ConflatedMessage<M, E, A>
+where
+    M: ConflationKey,
+    E: SystemEvent,
+    A: Actor<E> + Handler<E, M>,
+{
+    id: u64,
+    size: usize,
+    key: M::Key,
+    slots: Arc<ConflationSlots>,
+    latest: Option<Slot<M>>,
+    taken: bool,
+    enqueued_at: Instant,
+    _phantom_actor: PhantomData<A>,
+    _phantom_event: PhantomData<E>,
+}
+
+impl<M, E, A> ConflatedMessage<M, E, A>
+where
+    M: ConflationKey,
+    E: SystemEvent,
+    A: Actor<E> + Handler<E, M>,
+{
+    fn new(id: u64, size: usize, key: M::Key, slots: Arc<ConflationSlots>) -> Self {
+        ConflatedMessage {
+            id,
+            size,
+            key,
+            slots,
+            latest: None,
+            taken: false,
+            enqueued_at: Instant::now(),
+            _phantom_actor: PhantomData,
+            _phantom_event: PhantomData,
+        }
+    }
+
+    /// Takes the latest message out of its slot, so newer messages with the
+    /// same key are queued again.
+    fn take_slot(&mut self) {
+        if !self.taken {
+            self.taken = true;
+            self.latest = self.slots.take::<M>(&self.key);
+            if let Some(latest) = &self.latest {
+                self.size = latest.size;
+            }
+        }
+    }
+
+    fn take(&mut self) -> Option<Slot<M>> {
+        self.take_slot();
+        self.latest.take()
+    }
+}
+
+#[async_trait]
+impl<M, E, A> MessageHandler<E, A> for ConflatedMessage<M, E, A>
+where
+    M: ConflationKey,
+    E: SystemEvent,
+    A: Actor<E> + Handler<E, M>,
+{
+    async fn handle(&mut self, actor: &mut A, ctx: &mut ActorContext<E>) {
+        let Slot {
+            msg,
+            caller,
+            deadline,
+            ..
+        } = match self.take() {
+            Some(latest) => latest,
+            None => return,
+        };
+        if deadline.is_some_and(|deadline| deadline.is_expired()) {
+            log::debug!(
+                actor:% = ctx.path,
+                message_type = std::any::type_name::<M>();
+                "Dropping message to actor '{}' as its deadline passed",
+                &ctx.path
+            );
+            ctx.system.report_expired(&ctx.path, msg);
+            return;
+        }
+        ctx.caller = caller;
+        ctx.deadline = deadline;
+        let caller = ctx.caller.clone();
+        let handle = deadline::within(deadline, actor.handle(msg, ctx));
+        policy::calling_as(caller, handle).await;
+        ctx.caller = None;
+        ctx.deadline = None;
+    }
+
+    fn message_type(&self) -> &'static str {
+        std::any::type_name::<M>()
+    }
+
+    fn enqueued_at(&self) -> Instant {
+        self.enqueued_at
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn size(&self) -> usize {
+        // Replacements change the size of a message still in the mailbox
+        match self.taken {
+            true => self.size,
+            false => self.slots.size::<M>(&self.key).unwrap_or(self.size),
+        }
+    }
+
+    fn dequeued(&mut self) {
+        // Holds on to the latest message, so it is not replaced anymore once
+        // the room it took is given back
+        self.take_slot();
+    }
+
+    fn into_payload(mut self: Box<Self>) -> Arc<dyn Any + Send + Sync> {
+        match self.take() {
+            Some(latest) => Arc::new(latest.msg),
+            None => Arc::new(()),
+        }
+    }
+}
+
+impl<M, E, A> Drop for ConflatedMessage<M, E, A>
+where
+    M: ConflationKey,
+    E: SystemEvent,
+    A: Actor<E> + Handler<E, M>,
+{
+    fn drop(&mut self) {
+        if !self.taken {
+            self.slots.take::<M>(&self.key);
+        }
+    }
+}
+
+/// The receiving end of an actor mailbox.
+pub enum MailboxReceiver<E: SystemEvent, A: Actor<E>> {
+    Bounded(mpsc::Receiver<BoxedMessageHandler<E, A>>),
+    Unbounded(mpsc::UnboundedReceiver<BoxedMessageHandler<E, A>>),
+}
+
+impl<E: SystemEvent, A: Actor<E>> MailboxReceiver<E, A> {
+    pub async fn recv(&mut self) -> Option<BoxedMessageHandler<E, A>> {
+        match self {
+            MailboxReceiver::Bounded(receiver) => receiver.recv().await,
+            MailboxReceiver::Unbounded(receiver) => receiver.recv().await,
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Option<BoxedMessageHandler<E, A>> {
+        match self {
+            MailboxReceiver::Bounded(receiver) => receiver.try_recv().ok(),
+            MailboxReceiver::Unbounded(receiver) => receiver.try_recv().ok(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            MailboxReceiver::Bounded(receiver) => receiver.is_empty(),
+            MailboxReceiver::Unbounded(receiver) => receiver.is_empty(),
+        }
+    }
+
+    pub fn close(&mut self) {
+        match self {
+            MailboxReceiver::Bounded(receiver) => receiver.close(),
+            MailboxReceiver::Unbounded(receiver) => receiver.close(),
+        }
+    }
+}
+
+/// The sending end of an actor mailbox.
+pub enum MailboxSender<E: SystemEvent, A: Actor<E>> {
+    Bounded(mpsc::Sender<BoxedMessageHandler<E, A>>),
+    Unbounded(mpsc::UnboundedSender<BoxedMessageHandler<E, A>>),
+}
+
+impl<E: SystemEvent, A: Actor<E>> Clone for MailboxSender<E, A> {
+    fn clone(&self) -> Self {
+        match self {
+            MailboxSender::Bounded(sender) => MailboxSender::Bounded(sender.clone()),
+            MailboxSender::Unbounded(sender) => MailboxSender::Unbounded(sender.clone()),
+        }
+    }
+}
+
+impl<E: SystemEvent, A: Actor<E>> MailboxSender<E, A> {
+    /// Enqueues the message without waiting. Fails if a bounded mailbox is
+    /// full, handing back the message.
+    pub fn try_send(&self, msg: BoxedMessageHandler<E, A>) -> Result<(), MailboxError<E, A>> {
+        match self {
+            MailboxSender::Bounded(sender) => sender.try_send(msg).map_err(|error| match error {
+                mpsc::error::TrySendError::Full(msg) => (ActorError::MailboxFull, msg),
+                mpsc::error::TrySendError::Closed(msg) => (closed(), msg),
+            }),
+            MailboxSender::Unbounded(sender) => sender
+                .send(msg)
+                .map_err(|error| (ActorError::SendError(error.to_string()), error.0)),
+        }
+    }
+
+    /// Enqueues the message, blocking the current thread until there is
+    /// capacity if a bounded mailbox is full.
+    pub fn blocking_send(&self, msg: BoxedMessageHandler<E, A>) -> Result<(), MailboxError<E, A>> {
+        match self {
+            MailboxSender::Bounded(sender) => sender
+                .blocking_send(msg)
+                .map_err(|error| (ActorError::SendError(error.to_string()), error.0)),
+            MailboxSender::Unbounded(sender) => sender
+                .send(msg)
+                .map_err(|error| (ActorError::SendError(error.to_string()), error.0)),
+        }
+    }
+
+    /// Enqueues the message, waiting for capacity if a bounded mailbox is full.
+    pub async fn send(&self, msg: BoxedMessageHandler<E, A>) -> Result<(), MailboxError<E, A>> {
+        match self {
+            MailboxSender::Bounded(sender) => sender
+                .send(msg)
+                .await
+                .map_err(|error| (ActorError::SendError(error.to_string()), error.0)),
+            MailboxSender::Unbounded(sender) => sender
+                .send(msg)
+                .map_err(|error| (ActorError::SendError(error.to_string()), error.0)),
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        match self {
+            MailboxSender::Bounded(sender) => sender.is_closed(),
+            MailboxSender::Unbounded(sender) => sender.is_closed(),
+        }
+    }
+}
 
 pub struct ActorMailbox<E: SystemEvent, A: Actor<E>> {
     _phantom_actor: PhantomData<A>,
@@ -74,26 +411,246 @@ pub struct ActorMailbox<E: SystemEvent, A: Actor<E>> {
 }
 
 impl<E: SystemEvent, A: Actor<E>> ActorMailbox<E, A> {
-    pub fn create() -> (MailboxSender<E, A>, MailboxReceiver<E, A>) {
-        mpsc::unbounded_channel()
+    /// Creates a mailbox holding at most `capacity` messages, or an unbounded
+    /// one if no capacity is given.
+    pub fn create(capacity: Option<usize>) -> (MailboxSender<E, A>, MailboxReceiver<E, A>) {
+        match capacity {
+            Some(capacity) => {
+                let (sender, receiver) = mpsc::channel(capacity.max(1));
+                (
+                    MailboxSender::Bounded(sender),
+                    MailboxReceiver::Bounded(receiver),
+                )
+            }
+            None => {
+                let (sender, receiver) = mpsc::unbounded_channel();
+                (
+                    MailboxSender::Unbounded(sender),
+                    MailboxReceiver::Unbounded(receiver),
+                )
+            }
+        }
     }
 }
 
 pub type BoxedMessageHandler<E, A> = Box<dyn MessageHandler<E, A>>;
 
+/// A failed send to a mailbox, with the message that could not be enqueued.
+pub type MailboxError<E, A> = (ActorError, BoxedMessageHandler<E, A>);
+
+/// The error of a send to a mailbox that is closed.
+fn closed() -> ActorError {
+    ActorError::SendError("channel closed".to_string())
+}
+
+/// Out-of-band instructions for the actor runner. These are processed in
+/// between messages, so a handler that is already running always completes
+/// first.
+pub(crate) enum ControlMessage {
+    Suspend(oneshot::Sender<()>),
+    Resume,
+    Replace(Box<dyn Any + Send>, oneshot::Sender<Result<(), ActorError>>),
+    Restart(oneshot::Sender<Result<(), ActorError>>),
+    Stop(oneshot::Sender<()>),
+    Drain(oneshot::Sender<()>),
+    ChildFailed(ChildFailed),
+    Ping(oneshot::Sender<()>),
+}
+
+pub(crate) type ControlReceiver = mpsc::UnboundedReceiver<ControlMessage>;
+
+/// A type erased handle to the control channel of an actor runner.
+#[derive(Clone)]
+pub(crate) struct ControlRef {
+    sender: mpsc::UnboundedSender<ControlMessage>,
+    cancellation: CancellationToken,
+    draining: Arc<AtomicBool>,
+}
+
+impl ControlRef {
+    pub fn create() -> (Self, ControlReceiver) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let cancellation = CancellationToken::new();
+        (
+            ControlRef {
+                sender,
+                cancellation,
+                draining: Arc::new(AtomicBool::new(false)),
+            },
+            receiver,
+        )
+    }
+
+    pub async fn suspend(&self) -> Result<(), ActorError> {
+        self.request_suspend()?
+            .await
+            .map_err(|error| ActorError::SendError(error.to_string()))
+    }
+
+    /// Asks the runner to suspend, returning the receiver of its ack, which
+    /// arrives once the message being handled (if any) has completed.
+    pub fn request_suspend(&self) -> Result<oneshot::Receiver<()>, ActorError> {
+        let (ack_sender, ack_receiver) = oneshot::channel();
+        match self.sender.send(ControlMessage::Suspend(ack_sender)) {
+            Ok(()) => Ok(ack_receiver),
+            Err(error) => {
+                log::error!("Failed to suspend actor! {}", error);
+                Err(ActorError::SendError(error.to_string()))
+            }
+        }
+    }
+
+    /// Resolves once the runner of the actor answered, i.e. it is not stuck
+    /// in a handler.
+    pub async fn ping(&self) -> Result<(), ActorError> {
+        let (ack_sender, ack_receiver) = oneshot::channel();
+        self.sender
+            .send(ControlMessage::Ping(ack_sender))
+            .map_err(|error| ActorError::SendError(error.to_string()))?;
+        ack_receiver
+            .await
+            .map_err(|error| ActorError::SendError(error.to_string()))
+    }
+
+    /// Whether both refer to the control channel of the same actor runner,
+    /// i.e. the same incarnation of an actor.
+    pub fn same_runner(&self, other: &ControlRef) -> bool {
+        self.sender.same_channel(&other.sender)
+    }
+
+    pub fn resume(&self) -> Result<(), ActorError> {
+        self.sender.send(ControlMessage::Resume).map_err(|error| {
+            log::error!("Failed to resume actor! {}", error);
+            ActorError::SendError(error.to_string())
+        })
+    }
+
+    pub async fn restart(&self) -> Result<(), ActorError> {
+        let (ack_sender, ack_receiver) = oneshot::channel();
+        if let Err(error) = self.sender.send(ControlMessage::Restart(ack_sender)) {
+            log::error!("Failed to restart actor! {}", error);
+            Err(ActorError::SendError(error.to_string()))
+        } else {
+            ack_receiver
+                .await
+                .map_err(|error| ActorError::SendError(error.to_string()))?
+        }
+    }
+
+    /// The token that is cancelled when the actor stops.
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// Requests the actor to stop. The returned receiver completes once the
+    /// actor has stopped, or immediately if it was not running anymore. The
+    /// cancellation token of the actor is cancelled right away, so a handler
+    /// that is currently running can wrap up.
+    pub fn stop(&self) -> oneshot::Receiver<()> {
+        self.cancellation.cancel();
+        let (ack_sender, ack_receiver) = oneshot::channel();
+        self.sender
+            .send(ControlMessage::Stop(ack_sender))
+            .unwrap_or_default();
+        ack_receiver
+    }
+
+    /// Requests the actor to stop once it has handled the messages that are
+    /// already in its mailbox, closing the mailbox to new messages. The
+    /// returned receiver completes once the actor has stopped.
+    pub fn drain(&self) -> oneshot::Receiver<()> {
+        self.draining.store(true, Ordering::Relaxed);
+        let (ack_sender, ack_receiver) = oneshot::channel();
+        self.sender
+            .send(ControlMessage::Drain(ack_sender))
+            .unwrap_or_default();
+        ack_receiver
+    }
+
+    /// Checks if the actor is draining its mailbox, i.e. it does not accept new
+    /// messages but has not stopped yet.
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed) && !self.cancellation.is_cancelled()
+    }
+
+    /// Informs the actor that one of its children failed.
+    pub fn child_failed(&self, failure: ChildFailed) {
+        self.sender
+            .send(ControlMessage::ChildFailed(failure))
+            .unwrap_or_else(|error| {
+                log::error!("Failed to escalate to actor! {}", error);
+            });
+    }
+
+    pub async fn replace<A: Send + 'static>(&self, actor: A) -> Result<(), ActorError> {
+        let (ack_sender, ack_receiver) = oneshot::channel();
+        let replace = ControlMessage::Replace(Box::new(actor), ack_sender);
+        if let Err(error) = self.sender.send(replace) {
+            log::error!("Failed to replace actor! {}", error);
+            Err(ActorError::SendError(error.to_string()))
+        } else {
+            ack_receiver
+                .await
+                .map_err(|error| ActorError::SendError(error.to_string()))?
+        }
+    }
+}
+
+/// Marks an ask as cancelled when the caller stops waiting for the response,
+/// so the handler can check [`ActorContext::is_cancelled()`].
+#[derive(Default)]
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl CancelOnDrop {
+    fn flag(&self) -> Arc<AtomicBool> {
+        self.0.clone()
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
 pub struct HandlerRef<E: SystemEvent, A: Actor<E>> {
-    sender: mpsc::UnboundedSender<BoxedMessageHandler<E, A>>,
+    sender: MailboxSender<E, A>,
+    control: ControlRef,
+    monitor: MailboxMonitor,
+    conflation: Arc<ConflationSlots>,
 }
 
 impl<E: SystemEvent, A: Actor<E>> Clone for HandlerRef<E, A> {
     fn clone(&self) -> Self {
-        Self { sender: self.sender.clone() }
+        Self {
+            sender: self.sender.clone(),
+            control: self.control.clone(),
+            monitor: self.monitor.clone(),
+            conflation: self.conflation.clone(),
+        }
     }
 }
 
 impl<E: SystemEvent, A: Actor<E>> HandlerRef<E, A> {
-    pub(crate) fn new(sender: mpsc::UnboundedSender<BoxedMessageHandler<E, A>>) -> Self {
-        HandlerRef { sender }
+    pub(crate) fn new(
+        sender: MailboxSender<E, A>,
+        control: ControlRef,
+        monitor: MailboxMonitor,
+    ) -> Self {
+        HandlerRef {
+            sender,
+            control,
+            monitor,
+            conflation: Default::default(),
+        }
+    }
+
+    pub(crate) fn control(&self) -> &ControlRef {
+        &self.control
+    }
+
+    pub(crate) fn monitor(&self) -> &MailboxMonitor {
+        &self.monitor
     }
 
     pub fn tell<M>(&self, msg: M) -> Result<(), ActorError>
@@ -101,13 +658,98 @@ impl<E: SystemEvent, A: Actor<E>> HandlerRef<E, A> {
         M: Message,
         A: Handler<E, M>,
     {
-        let message = ActorMessage::<M, E, A>::new(msg, None);
-        if let Err(error) = self.sender.send(Box::new(message)) {
-            log::error!("Failed to tell message! {}", error.to_string());
-            Err(ActorError::SendError(error.to_string()))
-        } else {
-            Ok(())
+        let message = ActorMessage::<M, E, A>::new(
+            self.monitor.track(std::any::type_name::<M>()),
+            msg,
+            None,
+            None,
+        );
+        self.try_enqueue::<M>(Box::new(message))
+    }
+
+    /// Tells the message, replacing the message with the same key if that is
+    /// still in the mailbox.
+    pub fn tell_latest<M>(&self, msg: M) -> Result<(), ActorError>
+    where
+        M: ConflationKey,
+        A: Handler<E, M>,
+    {
+        // Admitted before it can replace a queued message, so a replacement
+        // passes the message policy and the quotas like any other message
+        let message_type = std::any::type_name::<M>();
+        if let Err(error) = self.monitor.admit(message_type) {
+            let message =
+                ActorMessage::<M, E, A>::new(self.monitor.track(message_type), msg, None, None);
+            return Err(self.undelivered("tell", error, Box::new(message)));
+        }
+        let key = msg.conflation_key();
+        let size = msg.size_hint();
+        let latest = Slot {
+            msg,
+            caller: policy::current_caller(),
+            deadline: Deadline::current(),
+            size,
+        };
+        let resized = |replaced| self.monitor.resize(replaced, size);
+        if self.conflation.replace(&key, latest, resized) {
+            // The replacement takes the place of the replaced message
+            self.monitor.leave();
+            return Ok(());
         }
+        let message = ConflatedMessage::<M, E, A>::new(
+            self.monitor.track(message_type),
+            size,
+            key,
+            self.conflation.clone(),
+        );
+        self.enqueue_admitted::<M>(Box::new(message))
+    }
+
+    /// Enqueues the message if it is admitted and the mailbox has room.
+    fn try_enqueue<M: Message>(
+        &self,
+        message: BoxedMessageHandler<E, A>,
+    ) -> Result<(), ActorError> {
+        if let Err(error) = self.monitor.admit(std::any::type_name::<M>()) {
+            return Err(self.undelivered("tell", error, message));
+        }
+        self.enqueue_admitted::<M>(message)
+    }
+
+    /// Enqueues an admitted message if the mailbox has room.
+    fn enqueue_admitted<M: Message>(
+        &self,
+        message: BoxedMessageHandler<E, A>,
+    ) -> Result<(), ActorError> {
+        let size = message.size();
+        if !self.monitor.try_reserve(size) {
+            self.monitor.leave();
+            return Err(self.undelivered("tell", ActorError::MailboxFull, message));
+        }
+        match self.sender.try_send(message) {
+            Ok(()) => {
+                self.monitor.enqueued(std::any::type_name::<M>());
+                Ok(())
+            }
+            Err((error, message)) => {
+                self.monitor.release(size);
+                Err(self.undelivered("tell", error, message))
+            }
+        }
+    }
+
+    pub async fn ordered_tell<M>(&self, msg: M) -> Result<(), ActorError>
+    where
+        M: Message,
+        A: Handler<E, M>,
+    {
+        let message = ActorMessage::<M, E, A>::new(
+            self.monitor.track(std::any::type_name::<M>()),
+            msg,
+            None,
+            None,
+        );
+        self.enqueue::<M>("tell", Box::new(message)).await
     }
 
     pub async fn ask<M>(&self, msg: M) -> Result<M::Response, ActorError>
@@ -116,17 +758,156 @@ impl<E: SystemEvent, A: Actor<E>> HandlerRef<E, A> {
         A: Handler<E, M>,
     {
         let (response_sender, response_receiver) = oneshot::channel();
-        let message = ActorMessage::<M, E, A>::new(msg, Some(response_sender));
-        if let Err(error) = self.sender.send(Box::new(message)) {
-            log::error!("Failed to ask message! {}", error.to_string());
-            Err(ActorError::SendError(error.to_string()))
-        } else {
-            response_receiver
-                .await
-                .map_err(|error| ActorError::SendError(error.to_string()))
+        let cancelled = CancelOnDrop::default();
+        let message = ActorMessage::<M, E, A>::new(
+            self.monitor.track(std::any::type_name::<M>()),
+            msg,
+            Some(Rsvp::Channel(response_sender)),
+            Some(cancelled.flag()),
+        );
+        self.enqueue::<M>("ask", Box::new(message)).await?;
+        response_receiver
+            .await
+            .map_err(|error| ActorError::SendError(error.to_string()))
+    }
+
+    /// Sends an ask whose response is sent to `rsvp`, and resolves once it is
+    /// in the mailbox.
+    pub(crate) async fn ask_with<M>(
+        &self,
+        msg: M,
+        rsvp: Rsvp<M::Response>,
+        cancelled: Arc<AtomicBool>,
+    ) -> Result<(), ActorError>
+    where
+        M: Message,
+        A: Handler<E, M>,
+    {
+        let message = ActorMessage::<M, E, A>::new(
+            self.monitor.track(std::any::type_name::<M>()),
+            msg,
+            Some(rsvp),
+            Some(cancelled),
+        );
+        self.enqueue::<M>("ask", Box::new(message)).await
+    }
+
+    /// Enqueues the message, waiting for room if the mailbox is full.
+    async fn enqueue<M: Message>(
+        &self,
+        action: &str,
+        message: BoxedMessageHandler<E, A>,
+    ) -> Result<(), ActorError> {
+        let size = message.size();
+        if let Err(error) = self.monitor.admit(std::any::type_name::<M>()) {
+            return Err(self.undelivered(action, error, message));
+        }
+        if !self
+            .monitor
+            .reserve(size, self.control.cancellation_token())
+            .await
+        {
+            self.monitor.leave();
+            return Err(self.undelivered(action, closed(), message));
+        }
+        match self.sender.send(message).await {
+            Ok(()) => {
+                self.monitor.enqueued(std::any::type_name::<M>());
+                Ok(())
+            }
+            Err((error, message)) => {
+                self.monitor.release(size);
+                Err(self.undelivered(action, error, message))
+            }
         }
     }
 
+    pub fn blocking_tell<M>(&self, msg: M) -> Result<(), ActorError>
+    where
+        M: Message,
+        A: Handler<E, M>,
+    {
+        let message = ActorMessage::<M, E, A>::new(
+            self.monitor.track(std::any::type_name::<M>()),
+            msg,
+            None,
+            None,
+        );
+        self.blocking_enqueue::<M>(Box::new(message))
+    }
+
+    pub fn blocking_ask<M>(&self, msg: M) -> Result<M::Response, ActorError>
+    where
+        M: Message,
+        A: Handler<E, M>,
+    {
+        let (response_sender, response_receiver) = oneshot::channel();
+        let cancelled = CancelOnDrop::default();
+        let message = ActorMessage::<M, E, A>::new(
+            self.monitor.track(std::any::type_name::<M>()),
+            msg,
+            Some(Rsvp::Channel(response_sender)),
+            Some(cancelled.flag()),
+        );
+        self.blocking_enqueue::<M>(Box::new(message))?;
+        response_receiver
+            .blocking_recv()
+            .map_err(|error| ActorError::SendError(error.to_string()))
+    }
+
+    fn blocking_enqueue<M: Message>(
+        &self,
+        message: BoxedMessageHandler<E, A>,
+    ) -> Result<(), ActorError> {
+        let size = message.size();
+        if let Err(error) = self.monitor.admit(std::any::type_name::<M>()) {
+            return Err(self.undelivered("send", error, message));
+        }
+        if !self
+            .monitor
+            .blocking_reserve(size, self.control.cancellation_token())
+        {
+            self.monitor.leave();
+            return Err(self.undelivered("send", closed(), message));
+        }
+        match self.sender.blocking_send(message) {
+            Ok(()) => {
+                self.monitor.enqueued(std::any::type_name::<M>());
+                Ok(())
+            }
+            Err((error, message)) => {
+                self.monitor.release(size);
+                Err(self.undelivered("send", error, message))
+            }
+        }
+    }
+
+    /// Logs that the message could not be enqueued and turns it into a dead
+    /// letter.
+    fn undelivered(
+        &self,
+        action: &str,
+        error: ActorError,
+        message: BoxedMessageHandler<E, A>,
+    ) -> ActorError {
+        actor_log!(
+            self.monitor,
+            Level::Error,
+            message_type = message.message_type();
+            "Failed to {} message! {}",
+            action,
+            error
+        );
+        self.monitor
+            .dropped(message, DeadLetterReason::from(&error));
+        error
+    }
+
+    #[cfg(feature = "inspect")]
+    pub fn peek_mailbox(&self, n: usize) -> Vec<crate::instrumentation::QueuedMessage> {
+        self.monitor.peek(n)
+    }
+
     pub fn is_closed(&self) -> bool {
         self.sender.is_closed()
     }
@@ -135,7 +916,10 @@ impl<E: SystemEvent, A: Actor<E>> HandlerRef<E, A> {
 #[cfg(test)]
 mod tests {
 
-    use crate::{bus::EventBus, system::ActorSystem, ActorPath};
+    use crate::{
+        builtin::BuiltinBuses, bus::EventBus, dead_letters::DeadLetters, system::ActorSystem,
+        ActorPath,
+    };
 
     use super::*;
 
@@ -144,6 +928,7 @@ mod tests {
         counter: usize,
     }
 
+    #[allow(dead_code)]
     #[derive(Debug, Clone)]
     struct MyMessage(String);
 
@@ -177,12 +962,18 @@ mod tests {
         let (sender, mut receiver): (
             MailboxSender<MyMessage, MyActor>,
             MailboxReceiver<MyMessage, MyActor>,
-        ) = ActorMailbox::create();
-        let actor_ref = HandlerRef { sender };
+        ) = ActorMailbox::create(None);
+        let (control, _control_receiver) = ControlRef::create();
         let bus = EventBus::<MyMessage>::new(1000);
         let system = ActorSystem::new("test", bus);
         let path = ActorPath::from("/test");
-        let mut ctx = ActorContext { path, system };
+        let monitor = MailboxMonitor::new(
+            path.clone(),
+            DeadLetters::new(BuiltinBuses::new(1).bus()),
+            None,
+        );
+        let actor_ref = HandlerRef::new(sender, control, monitor);
+        let mut ctx = ActorContext::new(path, 0, CancellationToken::new(), system);
         tokio::spawn(async move {
             while let Some(mut msg) = receiver.recv().await {
                 msg.handle(&mut actor, &mut ctx).await;
@@ -206,12 +997,18 @@ mod tests {
         let (sender, mut receiver): (
             MailboxSender<MyMessage, MyActor>,
             MailboxReceiver<MyMessage, MyActor>,
-        ) = ActorMailbox::create();
-        let actor_ref = HandlerRef { sender };
+        ) = ActorMailbox::create(None);
+        let (control, _control_receiver) = ControlRef::create();
         let bus = EventBus::<MyMessage>::new(1000);
         let system = ActorSystem::new("test", bus);
         let path = ActorPath::from("/test");
-        let mut ctx = ActorContext { path, system };
+        let monitor = MailboxMonitor::new(
+            path.clone(),
+            DeadLetters::new(BuiltinBuses::new(1).bus()),
+            None,
+        );
+        let actor_ref = HandlerRef::new(sender, control, monitor);
+        let mut ctx = ActorContext::new(path, 0, CancellationToken::new(), system);
         tokio::spawn(async move {
             while let Some(mut msg) = receiver.recv().await {
                 msg.handle(&mut actor, &mut ctx).await;
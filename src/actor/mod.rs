@@ -2,31 +2,460 @@ pub(crate) mod handler;
 pub(crate) mod runner;
 pub(crate) mod supervision;
 
+use std::{
+    any::Any,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
 use async_trait::async_trait;
 use thiserror::Error;
+use tokio_util::sync::CancellationToken;
 
+mod bridge;
+mod config;
+pub(crate) mod conflation;
+mod extensions;
 mod path;
-pub use path::ActorPath;
+pub(crate) mod props;
+pub(crate) mod requester;
+mod scope;
+mod throttle;
+mod transaction;
+pub(crate) mod unreferenced;
+pub use bridge::SyncBridge;
+pub use config::ActorConfig;
+pub use conflation::ConflationKey;
+pub use extensions::Extensions;
+pub use path::{ActorPath, PathPattern};
+pub use props::Props;
+pub use requester::Requester;
+pub use scope::TaskScope;
+pub use transaction::{Participant, Transaction, TxToken};
 
-use supervision::SupervisionStrategy;
+use supervision::{ChildFailed, CrashReport, SupervisionStrategy};
+use throttle::LogThrottle;
+use unreferenced::RefGuard;
 
-use crate::system::{ActorSystem, SystemEvent};
+use crate::{
+    bus::EventPublisher,
+    deadline::Deadline,
+    election::{self, Leadership, LeaseStore},
+    instrumentation::{actor_log, MailboxMonitor},
+    policy::Caller,
+    quota::{Quota, QuotaScope},
+    subscription::{EventPolicy, EventSubscription},
+    system::{validate_name, ActorSystem, NoEvent, SystemEvent},
+};
 
 /// The actor context gives a running actor access to its path, as well as the system that
 /// is running it.
-pub struct ActorContext<E: SystemEvent> {
+pub struct ActorContext<E: SystemEvent = NoEvent> {
     pub path: ActorPath,
     pub system: ActorSystem<E>,
+    incarnation: u64,
+    cancellation: CancellationToken,
+    handoff: Option<Handoff>,
+    cancelled: Option<Arc<AtomicBool>>,
+    pub(crate) caller: Option<Arc<Caller>>,
+    pub(crate) deadline: Option<Deadline>,
+    subscriptions: Vec<EventSubscription>,
+    throttle: LogThrottle,
+    pub(crate) log_level: log::LevelFilter,
+    pub(crate) extensions: Extensions,
+    pub(crate) finalizers: Finalizers,
+}
+
+/// Cleanup registered through [`ActorContext::add_finalizer()`], run in
+/// reverse order of registration. Pending finalizers run when dropped, so
+/// they also run when the runner of the actor is aborted. The mutex keeps the
+/// context `Sync` without requiring finalizers to be.
+#[derive(Default)]
+pub(crate) struct Finalizers(std::sync::Mutex<Vec<Box<dyn FnOnce() + Send>>>);
+
+impl Finalizers {
+    pub fn run(&mut self) {
+        let finalizers = self.0.get_mut().unwrap();
+        while let Some(finalizer) = finalizers.pop() {
+            finalizer();
+        }
+    }
 }
 
+impl Drop for Finalizers {
+    fn drop(&mut self) {
+        self.run();
+    }
+}
+
+/// What to do when an actor watched through [`ActorContext::watch_with()`]
+/// terminates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnTerminate {
+    /// Stop the watching actor (and its children) as well.
+    StopSelf,
+}
+
+/// State handed over from an actor instance to the instance replacing it. See
+/// [`Actor::handoff()`].
+pub type Handoff = Box<dyn Any + Send + Sync>;
+
 impl<E: SystemEvent> ActorContext<E> {
+    pub(crate) fn new(
+        path: ActorPath,
+        incarnation: u64,
+        cancellation: CancellationToken,
+        system: ActorSystem<E>,
+    ) -> Self {
+        ActorContext {
+            path,
+            system,
+            incarnation,
+            cancellation,
+            handoff: None,
+            cancelled: None,
+            caller: None,
+            deadline: None,
+            subscriptions: Vec::new(),
+            throttle: LogThrottle::default(),
+            log_level: log::LevelFilter::Trace,
+            extensions: Extensions::default(),
+            finalizers: Finalizers::default(),
+        }
+    }
+
+    /// Get the cancellation token of this actor. It is cancelled as soon as the
+    /// actor is asked to stop, so long running handlers can `select!` on
+    /// [`CancellationToken::cancelled()`] and return promptly on shutdown.
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// Checks if the caller that asked the message currently being handled has
+    /// stopped waiting for the response, for example because its `ask` timed
+    /// out or was dropped. Long running handlers can use this to give up
+    /// early. This is always `false` for messages sent with `tell`.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+            .as_ref()
+            .is_some_and(|cancelled| cancelled.load(Ordering::Relaxed))
+    }
+
+    /// Get the identity on whose behalf the message currently being handled
+    /// was sent, see [`Caller`]. This is `None` for messages sent outside of
+    /// [`Caller::scope()`], and outside of handlers.
+    pub fn caller(&self) -> Option<&Caller> {
+        self.caller.as_deref()
+    }
+
+    /// The deadline of the message currently being handled, if it was sent
+    /// within one, see [`Deadline`].
+    pub fn deadline(&self) -> Option<Deadline> {
+        self.deadline
+    }
+
+    /// The time left until the deadline of the message currently being
+    /// handled, if it has one, see [`Deadline`].
+    pub fn remaining_time(&self) -> Option<Duration> {
+        self.deadline.map(|deadline| deadline.remaining())
+    }
+
+    /// Logs the message at the level, at most once per 10 seconds for each
+    /// key of this actor, e.g. to keep a handler crash looping on the same
+    /// error from flooding the logs. The repeats held back are counted, and
+    /// the next line logged for the key says how often the message
+    /// repeated. Lines below the log level of the actor (see
+    /// [`ActorConfig::with_log_level()`]) are neither logged nor counted. For
+    /// example:
+    /// ```
+    /// # use tiny_tokio_actor::*;
+    /// # #[derive(Clone, Debug)]
+    /// # struct Store;
+    /// # impl Message for Store { type Response = (); }
+    /// # struct Writer;
+    /// # impl Writer { async fn write(&mut self) -> Result<(), std::io::Error> { Ok(()) } }
+    /// # impl Actor for Writer {}
+    /// #[async_trait]
+    /// impl Handler<NoEvent, Store> for Writer {
+    ///     async fn handle(&mut self, _msg: Store, ctx: &mut ActorContext) {
+    ///         if let Err(error) = self.write().await {
+    ///             ctx.log_throttled("write", log::Level::Error, format_args!("Write failed: {}", error));
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn log_throttled(&mut self, key: &str, level: log::Level, msg: impl std::fmt::Display) {
+        if level > log::max_level() || level > self.log_level {
+            return;
+        }
+        match self.throttle.admit(key, std::time::Instant::now()) {
+            Some(0) => log::log!(level, actor:% = &self.path; "{}", msg),
+            Some(repeated) => log::log!(
+                level,
+                actor:% = &self.path,
+                repeated = repeated;
+                "{} (repeated {} times)",
+                msg,
+                repeated
+            ),
+            None => {}
+        }
+    }
+
+    /// Get the incarnation id of this actor. See [`ActorRef::incarnation()`].
+    pub fn incarnation(&self) -> u64 {
+        self.incarnation
+    }
+
+    /// Registers cleanup that must run when the actor terminates, e.g. to
+    /// release a file lock or lease it holds. Unlike
+    /// [`Actor::post_stop()`], which only runs when the actor stops normally,
+    /// finalizers also run when it fails, panics, or its runner task is
+    /// aborted, e.g. because the runtime shuts down. They are synchronous so
+    /// they can run while the task is being dropped, and run after
+    /// `post_stop` in reverse order of registration. They do not run when the
+    /// actor restarts. For example:
+    /// ```
+    /// # use tiny_tokio_actor::*;
+    /// # #[derive(Clone, Debug)]
+    /// # struct TestEvent;
+    /// # impl SystemEvent for TestEvent {}
+    /// struct Leaseholder;
+    ///
+    /// #[async_trait]
+    /// impl Actor<TestEvent> for Leaseholder {
+    ///     async fn pre_start(&mut self, ctx: &mut ActorContext<TestEvent>) -> Result<(), ActorError> {
+    ///         let lease = std::env::temp_dir().join("leaseholder.lock");
+    ///         std::fs::write(&lease, b"")
+    ///             .map_err(|error| ActorError::CreateError(error.to_string()))?;
+    ///         ctx.add_finalizer(move || std::fs::remove_file(lease).unwrap_or_default());
+    ///         Ok(())
+    ///     }
+    /// }
+    /// ```
+    pub fn add_finalizer<F: FnOnce() + Send + 'static>(&mut self, finalizer: F) {
+        self.finalizers
+            .0
+            .get_mut()
+            .unwrap()
+            .push(Box::new(finalizer));
+    }
+
+    /// Get the extensions of this actor: values of any type that live as long
+    /// as the actor instance, and are dropped when it is replaced or stopped.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Get the extensions of this actor for modification. For example:
+    /// ```
+    /// # use tiny_tokio_actor::*;
+    /// # #[derive(Clone, Debug)]
+    /// # struct TestEvent;
+    /// # impl SystemEvent for TestEvent {}
+    /// #[derive(Default)]
+    /// struct Handled(usize);
+    ///
+    /// fn count_handled(ctx: &mut ActorContext<TestEvent>) -> usize {
+    ///     let handled = ctx.extensions_mut().get_or_insert_with(Handled::default);
+    ///     handled.0 += 1;
+    ///     handled.0
+    /// }
+    /// ```
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// A publisher of events of type `T` on the event bus of the actor system,
+    /// see [`ActorSystem::publisher()`]. This lets an actor that is generic
+    /// over the event type, e.g. one provided by a library, publish its own
+    /// events:
+    /// ```
+    /// # use tiny_tokio_actor::*;
+    /// #[derive(Clone, Debug)]
+    /// pub struct Tick(pub u64);
+    ///
+    /// #[derive(Clone, Debug)]
+    /// pub struct Ping;
+    /// impl Message for Ping {
+    ///     type Response = ();
+    /// }
+    ///
+    /// pub struct Ticker(u64);
+    ///
+    /// impl<E: SystemEvent + From<Tick>> Actor<E> for Ticker {}
+    ///
+    /// #[async_trait]
+    /// impl<E: SystemEvent + From<Tick>> Handler<E, Ping> for Ticker {
+    ///     async fn handle(&mut self, _msg: Ping, ctx: &mut ActorContext<E>) {
+    ///         self.0 += 1;
+    ///         ctx.publisher().publish(Tick(self.0));
+    ///     }
+    /// }
+    /// ```
+    pub fn publisher<T: Into<E> + 'static>(&self) -> EventPublisher<T> {
+        self.system.publisher()
+    }
+
+    /// Subscribes this actor, of type `A`, to the events of the actor system,
+    /// see [`ActorSystem::subscribe_actor()`]. The subscription is torn down
+    /// when the actor stops, or through [`ActorContext::unsubscribe()`], so
+    /// unlike a forwarding loop spawned by hand it never outlives the actor.
+    /// Fails with an `ActorError::NotFound` if this actor is not of type `A`.
+    /// For example, in [`Actor::pre_start()`]:
+    /// ```
+    /// # use tiny_tokio_actor::*;
+    /// #[derive(Clone, Debug)]
+    /// struct Tick(u64);
+    /// impl SystemEvent for Tick {}
+    /// impl Message for Tick {
+    ///     type Response = ();
+    /// }
+    ///
+    /// struct Clock;
+    ///
+    /// #[async_trait]
+    /// impl Actor<Tick> for Clock {
+    ///     async fn pre_start(&mut self, ctx: &mut ActorContext<Tick>) -> Result<(), ActorError> {
+    ///         ctx.subscribe::<Self, _, _>(EventPolicy::all(), Some).await?;
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// #[async_trait]
+    /// impl Handler<Tick, Tick> for Clock {
+    ///     async fn handle(&mut self, tick: Tick, _ctx: &mut ActorContext<Tick>) {
+    ///         println!("tick {}", tick.0);
+    ///     }
+    /// }
+    /// ```
+    pub async fn subscribe<A, M, F>(
+        &mut self,
+        policy: EventPolicy<M>,
+        map: F,
+    ) -> Result<EventSubscription, ActorError>
+    where
+        A: Actor<E> + Handler<E, M>,
+        M: Message,
+        F: Fn(E) -> Option<M> + Send + 'static,
+    {
+        let actor_ref = self
+            .system
+            .get_actor::<A>(&self.path)
+            .await
+            .ok_or_else(|| ActorError::NotFound(self.path.clone()))?;
+        let subscription = self.system.subscribe_actor(&actor_ref, policy, map);
+        self.subscriptions.push(subscription.clone());
+        Ok(subscription)
+    }
+
+    /// Campaigns for the lease in the store, so that of the actors
+    /// campaigning for the same lease exactly one is the leader at a time,
+    /// e.g. to have one of several identical actors perform a periodic duty.
+    /// The lease is acquired for the time to live, and renewed three times
+    /// per time to live while the actor holds it; when the leader stops, or
+    /// stops renewing, another candidate takes over once the lease expired.
+    /// Changes of leadership are published as [`crate::LeadershipChange`]s,
+    /// see [`ActorSystem::leadership_changes()`]. The campaign ends when the
+    /// actor stops. For example, in [`Actor::pre_start()`]:
+    /// ```
+    /// # use tiny_tokio_actor::*;
+    /// # use std::{sync::Arc, time::Duration};
+    /// # #[derive(Clone, Debug)]
+    /// # struct TestEvent;
+    /// # impl SystemEvent for TestEvent {}
+    /// struct Janitor {
+    ///     leases: Arc<LocalLeases>,
+    ///     leadership: Option<Leadership>,
+    /// }
+    ///
+    /// #[async_trait]
+    /// impl Actor<TestEvent> for Janitor {
+    ///     async fn pre_start(&mut self, ctx: &mut ActorContext<TestEvent>) -> Result<(), ActorError> {
+    ///         let ttl = Duration::from_secs(10);
+    ///         self.leadership = Some(ctx.campaign("cleanup", self.leases.clone(), ttl));
+    ///         Ok(())
+    ///     }
+    /// }
+    /// ```
+    /// The actor then only performs the duty while
+    /// [`Leadership::is_leader()`] is true.
+    pub fn campaign<S: LeaseStore>(&self, lease: &str, store: Arc<S>, ttl: Duration) -> Leadership {
+        election::campaign(
+            self.system.clone(),
+            self.path.clone(),
+            lease,
+            store,
+            ttl,
+            self.cancellation.child_token(),
+        )
+    }
+
+    /// Ends all subscriptions made through [`ActorContext::subscribe()`].
+    pub fn unsubscribe(&mut self) {
+        for subscription in self.subscriptions.drain(..) {
+            subscription.cancel();
+        }
+    }
+
+    /// Takes the state handed over by the previous instance of this actor, if
+    /// any and if it is of type `S`. This is only available during
+    /// [`Actor::pre_start()`] of an instance that replaced another one.
+    pub fn take_handoff<S: Any>(&mut self) -> Option<S> {
+        match self.handoff.take()?.downcast::<S>() {
+            Ok(state) => Some(*state),
+            Err(handoff) => {
+                self.handoff = Some(handoff);
+                None
+            }
+        }
+    }
+
     /// Create a child actor under this actor.
     pub async fn create_child<A: Actor<E>>(
         &self,
         name: &str,
         actor: A,
     ) -> Result<ActorRef<E, A>, ActorError> {
+        validate_name(name)?;
+        let path = self.path.clone() / name;
+        self.system.create_actor_path(path, actor).await
+    }
+
+    /// Create a child actor under this actor, using the given configuration.
+    pub async fn create_child_with_config<A: Actor<E>>(
+        &self,
+        name: &str,
+        actor: A,
+        config: ActorConfig,
+    ) -> Result<ActorRef<E, A>, ActorError> {
+        validate_name(name)?;
+        let path = self.path.clone() / name;
+        self.system
+            .create_actor_path_with_config(path, actor, config)
+            .await
+    }
+
+    /// Create a child actor under this actor from its props, see [`Props`].
+    pub async fn create_child_from_props<A: Actor<E>>(
+        &self,
+        name: &str,
+        props: Props<A>,
+    ) -> Result<ActorRef<E, A>, ActorError> {
+        validate_name(name)?;
         let path = self.path.clone() / name;
+        self.system.create_actor_path_from_props(path, props).await
+    }
+
+    /// Create a child actor under this actor with a generated unique name.
+    pub async fn spawn_anonymous<A: Actor<E>>(
+        &self,
+        actor: A,
+    ) -> Result<ActorRef<E, A>, ActorError> {
+        let path = self.path.clone() / &self.system.anonymous_name();
         self.system.create_actor_path(path, actor).await
     }
 
@@ -46,16 +475,225 @@ impl<E: SystemEvent> ActorContext<E> {
         A: Actor<E>,
         F: FnOnce() -> A,
     {
+        validate_name(name)?;
         let path = self.path.clone() / name;
         self.system.get_or_create_actor_path(&path, actor_fn).await
     }
 
+    /// Retrieve the paths of all children running under this actor.
+    pub async fn children(&self) -> Vec<ActorPath> {
+        let mut children = self
+            .system
+            .find_paths(|path| path.is_child_of(&self.path))
+            .await;
+        children.sort_unstable();
+        children
+    }
+
+    /// Send a message to all children of type `A` running under this actor.
+    /// Returns the number of children the message was sent to.
+    pub async fn broadcast_children<A, M>(&self, msg: M) -> usize
+    where
+        A: Actor<E> + Handler<E, M>,
+        M: Message,
+    {
+        let children = self
+            .system
+            .find_actors::<A, _>(|path| path.is_child_of(&self.path))
+            .await;
+        children
+            .iter()
+            .filter(|child| child.tell(msg.clone()).is_ok())
+            .count()
+    }
+
+    /// Watches the given actor, applying `on_terminate` to this actor once the
+    /// watched actor stops for any reason. For example, with
+    /// [`OnTerminate::StopSelf`] this actor is bound to the lifetime of the
+    /// other one.
+    pub fn watch_with<W: Actor<E>>(&self, actor: &ActorRef<E, W>, on_terminate: OnTerminate) {
+        let watched = actor.control().cancellation_token().clone();
+        let own = self.cancellation.clone();
+        let system = self.system.clone();
+        let path = self.path.clone();
+        let watched_path = actor.path().clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = watched.cancelled() => match on_terminate {
+                    OnTerminate::StopSelf => {
+                        log::debug!(
+                            "Stopping actor '{}' as watched actor '{}' terminated.",
+                            &path,
+                            &watched_path
+                        );
+                        system.terminate(&path).await;
+                    }
+                },
+                _ = own.cancelled() => {}
+            }
+        });
+    }
+
     /// Stops the child actor
     pub async fn stop_child(&self, name: &str) {
         let path = self.path.clone() / name;
         self.system.stop_actor(&path).await;
     }
 
+    /// Asks another actor from within a handler. Unlike [`ActorRef::ask()`]
+    /// this rejects asks to the actor itself with an
+    /// `Err(ActorError::SelfAsk)`, as the response could only be produced
+    /// after the current handler returns, so awaiting it would block the
+    /// actor forever. Asks that go around in a circle through other actors are
+    /// not detected; use a timeout or [`ActorContext::pipe_to()`] for those.
+    pub async fn ask<A, M>(&self, actor: &ActorRef<E, A>, msg: M) -> Result<M::Response, ActorError>
+    where
+        A: Actor<E> + Handler<E, M>,
+        M: Message,
+    {
+        self.check_not_self(actor)?;
+        actor.ask(msg).await
+    }
+
+    /// Asks another actor from within a handler like [`ActorContext::ask()`],
+    /// expecting a response within the given timeout instead of the configured
+    /// one.
+    pub async fn ask_with_timeout<A, M>(
+        &self,
+        actor: &ActorRef<E, A>,
+        msg: M,
+        timeout: Duration,
+    ) -> Result<M::Response, ActorError>
+    where
+        A: Actor<E> + Handler<E, M>,
+        M: Message,
+    {
+        self.check_not_self(actor)?;
+        actor.ask_with_timeout(msg, Some(timeout)).await
+    }
+
+    fn check_not_self<A: Actor<E>>(&self, actor: &ActorRef<E, A>) -> Result<(), ActorError> {
+        if actor.path() == &self.path {
+            return Err(ActorError::SelfAsk(self.path.clone()));
+        }
+        Ok(())
+    }
+
+    /// Runs the future in the background and tells its output to `target`,
+    /// so a handler can wait for something, such as the response of an ask,
+    /// without blocking the actor. The target is typically the actor itself,
+    /// which then handles the outcome as a regular message. For example:
+    /// ```
+    /// # use tiny_tokio_actor::*;
+    /// # #[derive(Clone, Debug)]
+    /// # struct TestEvent;
+    /// # impl SystemEvent for TestEvent {}
+    /// # #[derive(Clone)]
+    /// # struct Lookup(String);
+    /// # impl Message for Lookup { type Response = Option<u64>; }
+    /// # struct Directory;
+    /// # impl Actor<TestEvent> for Directory {}
+    /// # #[async_trait]
+    /// # impl Handler<TestEvent, Lookup> for Directory {
+    /// #     async fn handle(&mut self, _msg: Lookup, _ctx: &mut ActorContext<TestEvent>) -> Option<u64> { None }
+    /// # }
+    /// #[derive(Clone)]
+    /// struct Resolved(Result<Option<u64>, String>);
+    ///
+    /// impl Message for Resolved {
+    ///     type Response = ();
+    /// }
+    ///
+    /// async fn resolve(
+    ///     ctx: &mut ActorContext<TestEvent>,
+    ///     directory: ActorRef<TestEvent, Directory>,
+    ///     me: ActorRef<TestEvent, impl Actor<TestEvent> + Handler<TestEvent, Resolved>>,
+    /// ) {
+    ///     ctx.pipe_to(
+    ///         async move {
+    ///             let response = directory.ask(Lookup("alice".into())).await;
+    ///             Resolved(response.map_err(|error| error.to_string()))
+    ///         },
+    ///         &me,
+    ///     );
+    /// }
+    /// ```
+    ///
+    /// The future is dropped if this actor stops before it completes.
+    pub fn pipe_to<F, A>(&self, future: F, target: &ActorRef<E, A>)
+    where
+        F: std::future::Future + Send + 'static,
+        F::Output: Message,
+        A: Actor<E> + Handler<E, F::Output>,
+    {
+        let own = self.cancellation.clone();
+        let target = target.clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                msg = future => {
+                    if let Err(error) = target.tell(msg) {
+                        log::debug!("Failed to pipe to actor '{}': {}", target.path(), error);
+                    }
+                }
+                _ = own.cancelled() => {}
+            }
+        });
+    }
+
+    /// Runs the tasks that `spawn` spawns on the scope concurrently, and
+    /// resolves to their outputs, in the order they were spawned, once all
+    /// of them completed. So a handler can fan out work without leaking
+    /// detached tasks: when the actor is asked to stop in the meantime, the
+    /// tasks are aborted and an `ActorError::Terminated` is returned, and
+    /// when the handler is dropped so are its tasks. A panic in a task is
+    /// resumed in the handler. For example:
+    /// ```
+    /// # use tiny_tokio_actor::*;
+    /// # #[derive(Clone, Debug)]
+    /// # struct TestEvent;
+    /// # impl SystemEvent for TestEvent {}
+    /// #[derive(Clone, Debug)]
+    /// struct FetchAll(Vec<String>);
+    ///
+    /// impl Message for FetchAll {
+    ///     type Response = Result<Vec<usize>, ActorError>;
+    /// }
+    ///
+    /// struct Fetcher;
+    ///
+    /// impl Actor<TestEvent> for Fetcher {}
+    ///
+    /// #[async_trait]
+    /// impl Handler<TestEvent, FetchAll> for Fetcher {
+    ///     async fn handle(
+    ///         &mut self,
+    ///         msg: FetchAll,
+    ///         ctx: &mut ActorContext<TestEvent>,
+    ///     ) -> Result<Vec<usize>, ActorError> {
+    ///         ctx.scope(|scope| {
+    ///             for url in msg.0 {
+    ///                 scope.spawn(async move { url.len() });
+    ///             }
+    ///         })
+    ///         .await
+    ///     }
+    /// }
+    /// ```
+    pub async fn scope<T, F>(&self, spawn: F) -> Result<Vec<T>, ActorError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut TaskScope<T>),
+    {
+        let mut scope = TaskScope::new();
+        spawn(&mut scope);
+        tokio::select! {
+            outputs = scope.join() => Ok(outputs),
+            _ = self.cancellation.cancelled() => {
+                Err(ActorError::Terminated(self.path.clone(), self.incarnation))
+            }
+        }
+    }
+
     pub(crate) async fn restart<A>(
         &mut self,
         actor: &mut A,
@@ -64,6 +702,7 @@ impl<E: SystemEvent> ActorContext<E> {
     where
         A: Actor<E>,
     {
+        self.system.record_restart(&self.path);
         actor.pre_restart(self, error).await
     }
 }
@@ -73,6 +712,14 @@ pub trait Message: Clone + Send + Sync + 'static {
     /// response an actor should give when it receives this message. If no response is
     /// required, use `()`.
     type Response: Send + Sync + 'static;
+
+    /// An estimate of the memory held by the message in bytes, used to bound
+    /// mailboxes by size (see [`ActorConfig::with_mailbox_bytes()`]). Defaults
+    /// to the size of the message type itself; override it for messages that
+    /// own data on the heap, such as buffers or collections.
+    fn size_hint(&self) -> usize {
+        std::mem::size_of::<Self>()
+    }
 }
 
 /// Defines what the actor does with a message.
@@ -132,7 +779,7 @@ pub trait Handler<E: SystemEvent, M: Message>: Send + Sync {
 /// }
 /// ```
 #[async_trait]
-pub trait Actor<E: SystemEvent>: Send + Sync + 'static {
+pub trait Actor<E: SystemEvent = NoEvent>: Send + Sync + 'static {
     /// Defines the supervision strategy to use for this actor. By default it is
     /// `Stop` which simply stops the actor if an error occurs at startup. You
     /// can also set this to [`SupervisionStrategy::Retry`] with a chosen
@@ -160,18 +807,69 @@ pub trait Actor<E: SystemEvent>: Send + Sync + 'static {
 
     /// Override this function if you like to perform work when the actor is stopped
     async fn post_stop(&mut self, _ctx: &mut ActorContext<E>) {}
+
+    /// Override this function to handle the failure of a child actor that could
+    /// not be started, even after applying its supervision strategy. The failed
+    /// child and its descendants have already been stopped and removed, so the
+    /// child can for example be created again, possibly with a different
+    /// implementation, or the subtree of this actor restarted. Returning an
+    /// error fails this actor as well, escalating the failure to its parent.
+    /// By default the failure is logged and otherwise ignored.
+    async fn child_failed(
+        &mut self,
+        _ctx: &mut ActorContext<E>,
+        failure: ChildFailed,
+    ) -> Result<(), ActorError> {
+        log::warn!(
+            "Child actor '{}' failed: {:?}",
+            failure.path,
+            failure.reason
+        );
+        Ok(())
+    }
+
+    /// Override this function to hand over state to the new instance when this
+    /// actor is replaced (see [`ActorSystem::replace_actor()`]). It is called
+    /// before [`Actor::post_stop()`], and the new instance can retrieve the
+    /// state in its `pre_start()` through [`ActorContext::take_handoff()`].
+    fn handoff(&mut self) -> Option<Handoff> {
+        None
+    }
 }
 
 /// A clonable actor reference. It basically holds a Sender that can send messages
 /// to the mailbox (receiver) of the actor.
+///
+/// The mailbox is a queue, so messages are handled in the order they were
+/// enqueued. As a result, messages sent one after the other by the same task
+/// are always handled in the order they were sent, whether through
+/// [`ActorRef::tell()`], [`ActorRef::ordered_tell()`] or [`ActorRef::ask()`].
+/// A `tell` is enqueued right away, an `ask` or `ordered_tell` once its
+/// future is first polled; when a bounded mailbox is full they wait for room
+/// in line with all other waiting senders. There is no ordering between
+/// messages sent concurrently by different tasks.
+///
+/// All methods take `&self`, so there is no need to clone a reference per
+/// call site: a single reference, e.g. shared through an `Arc`, can be used
+/// by many tasks at once. Asks through the same reference do not wait for each
+/// other; they are pipelined into the mailbox and each gets its own response.
 pub struct ActorRef<E: SystemEvent, A: Actor<E>> {
     path: ActorPath,
+    incarnation: u64,
     sender: handler::HandlerRef<E, A>,
+    ask_timeout: Option<Duration>,
+    guard: Option<Arc<RefGuard<E>>>,
 }
 
 impl<E: SystemEvent, A: Actor<E>> Clone for ActorRef<E, A> {
     fn clone(&self) -> Self {
-        Self { path: self.path.clone(), sender: self.sender.clone() }
+        Self {
+            path: self.path.clone(),
+            incarnation: self.incarnation,
+            sender: self.sender.clone(),
+            ask_timeout: self.ask_timeout,
+            guard: self.guard.clone(),
+        }
     }
 }
 
@@ -181,48 +879,378 @@ impl<E: SystemEvent, A: Actor<E>> ActorRef<E, A> {
         &self.path
     }
 
+    /// Get the incarnation id of this actor. Every actor created by the system
+    /// gets a new id, so an actor that is stopped and created again at the same
+    /// path can be told apart from the original. Replacing or restarting an
+    /// actor keeps its incarnation.
+    pub fn incarnation(&self) -> u64 {
+        self.incarnation
+    }
+
     /// Get the path of this actor
     #[deprecated(since = "0.2.3", note = "please use `path` instead")]
     pub fn get_path(&self) -> &ActorPath {
         &self.path
     }
 
-    /// Fire and forget sending of messages to this actor.
+    /// Fire and forget sending of messages to this actor. If the actor has a
+    /// bounded mailbox that is full, an `Err(ActorError::MailboxFull)` is returned.
     pub fn tell<M>(&self, msg: M) -> Result<(), ActorError>
     where
         M: Message,
         A: Handler<E, M>,
     {
-        self.sender.tell(msg)
+        self.sender
+            .tell(msg)
+            .map_err(|error| self.terminated(error))
     }
 
-    /// Send a message to an actor, expecting a response.
+    /// Enqueues the message if the mailbox has room right now, failing with
+    /// `Err(ActorError::MailboxFull)` otherwise. This never awaits nor
+    /// requires a tokio context, so it can be used from synchronous code such
+    /// as `Drop` implementations or callbacks on threads that are not managed
+    /// by tokio.
+    pub fn try_tell<M>(&self, msg: M) -> Result<(), ActorError>
+    where
+        M: Message,
+        A: Handler<E, M>,
+    {
+        self.tell(msg)
+    }
+
+    /// Prepares a transaction with an actor guarding a resource, see
+    /// [`Participant`]. The transaction is aborted if it is neither committed
+    /// nor aborted within the timeout, or when it is dropped.
+    pub async fn prepare(
+        &self,
+        request: A::Request,
+        timeout: Duration,
+    ) -> Result<Transaction<E, A>, ActorError>
+    where
+        A: Participant<E>,
+    {
+        Transaction::prepare(self, request, timeout).await
+    }
+
+    /// Fire and forget sending of a message of which only the latest one per
+    /// key matters, see [`ConflationKey`]. If a message with the same key is
+    /// still waiting in the mailbox, it is replaced by this one, which takes
+    /// its place in line; otherwise this behaves like [`ActorRef::tell()`].
+    /// So an actor that falls behind a stream of updates skips to the latest
+    /// one per key instead of working through all of them.
+    pub fn tell_latest<M>(&self, msg: M) -> Result<(), ActorError>
+    where
+        M: ConflationKey,
+        A: Handler<E, M>,
+    {
+        self.sender
+            .tell_latest(msg)
+            .map_err(|error| self.terminated(error))
+    }
+
+    /// Sends the message without expecting a response, waiting for room if the
+    /// actor has a bounded mailbox that is full. Unlike [`ActorRef::tell()`],
+    /// which then fails with `ActorError::MailboxFull` and leaves retrying to
+    /// the caller, this waits in line, so all messages of a producer are
+    /// delivered in the order they were sent.
+    pub async fn ordered_tell<M>(&self, msg: M) -> Result<(), ActorError>
+    where
+        M: Message,
+        A: Handler<E, M>,
+    {
+        self.sender
+            .ordered_tell(msg)
+            .await
+            .map_err(|error| self.terminated(error))
+    }
+
+    /// Send a message to an actor, expecting a response. If the actor has a
+    /// bounded mailbox that is full, this waits until there is room. When the
+    /// actor (or the actor system) is configured with an ask timeout, an
+    /// `ActorError::Timeout` is returned if no response arrived in time.
+    /// Failed asks are logged with the path of the actor, the message type
+    /// and how long the ask took; [`ActorRef::ask_traced()`] returns those
+    /// with the error instead.
+    ///
+    /// An actor that awaits an ask to itself never gets a response, as it can
+    /// only handle the ask once the current handler returns. From within a
+    /// handler, use [`ActorContext::ask()`] which rejects such asks.
     pub async fn ask<M>(&self, msg: M) -> Result<M::Response, ActorError>
     where
         M: Message,
         A: Handler<E, M>,
     {
-        self.sender.ask(msg).await
+        self.ask_within(msg, self.ask_timeout).await
+    }
+
+    /// Same as [`ActorRef::ask()`], but a failed ask returns an [`AskError`]
+    /// naming the actor, the message type and how long the ask took, next to
+    /// the `ActorError` that [`ActorRef::ask()`] would have returned. An
+    /// `AskError` converts into that `ActorError`, so `?` still works in
+    /// functions returning an `ActorError`.
+    pub async fn ask_traced<M>(&self, msg: M) -> Result<M::Response, AskError>
+    where
+        M: Message,
+        A: Handler<E, M>,
+    {
+        self.trace_ask_within::<M, _>(self.sender.ask(msg), self.ask_timeout)
+            .await
+    }
+
+    /// Same as [`ActorRef::ask()`], passing the response through `f` before
+    /// returning it.
+    pub async fn ask_map<M, R, F>(&self, msg: M, f: F) -> Result<R, ActorError>
+    where
+        M: Message,
+        A: Handler<E, M>,
+        F: FnOnce(M::Response) -> R,
+    {
+        self.ask(msg).await.map(f)
+    }
+
+    /// Send a message to an actor, expecting a response within the given
+    /// timeout instead of the configured one. Passing `None` waits
+    /// indefinitely.
+    pub async fn ask_with_timeout<M>(
+        &self,
+        msg: M,
+        timeout: Option<Duration>,
+    ) -> Result<M::Response, ActorError>
+    where
+        M: Message,
+        A: Handler<E, M>,
+    {
+        self.ask_within(msg, timeout).await
+    }
+
+    async fn ask_within<M>(
+        &self,
+        msg: M,
+        timeout: Option<Duration>,
+    ) -> Result<M::Response, ActorError>
+    where
+        M: Message,
+        A: Handler<E, M>,
+    {
+        self.trace_ask_within::<M, _>(self.sender.ask(msg), timeout)
+            .await
+            .map_err(ActorError::from)
+    }
+
+    /// Awaits the response of an ask within the configured ask timeout.
+    pub(crate) async fn finish_ask<M, F>(&self, ask: F) -> Result<M::Response, ActorError>
+    where
+        M: Message,
+        F: std::future::Future<Output = Result<M::Response, ActorError>>,
+    {
+        self.trace_ask_within::<M, _>(ask, self.ask_timeout)
+            .await
+            .map_err(ActorError::from)
+    }
+
+    /// Awaits the response of an ask within the timeout, adding the context of
+    /// the ask to its error.
+    async fn trace_ask_within<M, F>(
+        &self,
+        ask: F,
+        timeout: Option<Duration>,
+    ) -> Result<M::Response, AskError>
+    where
+        M: Message,
+        F: std::future::Future<Output = Result<M::Response, ActorError>>,
+    {
+        let started = Instant::now();
+        // Asks within a deadline give up once it passes
+        let timeout = match (timeout, Deadline::current()) {
+            (Some(timeout), Some(deadline)) => Some(timeout.min(deadline.remaining())),
+            (timeout, deadline) => timeout.or(deadline.map(|deadline| deadline.remaining())),
+        };
+        match timeout {
+            Some(duration) => {
+                tokio::time::timeout(duration, ask)
+                    .await
+                    .unwrap_or(Err(ActorError::Timeout))
+            }
+            None => ask.await,
+        }
+        .map_err(|error| {
+            let error = AskError {
+                path: self.path.clone(),
+                message_type: std::any::type_name::<M>(),
+                elapsed: started.elapsed(),
+                cause: self.terminated(error),
+            };
+            // Timeouts point at a slow or stuck actor, other failures mostly
+            // at one that stopped
+            let level = match error.cause {
+                ActorError::Timeout => log::Level::Warn,
+                _ => log::Level::Debug,
+            };
+            actor_log!(
+                self.sender.monitor(),
+                level,
+                message_type = error.message_type;
+                "{}",
+                error
+            );
+            error
+        })
+    }
+
+    /// Lists the first `n` messages waiting in the mailbox of this actor,
+    /// oldest first, by type name and the time they were sent. Meant for
+    /// debugging, e.g. to see what a stuck actor has yet to handle. Requires
+    /// the `inspect` feature.
+    #[cfg(feature = "inspect")]
+    pub fn peek_mailbox(&self, n: usize) -> Vec<crate::QueuedMessage> {
+        self.sender.peek_mailbox(n)
+    }
+
+    /// Get a handle to ask this actor messages of type `M`, reusing the state
+    /// needed for the responses across asks. See [`Requester`].
+    pub fn requester<M>(&self) -> Requester<E, A, M>
+    where
+        M: Message,
+        A: Handler<E, M>,
+    {
+        Requester::new(self)
+    }
+
+    /// Get a handle to send messages to this actor from threads that are not
+    /// managed by tokio.
+    pub fn sync_bridge(&self) -> SyncBridge<E, A> {
+        SyncBridge::new(self)
+    }
+
+    /// Suspends message processing of this actor. Messages that arrive while
+    /// the actor is suspended are kept in its mailbox. The returned future
+    /// resolves once any message that was being handled has completed.
+    pub async fn suspend(&self) -> Result<(), ActorError> {
+        self.sender.control().suspend().await
+    }
+
+    /// Checks that the actor is responsive, returning how long it took to
+    /// answer. The ping is answered by the runner of the actor in between
+    /// messages, without involving any handler, so it tells a healthy actor
+    /// apart from one whose mailbox is open but which is stuck in a handler.
+    /// A suspended actor still answers. Returns an `ActorError::Timeout` if
+    /// no answer arrived within the timeout, and an `ActorError::Terminated`
+    /// if the actor is not running.
+    pub async fn ping(&self, timeout: Duration) -> Result<Duration, ActorError> {
+        let started = Instant::now();
+        match tokio::time::timeout(timeout, self.sender.control().ping()).await {
+            Ok(Ok(())) => Ok(started.elapsed()),
+            Ok(Err(error)) => Err(self.terminated(error)),
+            Err(_elapsed) => Err(ActorError::Timeout),
+        }
+    }
+
+    /// Resumes message processing of a suspended actor.
+    pub fn resume(&self) -> Result<(), ActorError> {
+        self.sender.control().resume()
+    }
+
+    /// Stops the actor once it has handled all messages already in its
+    /// mailbox. New messages are rejected right away with an
+    /// `ActorError::MailboxClosed`. Resolves once the actor has stopped, which
+    /// also stops its children. A suspended actor drains its mailbox once it
+    /// is resumed.
+    ///
+    /// Do not await this from within the actor itself, as it can only stop
+    /// once its current handler completes.
+    pub async fn drain(&self) -> Result<(), ActorError> {
+        self.sender
+            .control()
+            .drain()
+            .await
+            .map_err(|error| self.terminated(ActorError::SendError(error.to_string())))
     }
 
     /// Checks if the actor message box is still open. If it is closed, the actor
-    /// is not running.
+    /// is not running or is draining its mailbox.
     pub fn is_closed(&self) -> bool {
         self.sender.is_closed()
     }
 
-    pub(crate) fn new(path: ActorPath, sender: handler::MailboxSender<E, A>) -> Self {
-        let handler = handler::HandlerRef::new(sender);
+    pub(crate) fn new(
+        path: ActorPath,
+        incarnation: u64,
+        sender: handler::MailboxSender<E, A>,
+        control: handler::ControlRef,
+        monitor: MailboxMonitor,
+        ask_timeout: Option<Duration>,
+    ) -> Self {
+        let handler = handler::HandlerRef::new(sender, control, monitor);
         ActorRef {
             path,
+            incarnation,
             sender: handler,
+            ask_timeout,
+            guard: None,
+        }
+    }
+
+    /// This reference, keeping the actor running for as long as the guard is
+    /// referenced, see [`ActorConfig::with_stop_when_unreferenced()`].
+    pub(crate) fn with_guard(mut self, guard: Arc<RefGuard<E>>) -> Self {
+        self.guard = Some(guard);
+        self
+    }
+
+    pub(crate) fn control(&self) -> &handler::ControlRef {
+        self.sender.control()
+    }
+
+    /// Turns a failed send to an actor that is no longer running into an
+    /// `ActorError::Terminated`, or into an `ActorError::MailboxClosed` if it
+    /// is draining its mailbox.
+    fn terminated(&self, error: ActorError) -> ActorError {
+        match error {
+            ActorError::SendError(_) if self.control().is_draining() => {
+                ActorError::MailboxClosed(self.path.clone())
+            }
+            ActorError::SendError(_) if self.is_closed() => {
+                ActorError::Terminated(self.path.clone(), self.incarnation)
+            }
+            error => error,
         }
     }
 }
 
 impl<E: SystemEvent, A: Actor<E>> std::fmt::Debug for ActorRef<E, A> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.path)
+        write!(f, "{}#{}", self.path, self.incarnation)
+    }
+}
+
+/// Actor references compare by the path and incarnation of the actor they
+/// refer to, so they can be stored in sets or used as map keys.
+impl<E: SystemEvent, A: Actor<E>> PartialEq for ActorRef<E, A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path && self.incarnation == other.incarnation
+    }
+}
+
+impl<E: SystemEvent, A: Actor<E>> Eq for ActorRef<E, A> {}
+
+impl<E: SystemEvent, A: Actor<E>> std::hash::Hash for ActorRef<E, A> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.path.hash(state);
+        self.incarnation.hash(state);
+    }
+}
+
+impl<E: SystemEvent, A: Actor<E>> PartialOrd for ActorRef<E, A> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<E: SystemEvent, A: Actor<E>> Ord for ActorRef<E, A> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.path
+            .cmp(&other.path)
+            .then(self.incarnation.cmp(&other.incarnation))
     }
 }
 
@@ -231,14 +1259,56 @@ pub enum ActorError {
     #[error("Actor exists")]
     Exists(ActorPath),
 
+    #[error("Actor not found")]
+    NotFound(ActorPath),
+
     #[error("Actor creation failed")]
     CreateError(String),
 
     #[error("Sending message failed")]
     SendError(String),
 
+    #[error("Mailbox full")]
+    MailboxFull,
+
+    #[error("Mailbox closed")]
+    MailboxClosed(ActorPath),
+
+    #[error("Ask timed out")]
+    Timeout,
+
+    #[error("Actor terminated")]
+    Terminated(ActorPath, u64),
+
     #[error("Actor runtime error")]
     RuntimeError(anyhow::Error),
+
+    #[error("Actor panicked")]
+    Panicked(CrashReport),
+
+    #[error("Actor asked itself")]
+    SelfAsk(ActorPath),
+
+    #[error("Transaction {0} aborted")]
+    TransactionAborted(TxToken),
+
+    #[error("Sending {message_type} to actor '{path}' is not authorized")]
+    Unauthorized {
+        path: ActorPath,
+        message_type: &'static str,
+    },
+
+    #[error("Quota {quota:?} of {scope} exceeded")]
+    QuotaExceeded { scope: QuotaScope, quota: Quota },
+
+    #[error("Actor '{0}' is disconnected")]
+    Disconnected(ActorPath),
+
+    #[error("No responder for {0}")]
+    NoResponder(&'static str),
+
+    #[error("No quorum of {needed} among {asked} actors asked")]
+    NoQuorum { asked: usize, needed: usize },
 }
 
 impl ActorError {
@@ -249,3 +1319,26 @@ impl ActorError {
         Self::RuntimeError(anyhow::Error::new(error))
     }
 }
+
+/// A failed ask as returned by [`ActorRef::ask_traced()`]: the error of the
+/// ask together with the actor and message type it was for and how long it
+/// took.
+#[derive(Error, Debug)]
+#[error("Ask {message_type} to actor '{path}' failed after {elapsed:?}: {cause}")]
+pub struct AskError {
+    /// The path of the actor asked.
+    pub path: ActorPath,
+    /// The type name of the message.
+    pub message_type: &'static str,
+    /// How long the ask took before it failed.
+    pub elapsed: Duration,
+    /// Why the ask failed, as returned by [`ActorRef::ask()`].
+    #[source]
+    pub cause: ActorError,
+}
+
+impl From<AskError> for ActorError {
+    fn from(error: AskError) -> Self {
+        error.cause
+    }
+}
@@ -75,6 +75,54 @@ impl ActorPath {
     }
 }
 
+/// A pattern that matches actor paths. Segments are matched literally, except
+/// for `*` which matches any single segment and `**` which matches any number
+/// of segments (including none). For example `/user/ingest/*` matches
+/// `/user/ingest/worker-1` but not `/user/ingest/worker-1/child`, whereas
+/// `/user/ingest/**` matches both.
+#[derive(Clone, Hash, Eq, PartialEq)]
+pub struct PathPattern(Vec<String>);
+
+impl PathPattern {
+    /// Checks if the given path matches this pattern
+    pub fn matches(&self, path: &ActorPath) -> bool {
+        Self::matches_tokens(&self.0, &path.0)
+    }
+
+    fn matches_tokens(pattern: &[String], tokens: &[String]) -> bool {
+        match pattern.split_first() {
+            None => tokens.is_empty(),
+            Some((first, rest)) if first == "**" => {
+                (0..=tokens.len()).any(|skip| Self::matches_tokens(rest, &tokens[skip..]))
+            }
+            Some((first, rest)) => match tokens.split_first() {
+                Some((token, remaining)) => {
+                    (first == "*" || first == token) && Self::matches_tokens(rest, remaining)
+                }
+                None => false,
+            },
+        }
+    }
+}
+
+impl From<&str> for PathPattern {
+    fn from(str: &str) -> Self {
+        PathPattern(ActorPath::from(str).0)
+    }
+}
+
+impl std::fmt::Display for PathPattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "/{}", self.0.join("/"))
+    }
+}
+
+impl std::fmt::Debug for PathPattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "/{}", self.0.join("/"))
+    }
+}
+
 impl From<&str> for ActorPath {
     fn from(str: &str) -> Self {
         let tokens: Vec<String> = str
@@ -282,4 +330,25 @@ mod tests {
         println!("{}", &child);
         assert!(path.is_parent_of(&child))
     }
+
+    #[test]
+    fn test_pattern_single_segment() {
+        let pattern = PathPattern::from("/user/ingest/*");
+        assert!(pattern.matches(&ActorPath::from("/user/ingest/worker-1")));
+        assert!(!pattern.matches(&ActorPath::from("/user/ingest")));
+        assert!(!pattern.matches(&ActorPath::from("/user/ingest/worker-1/child")));
+        assert!(!pattern.matches(&ActorPath::from("/user/other/worker-1")));
+    }
+
+    #[test]
+    fn test_pattern_any_depth() {
+        let pattern = PathPattern::from("/user/**/sensor");
+        assert!(pattern.matches(&ActorPath::from("/user/sensor")));
+        assert!(pattern.matches(&ActorPath::from("/user/acme/building/sensor")));
+        assert!(!pattern.matches(&ActorPath::from("/user/acme/building")));
+        let all = PathPattern::from("/user/**");
+        assert!(all.matches(&ActorPath::from("/user")));
+        assert!(all.matches(&ActorPath::from("/user/acme/building")));
+        assert_eq!(all.to_string(), "/user/**");
+    }
 }
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use super::ActorConfig;
+
+/// Creates the instances of an actor, see [`Props`].
+pub(crate) type Factory<A> = Arc<dyn Fn() -> A + Send + Sync>;
+
+/// How to create an actor: a factory for its instances together with its
+/// configuration. An actor created from props through
+/// [`ActorSystem::create_actor_from_props()`](crate::ActorSystem::create_actor_from_props)
+/// is restarted with a fresh instance from the factory, instead of keeping
+/// the state of the instance that failed. The fresh instance then runs its
+/// `pre_restart`. For example:
+/// ```
+/// # use tiny_tokio_actor::*;
+/// # #[derive(Clone, Debug)]
+/// # struct TestEvent;
+/// # impl SystemEvent for TestEvent {}
+/// #[derive(Default)]
+/// struct Session {
+///     requests: Vec<String>,
+/// }
+///
+/// impl Actor<TestEvent> for Session {}
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), ActorError> {
+/// let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+/// let config = ActorConfig::default().with_label("role", "session");
+/// let props = Props::new(Session::default).with_config(config);
+/// let session = system.create_actor_from_props("session", props).await?;
+/// system.restart_subtree(session.path()).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Props<A> {
+    factory: Factory<A>,
+    config: ActorConfig,
+}
+
+impl<A> Props<A> {
+    /// Props creating instances through the factory, with the default
+    /// configuration.
+    pub fn new<F>(factory: F) -> Self
+    where
+        F: Fn() -> A + Send + Sync + 'static,
+    {
+        Props {
+            factory: Arc::new(factory),
+            config: ActorConfig::default(),
+        }
+    }
+
+    /// Use the given configuration for the actor.
+    pub fn with_config(mut self, config: ActorConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// The configuration of the actor
+    pub fn config(&self) -> &ActorConfig {
+        &self.config
+    }
+
+    /// Creates a new instance of the actor.
+    pub fn create(&self) -> A {
+        (self.factory)()
+    }
+
+    pub(crate) fn into_parts(self) -> (Factory<A>, ActorConfig) {
+        (self.factory, self.config)
+    }
+}
+
+impl<A> Clone for Props<A> {
+    fn clone(&self) -> Self {
+        Props {
+            factory: self.factory.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
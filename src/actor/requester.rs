@@ -0,0 +1,224 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+use tokio::sync::{oneshot, Notify};
+
+use crate::system::SystemEvent;
+
+use super::{Actor, ActorError, ActorPath, ActorRef, Handler, Message};
+
+/// Where the response to an ask is sent.
+pub(crate) enum Rsvp<R> {
+    Channel(oneshot::Sender<R>),
+    Slot(SlotSender<R>),
+}
+
+impl<R> Rsvp<R> {
+    /// Sends the response, handing it back if nobody is waiting for it.
+    pub fn send(self, response: R) -> Result<(), R> {
+        match self {
+            Rsvp::Channel(sender) => sender.send(response),
+            Rsvp::Slot(sender) => {
+                sender.send(response);
+                Ok(())
+            }
+        }
+    }
+}
+
+enum SlotState<R> {
+    Waiting,
+    Replied(R),
+    Dropped,
+}
+
+/// A reusable alternative to a oneshot channel, see [`Requester`].
+struct ReplySlot<R> {
+    state: Mutex<SlotState<R>>,
+    ready: Notify,
+}
+
+impl<R> ReplySlot<R> {
+    fn new() -> Self {
+        ReplySlot {
+            state: Mutex::new(SlotState::Waiting),
+            ready: Notify::new(),
+        }
+    }
+
+    fn set(&self, state: SlotState<R>) {
+        *self.state.lock().unwrap() = state;
+        self.ready.notify_one();
+    }
+
+    /// Waits for the response, failing if the message was dropped without
+    /// one. Leaves the slot ready for the next ask.
+    async fn recv(&self) -> Result<R, ActorError> {
+        loop {
+            let state = std::mem::replace(&mut *self.state.lock().unwrap(), SlotState::Waiting);
+            match state {
+                SlotState::Replied(response) => return Ok(response),
+                SlotState::Dropped => {
+                    return Err(ActorError::SendError("response dropped".to_string()))
+                }
+                SlotState::Waiting => self.ready.notified().await,
+            }
+        }
+    }
+}
+
+/// The sending half of a [`ReplySlot`]. Dropping it without sending wakes up
+/// the receiver with an error, like dropping a oneshot sender.
+pub(crate) struct SlotSender<R> {
+    slot: Option<Arc<ReplySlot<R>>>,
+}
+
+impl<R> SlotSender<R> {
+    fn send(mut self, response: R) {
+        if let Some(slot) = self.slot.take() {
+            slot.set(SlotState::Replied(response));
+        }
+    }
+}
+
+impl<R> Drop for SlotSender<R> {
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot.take() {
+            slot.set(SlotState::Dropped);
+        }
+    }
+}
+
+/// The state reused by the asks of a [`Requester`].
+struct PooledReply<R> {
+    slot: Arc<ReplySlot<R>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// An ask in flight. If the caller stops waiting, the ask is marked as
+/// cancelled and its state is not reused, as the actor may still respond.
+struct PendingReply<'a, R> {
+    reply: Option<PooledReply<R>>,
+    pool: &'a Mutex<Vec<PooledReply<R>>>,
+}
+
+impl<R> PendingReply<'_, R> {
+    fn complete(mut self) {
+        if let Some(reply) = self.reply.take() {
+            self.pool.lock().unwrap().push(reply);
+        }
+    }
+}
+
+impl<R> Drop for PendingReply<'_, R> {
+    fn drop(&mut self) {
+        if let Some(reply) = &self.reply {
+            reply.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// A handle to ask an actor the same type of message over and over, reusing
+/// the state needed to get the responses back instead of allocating it for
+/// every ask like [`ActorRef::ask()`] does. It grows to hold the state of as
+/// many asks as were in flight at the same time. Clones share the same state,
+/// so one requester can be cloned to all tasks asking the actor. Get one
+/// through [`ActorRef::requester()`]. For example:
+/// ```
+/// # use tiny_tokio_actor::*;
+/// # #[derive(Clone, Debug)]
+/// # struct TestEvent;
+/// # impl SystemEvent for TestEvent {}
+/// #[derive(Clone, Debug)]
+/// struct Double(u64);
+///
+/// impl Message for Double {
+///     type Response = u64;
+/// }
+///
+/// struct Doubler;
+///
+/// impl Actor<TestEvent> for Doubler {}
+///
+/// #[async_trait]
+/// impl Handler<TestEvent, Double> for Doubler {
+///     async fn handle(&mut self, msg: Double, _ctx: &mut ActorContext<TestEvent>) -> u64 {
+///         msg.0 * 2
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), ActorError> {
+/// let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+/// let doubler = system.create_actor("doubler", Doubler).await?;
+///
+/// let requester = doubler.requester::<Double>();
+/// for i in 0..1000 {
+///     assert_eq!(requester.ask(Double(i)).await?, i * 2);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Requester<E: SystemEvent, A: Actor<E>, M: Message> {
+    actor_ref: ActorRef<E, A>,
+    pool: Arc<Mutex<Vec<PooledReply<M::Response>>>>,
+}
+
+impl<E: SystemEvent, A: Actor<E>, M: Message> Clone for Requester<E, A, M> {
+    fn clone(&self) -> Self {
+        Requester {
+            actor_ref: self.actor_ref.clone(),
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+impl<E, A, M> Requester<E, A, M>
+where
+    E: SystemEvent,
+    A: Actor<E> + Handler<E, M>,
+    M: Message,
+{
+    pub(crate) fn new(actor_ref: &ActorRef<E, A>) -> Self {
+        Requester {
+            actor_ref: actor_ref.clone(),
+            pool: Default::default(),
+        }
+    }
+
+    /// Get the path of the actor
+    pub fn path(&self) -> &ActorPath {
+        self.actor_ref.path()
+    }
+
+    /// Sends the message to the actor, expecting a response. This behaves the
+    /// same as [`ActorRef::ask()`], including the ask timeout of the actor.
+    pub async fn ask(&self, msg: M) -> Result<M::Response, ActorError> {
+        let reply = self.pool.lock().unwrap().pop();
+        let reply = reply.unwrap_or_else(|| PooledReply {
+            slot: Arc::new(ReplySlot::new()),
+            cancelled: Default::default(),
+        });
+        reply.cancelled.store(false, Ordering::Relaxed);
+        let rsvp = Rsvp::Slot(SlotSender {
+            slot: Some(reply.slot.clone()),
+        });
+        let cancelled = reply.cancelled.clone();
+        let slot = reply.slot.clone();
+        let pending = PendingReply {
+            reply: Some(reply),
+            pool: &self.pool,
+        };
+        let ask = async {
+            self.actor_ref.sender.ask_with(msg, rsvp, cancelled).await?;
+            slot.recv().await
+        };
+        let response = self.actor_ref.finish_ask::<M, _>(ask).await;
+        if response.is_ok() {
+            pending.complete();
+        }
+        response
+    }
+}
@@ -1,55 +1,366 @@
-use crate::system::{ActorSystem, SystemEvent};
+use std::{
+    any::Any,
+    future::Future,
+    panic::AssertUnwindSafe,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant, SystemTime},
+};
+
+use log::Level;
+use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
+
+#[cfg(feature = "chaos")]
+use crate::chaos::{Chaos, ChaosAction, INJECTED_FAILURE};
+use crate::{
+    dead_letters::DeadLetterReason,
+    instrumentation::{actor_log, MailboxMonitor, StarvationWarning},
+    system::{ActorSystem, SystemEvent},
+};
 
 use super::{
-    handler::{ActorMailbox, MailboxReceiver},
-    Actor, ActorContext, ActorPath, ActorRef, SupervisionStrategy,
+    handler::{ActorMailbox, ControlMessage, ControlReceiver, ControlRef, MailboxReceiver},
+    props::Factory,
+    supervision::{CrashReport, SupervisionPolicy},
+    Actor, ActorConfig, ActorContext, ActorError, ActorPath, ActorRef, SupervisionStrategy,
 };
 
 pub(crate) struct ActorRunner<E: SystemEvent, A: Actor<E>> {
     path: ActorPath,
+    incarnation: u64,
     actor: A,
     receiver: MailboxReceiver<E, A>,
+    control: ControlReceiver,
+    cancellation: CancellationToken,
+    monitor: MailboxMonitor,
+    throughput: Option<usize>,
+    time_slice: Option<Duration>,
+    supervision: Option<SupervisionPolicy>,
+    dependencies: Vec<ActorPath>,
+    factory: Option<Factory<A>>,
+    ready: Option<oneshot::Sender<Result<(), ActorError>>>,
+    #[cfg(feature = "chaos")]
+    chaos: Option<Chaos>,
 }
 
 impl<E: SystemEvent, A: Actor<E>> ActorRunner<E, A> {
-    pub fn create(path: ActorPath, actor: A) -> (Self, ActorRef<E, A>) {
-        let (sender, receiver) = ActorMailbox::create();
-        let actor_ref = ActorRef::new(path.clone(), sender);
+    pub fn create(
+        path: ActorPath,
+        incarnation: u64,
+        actor: A,
+        config: &ActorConfig,
+        monitor: MailboxMonitor,
+    ) -> (Self, ActorRef<E, A>) {
+        let (sender, receiver) = ActorMailbox::create(config.mailbox_capacity());
+        let (control_ref, control) = ControlRef::create();
+        let cancellation = control_ref.cancellation_token().clone();
+        let actor_ref = ActorRef::new(
+            path.clone(),
+            incarnation,
+            sender,
+            control_ref,
+            monitor.clone(),
+            config.ask_timeout(),
+        );
         let runner = ActorRunner {
             path,
+            incarnation,
             actor,
             receiver,
+            control,
+            cancellation,
+            monitor,
+            throughput: config.throughput(),
+            time_slice: config.time_slice(),
+            supervision: config.supervision().cloned(),
+            dependencies: config.dependencies().to_vec(),
+            factory: None,
+            ready: None,
+            #[cfg(feature = "chaos")]
+            chaos: None,
         };
         (runner, actor_ref)
     }
 
-    pub async fn start(&mut self, system: ActorSystem<E>) {
-        log::debug!("Starting actor '{}'...", &self.path);
+    /// Reports the outcome of starting the actor to `ready`. A failure to
+    /// start is then not escalated to the parent.
+    pub fn notify_ready(&mut self, ready: oneshot::Sender<Result<(), ActorError>>) {
+        self.ready = Some(ready);
+    }
+
+    /// Restarts the actor with fresh instances from the factory, see
+    /// [`super::Props`].
+    pub fn restart_from(&mut self, factory: Factory<A>) {
+        self.factory = Some(factory);
+    }
+
+    /// Injects faults into the handling of messages.
+    #[cfg(feature = "chaos")]
+    pub fn inject_chaos(&mut self, chaos: Chaos) {
+        self.chaos = Some(chaos);
+    }
 
-        let mut ctx = ActorContext {
-            path: self.path.clone(),
+    pub async fn start(&mut self, system: ActorSystem<E>) {
+        actor_log!(
+            self.monitor,
+            Level::Debug,
+            "Starting actor '{}'...",
+            &self.path
+        );
+
+        let mut ctx = ActorContext::new(
+            self.path.clone(),
+            self.incarnation,
+            self.cancellation.clone(),
             system,
-        };
+        );
+        ctx.log_level = self.monitor.log_level();
+
+        let mut stopped = None;
+        let mut drained = None;
+        let mut failed = None;
+        let mut running = self.await_dependencies(&ctx).await
+            && match self.init(&mut ctx, false).await {
+                Ok(()) => true,
+                Err(error) => {
+                    failed = Some(error);
+                    false
+                }
+            };
+        if running {
+            // An actor that was stopped while starting is not registered anymore
+            if !self.cancellation.is_cancelled() {
+                ctx.system.actor_started(&self.path);
+            }
+            actor_log!(
+                self.monitor,
+                Level::Debug,
+                "Actor '{}' has started successfully.",
+                &self.path
+            );
+            if let Some(ready) = self.ready.take() {
+                ready.send(Ok(())).unwrap_or_default();
+            }
+            let mut suspended = false;
+            let mut handled = 0;
+            let mut slice_started: Option<Instant> = None;
+            let system = ctx.system.clone();
+            let mut watch = StarvationWatch::new(system.config().starvation_threshold());
+            while running {
+                tokio::select! {
+                    biased;
+
+                    Some(control) = self.control.recv() => match control {
+                        ControlMessage::Suspend(ack) => {
+                            actor_log!(self.monitor, Level::Debug, "Actor '{}' suspended.", &self.path);
+                            suspended = true;
+                            ack.send(()).unwrap_or_default();
+                        }
+                        ControlMessage::Resume => {
+                            actor_log!(self.monitor, Level::Debug, "Actor '{}' resumed.", &self.path);
+                            suspended = false;
+                        }
+                        ControlMessage::Replace(actor, ack) => match actor.downcast::<A>() {
+                            Ok(actor) => {
+                                let result = self.replace(&mut ctx, *actor).await;
+                                running = result.is_ok();
+                                ack.send(result).unwrap_or_default();
+                            }
+                            Err(_) => {
+                                let error = ActorError::CreateError(format!(
+                                    "Replacement for actor '{}' is of a different type",
+                                    &self.path
+                                ));
+                                ack.send(Err(error)).unwrap_or_default();
+                            }
+                        },
+                        ControlMessage::Restart(ack) => {
+                            let result = self.restart(&mut ctx).await;
+                            running = result.is_ok();
+                            ack.send(result).unwrap_or_default();
+                        }
+                        ControlMessage::Stop(ack) => {
+                            stopped = Some(ack);
+                            break;
+                        }
+                        ControlMessage::Drain(ack) => {
+                            actor_log!(self.monitor, Level::Debug, "Actor '{}' draining.", &self.path);
+                            // Messages already queued are still received,
+                            // after which the mailbox reports it is empty
+                            self.receiver.close();
+                            drained = Some(ack);
+                        }
+                        ControlMessage::Ping(ack) => {
+                            ack.send(()).unwrap_or_default();
+                        }
+                        ControlMessage::ChildFailed(failure) => {
+                            if let Err(error) = self.actor.child_failed(&mut ctx, failure).await {
+                                actor_log!(self.monitor, Level::Error, "Actor '{}' failed due to a failed child.", &self.path);
+                                failed = Some(error);
+                                break;
+                            }
+                        }
+                    },
+                    msg = self.receiver.recv(), if !suspended => match msg {
+                        Some(mut msg) => {
+                            let message_type = msg.message_type();
+                            let _handling = self.monitor.handling();
+                            self.monitor.dequeued(msg.id(), message_type, msg.enqueued_at().elapsed());
+                            msg.dequeued();
+                            self.monitor.release(msg.size());
+                            #[cfg(feature = "chaos")]
+                            let injected = match self.chaos.as_mut().map(Chaos::next_action) {
+                                Some(ChaosAction::Delay(delay)) => {
+                                    tokio::time::sleep(delay).await;
+                                    None
+                                }
+                                Some(ChaosAction::Drop) => {
+                                    self.monitor.dropped(msg, DeadLetterReason::Injected);
+                                    continue;
+                                }
+                                Some(ChaosAction::Fail) => Some(INJECTED_FAILURE),
+                                _ => None,
+                            };
+                            #[cfg(not(feature = "chaos"))]
+                            let injected: Option<&'static str> = None;
+                            let started = Instant::now();
+                            watch.begin();
+                            let handled_msg = match injected {
+                                Some(failure) => Err(Box::new(failure) as Box<dyn Any + Send>),
+                                None => {
+                                    let handling = CatchUnwind(msg.handle(&mut self.actor, &mut ctx));
+                                    watch.watch(handling, &system, &self.path, message_type).await
+                                }
+                            };
+                            if let Err(payload) = handled_msg {
+                                let report = CrashReport::new(self.path.clone(), message_type, payload);
+                                actor_log!(self.monitor, Level::Error, message_type = message_type; "Actor '{}' panicked handling {}: {}", &self.path, message_type, report.panic());
+                                ctx.system.report_crash(report.clone());
+                                // The state of the actor is unknown, so post_stop is not run
+                                running = false;
+                                failed = Some(ActorError::Panicked(report));
+                                break;
+                            }
+                            self.monitor.handled(message_type, started.elapsed());
+                            if let Some(warning) = watch.overdue(&self.path, message_type) {
+                                system.report_starvation(warning);
+                            }
+                            handled += 1;
+                            let sliced = self.time_slice.is_some_and(|slice| slice_started.get_or_insert(started).elapsed() >= slice);
+                            if sliced || self.throughput.is_some_and(|throughput| handled >= throughput) {
+                                self.monitor.yielded(handled);
+                                handled = 0;
+                                slice_started = None;
+                                watch.end();
+                                tokio::task::yield_now().await;
+                            } else if self.receiver.is_empty() {
+                                slice_started = None;
+                                watch.end();
+                            }
+                        }
+                        None => break,
+                    },
+                    else => break,
+                }
+            }
+
+            if running {
+                self.actor.post_stop(&mut ctx).await;
+                actor_log!(
+                    self.monitor,
+                    Level::Debug,
+                    "Actor '{}' stopped.",
+                    &self.path
+                );
+            }
+        }
+
+        self.cancellation.cancel();
+        self.receiver.close();
+        self.control.close();
+        while let Some(mut msg) = self.receiver.try_recv() {
+            msg.dequeued();
+            self.monitor.release(msg.size());
+            self.monitor.dropped(msg, DeadLetterReason::Terminated);
+        }
+        ctx.finalizers.run();
+
+        if let Some(error) = failed {
+            match self.ready.take() {
+                Some(ready) => {
+                    ctx.system.terminate(&self.path).await;
+                    ready.send(Err(error)).unwrap_or_default();
+                }
+                None => ctx.system.escalate(&self.path, error).await,
+            }
+        } else if drained.is_some() {
+            ctx.system.terminate(&self.path).await;
+        }
+
+        for ack in stopped.into_iter().chain(drained) {
+            ack.send(()).unwrap_or_default();
+        }
+    }
+
+    /// Waits until the actors this actor depends on have started, see
+    /// [`ActorConfig::with_dependency()`]. Returns `false` if the actor was
+    /// stopped before.
+    async fn await_dependencies(&self, ctx: &ActorContext<E>) -> bool {
+        if self.dependencies.is_empty() {
+            return true;
+        }
+        actor_log!(
+            self.monitor,
+            Level::Debug,
+            "Actor '{}' is waiting for {:?} to start...",
+            &self.path,
+            &self.dependencies
+        );
+        tokio::select! {
+            _ = ctx.system.wait_for_start(&self.dependencies) => true,
+            _ = self.cancellation.cancelled() => false,
+        }
+    }
 
-        let mut start_error = self.actor.pre_start(&mut ctx).await.err();
+    /// Runs `pre_start` of the actor (or `pre_restart` when restarting), applying
+    /// the actor's supervision strategy if it fails.
+    async fn init(&mut self, ctx: &mut ActorContext<E>, restart: bool) -> Result<(), ActorError> {
+        let mut start_error = if restart {
+            ctx.restart(&mut self.actor, None).await.err()
+        } else {
+            self.actor.pre_start(ctx).await.err()
+        };
         if start_error.is_some() {
             let mut retries = 0;
-            match A::supervision_strategy() {
+            let strategy = match &self.supervision {
+                Some(policy) => policy.strategy(),
+                None => A::supervision_strategy(),
+            };
+            match strategy {
                 SupervisionStrategy::Stop => {
-                    log::error!("Actor '{}' failed to start!", &self.path);
+                    actor_log!(
+                        self.monitor,
+                        Level::Error,
+                        "Actor '{}' failed to start!",
+                        &self.path
+                    );
                 }
                 SupervisionStrategy::Retry(mut retry_strategy) => {
-                    log::debug!(
+                    actor_log!(
+                        self.monitor,
+                        Level::Debug,
                         "Restarting actor with retry strategy: {:?}",
                         &retry_strategy
                     );
                     while retries < retry_strategy.max_retries() && start_error.is_some() {
-                        log::debug!("retries: {}", &retries);
+                        actor_log!(self.monitor, Level::Debug, "retries: {}", &retries);
                         if let Some(duration) = retry_strategy.next_backoff() {
-                            log::debug!("Backoff for {:?}", &duration);
+                            actor_log!(self.monitor, Level::Debug, "Backoff for {:?}", &duration);
                             tokio::time::sleep(duration).await;
                         }
                         retries += 1;
+                        self.renew(ctx);
                         start_error = ctx
                             .restart(&mut self.actor, start_error.as_ref())
                             .await
@@ -58,29 +369,191 @@ impl<E: SystemEvent, A: Actor<E>> ActorRunner<E, A> {
                 }
             }
         }
-
-        if start_error.is_none() {
-            log::debug!("Actor '{}' has started successfully.", &self.path);
-            while let Some(mut msg) = self.receiver.recv().await {
-                msg.handle(&mut self.actor, &mut ctx).await;
+        match start_error {
+            Some(error) => {
+                self.monitor.failed(&error);
+                Err(error)
             }
+            None => Ok(()),
+        }
+    }
+
+    /// Stops the running actor instance and starts the given one in its place.
+    /// The mailbox is left untouched, so any pending messages will be handled
+    /// by the new instance.
+    async fn replace(&mut self, ctx: &mut ActorContext<E>, actor: A) -> Result<(), ActorError> {
+        actor_log!(
+            self.monitor,
+            Level::Debug,
+            "Replacing actor '{}'...",
+            &self.path
+        );
+        let handoff = self.actor.handoff();
+        self.actor.post_stop(ctx).await;
+        self.actor = actor;
+        ctx.extensions.clear();
+        ctx.handoff = handoff;
+        let result = self.init(ctx, false).await;
+        ctx.handoff = None;
+        result?;
+        actor_log!(
+            self.monitor,
+            Level::Debug,
+            "Actor '{}' has been replaced.",
+            &self.path
+        );
+        Ok(())
+    }
 
-            self.actor.post_stop(&mut ctx).await;
+    /// Replaces the actor instance with a fresh one if it was created from
+    /// props, dropping its extensions along with it.
+    fn renew(&mut self, ctx: &mut ActorContext<E>) {
+        if let Some(factory) = &self.factory {
+            self.actor = factory();
+            ctx.extensions.clear();
+        }
+    }
+
+    /// Stops the running actor instance and starts it again through its
+    /// `pre_restart`. Pending messages are kept in the mailbox.
+    async fn restart(&mut self, ctx: &mut ActorContext<E>) -> Result<(), ActorError> {
+        actor_log!(
+            self.monitor,
+            Level::Debug,
+            "Restarting actor '{}'...",
+            &self.path
+        );
+        self.actor.post_stop(ctx).await;
+        self.renew(ctx);
+        self.init(ctx, true).await?;
+        actor_log!(
+            self.monitor,
+            Level::Debug,
+            "Actor '{}' has restarted.",
+            &self.path
+        );
+        Ok(())
+    }
+}
 
-            log::debug!("Actor '{}' stopped.", &self.path);
+/// Tracks how long an actor has been handling messages without a break, i.e.
+/// without its mailbox running empty or yielding, see
+/// [`crate::SystemConfig::with_starvation_threshold()`].
+struct StarvationWatch {
+    threshold: Option<Duration>,
+    started: Option<Instant>,
+    messages: usize,
+    reported: bool,
+}
+
+impl StarvationWatch {
+    fn new(threshold: Option<Duration>) -> Self {
+        StarvationWatch {
+            threshold,
+            started: None,
+            messages: 0,
+            reported: false,
         }
+    }
 
-        self.receiver.close();
+    /// A message is about to be handled.
+    fn begin(&mut self) {
+        if self.threshold.is_some() {
+            self.started.get_or_insert_with(Instant::now);
+            self.messages += 1;
+        }
+    }
+
+    /// The run of messages came to an end.
+    fn end(&mut self) {
+        self.started = None;
+        self.messages = 0;
+        self.reported = false;
+    }
+
+    /// When the current run exceeds the threshold, if not reported yet.
+    fn deadline(&self) -> Option<Instant> {
+        match (self.started, self.threshold) {
+            (Some(started), Some(threshold)) if !self.reported => Some(started + threshold),
+            _ => None,
+        }
+    }
+
+    /// Reports the current run once it exceeded the threshold.
+    fn overdue(
+        &mut self,
+        path: &ActorPath,
+        message_type: &'static str,
+    ) -> Option<StarvationWarning> {
+        let deadline = self.deadline()?;
+        if Instant::now() < deadline {
+            return None;
+        }
+        self.reported = true;
+        Some(StarvationWarning {
+            path: path.clone(),
+            message_type,
+            elapsed: self
+                .started
+                .map(|started| started.elapsed())
+                .unwrap_or_default(),
+            messages: self.messages,
+            timestamp: SystemTime::now(),
+        })
+    }
+
+    /// Awaits the handling of a message, reporting it as soon as the current
+    /// run exceeds the threshold rather than only once it completes, so
+    /// handlers that never complete are reported too.
+    async fn watch<F: Future + Unpin, E: SystemEvent>(
+        &mut self,
+        mut handling: F,
+        system: &ActorSystem<E>,
+        path: &ActorPath,
+        message_type: &'static str,
+    ) -> F::Output {
+        let deadline = match self.deadline() {
+            Some(deadline) => deadline,
+            None => return handling.await,
+        };
+        tokio::select! {
+            biased;
+
+            output = &mut handling => return output,
+            _ = tokio::time::sleep_until(deadline.into()) => {}
+        }
+        if let Some(warning) = self.overdue(path, message_type) {
+            system.report_starvation(warning);
+        }
+        handling.await
+    }
+}
+
+/// Resolves to the output of the future, or to the payload of the panic if
+/// polling it panicked.
+struct CatchUnwind<F>(F);
+
+impl<F: Future + Unpin> Future for CatchUnwind<F> {
+    type Output = Result<F::Output, Box<dyn Any + Send>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let future = &mut self.0;
+        match std::panic::catch_unwind(AssertUnwindSafe(|| Pin::new(&mut *future).poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use crate::*;
+    use crate::{builtin::BuiltinBuses, dead_letters::DeadLetters, *};
 
     use super::*;
 
+    #[allow(dead_code)]
     #[derive(Clone, Debug)]
     struct TestEvent(String);
 
@@ -113,7 +586,13 @@ mod tests {
         let system = start_system();
         let path = ActorPath::from("/test/actor");
         let actor = NoRetryActor;
-        let (mut runner, actor_ref) = ActorRunner::create(path, actor);
+        let monitor = MailboxMonitor::new(
+            path.clone(),
+            DeadLetters::new(BuiltinBuses::new(1).bus()),
+            None,
+        );
+        let (mut runner, actor_ref) =
+            ActorRunner::create(path, 1, actor, &ActorConfig::default(), monitor);
 
         runner.start(system).await;
 
@@ -163,7 +642,13 @@ mod tests {
         let system = start_system();
         let path = ActorPath::from("/test/actor");
         let actor = RetryNoIntervalActor::default();
-        let (mut runner, actor_ref) = ActorRunner::create(path, actor);
+        let monitor = MailboxMonitor::new(
+            path.clone(),
+            DeadLetters::new(BuiltinBuses::new(1).bus()),
+            None,
+        );
+        let (mut runner, actor_ref) =
+            ActorRunner::create(path, 1, actor, &ActorConfig::default(), monitor);
 
         runner.start(system).await;
 
@@ -206,7 +691,13 @@ mod tests {
         let system = start_system();
         let path = ActorPath::from("/test/actor");
         let actor = RetryExpBackoffActor { counter: 0 };
-        let (mut runner, actor_ref) = ActorRunner::create(path, actor);
+        let monitor = MailboxMonitor::new(
+            path.clone(),
+            DeadLetters::new(BuiltinBuses::new(1).bus()),
+            None,
+        );
+        let (mut runner, actor_ref) =
+            ActorRunner::create(path, 1, actor, &ActorConfig::default(), monitor);
 
         runner.start(system).await;
 
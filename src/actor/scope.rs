@@ -0,0 +1,56 @@
+use std::future::Future;
+
+use tokio::task::JoinSet;
+
+/// The tasks spawned within [`ActorContext::scope()`](super::ActorContext::scope).
+/// None of them outlives the scope: they are all awaited before the scope
+/// returns, or aborted when it is cancelled or dropped.
+pub struct TaskScope<T> {
+    tasks: JoinSet<(usize, T)>,
+    spawned: usize,
+}
+
+impl<T: Send + 'static> TaskScope<T> {
+    pub(crate) fn new() -> Self {
+        TaskScope {
+            tasks: JoinSet::new(),
+            spawned: 0,
+        }
+    }
+
+    /// Runs the future as a task of the scope. Its output is returned by the
+    /// scope in the order the tasks were spawned.
+    pub fn spawn<F>(&mut self, task: F)
+    where
+        F: Future<Output = T> + Send + 'static,
+    {
+        let index = self.spawned;
+        self.spawned += 1;
+        self.tasks.spawn(async move { (index, task.await) });
+    }
+
+    /// The number of tasks spawned so far
+    pub fn len(&self) -> usize {
+        self.spawned
+    }
+
+    /// True if no task was spawned
+    pub fn is_empty(&self) -> bool {
+        self.spawned == 0
+    }
+
+    /// Awaits all tasks, resuming the panic of a task that panicked.
+    pub(crate) async fn join(mut self) -> Vec<T> {
+        let mut outputs = Vec::with_capacity(self.spawned);
+        while let Some(joined) = self.tasks.join_next().await {
+            match joined {
+                Ok(output) => outputs.push(output),
+                Err(error) if error.is_panic() => std::panic::resume_unwind(error.into_panic()),
+                // Tasks are only aborted by dropping the scope
+                Err(_) => {}
+            }
+        }
+        outputs.sort_unstable_by_key(|(index, _)| *index);
+        outputs.into_iter().map(|(_, output)| output).collect()
+    }
+}
@@ -1,10 +1,14 @@
 use std::{
+    any::Any,
+    collections::VecDeque,
     sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, Instant, SystemTime},
 };
 
 use backoff::backoff::Backoff as InnerBackoff;
 
+use crate::{ActorError, ActorPath};
+
 /// A SupervisionStrategy defined what to do when an actor fails at startup.
 /// Currently there are two choices: Stop the actor and do nothing, or Retry
 /// the startup. For Retry you can set a RetryStrategy.
@@ -14,6 +18,187 @@ pub enum SupervisionStrategy {
     Retry(Box<dyn RetryStrategy>),
 }
 
+/// The failure of a child actor that could not be started, even after applying
+/// its supervision strategy, or that panicked while handling a message. It is
+/// escalated to the parent through [`crate::Actor::child_failed()`].
+#[derive(Debug)]
+pub struct ChildFailed {
+    pub path: ActorPath,
+    pub reason: ActorError,
+}
+
+/// Describes a panic of an actor while it handled a message. The actor is
+/// stopped, and the report is both escalated to its parent as an
+/// `ActorError::Panicked` and published to
+/// [`ActorSystem::crash_reports()`](crate::ActorSystem::crash_reports).
+#[derive(Clone, Debug)]
+pub struct CrashReport {
+    path: ActorPath,
+    message_type: &'static str,
+    panic: String,
+    timestamp: SystemTime,
+}
+
+impl CrashReport {
+    pub(crate) fn new(
+        path: ActorPath,
+        message_type: &'static str,
+        payload: Box<dyn Any + Send>,
+    ) -> Self {
+        let panic = match payload.downcast::<String>() {
+            Ok(panic) => *panic,
+            Err(payload) => match payload.downcast::<&'static str>() {
+                Ok(panic) => panic.to_string(),
+                Err(_) => "unknown panic".to_string(),
+            },
+        };
+        CrashReport {
+            path,
+            message_type,
+            panic,
+            timestamp: SystemTime::now(),
+        }
+    }
+
+    /// The path of the actor that panicked.
+    pub fn path(&self) -> &ActorPath {
+        &self.path
+    }
+
+    /// The type name of the message that was being handled.
+    pub fn message_type(&self) -> &'static str {
+        self.message_type
+    }
+
+    /// The message the handler panicked with.
+    pub fn panic(&self) -> &str {
+        &self.panic
+    }
+
+    /// When the actor panicked.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+}
+
+/// How often an actor restarted, as returned by
+/// [`ActorSystem::restart_stats()`](crate::ActorSystem::restart_stats). An
+/// actor restarts when it is retried after failing to start, and when it is
+/// restarted explicitly, e.g. through
+/// [`ActorSystem::restart_subtree()`](crate::ActorSystem::restart_subtree).
+/// The statistics are kept per path, so they carry over when an actor is
+/// created again at the same path.
+#[derive(Clone, Debug, Default)]
+pub struct RestartStats {
+    restarts: u64,
+    last_restart: Option<SystemTime>,
+    recent: VecDeque<Instant>,
+}
+
+impl RestartStats {
+    /// The number of restarts in total.
+    pub fn restarts(&self) -> u64 {
+        self.restarts
+    }
+
+    /// When the actor last restarted, if ever.
+    pub fn last_restart(&self) -> Option<SystemTime> {
+        self.last_restart
+    }
+
+    /// Counts a restart, returning the number of restarts within `window`.
+    pub(crate) fn record(&mut self, window: Option<Duration>) -> usize {
+        let now = Instant::now();
+        self.restarts += 1;
+        self.last_restart = Some(SystemTime::now());
+        if let Some(window) = window {
+            self.recent.push_back(now);
+            while self
+                .recent
+                .front()
+                .is_some_and(|restart| now.duration_since(*restart) > window)
+            {
+                self.recent.pop_front();
+            }
+        }
+        self.recent.len()
+    }
+}
+
+/// An actor restarted more often than allowed by
+/// [`SystemConfig::with_flap_detection()`](crate::SystemConfig::with_flap_detection),
+/// for example because it keeps failing to start while its supervision
+/// strategy keeps retrying. Published to
+/// [`ActorSystem::flapping_actors()`](crate::ActorSystem::flapping_actors).
+#[derive(Clone, Debug)]
+pub struct FlappingActor {
+    /// The path of the actor.
+    pub path: ActorPath,
+    /// The number of restarts within the window.
+    pub restarts: usize,
+    /// The window the restarts were counted in.
+    pub within: Duration,
+    /// When the actor was found to be flapping.
+    pub timestamp: SystemTime,
+}
+
+/// A cloneable description of a supervision strategy, for example to set the
+/// strategy of actors through configuration (see
+/// [`crate::ActorConfig::with_supervision()`]). When set, it takes precedence
+/// over [`crate::Actor::supervision_strategy()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SupervisionPolicy {
+    /// Stop the actor when it fails to start
+    Stop,
+    /// Retry using a [`NoIntervalStrategy`]
+    NoInterval { max_retries: usize },
+    /// Retry using a [`FixedIntervalStrategy`]
+    FixedInterval {
+        max_retries: usize,
+        interval: Duration,
+    },
+    /// Retry using an [`ExponentialBackoffStrategy`]
+    ExponentialBackoff { max_retries: usize },
+}
+
+impl SupervisionPolicy {
+    /// Creates the supervision strategy described by this policy
+    pub fn strategy(&self) -> SupervisionStrategy {
+        match self {
+            SupervisionPolicy::Stop => SupervisionStrategy::Stop,
+            SupervisionPolicy::NoInterval { max_retries } => {
+                SupervisionStrategy::Retry(Box::new(NoIntervalStrategy::new(*max_retries)))
+            }
+            SupervisionPolicy::FixedInterval {
+                max_retries,
+                interval,
+            } => SupervisionStrategy::Retry(Box::new(FixedIntervalStrategy::new(
+                *max_retries,
+                *interval,
+            ))),
+            SupervisionPolicy::ExponentialBackoff { max_retries } => {
+                SupervisionStrategy::Retry(Box::new(ExponentialBackoffStrategy::new(*max_retries)))
+            }
+        }
+    }
+}
+
+/// How an actor system treats an actor panicking while handling a message,
+/// see [`crate::SystemConfig::with_panic_policy()`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PanicPolicy {
+    /// Stop the actor that panicked and escalate the failure to its parent,
+    /// which may restart it (see [`crate::Actor::child_failed()`]). The rest
+    /// of the system keeps running.
+    #[default]
+    Supervise,
+    /// Shut down the whole actor system, see
+    /// [`crate::ActorSystem::shutdown()`].
+    Shutdown,
+    /// Abort the process right away, without running any further cleanup.
+    Abort,
+}
+
 /// Trait to define a RetryStrategy. You can use this trait to define your
 /// custom retry strategy.
 pub trait RetryStrategy: std::fmt::Debug + Send + Sync {
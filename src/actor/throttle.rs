@@ -0,0 +1,81 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// How long repeats of a log line throttled through
+/// [`ActorContext::log_throttled()`](super::ActorContext::log_throttled) are
+/// held back.
+pub(crate) const LOG_THROTTLE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The log lines of an actor throttled by key.
+#[derive(Default)]
+pub(crate) struct LogThrottle {
+    keys: HashMap<String, Throttled>,
+}
+
+struct Throttled {
+    logged: Instant,
+    repeated: usize,
+}
+
+impl LogThrottle {
+    /// Decides whether a line with the key is logged at the given instant.
+    /// Returns the number of times it repeated since it was last logged, or
+    /// `None` if it is held back.
+    pub(crate) fn admit(&mut self, key: &str, now: Instant) -> Option<usize> {
+        match self.keys.get_mut(key) {
+            Some(throttled) if now.duration_since(throttled.logged) < LOG_THROTTLE_INTERVAL => {
+                throttled.repeated += 1;
+                None
+            }
+            Some(throttled) => {
+                throttled.logged = now;
+                Some(std::mem::take(&mut throttled.repeated))
+            }
+            None => {
+                // Drop the keys that went quiet, so ever changing keys do
+                // not pile up
+                self.keys.retain(|_, throttled| {
+                    throttled.repeated > 0
+                        || now.duration_since(throttled.logged) < LOG_THROTTLE_INTERVAL
+                });
+                self.keys.insert(
+                    key.to_string(),
+                    Throttled {
+                        logged: now,
+                        repeated: 0,
+                    },
+                );
+                Some(0)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttles_repeats() {
+        let mut throttle = LogThrottle::default();
+        let start = Instant::now();
+        assert_eq!(throttle.admit("db", start), Some(0));
+        assert_eq!(throttle.admit("db", start + Duration::from_secs(1)), None);
+        assert_eq!(throttle.admit("db", start + Duration::from_secs(2)), None);
+        assert_eq!(
+            throttle.admit("disk", start + Duration::from_secs(2)),
+            Some(0)
+        );
+
+        let later = start + LOG_THROTTLE_INTERVAL;
+        assert_eq!(throttle.admit("db", later), Some(2));
+        assert_eq!(throttle.admit("db", later), None);
+        assert_eq!(throttle.admit("db", later + LOG_THROTTLE_INTERVAL), Some(1));
+        assert_eq!(
+            throttle.admit("disk", later + LOG_THROTTLE_INTERVAL),
+            Some(0)
+        );
+    }
+}
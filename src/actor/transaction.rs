@@ -0,0 +1,283 @@
+use std::{
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+
+use crate::system::SystemEvent;
+
+use super::{Actor, ActorContext, ActorError, ActorRef, Handler, Message};
+
+/// Source of the ids of transactions, unique within the process.
+static NEXT_TRANSACTION: AtomicU64 = AtomicU64::new(1);
+
+/// Identifies a transaction prepared by a [`Participant`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TxToken(u64);
+
+impl TxToken {
+    fn next() -> Self {
+        TxToken(NEXT_TRANSACTION.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// The id of the transaction
+    pub fn id(&self) -> u64 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for TxToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "tx-{}", self.0)
+    }
+}
+
+/// An actor guarding a resource that callers reserve before using it, in two
+/// phases: the caller prepares a transaction, e.g. reserving stock, and then
+/// either commits or aborts it. Callers go through
+/// [`ActorRef::prepare()`](super::ActorRef::prepare), which aborts the
+/// transaction when it is not committed in time or dropped. For example:
+/// ```
+/// # use tiny_tokio_actor::*;
+/// # use std::collections::HashMap;
+/// # use std::time::Duration;
+/// # #[derive(Clone, Debug)]
+/// # struct TestEvent;
+/// # impl SystemEvent for TestEvent {}
+/// #[derive(Default)]
+/// struct Inventory {
+///     available: u32,
+///     reserved: HashMap<TxToken, u32>,
+/// }
+///
+/// impl Actor<TestEvent> for Inventory {}
+///
+/// #[async_trait]
+/// impl Participant<TestEvent> for Inventory {
+///     type Request = u32;
+///     type Output = u32;
+///
+///     async fn prepare(
+///         &mut self,
+///         token: TxToken,
+///         amount: u32,
+///         _ctx: &mut ActorContext<TestEvent>,
+///     ) -> Result<(), ActorError> {
+///         if amount > self.available {
+///             return Err(ActorError::CreateError("out of stock".to_string()));
+///         }
+///         self.available -= amount;
+///         self.reserved.insert(token, amount);
+///         Ok(())
+///     }
+///
+///     async fn commit(
+///         &mut self,
+///         token: TxToken,
+///         _ctx: &mut ActorContext<TestEvent>,
+///     ) -> Result<u32, ActorError> {
+///         self.reserved
+///             .remove(&token)
+///             .ok_or(ActorError::TransactionAborted(token))
+///     }
+///
+///     async fn abort(&mut self, token: TxToken, _ctx: &mut ActorContext<TestEvent>) {
+///         self.available += self.reserved.remove(&token).unwrap_or_default();
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), ActorError> {
+/// let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+/// let inventory = Inventory { available: 10, ..Default::default() };
+/// let inventory = system.create_actor("inventory", inventory).await?;
+///
+/// let transaction = inventory.prepare(3, Duration::from_secs(5)).await?;
+/// assert_eq!(transaction.commit().await?, 3);
+/// # Ok(())
+/// # }
+/// ```
+#[async_trait]
+pub trait Participant<E: SystemEvent>: Actor<E> {
+    /// What a caller asks to reserve.
+    type Request: Clone + Send + Sync + 'static;
+
+    /// The result of committing a transaction.
+    type Output: Send + Sync + 'static;
+
+    /// Reserves what is requested under the token, failing if it cannot be
+    /// reserved.
+    async fn prepare(
+        &mut self,
+        token: TxToken,
+        request: Self::Request,
+        ctx: &mut ActorContext<E>,
+    ) -> Result<(), ActorError>;
+
+    /// Completes the prepared transaction. Called at most once per token,
+    /// and never after the transaction was aborted.
+    async fn commit(
+        &mut self,
+        token: TxToken,
+        ctx: &mut ActorContext<E>,
+    ) -> Result<Self::Output, ActorError>;
+
+    /// Releases what was reserved under the token. Called at most once per
+    /// token, and never after the transaction was committed. It is also
+    /// called when the caller could not tell whether the transaction was
+    /// prepared, e.g. because its ask timed out, so tokens that were never
+    /// prepared must be ignored.
+    async fn abort(&mut self, token: TxToken, ctx: &mut ActorContext<E>);
+}
+
+/// Prepares a transaction, see [`Participant::prepare()`].
+#[derive(Clone, Debug)]
+pub struct Prepare<R> {
+    token: TxToken,
+    request: R,
+}
+
+impl<R: Clone + Send + Sync + 'static> Message for Prepare<R> {
+    type Response = Result<(), ActorError>;
+}
+
+/// Commits a transaction, see [`Participant::commit()`].
+#[derive(Debug)]
+pub struct Commit<O> {
+    token: TxToken,
+    _phantom_output: PhantomData<fn() -> O>,
+}
+
+impl<O> Clone for Commit<O> {
+    fn clone(&self) -> Self {
+        Commit {
+            token: self.token,
+            _phantom_output: PhantomData,
+        }
+    }
+}
+
+impl<O: Send + Sync + 'static> Message for Commit<O> {
+    type Response = Result<O, ActorError>;
+}
+
+/// Aborts a transaction, see [`Participant::abort()`].
+#[derive(Clone, Debug)]
+pub struct Abort(TxToken);
+
+impl Message for Abort {
+    type Response = ();
+}
+
+#[async_trait]
+impl<E: SystemEvent, A: Participant<E>> Handler<E, Prepare<A::Request>> for A {
+    async fn handle(
+        &mut self,
+        msg: Prepare<A::Request>,
+        ctx: &mut ActorContext<E>,
+    ) -> Result<(), ActorError> {
+        self.prepare(msg.token, msg.request, ctx).await
+    }
+}
+
+#[async_trait]
+impl<E: SystemEvent, A: Participant<E>> Handler<E, Commit<A::Output>> for A {
+    async fn handle(
+        &mut self,
+        msg: Commit<A::Output>,
+        ctx: &mut ActorContext<E>,
+    ) -> Result<A::Output, ActorError> {
+        self.commit(msg.token, ctx).await
+    }
+}
+
+#[async_trait]
+impl<E: SystemEvent, A: Participant<E>> Handler<E, Abort> for A {
+    async fn handle(&mut self, msg: Abort, ctx: &mut ActorContext<E>) {
+        self.abort(msg.0, ctx).await
+    }
+}
+
+/// A transaction prepared by a [`Participant`], see
+/// [`ActorRef::prepare()`](super::ActorRef::prepare). It is aborted when it
+/// is not committed before its timeout, or when it is dropped.
+pub struct Transaction<E: SystemEvent, A: Participant<E>> {
+    actor_ref: ActorRef<E, A>,
+    token: TxToken,
+    settled: Arc<AtomicBool>,
+}
+
+impl<E: SystemEvent, A: Participant<E>> Transaction<E, A> {
+    /// Prepares the transaction with the actor, aborting it once the
+    /// timeout elapsed unless it was settled before.
+    pub(crate) async fn prepare(
+        actor_ref: &ActorRef<E, A>,
+        request: A::Request,
+        timeout: Duration,
+    ) -> Result<Self, ActorError> {
+        let token = TxToken::next();
+        match actor_ref.ask(Prepare { token, request }).await {
+            Ok(prepared) => prepared?,
+            Err(error) => {
+                // The actor may still prepare the transaction later on
+                actor_ref.tell(Abort(token)).unwrap_or_default();
+                return Err(error);
+            }
+        }
+        let transaction = Transaction {
+            actor_ref: actor_ref.clone(),
+            token,
+            settled: Default::default(),
+        };
+        let settled = transaction.settled.clone();
+        let expiring = actor_ref.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            if !settled.swap(true, Ordering::AcqRel) {
+                log::debug!(
+                    "Transaction {} with actor '{}' timed out",
+                    token,
+                    expiring.path()
+                );
+                expiring.tell(Abort(token)).unwrap_or_default();
+            }
+        });
+        Ok(transaction)
+    }
+
+    /// The token the transaction was prepared under
+    pub fn token(&self) -> TxToken {
+        self.token
+    }
+
+    /// Commits the transaction. Returns an `ActorError::TransactionAborted`
+    /// if the timeout elapsed before.
+    pub async fn commit(self) -> Result<A::Output, ActorError> {
+        if self.settled.swap(true, Ordering::AcqRel) {
+            return Err(ActorError::TransactionAborted(self.token));
+        }
+        let commit = Commit {
+            token: self.token,
+            _phantom_output: PhantomData,
+        };
+        self.actor_ref.ask(commit).await?
+    }
+
+    /// Aborts the transaction.
+    pub fn abort(self) {
+        // Aborted when dropped
+    }
+}
+
+impl<E: SystemEvent, A: Participant<E>> Drop for Transaction<E, A> {
+    fn drop(&mut self) {
+        if !self.settled.swap(true, Ordering::AcqRel) {
+            self.actor_ref.tell(Abort(self.token)).unwrap_or_default();
+        }
+    }
+}
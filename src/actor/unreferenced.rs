@@ -0,0 +1,43 @@
+use crate::{system::ActorSystem, SystemEvent};
+
+use super::{handler::ControlRef, ActorPath};
+
+/// Shared by the references handed out for an actor created with
+/// [`ActorConfig::with_stop_when_unreferenced()`](super::ActorConfig::with_stop_when_unreferenced).
+/// The registry only keeps a weak reference to it, so once the last of them
+/// is dropped, the actor is stopped.
+pub(crate) struct RefGuard<E: SystemEvent> {
+    system: ActorSystem<E>,
+    path: ActorPath,
+    control: ControlRef,
+}
+
+impl<E: SystemEvent> RefGuard<E> {
+    pub fn new(system: ActorSystem<E>, path: ActorPath, control: ControlRef) -> Self {
+        RefGuard {
+            system,
+            path,
+            control,
+        }
+    }
+}
+
+impl<E: SystemEvent> Drop for RefGuard<E> {
+    fn drop(&mut self) {
+        // The actor may have stopped already, and another one may have been
+        // created at its path since
+        if self.control.cancellation_token().is_cancelled() {
+            return;
+        }
+        // Outside of a runtime, the actor stops together with its runtime
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            log::debug!(
+                "Stopping actor '{}' as it is no longer referenced",
+                &self.path
+            );
+            let system = self.system.clone();
+            let path = self.path.clone();
+            runtime.spawn(async move { system.terminate(&path).await });
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::sync::Arc;
+
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+use crate::{
+    bus::{EventReceiver, EventRecvError},
+    Actor, ActorPath, ActorRef, ActorSystem, SystemEvent,
+};
+
+/// A link between two actor systems in the same process, possibly with
+/// different event types, so each can be used as a module of a larger
+/// application without going through the network. Get one through
+/// [`ActorSystem::bridge()`]. For example:
+/// ```
+/// # use tiny_tokio_actor::*;
+/// #[derive(Clone, Debug)]
+/// enum OrderEvent {
+///     Placed(u64),
+/// }
+/// impl SystemEvent for OrderEvent {}
+///
+/// #[derive(Clone, Debug)]
+/// enum BillingEvent {
+///     Invoice(u64),
+/// }
+/// impl SystemEvent for BillingEvent {}
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let orders = ActorSystem::new("orders", EventBus::<OrderEvent>::new(1000));
+/// let billing = ActorSystem::new("billing", EventBus::<BillingEvent>::new(1000));
+///
+/// let bridge = orders.bridge(&billing);
+/// bridge.forward_events(|OrderEvent::Placed(id)| Some(BillingEvent::Invoice(id)));
+/// # }
+/// ```
+///
+/// Events are forwarded until [`SystemBridge::close()`] is called or the last
+/// clone of the bridge is dropped. When forwarding in both directions, the
+/// mappings should not map forwarded events back, or they go around forever.
+pub struct SystemBridge<E: SystemEvent, F: SystemEvent> {
+    local: ActorSystem<E>,
+    remote: ActorSystem<F>,
+    stopped: CancellationToken,
+    _guard: Arc<DropGuard>,
+}
+
+impl<E: SystemEvent, F: SystemEvent> Clone for SystemBridge<E, F> {
+    fn clone(&self) -> Self {
+        SystemBridge {
+            local: self.local.clone(),
+            remote: self.remote.clone(),
+            stopped: self.stopped.clone(),
+            _guard: self._guard.clone(),
+        }
+    }
+}
+
+impl<E: SystemEvent, F: SystemEvent> SystemBridge<E, F> {
+    pub(crate) fn new(local: ActorSystem<E>, remote: ActorSystem<F>) -> Self {
+        let stopped = CancellationToken::new();
+        SystemBridge {
+            local,
+            remote,
+            _guard: Arc::new(stopped.clone().drop_guard()),
+            stopped,
+        }
+    }
+
+    /// The actor system the bridge was created from
+    pub fn local(&self) -> &ActorSystem<E> {
+        &self.local
+    }
+
+    /// The actor system the bridge links to
+    pub fn remote(&self) -> &ActorSystem<F> {
+        &self.remote
+    }
+
+    /// Publishes the events of the local system on the remote system, after
+    /// mapping them. Events mapped to `None` are not forwarded. Only events
+    /// published after this call are forwarded.
+    pub fn forward_events<M>(&self, map: M)
+    where
+        M: Fn(E) -> Option<F> + Send + 'static,
+    {
+        forward(
+            self.local.events(),
+            self.remote.clone(),
+            map,
+            self.stopped.clone(),
+        );
+    }
+
+    /// Publishes the events of the remote system on the local system, like
+    /// [`SystemBridge::forward_events()`] in the other direction.
+    pub fn forward_events_back<M>(&self, map: M)
+    where
+        M: Fn(F) -> Option<E> + Send + 'static,
+    {
+        forward(
+            self.remote.events(),
+            self.local.clone(),
+            map,
+            self.stopped.clone(),
+        );
+    }
+
+    /// Retrieves an actor of the local system, see [`ActorSystem::get_actor()`].
+    pub async fn get_local_actor<A: Actor<E>>(&self, path: &ActorPath) -> Option<ActorRef<E, A>> {
+        self.local.get_actor(path).await
+    }
+
+    /// Retrieves an actor of the remote system, see [`ActorSystem::get_actor()`].
+    pub async fn get_remote_actor<A: Actor<F>>(&self, path: &ActorPath) -> Option<ActorRef<F, A>> {
+        self.remote.get_actor(path).await
+    }
+
+    /// Stops forwarding events in both directions, for all clones of the
+    /// bridge.
+    pub fn close(&self) {
+        self.stopped.cancel();
+    }
+
+    /// True if the bridge was closed
+    pub fn is_closed(&self) -> bool {
+        self.stopped.is_cancelled()
+    }
+}
+
+/// Publishes the mapped events on the target until stopped.
+fn forward<S, T, M>(
+    mut events: EventReceiver<S>,
+    target: ActorSystem<T>,
+    map: M,
+    stopped: CancellationToken,
+) where
+    S: SystemEvent,
+    T: SystemEvent,
+    M: Fn(S) -> Option<T> + Send + 'static,
+{
+    tokio::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                _ = stopped.cancelled() => break,
+                event = events.recv() => event,
+            };
+            match event {
+                Ok(event) => {
+                    if let Some(event) = map(event) {
+                        if target.try_publish(event).is_err() {
+                            break;
+                        }
+                    }
+                }
+                Err(EventRecvError::Lagged(skipped)) => {
+                    log::warn!(
+                        "Bridge to actor system '{}' dropped {} events",
+                        target.name(),
+                        skipped
+                    );
+                }
+                Err(EventRecvError::Closed) => break,
+            }
+        }
+    });
+}
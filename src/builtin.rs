@@ -0,0 +1,141 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    bus::{BusStats, EventBus, EventReceiver},
+    connection::ConnectionChange,
+    dead_letters::DeadLetter,
+    election::LeadershipChange,
+    group::GroupEvent,
+    instrumentation::StarvationWarning,
+    quota::QuotaEvent,
+    system::{RegistryEvent, SystemMessage},
+    CrashReport, FlappingActor, SystemEvent,
+};
+
+/// Declares the built-in events of an actor system: each variant becomes a
+/// variant of [`BuiltinEvent`] that is received as the [`SystemMessage`]
+/// variant of the same name.
+macro_rules! builtin_events {
+    ($($variant:ident($event:ty)),+ $(,)?) => {
+        /// A built-in event of an actor system, as published on the bus of
+        /// all built-in events, see [`BuiltinBuses`].
+        #[derive(Clone, Debug)]
+        pub(crate) enum BuiltinEvent {
+            $($variant($event)),+
+        }
+
+        impl BuiltinEvent {
+            pub(crate) fn into_message<E: SystemEvent>(self) -> SystemMessage<E> {
+                match self {
+                    $(BuiltinEvent::$variant(event) => SystemMessage::$variant(event)),+
+                }
+            }
+        }
+
+        $(
+            impl From<$event> for BuiltinEvent {
+                fn from(event: $event) -> Self {
+                    BuiltinEvent::$variant(event)
+                }
+            }
+        )+
+    };
+}
+
+builtin_events! {
+    Registry(RegistryEvent),
+    Crash(CrashReport),
+    DeadLetter(DeadLetter),
+    Quota(QuotaEvent),
+    Flapping(FlappingActor),
+    Starvation(StarvationWarning),
+    BusStats(BusStats),
+    Leadership(LeadershipChange),
+    Connection(ConnectionChange),
+    SupervisionGroup(GroupEvent),
+}
+
+/// Closes the bus of one kind of built-in events.
+type Closer = Box<dyn Fn() + Send + Sync>;
+
+/// The buses of the built-in events of an actor system. Every event sent on
+/// one of them is sent on the bus of all built-in events as well, which
+/// [`crate::SystemMessageReceiver`] receives from, and all of them are closed
+/// together when the system shuts down.
+#[derive(Clone)]
+pub(crate) struct BuiltinBuses {
+    capacity: usize,
+    all: EventBus<BuiltinEvent>,
+    closers: Arc<Mutex<Vec<Closer>>>,
+}
+
+impl BuiltinBuses {
+    pub fn new(capacity: usize) -> Self {
+        BuiltinBuses {
+            capacity,
+            all: EventBus::new(capacity),
+            closers: Default::default(),
+        }
+    }
+
+    /// Creates the bus of one kind of built-in events.
+    pub fn bus<T>(&self) -> BuiltinBus<T>
+    where
+        T: Into<BuiltinEvent> + Clone + Send + 'static,
+    {
+        let bus = EventBus::new(self.capacity);
+        let closing = bus.clone();
+        self.closers
+            .lock()
+            .unwrap()
+            .push(Box::new(move || closing.close()));
+        BuiltinBus {
+            bus,
+            all: self.all.clone(),
+        }
+    }
+
+    /// Subscribes to all built-in events from now on.
+    pub fn subscribe(&self) -> EventReceiver<BuiltinEvent> {
+        self.all.subscribe()
+    }
+
+    /// Closes all buses, see [`EventBus::close()`].
+    pub fn close(&self) {
+        for close in self.closers.lock().unwrap().iter() {
+            close();
+        }
+        self.all.close();
+    }
+}
+
+/// The bus of one kind of built-in events, see [`BuiltinBuses`].
+#[derive(Clone)]
+pub(crate) struct BuiltinBus<T: Clone> {
+    bus: EventBus<T>,
+    all: EventBus<BuiltinEvent>,
+}
+
+impl<T: Into<BuiltinEvent> + Clone> BuiltinBus<T> {
+    /// Sends the event to the subscribers of its kind and of all built-in
+    /// events.
+    pub fn send(&self, event: T) {
+        if self.all.receiver_count() > 0 {
+            self.all.send(event.clone().into()).unwrap_or_default();
+        }
+        self.bus.send(event).unwrap_or_default();
+    }
+
+    pub fn subscribe(&self) -> EventReceiver<T> {
+        self.bus.subscribe()
+    }
+
+    /// The number of subscribers the events would be sent to
+    pub fn receiver_count(&self) -> usize {
+        self.bus.receiver_count() + self.all.receiver_count()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.bus.is_closed()
+    }
+}
@@ -1,27 +1,550 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Weak,
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
 use tokio::sync::broadcast;
 pub use tokio::sync::broadcast::error::RecvError as EventRecvError;
 pub use tokio::sync::broadcast::error::SendError;
 use tokio::sync::broadcast::{Receiver as BroadcastReceiver, Sender as BroadcastSender};
+use tokio::sync::Mutex;
 
 pub type EventReceiver<T> = BroadcastReceiver<T>;
 pub(crate) type EventSender<T> = BroadcastSender<T>;
 
+/// How long [`EventBus::send_lossless()`] waits before checking again whether
+/// the slowest subscriber caught up.
+const LOSSLESS_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+type SharedReceiver<T> = Arc<Mutex<EventReceiver<T>>>;
+type SharedSender<T> = Arc<std::sync::RwLock<Option<EventSender<T>>>>;
+type Groups<T> = HashMap<String, Weak<Mutex<EventReceiver<T>>>>;
+
+/// The events held back until the first subscriber attaches, see
+/// [`EventBus::buffer_until_subscribed()`]. `None` once they were delivered.
+type EarlyEvents<T> = Arc<std::sync::Mutex<Option<Buffered<T>>>>;
+
+struct Buffered<T> {
+    limit: usize,
+    events: VecDeque<T>,
+}
+
+/// What an [`EventBus`] has seen since it was created, see
+/// [`EventBus::stats()`], e.g. to tune its capacity from data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BusStats {
+    /// The number of subscribers, where a consumer group counts as one.
+    pub subscribers: usize,
+    /// The number of events the bus holds for its slowest subscriber.
+    pub capacity: usize,
+    /// The number of events sent.
+    pub published: u64,
+    /// The number of events sent while there were no subscribers.
+    pub unheard: u64,
+    /// The number of events that were dropped before the slowest subscriber
+    /// received them, because the bus was full.
+    pub overwritten: u64,
+    /// When the stats were taken.
+    pub timestamp: Instant,
+}
+
+impl BusStats {
+    /// The number of events sent per second since the earlier stats.
+    pub fn publish_rate(&self, earlier: &BusStats) -> f64 {
+        let elapsed = self.timestamp.duration_since(earlier.timestamp);
+        if elapsed.is_zero() {
+            return 0.0;
+        }
+        self.published.saturating_sub(earlier.published) as f64 / elapsed.as_secs_f64()
+    }
+}
+
+/// What a named subscriber of an [`EventBus`] has received, see
+/// [`EventBus::subscriber_stats()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubscriberStats {
+    /// The name of the subscriber.
+    pub name: String,
+    /// The number of events it received.
+    pub received: u64,
+    /// The number of events it missed because it lagged behind.
+    pub dropped: u64,
+    /// The number of events waiting for it, as of the last time it received
+    /// one.
+    pub pending: usize,
+}
+
+struct SubscriberCounters {
+    name: String,
+    received: AtomicU64,
+    dropped: AtomicU64,
+    pending: AtomicUsize,
+}
+
+impl SubscriberCounters {
+    fn stats(&self) -> SubscriberStats {
+        SubscriberStats {
+            name: self.name.clone(),
+            received: self.received.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            pending: self.pending.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Default)]
+struct BusCounters {
+    published: AtomicU64,
+    unheard: AtomicU64,
+    overwritten: AtomicU64,
+}
+
 #[derive(Clone)]
 pub struct EventBus<T: Clone> {
-    tx: EventSender<T>,
+    tx: SharedSender<T>,
+    capacity: usize,
+    lossless: Arc<Mutex<()>>,
+    groups: Arc<std::sync::Mutex<Groups<T>>>,
+    counters: Arc<BusCounters>,
+    named: Arc<std::sync::Mutex<Vec<Weak<SubscriberCounters>>>>,
+    early: Option<EarlyEvents<T>>,
 }
 
 impl<T: Clone> EventBus<T> {
     pub fn subscribe(&self) -> EventReceiver<T> {
-        self.tx.subscribe()
+        let tx = self.tx.read().unwrap();
+        let tx = match &*tx {
+            Some(tx) => tx,
+            None => return closed_receiver(),
+        };
+        let mut early = self.early.as_ref().map(|early| early.lock().unwrap());
+        let receiver = tx.subscribe();
+        if let Some(buffered) = early.as_deref_mut().and_then(Option::take) {
+            for event in buffered.events {
+                tx.send(event).unwrap_or_default();
+            }
+        }
+        receiver
+    }
+
+    /// Holds on to at most `limit` events sent before the first subscriber
+    /// attaches, dropping the oldest ones, and delivers them to that
+    /// subscriber, e.g. so the events published while an application starts
+    /// up are not lost. Later subscribers only receive the events sent after
+    /// they subscribed, as usual. The held back events count as sent to zero
+    /// subscribers.
+    pub fn buffer_until_subscribed(mut self, limit: usize) -> Self {
+        self.early = Some(Arc::new(std::sync::Mutex::new(Some(Buffered {
+            limit,
+            events: VecDeque::new(),
+        }))));
+        self
+    }
+
+    /// Joins the consumer group with the given name. The members of a group
+    /// compete for events: each event is received by only one of them, while
+    /// the group as a whole receives every event like a single subscriber.
+    /// The group exists as long as it has members.
+    pub fn subscribe_group(&self, group: &str) -> GroupReceiver<T> {
+        let mut groups = self.groups.lock().unwrap();
+        groups.retain(|_, receiver| receiver.strong_count() > 0);
+        let receiver = match groups.get(group).and_then(Weak::upgrade) {
+            Some(receiver) => receiver,
+            None => {
+                let receiver = Arc::new(Mutex::new(self.subscribe()));
+                groups.insert(group.to_string(), Arc::downgrade(&receiver));
+                receiver
+            }
+        };
+        GroupReceiver { receiver }
     }
 
+    /// Subscribes under the given name, which identifies the subscriber in
+    /// [`EventBus::subscriber_stats()`] and in the warning logged when it
+    /// lags behind. Names need not be unique.
+    pub fn subscribe_named(&self, name: &str) -> NamedReceiver<T> {
+        let counters = Arc::new(SubscriberCounters {
+            name: name.to_string(),
+            received: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            pending: AtomicUsize::new(0),
+        });
+        let mut named = self.named.lock().unwrap();
+        named.retain(|counters| counters.strong_count() > 0);
+        named.push(Arc::downgrade(&counters));
+        NamedReceiver {
+            receiver: self.subscribe(),
+            counters,
+        }
+    }
+
+    /// The stats of the named subscribers that are still subscribed, ordered
+    /// by name. See [`EventBus::subscribe_named()`].
+    pub fn subscriber_stats(&self) -> Vec<SubscriberStats> {
+        let mut stats: Vec<_> = self
+            .named
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .map(|counters| counters.stats())
+            .collect();
+        stats.sort_by(|a, b| a.name.cmp(&b.name));
+        stats
+    }
+
+    /// Sends the event to all subscribers, returning how many there are. When
+    /// the bus is full the oldest event is dropped for subscribers that did
+    /// not receive it yet.
     pub fn send(&self, event: T) -> Result<usize, SendError<T>> {
-        self.tx.send(event)
+        let tx = self.tx.read().unwrap();
+        let tx = match &*tx {
+            Some(tx) => tx,
+            None => return Err(SendError(event)),
+        };
+        let mut early = self.early.as_ref().map(|early| early.lock().unwrap());
+        if let Some(buffered) = early.as_deref_mut().and_then(Option::as_mut) {
+            if tx.receiver_count() == 0 {
+                if buffered.events.len() >= buffered.limit {
+                    buffered.events.pop_front();
+                }
+                if buffered.limit > 0 {
+                    buffered.events.push_back(event);
+                }
+                self.counters.published.fetch_add(1, Ordering::Relaxed);
+                return Ok(0);
+            }
+        }
+        // The channel rounds its capacity up to a power of two
+        if tx.receiver_count() > 0 && tx.len() >= self.capacity.next_power_of_two() {
+            self.counters.overwritten.fetch_add(1, Ordering::Relaxed);
+        }
+        self.counters.published.fetch_add(1, Ordering::Relaxed);
+        let sent = tx.send(event);
+        if sent.is_err() {
+            self.counters.unheard.fetch_add(1, Ordering::Relaxed);
+        }
+        sent
+    }
+
+    /// Sends the event once every subscriber has room for it, so no subscriber
+    /// misses an event it has not received yet. Events can still be dropped
+    /// if [`EventBus::send()`] is used on the same bus at the same time.
+    pub async fn send_lossless(&self, event: T) -> Result<usize, SendError<T>> {
+        let _guard = self.lossless.lock().await;
+        while self.is_full() {
+            tokio::time::sleep(LOSSLESS_POLL_INTERVAL).await;
+        }
+        self.send(event)
+    }
+
+    /// True if the slowest subscriber has as many events waiting as the bus
+    /// can hold.
+    fn is_full(&self) -> bool {
+        match &*self.tx.read().unwrap() {
+            Some(tx) => tx.receiver_count() > 0 && tx.len() >= self.capacity,
+            None => false,
+        }
+    }
+
+    /// The number of subscribers, where a consumer group counts as one.
+    pub fn receiver_count(&self) -> usize {
+        match &*self.tx.read().unwrap() {
+            Some(tx) => tx.receiver_count(),
+            None => 0,
+        }
+    }
+
+    /// Closes the bus for all its clones. Subscribers still receive the
+    /// events that were sent before, after which they receive
+    /// `Err(EventRecvError::Closed)`, so loops over the events can exit
+    /// cleanly. Events sent afterwards are rejected, and new subscribers are
+    /// closed right away. Called for the buses of an actor system by
+    /// [`ActorSystem::shutdown()`](crate::ActorSystem::shutdown).
+    pub fn close(&self) {
+        self.tx.write().unwrap().take();
+    }
+
+    /// True if the bus was closed
+    pub fn is_closed(&self) -> bool {
+        self.tx.read().unwrap().is_none()
+    }
+
+    /// The number of events the bus holds for its slowest subscriber.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// What the bus has seen since it was created, shared by all its clones.
+    pub fn stats(&self) -> BusStats {
+        BusStats {
+            subscribers: self.receiver_count(),
+            capacity: self.capacity,
+            published: self.counters.published.load(Ordering::Relaxed),
+            unheard: self.counters.unheard.load(Ordering::Relaxed),
+            overwritten: self.counters.overwritten.load(Ordering::Relaxed),
+            timestamp: Instant::now(),
+        }
     }
 
     pub fn new(capacity: usize) -> Self {
         let (tx, _) = broadcast::channel(capacity);
-        EventBus { tx }
+        EventBus {
+            tx: Arc::new(std::sync::RwLock::new(Some(tx))),
+            capacity,
+            lossless: Arc::new(Mutex::new(())),
+            groups: Default::default(),
+            counters: Default::default(),
+            named: Default::default(),
+            early: None,
+        }
+    }
+}
+
+/// A receiver of a closed bus, which has no events to receive.
+fn closed_receiver<T: Clone>() -> EventReceiver<T> {
+    broadcast::channel(1).1
+}
+
+/// A subscriber with a name, see [`EventBus::subscribe_named()`].
+pub struct NamedReceiver<T: Clone> {
+    receiver: EventReceiver<T>,
+    counters: Arc<SubscriberCounters>,
+}
+
+impl<T: Clone> NamedReceiver<T> {
+    /// The name of the subscriber
+    pub fn name(&self) -> &str {
+        &self.counters.name
+    }
+
+    /// Receives the next event like [`EventReceiver::recv()`], keeping track
+    /// of what was received and dropped.
+    pub async fn recv(&mut self) -> Result<T, EventRecvError> {
+        let received = self.receiver.recv().await;
+        match &received {
+            Ok(_) => {
+                self.counters.received.fetch_add(1, Ordering::Relaxed);
+                self.counters
+                    .pending
+                    .store(self.receiver.len(), Ordering::Relaxed);
+            }
+            Err(EventRecvError::Lagged(dropped)) => {
+                self.counters.dropped.fetch_add(*dropped, Ordering::Relaxed);
+                log::warn!(
+                    "Subscriber '{}' lagged behind, dropped {} events",
+                    self.name(),
+                    dropped
+                );
+            }
+            Err(EventRecvError::Closed) => {}
+        }
+        received
+    }
+}
+
+/// A member of a consumer group, see [`EventBus::subscribe_group()`].
+pub struct GroupReceiver<T: Clone> {
+    receiver: SharedReceiver<T>,
+}
+
+impl<T: Clone> Clone for GroupReceiver<T> {
+    fn clone(&self) -> Self {
+        GroupReceiver {
+            receiver: self.receiver.clone(),
+        }
+    }
+}
+
+impl<T: Clone> GroupReceiver<T> {
+    /// Receives the next event of the group. Members waiting at the same time
+    /// receive events in the order they started waiting.
+    pub async fn recv(&self) -> Result<T, EventRecvError> {
+        self.receiver.lock().await.recv().await
+    }
+}
+
+/// Converts the events of one bus into events of another type.
+type EventMap<T, U> = Arc<dyn Fn(T) -> Option<U> + Send + Sync>;
+
+#[async_trait]
+trait EventSource<U>: Send {
+    async fn recv(&mut self) -> Result<U, EventRecvError>;
+}
+
+struct MappedSource<T: Clone, U> {
+    receiver: EventReceiver<T>,
+    map: EventMap<T, U>,
+}
+
+#[async_trait]
+impl<T: Clone + Send + 'static, U: Send + 'static> EventSource<U> for MappedSource<T, U> {
+    async fn recv(&mut self) -> Result<U, EventRecvError> {
+        loop {
+            if let Some(event) = (self.map)(self.receiver.recv().await?) {
+                return Ok(event);
+            }
+        }
+    }
+}
+
+/// Receives the events of a bus converted to another type, see
+/// [`EventAdapter`]. Events the conversion maps to `None` are skipped.
+pub struct MappedReceiver<U> {
+    source: Box<dyn EventSource<U>>,
+}
+
+impl<U> MappedReceiver<U> {
+    /// Receives the next converted event. Fails with `EventRecvError::Lagged`
+    /// if events were dropped before they could be converted.
+    pub async fn recv(&mut self) -> Result<U, EventRecvError> {
+        self.source.recv().await
+    }
+}
+
+/// A view of an event bus as a bus of another event type, so a component
+/// written against its own event type can subscribe to a bus of the
+/// application's event type. Create one through [`EventBus::adapter()`] or
+/// [`ActorSystem::event_adapter()`](crate::ActorSystem::event_adapter). For
+/// example:
+/// ```
+/// use tiny_tokio_actor::*;
+///
+/// #[derive(Clone, Debug)]
+/// enum AppEvent {
+///     Cache(String),
+///     Other,
+/// }
+///
+/// let bus = EventBus::<AppEvent>::new(1000);
+/// let adapter: EventAdapter<String> = bus.adapter(|event| match event {
+///     AppEvent::Cache(key) => Some(key),
+///     _ => None,
+/// });
+/// let mut receiver = adapter.subscribe();
+/// ```
+pub struct EventAdapter<U> {
+    subscribe: Arc<dyn Fn() -> MappedReceiver<U> + Send + Sync>,
+}
+
+impl<U> Clone for EventAdapter<U> {
+    fn clone(&self) -> Self {
+        EventAdapter {
+            subscribe: self.subscribe.clone(),
+        }
+    }
+}
+
+impl<U> EventAdapter<U> {
+    /// Subscribe to the converted events. Only events sent after subscribing
+    /// are received.
+    pub fn subscribe(&self) -> MappedReceiver<U> {
+        (self.subscribe)()
+    }
+}
+
+impl<T: Clone + Send + 'static> EventBus<T> {
+    /// Subscribe to the events of the bus converted by `map`, see
+    /// [`EventAdapter`].
+    pub fn subscribe_mapped<U, M>(&self, map: M) -> MappedReceiver<U>
+    where
+        U: Send + 'static,
+        M: Fn(T) -> Option<U> + Send + Sync + 'static,
+    {
+        self.adapter(map).subscribe()
+    }
+
+    /// Views the bus as a bus of events converted by `map`, see
+    /// [`EventAdapter`].
+    pub fn adapter<U, M>(&self, map: M) -> EventAdapter<U>
+    where
+        U: Send + 'static,
+        M: Fn(T) -> Option<U> + Send + Sync + 'static,
+    {
+        let bus = self.clone();
+        let map: EventMap<T, U> = Arc::new(map);
+        EventAdapter {
+            subscribe: Arc::new(move || MappedReceiver {
+                source: Box::new(MappedSource {
+                    receiver: bus.subscribe(),
+                    map: map.clone(),
+                }),
+            }),
+        }
+    }
+}
+
+/// Publishes events of type `T` on a bus of another event type, so code that
+/// only knows its own event type can still publish, e.g. in a reusable actor
+/// library. Get one through [`ActorSystem::publisher()`](crate::ActorSystem::publisher)
+/// or [`ActorContext::publisher()`](crate::ActorContext::publisher). For
+/// example:
+/// ```
+/// use tiny_tokio_actor::*;
+///
+/// // In a library, which does not know the event type of the application
+/// #[derive(Clone, Debug)]
+/// pub enum CacheEvent {
+///     Evicted(String),
+/// }
+///
+/// pub struct Cache {
+///     events: EventPublisher<CacheEvent>,
+/// }
+///
+/// impl Cache {
+///     pub fn evict(&mut self, key: &str) {
+///         self.events.publish(CacheEvent::Evicted(key.to_string()));
+///     }
+/// }
+///
+/// // In the application
+/// #[derive(Clone, Debug)]
+/// enum AppEvent {
+///     Cache(CacheEvent),
+/// }
+/// impl SystemEvent for AppEvent {}
+///
+/// impl From<CacheEvent> for AppEvent {
+///     fn from(event: CacheEvent) -> Self {
+///         AppEvent::Cache(event)
+///     }
+/// }
+///
+/// let system = ActorSystem::new("test", EventBus::<AppEvent>::new(1000));
+/// let mut cache = Cache { events: system.publisher() };
+/// cache.evict("key");
+/// ```
+pub struct EventPublisher<T> {
+    publish: Arc<dyn Fn(T) -> usize + Send + Sync>,
+}
+
+impl<T> Clone for EventPublisher<T> {
+    fn clone(&self) -> Self {
+        EventPublisher {
+            publish: self.publish.clone(),
+        }
+    }
+}
+
+impl<T> EventPublisher<T> {
+    pub(crate) fn new<F>(publish: F) -> Self
+    where
+        F: Fn(T) -> usize + Send + Sync + 'static,
+    {
+        EventPublisher {
+            publish: Arc::new(publish),
+        }
+    }
+
+    /// Publishes the event, returning the number of subscribers that received
+    /// it.
+    pub fn publish(&self, event: T) -> usize {
+        (self.publish)(event)
     }
 }
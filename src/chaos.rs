@@ -0,0 +1,159 @@
+use std::time::Duration;
+
+use crate::ActorPath;
+
+/// Faults to inject into the message handling of all actors of a system, to
+/// shake out timing assumptions in tests. Register it through
+/// [`SystemConfig::with_chaos()`](crate::SystemConfig::with_chaos). Requires
+/// the `chaos` feature. For example:
+/// ```
+/// use tiny_tokio_actor::*;
+/// use std::time::Duration;
+///
+/// let chaos = ChaosConfig::new(42)
+///     .with_delays(0.5, Duration::from_millis(20))
+///     .with_drops(0.01)
+///     .with_failures(0.001);
+/// let config = SystemConfig::new("test").with_chaos(chaos);
+/// ```
+///
+/// Before handling a message, an actor decides at random whether to hold it
+/// back for a while, to drop it as a dead letter, or to fail as if its handler
+/// panicked. Delays change the order in which messages to different actors
+/// are handled; messages to the same actor are still handled in order. The
+/// choices of an actor depend only on the seed and the path of the actor, so
+/// the same seed gives every actor the same sequence of faults.
+#[derive(Clone, Debug, Default)]
+pub struct ChaosConfig {
+    seed: u64,
+    delay_probability: f64,
+    max_delay: Duration,
+    drop_probability: f64,
+    failure_probability: f64,
+}
+
+impl ChaosConfig {
+    /// Injects no faults until enabled through the other methods.
+    pub fn new(seed: u64) -> Self {
+        ChaosConfig {
+            seed,
+            ..Default::default()
+        }
+    }
+
+    /// Delays messages with the given probability by up to `max_delay`.
+    pub fn with_delays(mut self, probability: f64, max_delay: Duration) -> Self {
+        self.delay_probability = probability;
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Drops messages with the given probability. Dropped messages become dead
+    /// letters with [`DeadLetterReason::Injected`](crate::DeadLetterReason::Injected).
+    pub fn with_drops(mut self, probability: f64) -> Self {
+        self.drop_probability = probability;
+        self
+    }
+
+    /// Fails the handling of messages with the given probability. The actor
+    /// then fails the same way as when its handler panics, see
+    /// [`crate::CrashReport`].
+    pub fn with_failures(mut self, probability: f64) -> Self {
+        self.failure_probability = probability;
+        self
+    }
+
+    /// The seed of the injected faults
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+/// What to do with the next message.
+#[derive(Debug, PartialEq)]
+pub(crate) enum ChaosAction {
+    Deliver,
+    Delay(Duration),
+    Drop,
+    Fail,
+}
+
+/// The panic message of an injected failure.
+pub(crate) const INJECTED_FAILURE: &str = "injected failure";
+
+/// The fault injection of a single actor.
+pub(crate) struct Chaos {
+    config: ChaosConfig,
+    state: u64,
+}
+
+impl Chaos {
+    pub fn new(config: &ChaosConfig, path: &ActorPath) -> Self {
+        // FNV-1a, so the sequence of an actor does not depend on the hasher
+        // of the standard library
+        let hash = path
+            .to_string()
+            .bytes()
+            .fold(0xcbf29ce484222325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            });
+        Chaos {
+            config: config.clone(),
+            state: config.seed ^ hash,
+        }
+    }
+
+    /// The next number of a splitmix64 sequence, as a fraction in [0, 1).
+    fn next_fraction(&mut self) -> f64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn next_action(&mut self) -> ChaosAction {
+        if self.next_fraction() < self.config.failure_probability {
+            ChaosAction::Fail
+        } else if self.next_fraction() < self.config.drop_probability {
+            ChaosAction::Drop
+        } else if self.next_fraction() < self.config.delay_probability {
+            ChaosAction::Delay(self.config.max_delay.mul_f64(self.next_fraction()))
+        } else {
+            ChaosAction::Deliver
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn chaos_is_deterministic() {
+        let config = ChaosConfig::new(7)
+            .with_delays(0.5, Duration::from_millis(10))
+            .with_drops(0.2)
+            .with_failures(0.1);
+        let path = ActorPath::from("/user/test");
+        let actions = |config: &ChaosConfig| {
+            let mut chaos = Chaos::new(config, &path);
+            (0..100).map(|_| chaos.next_action()).collect::<Vec<_>>()
+        };
+
+        let first = actions(&config);
+        assert_eq!(first, actions(&config));
+        assert!(first.contains(&ChaosAction::Fail));
+        assert!(first.contains(&ChaosAction::Drop));
+        assert!(first.contains(&ChaosAction::Deliver));
+        assert!(first
+            .iter()
+            .any(|action| matches!(action, ChaosAction::Delay(_))));
+        assert_ne!(first, actions(&ChaosConfig { seed: 8, ..config }));
+
+        let quiet = actions(&ChaosConfig::new(7));
+        assert!(quiet.iter().all(|action| *action == ChaosAction::Deliver));
+    }
+}
@@ -0,0 +1,660 @@
+use std::{sync::Arc, time::Duration};
+
+#[cfg(feature = "chaos")]
+use crate::ChaosConfig;
+use crate::{
+    ids::{IdGenerator, Ids},
+    instrumentation::{Instrumentation, MailboxInstrumentation},
+    policy::{MessagePolicy, Policy},
+    ActorConfig, ActorPath, PanicPolicy, PathPattern,
+};
+
+/// Configuration of an actor system. Use [`crate::ActorSystem::from_config()`]
+/// to create an actor system from it. For example:
+/// ```
+/// use tiny_tokio_actor::*;
+/// use std::time::Duration;
+///
+/// #[derive(Clone, Debug)]
+/// struct TestEvent(String);
+///
+/// impl SystemEvent for TestEvent {}
+///
+/// let config = SystemConfig::new("test")
+///     .with_event_bus_capacity(100)
+///     .with_mailbox_capacity(1000)
+///     .with_ask_timeout(Duration::from_secs(5));
+///
+/// let system = ActorSystem::<TestEvent>::from_config(config);
+/// assert_eq!(system.name(), "test");
+/// ```
+///
+/// With the `serde` feature enabled the configuration can also be loaded from
+/// any serde supported format, such as TOML or JSON:
+/// ```toml
+/// name = "test"
+/// event_bus_capacity = 100
+/// mailbox_capacity = 1000
+/// ask_timeout_ms = 5000
+/// ```
+///
+/// There is no setting for a remoting listen address, as this crate has no
+/// remoting transport; a remoting layer built on top of it configures its own.
+#[derive(Clone, Debug)]
+pub struct SystemConfig {
+    name: String,
+    event_bus_capacity: usize,
+    mailbox_capacity: Option<usize>,
+    ask_timeout: Option<Duration>,
+    overrides: Vec<(PathPattern, ActorConfig)>,
+    instrumentation: Option<Instrumentation>,
+    policy: Option<Policy>,
+    flap_detection: Option<(usize, Duration)>,
+    starvation_threshold: Option<Duration>,
+    bus_stats_interval: Option<Duration>,
+    event_buffering: Option<usize>,
+    panic_policy: PanicPolicy,
+    ids: Option<Ids>,
+    #[cfg(feature = "chaos")]
+    chaos: Option<ChaosConfig>,
+}
+
+impl Default for SystemConfig {
+    fn default() -> Self {
+        SystemConfig {
+            name: "default".to_string(),
+            event_bus_capacity: 1000,
+            mailbox_capacity: None,
+            ask_timeout: None,
+            overrides: Vec::new(),
+            instrumentation: None,
+            policy: None,
+            flap_detection: None,
+            starvation_threshold: None,
+            bus_stats_interval: None,
+            event_buffering: None,
+            panic_policy: PanicPolicy::default(),
+            ids: None,
+            #[cfg(feature = "chaos")]
+            chaos: None,
+        }
+    }
+}
+
+impl SystemConfig {
+    /// Creates a default configuration for an actor system with the given name.
+    pub fn new(name: &str) -> Self {
+        SystemConfig {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Set the capacity of the system event bus. Defaults to 1000.
+    pub fn with_event_bus_capacity(mut self, capacity: usize) -> Self {
+        self.event_bus_capacity = capacity;
+        self
+    }
+
+    /// Bound the mailbox of every actor to the given number of messages. By
+    /// default mailboxes are unbounded.
+    pub fn with_mailbox_capacity(mut self, capacity: usize) -> Self {
+        self.mailbox_capacity = Some(capacity);
+        self
+    }
+
+    /// Fail asks that did not receive a response within the given duration. By
+    /// default asks wait indefinitely. It can be set per actor through
+    /// [`ActorConfig::with_ask_timeout()`].
+    pub fn with_ask_timeout(mut self, timeout: Duration) -> Self {
+        self.ask_timeout = Some(timeout);
+        self
+    }
+
+    /// Override the configuration of all actors whose path matches the pattern,
+    /// e.g. `/user/ingest/*`. The settings and labels of the override are
+    /// applied when the actor is created, taking precedence over both the system
+    /// defaults and the [`ActorConfig`] passed in by the code creating the
+    /// actor. When multiple overrides match, later ones take precedence.
+    pub fn with_override(mut self, pattern: &str, config: ActorConfig) -> Self {
+        self.overrides.push((PathPattern::from(pattern), config));
+        self
+    }
+
+    /// Report the activity of all actor mailboxes to the given instrumentation.
+    pub fn with_instrumentation<I: MailboxInstrumentation>(mut self, instrumentation: I) -> Self {
+        self.instrumentation = Some(Instrumentation(Arc::new(instrumentation)));
+        self
+    }
+
+    /// Check all messages against the policy before they enter a mailbox,
+    /// see [`MessagePolicy`].
+    pub fn with_message_policy<P: MessagePolicy>(mut self, policy: P) -> Self {
+        self.policy = Some(Policy(Arc::new(policy)));
+        self
+    }
+
+    /// Report actors that restart more than `max_restarts` times within the
+    /// given window as a [`crate::FlappingActor`]. An actor is reported once
+    /// each time it crosses the limit.
+    pub fn with_flap_detection(mut self, max_restarts: usize, within: Duration) -> Self {
+        self.flap_detection = Some((max_restarts, within));
+        self
+    }
+
+    /// Warn about actors that keep their runner busy for longer than the
+    /// threshold, in a single handler or a run of messages, as a
+    /// [`crate::StarvationWarning`].
+    pub fn with_starvation_threshold(mut self, threshold: Duration) -> Self {
+        self.starvation_threshold = Some(threshold);
+        self
+    }
+
+    /// Publish the [`crate::BusStats`] of the event bus at the given interval,
+    /// see [`crate::ActorSystem::bus_stats_events()`].
+    pub fn with_bus_stats_interval(mut self, interval: Duration) -> Self {
+        self.bus_stats_interval = Some(interval);
+        self
+    }
+
+    /// Hold on to at most `limit` events published before the first
+    /// subscriber of the event bus attaches, and deliver them to it, see
+    /// [`crate::EventBus::buffer_until_subscribed()`]. Only applies to the
+    /// event bus created from this configuration.
+    pub fn with_event_buffering(mut self, limit: usize) -> Self {
+        self.event_buffering = Some(limit);
+        self
+    }
+
+    /// Set how actors panicking while handling a message are treated, see
+    /// [`PanicPolicy`]. By default they are supervised.
+    pub fn with_panic_policy(mut self, policy: PanicPolicy) -> Self {
+        self.panic_policy = policy;
+        self
+    }
+
+    /// Generate the names of anonymous actors, the incarnation ids of actors
+    /// and the correlation ids of requests through the generator, e.g. a
+    /// [`crate::SeededIds`] for identifiers that are the same on every run.
+    /// By default they are counters.
+    pub fn with_id_generator<G: IdGenerator>(mut self, generator: G) -> Self {
+        self.ids = Some(Ids(Arc::new(generator)));
+        self
+    }
+
+    /// Inject faults into the message handling of all actors, see
+    /// [`ChaosConfig`]. Requires the `chaos` feature.
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(mut self, chaos: ChaosConfig) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
+
+    /// The name of the actor system
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The capacity of the system event bus
+    pub fn event_bus_capacity(&self) -> usize {
+        self.event_bus_capacity
+    }
+
+    /// The default mailbox capacity of actors, if bounded
+    pub fn mailbox_capacity(&self) -> Option<usize> {
+        self.mailbox_capacity
+    }
+
+    /// The default ask timeout, if any
+    pub fn ask_timeout(&self) -> Option<Duration> {
+        self.ask_timeout
+    }
+
+    /// The configured per path overrides
+    pub fn overrides(&self) -> &[(PathPattern, ActorConfig)] {
+        &self.overrides
+    }
+
+    /// The maximum number of restarts within a window, if flapping actors
+    /// are reported
+    pub fn flap_detection(&self) -> Option<(usize, Duration)> {
+        self.flap_detection
+    }
+
+    /// How long an actor may keep its runner busy, if starvation is reported
+    pub fn starvation_threshold(&self) -> Option<Duration> {
+        self.starvation_threshold
+    }
+
+    /// How often the stats of the event bus are published, if they are
+    pub fn bus_stats_interval(&self) -> Option<Duration> {
+        self.bus_stats_interval
+    }
+
+    /// How many events are held back for the first subscriber of the event
+    /// bus, if they are
+    pub fn event_buffering(&self) -> Option<usize> {
+        self.event_buffering
+    }
+
+    /// How actors panicking while handling a message are treated
+    pub fn panic_policy(&self) -> PanicPolicy {
+        self.panic_policy
+    }
+
+    pub(crate) fn instrumentation(&self) -> Option<&Instrumentation> {
+        self.instrumentation.as_ref()
+    }
+
+    pub(crate) fn message_policy(&self) -> Option<&Policy> {
+        self.policy.as_ref()
+    }
+
+    pub(crate) fn id_generator(&self) -> Option<&Ids> {
+        self.ids.as_ref()
+    }
+
+    /// The injected faults, if any
+    #[cfg(feature = "chaos")]
+    pub fn chaos(&self) -> Option<&ChaosConfig> {
+        self.chaos.as_ref()
+    }
+
+    /// Resolves the effective configuration for an actor created at `path`.
+    pub(crate) fn actor_config(&self, path: &ActorPath, config: ActorConfig) -> ActorConfig {
+        let defaults = ActorConfig {
+            mailbox_capacity: self.mailbox_capacity,
+            ask_timeout: self.ask_timeout,
+            ..Default::default()
+        };
+        self.overrides
+            .iter()
+            .filter(|(pattern, _)| pattern.matches(path))
+            .fold(defaults.merge(&config), |config, (_, over)| {
+                config.merge(over)
+            })
+    }
+}
+
+#[cfg(feature = "serde")]
+mod de {
+    use std::{fmt, time::Duration};
+
+    use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+
+    use super::SystemConfig;
+    use crate::{ActorConfig, ActorPath, PathPattern, SupervisionPolicy};
+
+    const SYSTEM_FIELDS: &[&str] = &[
+        "name",
+        "event_bus_capacity",
+        "mailbox_capacity",
+        "ask_timeout_ms",
+        "overrides",
+    ];
+
+    const ACTOR_FIELDS: &[&str] = &[
+        "labels",
+        "mailbox_capacity",
+        "mailbox_bytes",
+        "throughput",
+        "time_slice_ms",
+        "supervision",
+        "ask_timeout_ms",
+        "log_level",
+        "dedicated_thread",
+        "stop_when_unreferenced",
+        "dependencies",
+    ];
+
+    const OVERRIDE_FIELDS: &[&str] = &[
+        "pattern",
+        "labels",
+        "mailbox_capacity",
+        "mailbox_bytes",
+        "throughput",
+        "time_slice_ms",
+        "supervision",
+        "ask_timeout_ms",
+        "log_level",
+        "dedicated_thread",
+        "stop_when_unreferenced",
+        "dependencies",
+    ];
+
+    const SUPERVISION_FIELDS: &[&str] = &["strategy", "max_retries", "interval_ms"];
+
+    const STRATEGIES: &[&str] = &[
+        "stop",
+        "no_interval",
+        "fixed_interval",
+        "exponential_backoff",
+    ];
+
+    struct SystemConfigVisitor;
+
+    impl<'de> Visitor<'de> for SystemConfigVisitor {
+        type Value = SystemConfig;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("an actor system configuration")
+        }
+
+        fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<SystemConfig, M::Error> {
+            let mut config = SystemConfig::default();
+            while let Some(key) = map.next_key::<String>()? {
+                match key.as_str() {
+                    "name" => config.name = map.next_value()?,
+                    "event_bus_capacity" => config.event_bus_capacity = map.next_value()?,
+                    "mailbox_capacity" => config.mailbox_capacity = map.next_value()?,
+                    "ask_timeout_ms" => {
+                        config.ask_timeout =
+                            map.next_value::<Option<u64>>()?.map(Duration::from_millis)
+                    }
+                    "overrides" => {
+                        config.overrides = map
+                            .next_value::<Overrides>()?
+                            .0
+                            .into_iter()
+                            .map(|over| (over.pattern, over.config))
+                            .collect()
+                    }
+                    other => return Err(de::Error::unknown_field(other, SYSTEM_FIELDS)),
+                }
+            }
+            Ok(config)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for SystemConfig {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_struct("SystemConfig", SYSTEM_FIELDS, SystemConfigVisitor)
+        }
+    }
+
+    /// Reads a field of an [`ActorConfig`], returning `false` if the key is not
+    /// one of its fields.
+    fn actor_config_field<'de, M: MapAccess<'de>>(
+        config: &mut ActorConfig,
+        key: &str,
+        map: &mut M,
+    ) -> Result<bool, M::Error> {
+        match key {
+            "labels" => config.labels = map.next_value()?,
+            "mailbox_capacity" => config.mailbox_capacity = map.next_value()?,
+            "mailbox_bytes" => config.mailbox_bytes = map.next_value()?,
+            "throughput" => config.throughput = map.next_value()?,
+            "time_slice_ms" => {
+                config.time_slice = map.next_value::<Option<u64>>()?.map(Duration::from_millis)
+            }
+            "supervision" => config.supervision = map.next_value()?,
+            "ask_timeout_ms" => {
+                config.ask_timeout = map.next_value::<Option<u64>>()?.map(Duration::from_millis)
+            }
+            "log_level" => {
+                config.log_level = map
+                    .next_value::<Option<String>>()?
+                    .map(|level| level.parse().map_err(de::Error::custom))
+                    .transpose()?
+            }
+            "dedicated_thread" => config.dedicated_thread = map.next_value()?,
+            "stop_when_unreferenced" => config.stop_when_unreferenced = map.next_value()?,
+            "dependencies" => {
+                config.dependencies = map
+                    .next_value::<Vec<String>>()?
+                    .iter()
+                    .map(|path| ActorPath::from(path.as_str()))
+                    .collect()
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    struct ActorConfigVisitor;
+
+    impl<'de> Visitor<'de> for ActorConfigVisitor {
+        type Value = ActorConfig;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("an actor configuration")
+        }
+
+        fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<ActorConfig, M::Error> {
+            let mut config = ActorConfig::default();
+            while let Some(key) = map.next_key::<String>()? {
+                if !actor_config_field(&mut config, &key, &mut map)? {
+                    return Err(de::Error::unknown_field(&key, ACTOR_FIELDS));
+                }
+            }
+            Ok(config)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for ActorConfig {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_struct("ActorConfig", ACTOR_FIELDS, ActorConfigVisitor)
+        }
+    }
+
+    /// An actor configuration with the path pattern it applies to.
+    struct Override {
+        pattern: PathPattern,
+        config: ActorConfig,
+    }
+
+    struct OverrideVisitor;
+
+    impl<'de> Visitor<'de> for OverrideVisitor {
+        type Value = Override;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("an actor configuration override")
+        }
+
+        fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Override, M::Error> {
+            let mut pattern = None;
+            let mut config = ActorConfig::default();
+            while let Some(key) = map.next_key::<String>()? {
+                if key == "pattern" {
+                    pattern = Some(PathPattern::from(map.next_value::<String>()?.as_str()));
+                } else if !actor_config_field(&mut config, &key, &mut map)? {
+                    return Err(de::Error::unknown_field(&key, OVERRIDE_FIELDS));
+                }
+            }
+            let pattern = pattern.ok_or_else(|| de::Error::missing_field("pattern"))?;
+            Ok(Override { pattern, config })
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Override {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_struct("Override", OVERRIDE_FIELDS, OverrideVisitor)
+        }
+    }
+
+    struct Overrides(Vec<Override>);
+
+    struct OverridesVisitor;
+
+    impl<'de> Visitor<'de> for OverridesVisitor {
+        type Value = Overrides;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a list of actor configuration overrides")
+        }
+
+        fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<Overrides, S::Error> {
+            let mut overrides = Vec::new();
+            while let Some(over) = seq.next_element::<Override>()? {
+                overrides.push(over);
+            }
+            Ok(Overrides(overrides))
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Overrides {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_seq(OverridesVisitor)
+        }
+    }
+
+    struct SupervisionPolicyVisitor;
+
+    impl<'de> Visitor<'de> for SupervisionPolicyVisitor {
+        type Value = SupervisionPolicy;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a supervision policy")
+        }
+
+        fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<SupervisionPolicy, M::Error> {
+            let mut strategy: Option<String> = None;
+            let mut max_retries = None;
+            let mut interval = None;
+            while let Some(key) = map.next_key::<String>()? {
+                match key.as_str() {
+                    "strategy" => strategy = Some(map.next_value()?),
+                    "max_retries" => max_retries = Some(map.next_value()?),
+                    "interval_ms" => interval = Some(Duration::from_millis(map.next_value()?)),
+                    other => return Err(de::Error::unknown_field(other, SUPERVISION_FIELDS)),
+                }
+            }
+            let strategy = strategy.ok_or_else(|| de::Error::missing_field("strategy"))?;
+            let max_retries = || max_retries.ok_or_else(|| de::Error::missing_field("max_retries"));
+            match strategy.as_str() {
+                "stop" => Ok(SupervisionPolicy::Stop),
+                "no_interval" => Ok(SupervisionPolicy::NoInterval {
+                    max_retries: max_retries()?,
+                }),
+                "fixed_interval" => Ok(SupervisionPolicy::FixedInterval {
+                    max_retries: max_retries()?,
+                    interval: interval.ok_or_else(|| de::Error::missing_field("interval_ms"))?,
+                }),
+                "exponential_backoff" => Ok(SupervisionPolicy::ExponentialBackoff {
+                    max_retries: max_retries()?,
+                }),
+                other => Err(de::Error::unknown_variant(other, STRATEGIES)),
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for SupervisionPolicy {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_struct(
+                "SupervisionPolicy",
+                SUPERVISION_FIELDS,
+                SupervisionPolicyVisitor,
+            )
+        }
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn deserialize_json() {
+        let json = r#"{
+            "name": "test",
+            "event_bus_capacity": 10,
+            "mailbox_capacity": 100,
+            "ask_timeout_ms": 250
+        }"#;
+        let config: SystemConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.name(), "test");
+        assert_eq!(config.event_bus_capacity(), 10);
+        assert_eq!(config.mailbox_capacity(), Some(100));
+        assert_eq!(config.ask_timeout(), Some(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn deserialize_defaults() {
+        let config: SystemConfig = serde_json::from_str(r#"{ "name": "test" }"#).unwrap();
+        assert_eq!(config.event_bus_capacity(), 1000);
+        assert_eq!(config.mailbox_capacity(), None);
+        assert_eq!(config.ask_timeout(), None);
+    }
+
+    #[test]
+    fn deserialize_unknown_field() {
+        let result = serde_json::from_str::<SystemConfig>(r#"{ "nmae": "test" }"#);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn deserialize_overrides() {
+        let json = r#"{
+            "name": "test",
+            "mailbox_capacity": 100,
+            "overrides": [
+                {
+                    "pattern": "/user/ingest/*",
+                    "mailbox_capacity": 10,
+                    "mailbox_bytes": 4096,
+                    "throughput": 5,
+                    "time_slice_ms": 20,
+                    "stop_when_unreferenced": true,
+                    "labels": { "tier": "ingest" },
+                    "log_level": "warn",
+                    "supervision": {
+                        "strategy": "fixed_interval",
+                        "max_retries": 3,
+                        "interval_ms": 100
+                    }
+                }
+            ]
+        }"#;
+        let config: SystemConfig = serde_json::from_str(json).unwrap();
+        let ingest =
+            config.actor_config(&ActorPath::from("/user/ingest/a"), ActorConfig::default());
+        assert_eq!(ingest.mailbox_capacity(), Some(10));
+        assert_eq!(ingest.mailbox_bytes(), Some(4096));
+        assert_eq!(ingest.throughput(), Some(5));
+        assert_eq!(ingest.time_slice(), Some(Duration::from_millis(20)));
+        assert!(ingest.stop_when_unreferenced());
+        assert_eq!(ingest.label("tier"), Some("ingest"));
+        assert_eq!(ingest.log_level(), Some(log::LevelFilter::Warn));
+        assert_eq!(
+            ingest.supervision(),
+            Some(&crate::SupervisionPolicy::FixedInterval {
+                max_retries: 3,
+                interval: Duration::from_millis(100)
+            })
+        );
+    }
+}
+
+#[cfg(test)]
+mod override_tests {
+
+    use super::*;
+
+    #[test]
+    fn resolve_overrides() {
+        let config = SystemConfig::new("test")
+            .with_mailbox_capacity(100)
+            .with_override(
+                "/user/ingest/*",
+                ActorConfig::default().with_mailbox_capacity(10),
+            )
+            .with_override(
+                "/user/ingest/slow",
+                ActorConfig::default().with_throughput(1),
+            );
+
+        let other = config.actor_config(&ActorPath::from("/user/other"), ActorConfig::default());
+        assert_eq!(other.mailbox_capacity(), Some(100));
+
+        let code = ActorConfig::default()
+            .with_mailbox_capacity(50)
+            .with_throughput(20)
+            .with_label("tenant", "acme");
+        let slow = config.actor_config(&ActorPath::from("/user/ingest/slow"), code);
+        assert_eq!(slow.mailbox_capacity(), Some(10));
+        assert_eq!(slow.throughput(), Some(1));
+        assert_eq!(slow.label("tenant"), Some("acme"));
+    }
+}
@@ -0,0 +1,335 @@
+use std::{marker::PhantomData, time::Duration};
+
+use async_trait::async_trait;
+use tokio::time::Instant;
+
+use crate::{
+    Actor, ActorContext, ActorError, ActorPath, Handler, Message, NoEvent, RetryStrategy,
+    SupervisionPolicy, SupervisionStrategy, SystemEvent,
+};
+
+/// The state of the connection of a [`ManagedConnection`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// The actor is connecting.
+    Connecting,
+    /// The actor is connected.
+    Connected,
+    /// The actor lost its connection, or failed to connect. It reconnects
+    /// when it receives the next request.
+    Disconnected,
+}
+
+/// The connection of a [`ManagedConnection`] changed state, see
+/// [`ActorSystem::connection_changes()`](crate::ActorSystem::connection_changes).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConnectionChange {
+    /// The actor owning the connection.
+    pub path: ActorPath,
+    /// The new state of the connection.
+    pub state: ConnectionState,
+}
+
+/// What a [`ManagedConnection`] does with the requests it receives while it
+/// is disconnected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WhileDisconnected {
+    /// Reconnect before serving the request, backing off between attempts.
+    /// Requests arriving meanwhile wait in the mailbox. The request fails
+    /// with the error of the last attempt once the backoff gives up.
+    Buffer,
+    /// Attempt to reconnect at most once per backoff, failing the request
+    /// with the error of the attempt, or with `ActorError::Disconnected`
+    /// while waiting for the next attempt.
+    Reject,
+}
+
+/// Connects to an external resource such as a database or a websocket, for
+/// a [`ManagedConnection`].
+#[async_trait]
+pub trait Connector<E: SystemEvent = NoEvent>: Send + Sync + 'static {
+    /// The connection to the resource
+    type Connection: Send + Sync + 'static;
+
+    /// Opens a new connection.
+    async fn connect(&mut self, ctx: &mut ActorContext<E>) -> Result<Self::Connection, ActorError>;
+
+    /// Checks the connection before every request, dropping it and
+    /// reconnecting when it is not healthy. Keep it cheap, e.g. by checking
+    /// a flag the connection sets when it fails. Defaults to `true`.
+    async fn is_healthy(&mut self, _connection: &mut Self::Connection) -> bool {
+        true
+    }
+}
+
+/// A message sent to a [`ManagedConnection`], responding with a
+/// `Result<_, ActorError>`.
+pub trait ConnectionRequest: Message {}
+
+/// Serves the requests of type `M` sent to a [`ManagedConnection`] over its
+/// connection.
+#[async_trait]
+pub trait Serve<E: SystemEvent, M: ConnectionRequest>: Connector<E> {
+    async fn serve(
+        &mut self,
+        connection: &mut Self::Connection,
+        msg: M,
+        ctx: &mut ActorContext<E>,
+    ) -> M::Response;
+}
+
+/// An actor owning the connection of a [`Connector`]. It connects when it
+/// starts, checks the connection before every request, and reconnects once
+/// the connection is lost, backing off between attempts as set through
+/// [`ManagedConnection::with_backoff()`]. Requests are
+/// [`ConnectionRequest`]s, served through [`Serve`]. For example:
+/// ```
+/// # use tiny_tokio_actor::*;
+/// struct Database {
+///     url: String,
+/// }
+///
+/// struct Session;
+///
+/// # impl Session {
+/// #     async fn open(_url: &str) -> Result<Self, ActorError> { Ok(Session) }
+/// #     async fn query(&mut self, _sql: &str) -> Result<Vec<String>, ActorError> { Ok(vec![]) }
+/// # }
+/// #[async_trait]
+/// impl Connector for Database {
+///     type Connection = Session;
+///
+///     async fn connect(&mut self, _ctx: &mut ActorContext) -> Result<Session, ActorError> {
+///         Session::open(&self.url).await
+///     }
+/// }
+///
+/// #[derive(Clone, Debug)]
+/// struct Query(String);
+///
+/// impl Message for Query {
+///     type Response = Result<Vec<String>, ActorError>;
+/// }
+///
+/// impl ConnectionRequest for Query {}
+///
+/// #[async_trait]
+/// impl Serve<NoEvent, Query> for Database {
+///     async fn serve(
+///         &mut self,
+///         session: &mut Session,
+///         msg: Query,
+///         _ctx: &mut ActorContext,
+///     ) -> Result<Vec<String>, ActorError> {
+///         session.query(&msg.0).await
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), ActorError> {
+/// let system = ActorSystem::without_events("app");
+/// let database = Database { url: "postgres://localhost/app".to_string() };
+/// let backoff = SupervisionPolicy::ExponentialBackoff { max_retries: 5 };
+/// let database = ManagedConnection::new(database).with_backoff(backoff);
+/// let database = system.create_actor("database", database).await?;
+/// let rows = database.ask(Query("SELECT name FROM users".to_string())).await??;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Changes of the state of the connection are published as
+/// [`ConnectionChange`]s, see
+/// [`ActorSystem::connection_changes()`](crate::ActorSystem::connection_changes).
+pub struct ManagedConnection<C, E = NoEvent>
+where
+    C: Connector<E>,
+    E: SystemEvent,
+{
+    connector: C,
+    connection: Option<C::Connection>,
+    backoff: SupervisionPolicy,
+    while_disconnected: WhileDisconnected,
+    outage: Option<Outage>,
+    _events: PhantomData<fn() -> E>,
+}
+
+/// The backoff of the attempts to reconnect since the connection was lost.
+struct Outage {
+    retry: Option<Box<dyn RetryStrategy>>,
+    attempts: usize,
+    next_attempt: Option<Instant>,
+}
+
+impl<C, E> ManagedConnection<C, E>
+where
+    C: Connector<E>,
+    E: SystemEvent,
+{
+    /// Manages the connections of the connector, retrying to connect up to
+    /// 10 times with an exponential backoff, buffering requests meanwhile.
+    pub fn new(connector: C) -> Self {
+        ManagedConnection {
+            connector,
+            connection: None,
+            backoff: SupervisionPolicy::ExponentialBackoff { max_retries: 10 },
+            while_disconnected: WhileDisconnected::Buffer,
+            outage: None,
+            _events: PhantomData,
+        }
+    }
+
+    /// Back off between the attempts to reconnect as described by the policy.
+    /// With `SupervisionPolicy::Stop`, a single attempt is made per request.
+    pub fn with_backoff(mut self, backoff: SupervisionPolicy) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Set what to do with requests while disconnected, see
+    /// [`WhileDisconnected`].
+    pub fn with_disconnected_policy(mut self, while_disconnected: WhileDisconnected) -> Self {
+        self.while_disconnected = while_disconnected;
+        self
+    }
+
+    fn report(&self, ctx: &ActorContext<E>, state: ConnectionState) {
+        ctx.system.report_connection(ConnectionChange {
+            path: ctx.path.clone(),
+            state,
+        });
+    }
+
+    /// Attempts to connect once.
+    async fn connect(&mut self, ctx: &mut ActorContext<E>) -> Result<(), ActorError> {
+        self.report(ctx, ConnectionState::Connecting);
+        match self.connector.connect(ctx).await {
+            Ok(connection) => {
+                self.connection = Some(connection);
+                self.outage = None;
+                self.report(ctx, ConnectionState::Connected);
+                Ok(())
+            }
+            Err(error) => {
+                log::warn!(
+                    actor:% = &ctx.path;
+                    "Actor '{}' failed to connect: {:?}",
+                    &ctx.path,
+                    error
+                );
+                self.report(ctx, ConnectionState::Disconnected);
+                Err(error)
+            }
+        }
+    }
+
+    /// The time to wait before the next attempt to reconnect, or `None` once
+    /// the backoff gives up, which starts a new one.
+    fn next_backoff(&mut self) -> Option<Duration> {
+        let backoff = &self.backoff;
+        let outage = self.outage.get_or_insert_with(|| Outage {
+            retry: match backoff.strategy() {
+                SupervisionStrategy::Retry(retry) => Some(retry),
+                SupervisionStrategy::Stop => None,
+            },
+            attempts: 0,
+            next_attempt: None,
+        });
+        match &mut outage.retry {
+            Some(retry) if outage.attempts < retry.max_retries() => {
+                outage.attempts += 1;
+                Some(retry.next_backoff().unwrap_or_default())
+            }
+            _ => {
+                self.outage = None;
+                None
+            }
+        }
+    }
+
+    /// Makes sure the actor has a healthy connection before serving a request.
+    async fn ensure_connected(&mut self, ctx: &mut ActorContext<E>) -> Result<(), ActorError> {
+        if let Some(connection) = self.connection.as_mut() {
+            if self.connector.is_healthy(connection).await {
+                return Ok(());
+            }
+            log::warn!(actor:% = &ctx.path; "Actor '{}' lost its connection", &ctx.path);
+            self.connection = None;
+            self.report(ctx, ConnectionState::Disconnected);
+        }
+        match self.while_disconnected {
+            WhileDisconnected::Buffer => loop {
+                let error = match self.connect(ctx).await {
+                    Ok(()) => return Ok(()),
+                    Err(error) => error,
+                };
+                let delay = match self.next_backoff() {
+                    Some(delay) => delay,
+                    None => return Err(error),
+                };
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = ctx.cancellation_token().cancelled() => {
+                        return Err(ActorError::Disconnected(ctx.path.clone()));
+                    }
+                }
+            },
+            WhileDisconnected::Reject => {
+                let next_attempt = self.outage.as_ref().and_then(|outage| outage.next_attempt);
+                if next_attempt.is_some_and(|next_attempt| next_attempt > Instant::now()) {
+                    return Err(ActorError::Disconnected(ctx.path.clone()));
+                }
+                let connected = self.connect(ctx).await;
+                if connected.is_err() {
+                    // A backoff that gave up starts over, to keep trying
+                    let delay = self
+                        .next_backoff()
+                        .or_else(|| self.next_backoff())
+                        .unwrap_or_default();
+                    if let Some(outage) = self.outage.as_mut() {
+                        outage.next_attempt = Some(Instant::now() + delay);
+                    }
+                }
+                connected
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<C, E> Actor<E> for ManagedConnection<C, E>
+where
+    C: Connector<E>,
+    E: SystemEvent,
+{
+    async fn pre_start(&mut self, ctx: &mut ActorContext<E>) -> Result<(), ActorError> {
+        // Failing to connect does not fail the start, the actor reconnects
+        // with the next request
+        if self.connection.is_none() {
+            self.connect(ctx).await.unwrap_or_default();
+        }
+        Ok(())
+    }
+
+    async fn post_stop(&mut self, ctx: &mut ActorContext<E>) {
+        if self.connection.take().is_some() {
+            self.report(ctx, ConnectionState::Disconnected);
+        }
+    }
+}
+
+#[async_trait]
+impl<C, E, M, R> Handler<E, M> for ManagedConnection<C, E>
+where
+    C: Serve<E, M>,
+    E: SystemEvent,
+    M: ConnectionRequest<Response = Result<R, ActorError>>,
+    R: Send + Sync + 'static,
+{
+    async fn handle(&mut self, msg: M, ctx: &mut ActorContext<E>) -> Result<R, ActorError> {
+        self.ensure_connected(ctx).await?;
+        match self.connection.as_mut() {
+            Some(connection) => self.connector.serve(connection, msg, ctx).await,
+            None => Err(ActorError::Disconnected(ctx.path.clone())),
+        }
+    }
+}
@@ -0,0 +1,520 @@
+use std::{
+    any::Any,
+    collections::VecDeque,
+    ffi::OsString,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    actor::handler::BoxedMessageHandler, builtin::BuiltinBus, Actor, ActorContext, ActorError,
+    ActorPath, ActorRef, Handler, Message, SystemEvent,
+};
+
+/// Why a message could not be delivered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeadLetterReason {
+    /// The bounded mailbox of the recipient was full.
+    MailboxFull,
+    /// The recipient was not running anymore, or stopped before handling it.
+    Terminated,
+    /// Sending the message exceeded a quota of the recipient, see
+    /// [`crate::Quotas`].
+    QuotaExceeded,
+    /// The message policy of the system rejected the message, see
+    /// [`crate::MessagePolicy`].
+    Unauthorized,
+    /// The message was handled, but its caller stopped waiting for the
+    /// response before it could be sent back, e.g. as its ask timed out.
+    CallerGone,
+    /// The deadline of the message passed while it was in the mailbox, see
+    /// [`crate::Deadline`].
+    Expired,
+    /// The message was dropped by fault injection, see
+    /// `ChaosConfig::with_drops()`. Only produced with the `chaos` feature.
+    Injected,
+}
+
+impl From<&ActorError> for DeadLetterReason {
+    fn from(error: &ActorError) -> Self {
+        match error {
+            ActorError::MailboxFull => DeadLetterReason::MailboxFull,
+            ActorError::QuotaExceeded { .. } => DeadLetterReason::QuotaExceeded,
+            ActorError::Unauthorized { .. } => DeadLetterReason::Unauthorized,
+            _ => DeadLetterReason::Terminated,
+        }
+    }
+}
+
+/// A message that could not be delivered to its recipient.
+#[derive(Clone)]
+pub struct DeadLetter {
+    recipient: ActorPath,
+    message_type: &'static str,
+    reason: DeadLetterReason,
+    timestamp: SystemTime,
+    payload: Arc<dyn Any + Send + Sync>,
+}
+
+impl DeadLetter {
+    /// The path of the actor the message was sent to.
+    pub fn recipient(&self) -> &ActorPath {
+        &self.recipient
+    }
+
+    /// The type name of the message.
+    pub fn message_type(&self) -> &'static str {
+        self.message_type
+    }
+
+    pub fn reason(&self) -> DeadLetterReason {
+        self.reason
+    }
+
+    /// When the message was found to be undeliverable.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+
+    /// The message itself, if it is of type `M`.
+    pub fn payload<M: Message>(&self) -> Option<&M> {
+        self.payload.downcast_ref()
+    }
+
+    fn to_line(&self) -> String {
+        let millis = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis())
+            .unwrap_or_default();
+        format!(
+            "{} {:?} {} {}\n",
+            millis, self.reason, self.recipient, self.message_type
+        )
+    }
+}
+
+impl std::fmt::Debug for DeadLetter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeadLetter")
+            .field("recipient", &self.recipient)
+            .field("message_type", &self.message_type)
+            .field("reason", &self.reason)
+            .field("timestamp", &self.timestamp)
+            .finish()
+    }
+}
+
+impl Message for DeadLetter {
+    type Response = ();
+}
+
+type Subscriber = Arc<dyn Fn(DeadLetter) + Send + Sync>;
+
+/// The system wide destination of dead letters. Until a subscriber is set or
+/// the bus of dead letters is subscribed to, dead letters are only logged.
+#[derive(Clone)]
+pub(crate) struct DeadLetters {
+    subscriber: Arc<RwLock<Option<(ActorPath, Subscriber)>>>,
+    bus: BuiltinBus<DeadLetter>,
+}
+
+impl DeadLetters {
+    pub fn new(bus: BuiltinBus<DeadLetter>) -> Self {
+        DeadLetters {
+            subscriber: Default::default(),
+            bus,
+        }
+    }
+
+    /// Sends all dead letters to the actor at the given reference.
+    pub fn subscribe<E: SystemEvent, A: Actor<E> + Handler<E, DeadLetter>>(
+        &self,
+        actor_ref: ActorRef<E, A>,
+    ) {
+        let path = actor_ref.path().clone();
+        let subscriber: Subscriber = Arc::new(move |letter| {
+            actor_ref.tell(letter).unwrap_or_default();
+        });
+        *self.subscriber.write().unwrap() = Some((path, subscriber));
+    }
+
+    /// Sends the message to the subscriber as a dead letter to `recipient`.
+    pub fn publish<E: SystemEvent, A: Actor<E>>(
+        &self,
+        recipient: &ActorPath,
+        message: BoxedMessageHandler<E, A>,
+        reason: DeadLetterReason,
+    ) {
+        let message_type = message.message_type();
+        self.publish_with(recipient, message_type, reason, || message.into_payload());
+    }
+
+    /// Sends the message to the subscriber as a dead letter to `recipient`,
+    /// when it has been taken out of the mailbox already.
+    pub fn publish_message<M: Message>(
+        &self,
+        recipient: &ActorPath,
+        message: M,
+        reason: DeadLetterReason,
+    ) {
+        let message_type = std::any::type_name::<M>();
+        self.publish_with(recipient, message_type, reason, || Arc::new(message));
+    }
+
+    fn publish_with<F>(
+        &self,
+        recipient: &ActorPath,
+        message_type: &'static str,
+        reason: DeadLetterReason,
+        payload: F,
+    ) where
+        F: FnOnce() -> Arc<dyn Any + Send + Sync>,
+    {
+        // Letters to the subscriber itself are dropped, as they would otherwise
+        // be sent back to it indefinitely.
+        let subscriber = match &*self.subscriber.read().unwrap() {
+            Some((path, subscriber)) if path != recipient => Some(subscriber.clone()),
+            _ => None,
+        };
+        if subscriber.is_none() && self.bus.receiver_count() == 0 {
+            return;
+        }
+        let letter = DeadLetter {
+            recipient: recipient.clone(),
+            message_type,
+            reason,
+            timestamp: SystemTime::now(),
+            payload: payload(),
+        };
+        if self.bus.receiver_count() > 0 {
+            self.bus.send(letter.clone());
+        }
+        if let Some(subscriber) = subscriber {
+            subscriber(letter);
+        }
+    }
+}
+
+/// Configuration of the dead letter actor.
+#[derive(Clone, Debug)]
+pub struct DeadLetterConfig {
+    capacity: usize,
+    file: Option<DeadLetterFile>,
+}
+
+#[derive(Clone, Debug)]
+struct DeadLetterFile {
+    path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+}
+
+impl Default for DeadLetterConfig {
+    fn default() -> Self {
+        DeadLetterConfig {
+            capacity: 1000,
+            file: None,
+        }
+    }
+}
+
+impl DeadLetterConfig {
+    /// Keeps the last `capacity` dead letters in memory.
+    pub fn new(capacity: usize) -> Self {
+        DeadLetterConfig {
+            capacity,
+            file: None,
+        }
+    }
+
+    /// Also appends every dead letter as a line to the file at `path`. Once the
+    /// file grows beyond `max_size` bytes it is renamed to `<path>.1` (shifting
+    /// older files up), keeping at most `max_files` of the rotated files.
+    pub fn with_file<P: Into<PathBuf>>(mut self, path: P, max_size: u64, max_files: usize) -> Self {
+        self.file = Some(DeadLetterFile {
+            path: path.into(),
+            max_size,
+            max_files,
+        });
+        self
+    }
+}
+
+/// Appends lines to a file, rotating it once it grows too big.
+struct RotatingFile {
+    config: DeadLetterFile,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn new(config: DeadLetterFile) -> Self {
+        RotatingFile {
+            config,
+            file: None,
+            size: 0,
+        }
+    }
+
+    fn rotated(&self, index: usize) -> PathBuf {
+        let mut path = OsString::from(self.config.path.as_os_str());
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file = None;
+        self.size = 0;
+        if self.config.max_files == 0 {
+            return std::fs::remove_file(&self.config.path);
+        }
+        for index in (1..self.config.max_files).rev() {
+            let from = self.rotated(index);
+            if from.exists() {
+                std::fs::rename(&from, self.rotated(index + 1))?;
+            }
+        }
+        std::fs::rename(&self.config.path, self.rotated(1))
+    }
+
+    fn append(&mut self, line: &str) -> std::io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.config.max_size {
+            self.rotate()?;
+        }
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.config.path)?;
+                self.size = file.metadata()?.len();
+                self.file.insert(file)
+            }
+        };
+        file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+/// The built-in actor at '/system/deadletters' that keeps the most recent dead
+/// letters. It is started through [`ActorSystem::start_dead_letters()`](crate::ActorSystem::start_dead_letters).
+pub struct DeadLetterActor {
+    capacity: usize,
+    letters: VecDeque<DeadLetter>,
+    file: Option<RotatingFile>,
+}
+
+impl DeadLetterActor {
+    pub(crate) fn new(config: DeadLetterConfig) -> Self {
+        DeadLetterActor {
+            capacity: config.capacity,
+            letters: VecDeque::with_capacity(config.capacity),
+            file: config.file.map(RotatingFile::new),
+        }
+    }
+}
+
+impl<E: SystemEvent> Actor<E> for DeadLetterActor {}
+
+#[async_trait]
+impl<E: SystemEvent> Handler<E, DeadLetter> for DeadLetterActor {
+    async fn handle(&mut self, letter: DeadLetter, _ctx: &mut ActorContext<E>) {
+        if let Some(file) = &mut self.file {
+            if let Err(error) = file.append(&letter.to_line()) {
+                log::warn!("Failed to write dead letter to file: {}", error);
+            }
+        }
+        if self.capacity > 0 {
+            if self.letters.len() == self.capacity {
+                self.letters.pop_front();
+            }
+            self.letters.push_back(letter);
+        }
+    }
+}
+
+/// Asks the dead letter actor for the dead letters it holds, oldest first.
+#[derive(Clone, Debug)]
+pub struct GetDeadLetters;
+
+impl Message for GetDeadLetters {
+    type Response = Vec<DeadLetter>;
+}
+
+#[async_trait]
+impl<E: SystemEvent> Handler<E, GetDeadLetters> for DeadLetterActor {
+    async fn handle(
+        &mut self,
+        _msg: GetDeadLetters,
+        _ctx: &mut ActorContext<E>,
+    ) -> Vec<DeadLetter> {
+        self.letters.iter().cloned().collect()
+    }
+}
+
+type LetterFilter = Arc<dyn Fn(&DeadLetter) -> bool + Send + Sync>;
+
+/// Asks the dead letter actor to send the dead letters with a message of type
+/// `M` to their recipients again, if these are running as actors of type `A`
+/// by now, e.g. to recover the messages sent to an actor while it was
+/// restarting. For example:
+/// ```
+/// # use tiny_tokio_actor::*;
+/// # #[derive(Clone, Debug)]
+/// # struct Job(u64);
+/// # impl Message for Job { type Response = (); }
+/// # struct Worker;
+/// # impl Actor for Worker {}
+/// # #[async_trait]
+/// # impl Handler<NoEvent, Job> for Worker {
+/// #     async fn handle(&mut self, _msg: Job, _ctx: &mut ActorContext) {}
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), ActorError> {
+/// let system = ActorSystem::without_events("test");
+/// let dead_letters = system.start_dead_letters(DeadLetterConfig::new(100)).await?;
+///
+/// let worker = system.create_actor("worker", Worker).await?;
+/// system.stop_subtree(worker.path()).await;
+/// assert!(worker.tell(Job(1)).is_err());
+///
+/// system.create_actor("worker", Worker).await?;
+/// let replay = ReplayDeadLetters::<Worker, Job>::new()
+///     .with_filter(|letter| letter.reason() == DeadLetterReason::Terminated);
+/// # // The dead letter is sent to the dead letter actor asynchronously
+/// # while dead_letters.ask(GetDeadLetters).await?.is_empty() {
+/// #     tokio::task::yield_now().await;
+/// # }
+/// assert_eq!(dead_letters.ask(replay).await?, 1);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// The replayed letters are removed from the dead letter actor, while the
+/// ones whose recipient is not running are kept. Responds with the number of
+/// letters replayed.
+pub struct ReplayDeadLetters<A, M> {
+    filter: Option<LetterFilter>,
+    _recipient: std::marker::PhantomData<fn() -> (A, M)>,
+}
+
+impl<A, M> ReplayDeadLetters<A, M> {
+    /// Replays all dead letters with a message of type `M`.
+    pub fn new() -> Self {
+        ReplayDeadLetters {
+            filter: None,
+            _recipient: std::marker::PhantomData,
+        }
+    }
+
+    /// Only replays the dead letters matching the filter.
+    pub fn with_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&DeadLetter) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+}
+
+impl<A, M> Default for ReplayDeadLetters<A, M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A, M> Clone for ReplayDeadLetters<A, M> {
+    fn clone(&self) -> Self {
+        ReplayDeadLetters {
+            filter: self.filter.clone(),
+            _recipient: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<A: 'static, M: 'static> Message for ReplayDeadLetters<A, M> {
+    type Response = usize;
+}
+
+#[async_trait]
+impl<E, A, M> Handler<E, ReplayDeadLetters<A, M>> for DeadLetterActor
+where
+    E: SystemEvent,
+    A: Actor<E> + Handler<E, M>,
+    M: Message,
+{
+    async fn handle(&mut self, msg: ReplayDeadLetters<A, M>, ctx: &mut ActorContext<E>) -> usize {
+        let mut replayed = 0;
+        let mut kept = VecDeque::with_capacity(self.letters.len());
+        for letter in std::mem::take(&mut self.letters) {
+            let message = letter
+                .payload::<M>()
+                .filter(|_| msg.filter.as_ref().is_none_or(|filter| filter(&letter)));
+            let recipient = match message {
+                Some(_) => ctx.system.get_actor::<A>(letter.recipient()).await,
+                None => None,
+            };
+            match (message, recipient) {
+                (Some(message), Some(recipient)) => {
+                    // A letter that cannot be delivered again comes back as
+                    // a new dead letter
+                    if recipient.tell(message.clone()).is_ok() {
+                        replayed += 1;
+                    }
+                }
+                _ => kept.push_back(letter),
+            }
+        }
+        self.letters = kept;
+        replayed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn letter(index: usize) -> DeadLetter {
+        DeadLetter {
+            recipient: ActorPath::from("/user/test"),
+            message_type: "Test",
+            reason: DeadLetterReason::Terminated,
+            timestamp: SystemTime::now(),
+            payload: Arc::new(index),
+        }
+    }
+
+    #[test]
+    fn rotating_file() {
+        let dir = std::env::temp_dir().join(format!("dead-letters-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("deadletters.log");
+        let line = letter(0).to_line();
+        let mut file = RotatingFile::new(DeadLetterFile {
+            path: path.clone(),
+            max_size: line.len() as u64 * 2,
+            max_files: 2,
+        });
+
+        for _ in 0..7 {
+            file.append(&line).unwrap();
+        }
+
+        let len = |path: PathBuf| std::fs::metadata(path).unwrap().len();
+        assert_eq!(len(path.clone()), line.len() as u64);
+        assert_eq!(len(file.rotated(1)), line.len() as u64 * 2);
+        assert_eq!(len(file.rotated(2)), line.len() as u64 * 2);
+        assert!(!file.rotated(3).exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
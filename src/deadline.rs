@@ -0,0 +1,109 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+tokio::task_local! {
+    /// The deadline of the work the current task is doing.
+    static DEADLINE: Option<Deadline>;
+}
+
+/// The point in time by which the work for a request must be done, e.g. the
+/// timeout of the web request that a handler turned into a message. It is
+/// attached to the messages sent within [`Deadline::scope()`], and the
+/// messages that their handlers send and the asks they make carry it as
+/// well, so the whole budget of the request is shared by all actors working
+/// on it instead of every hop having its own timeout. For example:
+/// ```
+/// # use tiny_tokio_actor::*;
+/// # use std::time::Duration;
+/// # #[derive(Clone, Debug)]
+/// # struct TestEvent;
+/// # impl SystemEvent for TestEvent {}
+/// #[derive(Clone, Debug)]
+/// struct Search(String);
+///
+/// impl Message for Search {
+///     type Response = Vec<String>;
+/// }
+///
+/// struct Index;
+///
+/// impl Actor<TestEvent> for Index {}
+///
+/// #[async_trait]
+/// impl Handler<TestEvent, Search> for Index {
+///     async fn handle(&mut self, msg: Search, ctx: &mut ActorContext<TestEvent>) -> Vec<String> {
+///         // Skip the expensive ranking when the budget is nearly spent
+///         match ctx.remaining_time() {
+///             Some(remaining) if remaining < Duration::from_millis(10) => vec![],
+///             _ => vec![msg.0],
+///         }
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), ActorError> {
+/// let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+/// let index = system.create_actor("index", Index).await?;
+///
+/// let deadline = Deadline::after(Duration::from_millis(200));
+/// let results = deadline.scope(index.ask(Search("actors".to_string()))).await?;
+/// assert_eq!(results, vec!["actors"]);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Asks within a deadline fail with an `ActorError::Timeout` once it
+/// passes, even without an ask timeout. Messages that are still in the
+/// mailbox when their deadline passes are not handled, but become dead
+/// letters with reason [`crate::DeadLetterReason::Expired`]. A message sent
+/// through [`crate::ActorRef::tell_latest()`] that replaces a queued one
+/// carries its own deadline. Tasks spawned by a handler do not carry the
+/// deadline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    /// The deadline at the given instant
+    pub fn at(instant: Instant) -> Self {
+        Deadline(instant)
+    }
+
+    /// The deadline the timeout from now
+    pub fn after(timeout: Duration) -> Self {
+        Deadline(Instant::now() + timeout)
+    }
+
+    /// The instant of the deadline
+    pub fn instant(&self) -> Instant {
+        self.0
+    }
+
+    /// The time left until the deadline, zero once it passed
+    pub fn remaining(&self) -> Duration {
+        self.0.saturating_duration_since(Instant::now())
+    }
+
+    /// True once the deadline passed
+    pub fn is_expired(&self) -> bool {
+        self.0 <= Instant::now()
+    }
+
+    /// Runs the future within the deadline: the messages it sends carry it.
+    /// Within an earlier deadline, the earlier one is kept.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        let deadline = Deadline::current().map_or(self, |current| current.min(self));
+        DEADLINE.scope(Some(deadline), future).await
+    }
+
+    /// The deadline of the current task, if any
+    pub fn current() -> Option<Deadline> {
+        DEADLINE.try_with(|deadline| *deadline).ok().flatten()
+    }
+}
+
+/// Runs the handling of a message within the deadline it carries.
+pub(crate) async fn within<F: Future>(deadline: Option<Deadline>, handler: F) -> F::Output {
+    DEADLINE.scope(deadline, handler).await
+}
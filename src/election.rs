@@ -0,0 +1,150 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use tokio_util::sync::CancellationToken;
+
+use crate::{ActorPath, ActorSystem, SystemEvent};
+
+/// Where the leases of leaders are kept, see
+/// [`ActorContext::campaign()`](crate::ActorContext::campaign). Use
+/// [`LocalLeases`] to elect a leader among the actors of a process, or
+/// implement it on top of e.g. a database or lock service to elect one across
+/// processes. Both methods are called from a task of their own, and may block
+/// briefly.
+pub trait LeaseStore: Send + Sync + 'static {
+    /// Acquires the lease for the holder for the given time to live, or
+    /// renews it if the holder already has it. Returns `false` if another
+    /// holder has a lease that did not expire yet.
+    fn try_acquire(&self, lease: &str, holder: &str, ttl: Duration) -> bool;
+
+    /// Gives up the lease, if the holder has it.
+    fn release(&self, lease: &str, holder: &str);
+}
+
+/// A [`LeaseStore`] in memory, shared by the actors campaigning within a
+/// process.
+#[derive(Default)]
+pub struct LocalLeases {
+    leases: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl LocalLeases {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The current holder of the lease, if it did not expire
+    pub fn holder(&self, lease: &str) -> Option<String> {
+        let leases = self.leases.lock().unwrap();
+        leases
+            .get(lease)
+            .filter(|(_, expires)| *expires > Instant::now())
+            .map(|(holder, _)| holder.clone())
+    }
+}
+
+impl LeaseStore for LocalLeases {
+    fn try_acquire(&self, lease: &str, holder: &str, ttl: Duration) -> bool {
+        let mut leases = self.leases.lock().unwrap();
+        let now = Instant::now();
+        match leases.get(lease) {
+            Some((current, expires)) if current != holder && *expires > now => false,
+            _ => {
+                leases.insert(lease.to_string(), (holder.to_string(), now + ttl));
+                true
+            }
+        }
+    }
+
+    fn release(&self, lease: &str, holder: &str) {
+        let mut leases = self.leases.lock().unwrap();
+        if leases
+            .get(lease)
+            .is_some_and(|(current, _)| current == holder)
+        {
+            leases.remove(lease);
+        }
+    }
+}
+
+/// An actor became or stopped being the leader for a lease, see
+/// [`ActorSystem::leadership_changes()`](crate::ActorSystem::leadership_changes).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LeadershipChange {
+    /// The name of the lease.
+    pub lease: String,
+    /// The actor campaigning for the lease.
+    pub path: ActorPath,
+    /// True if the actor became the leader, false if it lost or gave up the
+    /// lease.
+    pub elected: bool,
+}
+
+/// The campaign of an actor for a lease, see
+/// [`ActorContext::campaign()`](crate::ActorContext::campaign). It ends when
+/// the actor stops or it resigns; dropping it does not end it.
+#[derive(Clone)]
+pub struct Leadership {
+    elected: Arc<AtomicBool>,
+    stopped: CancellationToken,
+}
+
+impl Leadership {
+    /// True if the actor currently holds the lease
+    pub fn is_leader(&self) -> bool {
+        self.elected.load(Ordering::Acquire)
+    }
+
+    /// Ends the campaign, giving up the lease if the actor holds it.
+    pub fn resign(&self) {
+        self.stopped.cancel();
+    }
+}
+
+/// Keeps acquiring the lease for the actor at the path, renewing it three
+/// times per time to live, until the campaign ends.
+pub(crate) fn campaign<E: SystemEvent>(
+    system: ActorSystem<E>,
+    path: ActorPath,
+    lease: &str,
+    store: Arc<dyn LeaseStore>,
+    ttl: Duration,
+    stopped: CancellationToken,
+) -> Leadership {
+    let leadership = Leadership {
+        elected: Default::default(),
+        stopped: stopped.clone(),
+    };
+    let elected = leadership.elected.clone();
+    let lease = lease.to_string();
+    // Distinguishes actors with the same path in different processes
+    let holder = format!("{}:{}", std::process::id(), path);
+    tokio::spawn(async move {
+        let change = |elected: bool| LeadershipChange {
+            lease: lease.clone(),
+            path: path.clone(),
+            elected,
+        };
+        loop {
+            let acquired = store.try_acquire(&lease, &holder, ttl);
+            if elected.swap(acquired, Ordering::AcqRel) != acquired {
+                system.report_leadership(change(acquired));
+            }
+            tokio::select! {
+                _ = stopped.cancelled() => break,
+                _ = tokio::time::sleep(ttl / 3) => {}
+            }
+        }
+        if elected.swap(false, Ordering::AcqRel) {
+            store.release(&lease, &holder);
+            system.report_leadership(change(false));
+        }
+    });
+    leadership
+}
@@ -0,0 +1,136 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{Actor, ActorPath, ActorRef, ActorSystem, SystemEvent};
+
+/// What a [`SupervisionGroup`] does with its other members once one of them
+/// fails permanently.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupStrategy {
+    /// Restart the other members with their descendants, see
+    /// [`ActorSystem::restart_subtree()`].
+    RestartAll,
+    /// Stop the other members with their descendants.
+    StopAll,
+}
+
+/// A member of a [`SupervisionGroup`] failed, and the strategy of the group
+/// was applied to the other members, see
+/// [`ActorSystem::supervision_group_events()`](crate::ActorSystem::supervision_group_events).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GroupEvent {
+    /// The name of the group.
+    pub group: String,
+    /// The member that failed.
+    pub failed: ActorPath,
+    /// What was done with the other members.
+    pub strategy: GroupStrategy,
+    /// The other members the strategy was applied to.
+    pub members: Vec<ActorPath>,
+}
+
+/// The members of a group, shared by the group and the actor system.
+pub(crate) struct GroupMembers {
+    pub(crate) name: String,
+    pub(crate) strategy: GroupStrategy,
+    pub(crate) paths: Mutex<Vec<ActorPath>>,
+}
+
+/// A set of actors that live and die together (all-for-one), e.g. actors
+/// sharing an external resource that becomes inconsistent when only one of
+/// them restarts. Once a member fails permanently, i.e. it could not be
+/// started or panicked, its failure is escalated to its parent as usual (see
+/// [`crate::Actor::child_failed()`]), and the other members are restarted or
+/// stopped as set by the [`GroupStrategy`]. For example:
+/// ```
+/// # use tiny_tokio_actor::*;
+/// # #[derive(Clone, Debug)]
+/// # struct TestEvent;
+/// # impl SystemEvent for TestEvent {}
+/// # struct Reader;
+/// # impl Actor<TestEvent> for Reader {}
+/// # struct Writer;
+/// # impl Actor<TestEvent> for Writer {}
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), ActorError> {
+/// let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+/// let reader = system.create_actor("reader", Reader).await?;
+/// let writer = system.create_actor("writer", Writer).await?;
+///
+/// let group = system.supervision_group("journal", GroupStrategy::RestartAll);
+/// group.join(&reader);
+/// group.join(&writer);
+/// assert_eq!(group.members(), vec![reader.path().clone(), writer.path().clone()]);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Membership is by path, so a member created again at the same path, e.g.
+/// by its parent once it failed, is still a member. An actor is a member of
+/// at most one group; joining another group leaves the previous one.
+#[derive(Clone)]
+pub struct SupervisionGroup<E: SystemEvent> {
+    system: ActorSystem<E>,
+    members: Arc<GroupMembers>,
+}
+
+impl<E: SystemEvent> SupervisionGroup<E> {
+    pub(crate) fn new(system: ActorSystem<E>, name: &str, strategy: GroupStrategy) -> Self {
+        let members = GroupMembers {
+            name: name.to_string(),
+            strategy,
+            paths: Default::default(),
+        };
+        SupervisionGroup {
+            system,
+            members: Arc::new(members),
+        }
+    }
+
+    /// The name of the group
+    pub fn name(&self) -> &str {
+        &self.members.name
+    }
+
+    /// The strategy of the group
+    pub fn strategy(&self) -> GroupStrategy {
+        self.members.strategy
+    }
+
+    /// Adds the actor to the group.
+    pub fn join<A: Actor<E>>(&self, actor: &ActorRef<E, A>) {
+        self.join_path(actor.path());
+    }
+
+    /// Adds the actor at the path to the group.
+    pub fn join_path(&self, path: &ActorPath) {
+        {
+            let mut paths = self.members.paths.lock().unwrap();
+            if paths.contains(path) {
+                return;
+            }
+            paths.push(path.clone());
+        }
+        if let Some(previous) = self.system.join_group(path, self.members.clone()) {
+            previous
+                .paths
+                .lock()
+                .unwrap()
+                .retain(|member| member != path);
+        }
+    }
+
+    /// Removes the actor at the path from the group.
+    pub fn leave(&self, path: &ActorPath) {
+        self.members
+            .paths
+            .lock()
+            .unwrap()
+            .retain(|member| member != path);
+        self.system.leave_group(path, &self.members);
+    }
+
+    /// The paths of the members, in the order they joined
+    pub fn members(&self) -> Vec<ActorPath> {
+        self.members.paths.lock().unwrap().clone()
+    }
+}
@@ -0,0 +1,136 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+/// What an id of an [`IdGenerator`] identifies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IdKind {
+    /// The name of an anonymous actor, see
+    /// [`ActorSystem::create_anonymous_actor()`](crate::ActorSystem::create_anonymous_actor).
+    Anonymous,
+    /// The incarnation of an actor instance, see
+    /// [`ActorRef::incarnation()`](crate::ActorRef::incarnation).
+    Incarnation,
+    /// The correlation of a request with its answer, see
+    /// [`Request::correlation_id()`](crate::Request::correlation_id).
+    Correlation,
+}
+
+/// Generates the ids of an actor system, see
+/// [`SystemConfig::with_id_generator()`](crate::SystemConfig::with_id_generator).
+/// The ids of a kind must be unique within the actor system, and incarnation
+/// ids must be unique within the process, as references to actors of
+/// different systems are compared by their path and incarnation. It is called
+/// from any task, and must not block.
+pub trait IdGenerator: Send + Sync + 'static {
+    fn next_id(&self, kind: IdKind) -> u64;
+}
+
+/// Source of the incarnation ids of actor instances, unique within the process.
+static NEXT_INCARNATION: AtomicU64 = AtomicU64::new(1);
+
+/// The ids of an actor system unless configured otherwise: counters per
+/// actor system, and a counter per process for incarnations.
+#[derive(Default)]
+pub(crate) struct SequentialIds {
+    anonymous: AtomicU64,
+    correlation: AtomicU64,
+}
+
+impl IdGenerator for SequentialIds {
+    fn next_id(&self, kind: IdKind) -> u64 {
+        let counter = match kind {
+            IdKind::Anonymous => &self.anonymous,
+            IdKind::Incarnation => &NEXT_INCARNATION,
+            IdKind::Correlation => &self.correlation,
+        };
+        counter.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+/// Generates the same names of anonymous actors and correlation ids for the
+/// same seed on every run, e.g. so that the traces of test runs can be
+/// compared, or a property based test reproduced from its seed. Those ids are
+/// scrambled counters, so they do not repeat. Incarnation ids come from the
+/// counter of the process like by default, so they stay unique across actor
+/// systems; they are the same on every run as long as the process creates
+/// its actors in the same order.
+pub struct SeededIds {
+    seed: u64,
+    anonymous: AtomicU64,
+    correlation: AtomicU64,
+}
+
+impl SeededIds {
+    pub fn new(seed: u64) -> Self {
+        SeededIds {
+            seed,
+            anonymous: AtomicU64::new(0),
+            correlation: AtomicU64::new(0),
+        }
+    }
+}
+
+impl IdGenerator for SeededIds {
+    fn next_id(&self, kind: IdKind) -> u64 {
+        let counter = match kind {
+            IdKind::Anonymous => &self.anonymous,
+            IdKind::Incarnation => return NEXT_INCARNATION.fetch_add(1, Ordering::Relaxed),
+            IdKind::Correlation => &self.correlation,
+        };
+        let count = counter.fetch_add(1, Ordering::Relaxed);
+        // Each kind of each seed walks its own stream from a scrambled start
+        let start = mix(mix(self.seed).wrapping_add(kind as u64));
+        mix(start.wrapping_add(count))
+    }
+}
+
+/// The finalizer of SplitMix64, a bijection on u64 so that distinct counters
+/// give distinct ids.
+fn mix(state: u64) -> u64 {
+    let mut z = state.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// A shared [`IdGenerator`] as held by the system configuration.
+#[derive(Clone)]
+pub(crate) struct Ids(pub Arc<dyn IdGenerator>);
+
+impl std::fmt::Debug for Ids {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Ids")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn seeded_ids() {
+        let ids = |seed| {
+            let generator = SeededIds::new(seed);
+            let mut ids = Vec::new();
+            for _ in 0..100 {
+                for kind in [IdKind::Anonymous, IdKind::Correlation] {
+                    ids.push(generator.next_id(kind));
+                }
+            }
+            ids
+        };
+        assert_eq!(ids(7), ids(7));
+        assert_ne!(ids(7), ids(8));
+        assert_eq!(ids(7).into_iter().collect::<HashSet<_>>().len(), 200);
+
+        // Incarnations stay unique across generators with the same seed
+        let (first, second) = (SeededIds::new(7), SeededIds::new(7));
+        let incarnation = first.next_id(IdKind::Incarnation);
+        assert_ne!(second.next_id(IdKind::Incarnation), incarnation);
+    }
+}
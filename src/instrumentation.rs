@@ -0,0 +1,459 @@
+#[cfg(feature = "inspect")]
+use std::{
+    collections::BTreeMap,
+    sync::{atomic::AtomicU64, Mutex},
+};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+use log::LevelFilter;
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    actor::handler::BoxedMessageHandler,
+    dead_letters::{DeadLetterReason, DeadLetters},
+    policy::Policy,
+    quota::MailboxQuotas,
+    Actor, ActorError, ActorPath, SystemEvent,
+};
+
+/// Callbacks on the life of messages in actor mailboxes, e.g. to feed a
+/// metrics backend. Register an implementation for all actors of a system
+/// through [`SystemConfig::with_instrumentation()`](crate::SystemConfig::with_instrumentation).
+/// All callbacks do nothing by default, and are called inline, so they should
+/// return quickly. For example:
+/// ```
+/// use tiny_tokio_actor::*;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::time::Duration;
+///
+/// #[derive(Default)]
+/// struct HandledCounter(AtomicUsize);
+///
+/// impl MailboxInstrumentation for HandledCounter {
+///     fn handled(&self, _path: &ActorPath, _message_type: &'static str, _elapsed: Duration) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let config = SystemConfig::new("test").with_instrumentation(HandledCounter::default());
+/// ```
+pub trait MailboxInstrumentation: Send + Sync + 'static {
+    /// A message was added to the mailbox of the actor at `path`.
+    fn enqueued(&self, _path: &ActorPath, _message_type: &'static str) {}
+
+    /// A message was taken from the mailbox after waiting in it for `waited`.
+    fn dequeued(&self, _path: &ActorPath, _message_type: &'static str, _waited: Duration) {}
+
+    /// A message was handled, which took `elapsed`.
+    fn handled(&self, _path: &ActorPath, _message_type: &'static str, _elapsed: Duration) {}
+
+    /// The actor yielded back to the runtime after handling `messages` in a
+    /// row, see [`ActorConfig::with_throughput()`](crate::ActorConfig::with_throughput)
+    /// and [`ActorConfig::with_time_slice()`](crate::ActorConfig::with_time_slice).
+    fn yielded(&self, _path: &ActorPath, _messages: usize) {}
+
+    /// The actor failed to start or restart.
+    fn failed(&self, _path: &ActorPath, _error: &ActorError) {}
+
+    /// A message could not be delivered and became a dead letter.
+    fn dropped(&self, _path: &ActorPath, _message_type: &'static str, _reason: DeadLetterReason) {}
+}
+
+/// An actor kept its runner busy for longer than configured through
+/// [`SystemConfig::with_starvation_threshold()`](crate::SystemConfig::with_starvation_threshold),
+/// either in a single long handler or a long run of messages without its
+/// mailbox running empty. Such an actor holds up its worker thread, and its
+/// own timers and shutdown. Published to
+/// [`ActorSystem::starvation_warnings()`](crate::ActorSystem::starvation_warnings),
+/// at most once per run.
+#[derive(Clone, Debug)]
+pub struct StarvationWarning {
+    /// The path of the actor.
+    pub path: ActorPath,
+    /// The type of the message being handled when the threshold was exceeded.
+    pub message_type: &'static str,
+    /// How long the actor had been busy.
+    pub elapsed: Duration,
+    /// The number of messages handled in the run, including the current one.
+    pub messages: usize,
+    /// When the warning was raised.
+    pub timestamp: SystemTime,
+}
+
+/// A shared [`MailboxInstrumentation`] as held by the system configuration.
+#[derive(Clone)]
+pub(crate) struct Instrumentation(pub Arc<dyn MailboxInstrumentation>);
+
+impl std::fmt::Debug for Instrumentation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Instrumentation")
+    }
+}
+
+/// Logs on behalf of the actor of a [`MailboxMonitor`], if its log level (see
+/// [`ActorConfig::with_log_level()`](crate::ActorConfig::with_log_level))
+/// allows it. The path of the actor is added as the `actor` key/value pair,
+/// and the type of the message, if given, as `message_type`.
+macro_rules! actor_log {
+    ($monitor:expr, $level:expr, message_type = $message_type:expr; $($arg:tt)+) => {
+        if $level <= $monitor.log_level() {
+            log::log!(
+                $level,
+                actor:% = $monitor.path(),
+                message_type = $message_type;
+                $($arg)+
+            )
+        }
+    };
+    ($monitor:expr, $level:expr, $($arg:tt)+) => {
+        if $level <= $monitor.log_level() {
+            log::log!($level, actor:% = $monitor.path(); $($arg)+)
+        }
+    };
+}
+
+pub(crate) use actor_log;
+
+/// A message waiting in the mailbox of an actor, as returned by
+/// [`ActorRef::peek_mailbox()`](crate::ActorRef::peek_mailbox). Requires the
+/// `inspect` feature.
+#[cfg(feature = "inspect")]
+#[derive(Clone, Debug)]
+pub struct QueuedMessage {
+    /// The type name of the message.
+    pub message_type: &'static str,
+    /// When the message was sent.
+    pub enqueued_at: SystemTime,
+}
+
+/// Source of the ids of messages in mailboxes.
+#[cfg(feature = "inspect")]
+static NEXT_MESSAGE: AtomicU64 = AtomicU64::new(1);
+
+/// How often a blocking send checks whether a mailbox bounded by bytes has
+/// room again.
+const BLOCKING_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// The bytes held by a mailbox bounded by size.
+struct MailboxBytes {
+    limit: usize,
+    used: AtomicUsize,
+    released: Notify,
+}
+
+/// The messages of all actors of a system that are queued or being handled,
+/// see [`ActorSystem::wait_until_idle()`](crate::ActorSystem::wait_until_idle).
+#[derive(Default)]
+pub(crate) struct Activity {
+    messages: AtomicUsize,
+    idle: Notify,
+}
+
+impl Activity {
+    fn enter(&self) {
+        self.messages.fetch_add(1, Ordering::AcqRel);
+    }
+
+    fn exit(&self) {
+        if self.messages.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.idle.notify_waiters();
+        }
+    }
+
+    /// Resolves once no message is queued or being handled.
+    pub async fn idle(&self) {
+        loop {
+            let idle = self.idle.notified();
+            if self.messages.load(Ordering::Acquire) == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+}
+
+/// Counts a message as being handled until dropped, see
+/// [`MailboxMonitor::handling()`].
+pub(crate) struct Handling(Arc<Activity>);
+
+impl Drop for Handling {
+    fn drop(&mut self) {
+        self.0.exit();
+    }
+}
+
+/// Reports what happens in the mailbox of one actor, both to the registered
+/// instrumentation and, for undeliverable messages, as dead letters.
+#[derive(Clone)]
+pub(crate) struct MailboxMonitor {
+    path: ActorPath,
+    dead_letters: DeadLetters,
+    instrumentation: Option<Instrumentation>,
+    log_level: LevelFilter,
+    bytes: Option<Arc<MailboxBytes>>,
+    quotas: Option<MailboxQuotas>,
+    policy: Option<Policy>,
+    activity: Arc<Activity>,
+    #[cfg(feature = "inspect")]
+    queued: Arc<Mutex<BTreeMap<u64, QueuedMessage>>>,
+}
+
+impl MailboxMonitor {
+    pub fn new(
+        path: ActorPath,
+        dead_letters: DeadLetters,
+        instrumentation: Option<Instrumentation>,
+    ) -> Self {
+        MailboxMonitor {
+            path,
+            dead_letters,
+            instrumentation,
+            log_level: LevelFilter::Trace,
+            bytes: None,
+            quotas: None,
+            policy: None,
+            activity: Default::default(),
+            #[cfg(feature = "inspect")]
+            queued: Default::default(),
+        }
+    }
+
+    /// Only logs on behalf of the actor up to the given level.
+    pub fn with_log_level(mut self, level: LevelFilter) -> Self {
+        self.log_level = level;
+        self
+    }
+
+    /// Bounds the mailbox to the given number of bytes, see
+    /// [`MailboxMonitor::try_reserve()`].
+    pub fn with_mailbox_bytes(mut self, limit: Option<usize>) -> Self {
+        self.bytes = limit.map(|limit| {
+            Arc::new(MailboxBytes {
+                limit,
+                used: AtomicUsize::new(0),
+                released: Notify::new(),
+            })
+        });
+        self
+    }
+
+    /// Applies the quotas to messages sent to the mailbox.
+    pub fn with_quotas(mut self, quotas: Option<MailboxQuotas>) -> Self {
+        self.quotas = quotas;
+        self
+    }
+
+    /// Checks messages sent to the mailbox against the policy.
+    pub fn with_policy(mut self, policy: Option<Policy>) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Counts the messages of the mailbox in the activity of the system.
+    pub fn with_activity(mut self, activity: Arc<Activity>) -> Self {
+        self.activity = activity;
+        self
+    }
+
+    pub fn path(&self) -> &ActorPath {
+        &self.path
+    }
+
+    pub fn log_level(&self) -> LevelFilter {
+        self.log_level
+    }
+
+    /// Takes room for a message of `size` bytes in a mailbox bounded by size,
+    /// returning `false` if it does not fit. An empty mailbox takes any
+    /// message. Room taken must be given back through
+    /// [`MailboxMonitor::release()`].
+    pub fn try_reserve(&self, size: usize) -> bool {
+        let bytes = match &self.bytes {
+            Some(bytes) => bytes,
+            None => return true,
+        };
+        bytes
+            .used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                (used == 0 || used + size <= bytes.limit).then(|| used + size)
+            })
+            .is_ok()
+    }
+
+    /// Takes room for a message like [`MailboxMonitor::try_reserve()`], waiting
+    /// for room if the mailbox is full. Returns `false` if the actor stopped
+    /// in the meantime.
+    pub async fn reserve(&self, size: usize, stopped: &CancellationToken) -> bool {
+        let bytes = match &self.bytes {
+            Some(bytes) => bytes,
+            None => return true,
+        };
+        loop {
+            let released = bytes.released.notified();
+            if self.try_reserve(size) {
+                return true;
+            }
+            tokio::select! {
+                _ = released => {}
+                _ = stopped.cancelled() => return false,
+            }
+        }
+    }
+
+    /// Takes room for a message like [`MailboxMonitor::reserve()`], blocking
+    /// the current thread while the mailbox is full.
+    pub fn blocking_reserve(&self, size: usize, stopped: &CancellationToken) -> bool {
+        while !self.try_reserve(size) {
+            if stopped.is_cancelled() {
+                return false;
+            }
+            std::thread::sleep(BLOCKING_POLL_INTERVAL);
+        }
+        true
+    }
+
+    /// Admits a message to the mailbox if the message policy of the system
+    /// and the quotas of the actor allow it. An admitted message must be given
+    /// back through [`MailboxMonitor::leave()`] or [`MailboxMonitor::release()`].
+    pub fn admit(&self, message_type: &'static str) -> Result<(), ActorError> {
+        if let Some(policy) = &self.policy {
+            if !policy.authorize(&self.path, message_type) {
+                return Err(ActorError::Unauthorized {
+                    path: self.path.clone(),
+                    message_type,
+                });
+            }
+        }
+        if let Some(quotas) = &self.quotas {
+            quotas.admit(&self.path)?;
+        }
+        self.activity.enter();
+        Ok(())
+    }
+
+    /// Gives back an admitted message that did not enter the mailbox.
+    pub fn leave(&self) {
+        if let Some(quotas) = &self.quotas {
+            quotas.leave();
+        }
+        self.activity.exit();
+    }
+
+    /// Counts a message taken from the mailbox as being handled until the
+    /// returned guard is dropped. Taken before the message is released, so
+    /// the system is not idle in between.
+    pub fn handling(&self) -> Handling {
+        self.activity.enter();
+        Handling(self.activity.clone())
+    }
+
+    /// Gives back the room taken by a message that left the mailbox, both in
+    /// bytes and in the quotas of the actor.
+    pub fn release(&self, size: usize) {
+        self.leave();
+        if let Some(bytes) = &self.bytes {
+            bytes.used.fetch_sub(size, Ordering::AcqRel);
+            bytes.released.notify_waiters();
+        }
+    }
+
+    /// Adjusts the room taken by a message in the mailbox whose size changed
+    /// from `old` to `new` bytes, as it was replaced by a message with the
+    /// same conflation key. The replacement keeps its place even if the
+    /// mailbox has no room for the difference.
+    pub fn resize(&self, old: usize, new: usize) {
+        if let Some(bytes) = &self.bytes {
+            if new > old {
+                bytes.used.fetch_add(new - old, Ordering::AcqRel);
+            } else if new < old {
+                bytes.used.fetch_sub(old - new, Ordering::AcqRel);
+                bytes.released.notify_waiters();
+            }
+        }
+    }
+
+    /// Assigns an id to a message that is about to be sent. With the `inspect`
+    /// feature the message is listed by [`MailboxMonitor::peek()`] until it is
+    /// dequeued or dropped.
+    #[cfg_attr(not(feature = "inspect"), allow(unused_variables))]
+    pub fn track(&self, message_type: &'static str) -> u64 {
+        #[cfg(feature = "inspect")]
+        {
+            let id = NEXT_MESSAGE.fetch_add(1, Ordering::Relaxed);
+            let message = QueuedMessage {
+                message_type,
+                enqueued_at: SystemTime::now(),
+            };
+            self.queued.lock().unwrap().insert(id, message);
+            id
+        }
+        #[cfg(not(feature = "inspect"))]
+        0
+    }
+
+    #[cfg_attr(not(feature = "inspect"), allow(unused_variables))]
+    fn untrack(&self, id: u64) {
+        #[cfg(feature = "inspect")]
+        self.queued.lock().unwrap().remove(&id);
+    }
+
+    /// The first `n` messages in the mailbox, oldest first.
+    #[cfg(feature = "inspect")]
+    pub fn peek(&self, n: usize) -> Vec<QueuedMessage> {
+        let queued = self.queued.lock().unwrap();
+        queued.values().take(n).cloned().collect()
+    }
+
+    fn instrument<F: FnOnce(&dyn MailboxInstrumentation)>(&self, f: F) {
+        if let Some(instrumentation) = &self.instrumentation {
+            f(instrumentation.0.as_ref());
+        }
+    }
+
+    pub fn enqueued(&self, message_type: &'static str) {
+        self.instrument(|i| i.enqueued(&self.path, message_type));
+    }
+
+    pub fn dequeued(&self, id: u64, message_type: &'static str, waited: Duration) {
+        self.untrack(id);
+        self.instrument(|i| i.dequeued(&self.path, message_type, waited));
+    }
+
+    pub fn handled(&self, message_type: &'static str, elapsed: Duration) {
+        self.instrument(|i| i.handled(&self.path, message_type, elapsed));
+    }
+
+    pub fn yielded(&self, messages: usize) {
+        self.instrument(|i| i.yielded(&self.path, messages));
+    }
+
+    pub fn failed(&self, error: &ActorError) {
+        self.instrument(|i| i.failed(&self.path, error));
+    }
+
+    pub fn dropped<E: SystemEvent, A: Actor<E>>(
+        &self,
+        message: BoxedMessageHandler<E, A>,
+        reason: DeadLetterReason,
+    ) {
+        actor_log!(
+            self,
+            log::Level::Debug,
+            message_type = message.message_type();
+            "Dead letter {} to actor '{}': {:?}",
+            message.message_type(),
+            &self.path,
+            reason
+        );
+        self.untrack(message.id());
+        self.instrument(|i| i.dropped(&self.path, message.message_type(), reason));
+        self.dead_letters.publish(&self.path, message, reason);
+    }
+}
@@ -0,0 +1,296 @@
+//! Actors feeding the lines of a file or stdin to another actor. Requires the
+//! `io` feature.
+
+use std::{path::PathBuf, sync::Arc};
+
+use async_trait::async_trait;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    Actor, ActorContext, ActorError, ActorPath, ActorRef, ActorSystem, Handler, Message,
+    SystemEvent,
+};
+
+type LineMap<M> = Arc<dyn Fn(String) -> M + Send + Sync>;
+
+type Reader = Box<dyn AsyncBufRead + Send + Sync + Unpin>;
+
+/// Where a [`LineSourceActor`] reads its lines from.
+enum Input {
+    File(PathBuf),
+    Stdin,
+    Reader(Reader),
+}
+
+/// An actor that reads its input line by line, and sends every line to the
+/// target actor as the message `map` turns it into, e.g. to replay a log of
+/// recorded requests or to feed a batch job. For example:
+/// ```no_run
+/// # use tiny_tokio_actor::{*, io::*};
+/// # #[derive(Clone, Debug)]
+/// # struct TestEvent;
+/// # impl SystemEvent for TestEvent {}
+/// #[derive(Clone, Debug)]
+/// struct Record(String);
+///
+/// impl Message for Record {
+///     type Response = ();
+/// }
+///
+/// # struct Importer;
+/// # impl Actor<TestEvent> for Importer {}
+/// # #[async_trait]
+/// # impl Handler<TestEvent, Record> for Importer {
+/// #     async fn handle(&mut self, _msg: Record, _ctx: &mut ActorContext<TestEvent>) {}
+/// # }
+/// # async fn run(system: ActorSystem<TestEvent>) -> Result<(), ActorError> {
+/// let config = ActorConfig::default().with_mailbox_capacity(100);
+/// let importer = system.create_actor_with_config("importer", Importer, config).await?;
+/// let source = LineSourceActor::from_file("records.txt", importer, Record);
+/// system.create_actor("records", source).await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Lines are sent through [`ActorRef::ordered_tell()`], so when the target
+/// has a bounded mailbox that is full, reading waits until there is room
+/// instead of buffering the input without bound.
+///
+/// The actor stops once the input is exhausted or the target stops. If the
+/// input cannot be opened or read, the actor stops and the failure is
+/// escalated to its parent (see [`crate::Actor::child_failed()`]).
+pub struct LineSourceActor<E: SystemEvent, T: Actor<E>, M> {
+    input: Option<Input>,
+    target: ActorRef<E, T>,
+    map: LineMap<M>,
+    reading: Option<CancellationToken>,
+}
+
+impl<E, T, M> LineSourceActor<E, T, M>
+where
+    E: SystemEvent,
+    T: Actor<E> + Handler<E, M>,
+    M: Message,
+{
+    /// Reads the lines of the reader.
+    pub fn new<R, F>(reader: R, target: ActorRef<E, T>, map: F) -> Self
+    where
+        R: AsyncBufRead + Send + Sync + Unpin + 'static,
+        F: Fn(String) -> M + Send + Sync + 'static,
+    {
+        Self::with_input(Input::Reader(Box::new(reader)), target, map)
+    }
+
+    /// Reads the lines of the file at the path, which is opened when the
+    /// actor starts.
+    pub fn from_file<P, F>(path: P, target: ActorRef<E, T>, map: F) -> Self
+    where
+        P: Into<PathBuf>,
+        F: Fn(String) -> M + Send + Sync + 'static,
+    {
+        Self::with_input(Input::File(path.into()), target, map)
+    }
+
+    /// Reads the lines of the standard input of the process.
+    pub fn stdin<F>(target: ActorRef<E, T>, map: F) -> Self
+    where
+        F: Fn(String) -> M + Send + Sync + 'static,
+    {
+        Self::with_input(Input::Stdin, target, map)
+    }
+
+    fn with_input<F>(input: Input, target: ActorRef<E, T>, map: F) -> Self
+    where
+        F: Fn(String) -> M + Send + Sync + 'static,
+    {
+        LineSourceActor {
+            input: Some(input),
+            target,
+            map: Arc::new(map),
+            reading: None,
+        }
+    }
+}
+
+#[async_trait]
+impl<E, T, M> Actor<E> for LineSourceActor<E, T, M>
+where
+    E: SystemEvent,
+    T: Actor<E> + Handler<E, M>,
+    M: Message,
+{
+    async fn pre_start(&mut self, ctx: &mut ActorContext<E>) -> Result<(), ActorError> {
+        // A restarted actor keeps reading with the task of its first start
+        if let Some(input) = self.input.take() {
+            let reading = ctx.cancellation_token().child_token();
+            self.reading = Some(reading.clone());
+            tokio::spawn(read(
+                input,
+                self.target.clone(),
+                self.map.clone(),
+                ctx.system.clone(),
+                ctx.path.clone(),
+                reading,
+            ));
+        }
+        Ok(())
+    }
+
+    async fn post_stop(&mut self, _ctx: &mut ActorContext<E>) {
+        if let Some(reading) = self.reading.take() {
+            reading.cancel();
+        }
+    }
+}
+
+async fn open(input: Input) -> std::io::Result<Reader> {
+    Ok(match input {
+        Input::File(path) => Box::new(BufReader::new(tokio::fs::File::open(path).await?)),
+        Input::Stdin => Box::new(BufReader::new(tokio::io::stdin())),
+        Input::Reader(reader) => reader,
+    })
+}
+
+async fn read<E, T, M>(
+    input: Input,
+    target: ActorRef<E, T>,
+    map: LineMap<M>,
+    system: ActorSystem<E>,
+    path: ActorPath,
+    reading: CancellationToken,
+) where
+    E: SystemEvent,
+    T: Actor<E> + Handler<E, M>,
+    M: Message,
+{
+    let mut lines = match open(input).await {
+        Ok(reader) => reader.lines(),
+        Err(error) => {
+            log::error!("Actor '{}' failed to open its input: {}", &path, error);
+            system.escalate(&path, ActorError::new(error)).await;
+            return;
+        }
+    };
+    loop {
+        let next = tokio::select! {
+            next = lines.next_line() => next,
+            _ = reading.cancelled() => return,
+        };
+        match next {
+            Ok(Some(line)) => {
+                let sent = tokio::select! {
+                    sent = target.ordered_tell(map(line)) => sent,
+                    _ = reading.cancelled() => return,
+                };
+                if let Err(error) = sent {
+                    log::debug!(
+                        "Actor '{}' stops as its target '{}' failed: {:?}",
+                        &path,
+                        target.path(),
+                        error
+                    );
+                    break;
+                }
+            }
+            Ok(None) => break,
+            Err(error) => {
+                log::error!("Actor '{}' failed to read a line: {}", &path, error);
+                system.escalate(&path, ActorError::new(error)).await;
+                return;
+            }
+        }
+    }
+    system.terminate(&path).await;
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{ActorConfig, EventBus};
+
+    use super::*;
+
+    #[derive(Clone, Debug)]
+    struct TestEvent;
+
+    impl SystemEvent for TestEvent {}
+
+    #[derive(Clone, Debug)]
+    struct Line(String);
+
+    impl Message for Line {
+        type Response = ();
+    }
+
+    #[derive(Clone, Debug)]
+    struct Collected;
+
+    impl Message for Collected {
+        type Response = Vec<String>;
+    }
+
+    #[derive(Default)]
+    struct CollectActor {
+        lines: Vec<String>,
+    }
+
+    impl Actor<TestEvent> for CollectActor {}
+
+    #[async_trait]
+    impl Handler<TestEvent, Line> for CollectActor {
+        async fn handle(&mut self, msg: Line, _ctx: &mut ActorContext<TestEvent>) {
+            // Slow enough for the bounded mailbox to fill up
+            tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+            self.lines.push(msg.0);
+        }
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, Collected> for CollectActor {
+        async fn handle(
+            &mut self,
+            _msg: Collected,
+            _ctx: &mut ActorContext<TestEvent>,
+        ) -> Vec<String> {
+            self.lines.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn line_source_actor() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(10));
+        let config = ActorConfig::default().with_mailbox_capacity(2);
+        let collect = system
+            .create_actor_with_config("collect", CollectActor::default(), config)
+            .await
+            .unwrap();
+
+        let input: Vec<String> = (0..20).map(|index| format!("line {}", index)).collect();
+        let reader = BufReader::new(std::io::Cursor::new(input.join("\n").into_bytes()));
+        let source = LineSourceActor::new(reader, collect.clone(), Line);
+        let source = system.create_actor("source", source).await.unwrap();
+
+        // The source stops once all lines are in the mailbox of the target
+        while !source.is_closed() {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        assert_eq!(collect.ask(Collected).await.unwrap(), input);
+    }
+
+    #[tokio::test]
+    async fn line_source_actor_missing_file() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(10));
+        let collect = system
+            .create_actor("collect", CollectActor::default())
+            .await
+            .unwrap();
+        let source = LineSourceActor::from_file("/nonexistent/lines.txt", collect.clone(), Line);
+        let source = system.create_actor("source", source).await.unwrap();
+
+        while !source.is_closed() {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        assert!(collect.ask(Collected).await.unwrap().is_empty());
+    }
+}
@@ -1,8 +1,10 @@
 //! A Simple Tiny Tokio Actor Crate
 //!
 //! This crate provides a minimally functioning actor system with a common
-//! event bus. Tokio unbounded channels are used for the mailbox of the
-//! actors, and actor behaviour (defined through the [`Handler`] trait) can
+//! event bus. Tokio channels are used for the mailbox of the actors, which
+//! are unbounded unless a capacity is set through
+//! [`SystemConfig::with_mailbox_capacity()`] or
+//! [`ActorConfig::with_mailbox_capacity()`], and actor behaviour (defined through the [`Handler`] trait) can
 //! use the request+response pattern (using tokio oneshot channel for
 //! responses). You an send messages to actors either through a `tell`
 //! where the method does not provide a response, or an `ask`. The `ask`
@@ -92,12 +94,46 @@
 //! ```
 
 mod actor;
+mod bridge;
+mod builtin;
 mod bus;
+#[cfg(feature = "chaos")]
+mod chaos;
+mod config;
+mod connection;
+mod dead_letters;
+mod deadline;
+mod election;
+mod group;
+mod ids;
+mod instrumentation;
+#[cfg(feature = "io")]
+pub mod io;
+mod logger;
+mod merge;
+#[cfg(feature = "prometheus")]
+mod metrics;
+mod namespace;
+#[cfg(feature = "net")]
+pub mod net;
+mod policy;
+mod protocol;
+mod quorum;
+mod quota;
+mod request;
+mod subscription;
 mod system;
+#[cfg(feature = "testkit")]
+pub mod testkit;
 
 pub use actor::{
-    supervision::{RetryStrategy, SupervisionStrategy},
-    Actor, ActorContext, ActorError, ActorPath, ActorRef, Handler, Message,
+    supervision::{
+        ChildFailed, CrashReport, FlappingActor, PanicPolicy, RestartStats, RetryStrategy,
+        SupervisionPolicy, SupervisionStrategy,
+    },
+    Actor, ActorConfig, ActorContext, ActorError, ActorPath, ActorRef, AskError, ConflationKey,
+    Extensions, Handler, Handoff, Message, OnTerminate, Participant, PathPattern, Props, Requester,
+    SyncBridge, TaskScope, Transaction, TxToken,
 };
 pub mod supervision {
     //! Actor Supervision Strategies
@@ -113,7 +149,41 @@ pub mod supervision {
         ExponentialBackoffStrategy, FixedIntervalStrategy, NoIntervalStrategy,
     };
 }
-pub use bus::{EventBus, EventReceiver};
-pub use system::{ActorSystem, SystemEvent};
+pub use bridge::SystemBridge;
+pub use bus::{
+    BusStats, EventAdapter, EventBus, EventPublisher, EventReceiver, EventRecvError, GroupReceiver,
+    MappedReceiver, NamedReceiver, SendError, SubscriberStats,
+};
+#[cfg(feature = "chaos")]
+pub use chaos::ChaosConfig;
+pub use config::SystemConfig;
+pub use connection::{
+    ConnectionChange, ConnectionRequest, ConnectionState, Connector, ManagedConnection, Serve,
+    WhileDisconnected,
+};
+pub use dead_letters::{
+    DeadLetter, DeadLetterActor, DeadLetterConfig, DeadLetterReason, GetDeadLetters,
+    ReplayDeadLetters,
+};
+pub use deadline::Deadline;
+pub use election::{Leadership, LeadershipChange, LeaseStore, LocalLeases};
+pub use group::{GroupEvent, GroupStrategy, SupervisionGroup};
+pub use ids::{IdGenerator, IdKind, SeededIds};
+#[cfg(feature = "inspect")]
+pub use instrumentation::QueuedMessage;
+pub use instrumentation::{MailboxInstrumentation, StarvationWarning};
+pub use logger::LoggerActor;
+pub use merge::MergeActor;
+pub use namespace::Namespace;
+pub use policy::{Caller, Delivery, MessagePolicy};
+pub use quorum::{ask_all, ask_hedged, Fastest, FirstSuccess, Fold, Gather, Majority};
+pub use quota::{Quota, QuotaEvent, QuotaScope, Quotas};
+pub use request::Request;
+pub use subscription::{EventPolicy, EventSubscription};
+pub use system::{
+    ActorSystem, BulkResult, NoEvent, RegistryEvent, ShutdownReport, SystemEvent, SystemMessage,
+    SystemMessageReceiver,
+};
 
 pub use async_trait::async_trait;
+pub use tokio_util::sync::CancellationToken;
@@ -0,0 +1,170 @@
+use async_trait::async_trait;
+use log::Level;
+use tokio::task::JoinHandle;
+
+use crate::{
+    bus::EventRecvError,
+    system::{RegistryEvent, SystemMessage},
+    Actor, ActorContext, ActorError, ConnectionState, GroupStrategy, SystemEvent,
+};
+
+/// The built-in actor at '/system/logger' that logs what happens in the actor
+/// system: actors being registered and unregistered, dead letters, crashes,
+/// exceeded quotas, flapping and starving actors and overflows of the event
+/// bus. It is started through
+/// [`ActorSystem::start_logger()`](crate::ActorSystem::start_logger). The
+/// events of the user are not logged.
+#[derive(Default)]
+pub struct LoggerActor {
+    task: Option<JoinHandle<()>>,
+}
+
+impl LoggerActor {
+    pub(crate) fn new() -> Self {
+        LoggerActor::default()
+    }
+}
+
+#[async_trait]
+impl<E: SystemEvent> Actor<E> for LoggerActor {
+    async fn pre_start(&mut self, ctx: &mut ActorContext<E>) -> Result<(), ActorError> {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        let mut messages = ctx.system.system_messages();
+        let stopped = ctx.cancellation_token().clone();
+        self.task = Some(tokio::spawn(async move {
+            loop {
+                let message = tokio::select! {
+                    _ = stopped.cancelled() => break,
+                    message = messages.recv() => message,
+                };
+                match message {
+                    Ok(message) => log_message(&message),
+                    Err(EventRecvError::Lagged(_)) => continue,
+                    Err(EventRecvError::Closed) => break,
+                }
+            }
+        }));
+        Ok(())
+    }
+
+    async fn post_stop(&mut self, _ctx: &mut ActorContext<E>) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+fn log_message<E: SystemEvent>(message: &SystemMessage<E>) {
+    match message {
+        SystemMessage::Event(_) => {}
+        SystemMessage::Registry(RegistryEvent::ActorRegistered(path)) => {
+            log::log!(Level::Debug, actor:% = path; "Actor '{}' registered", path);
+        }
+        SystemMessage::Registry(RegistryEvent::ActorUnregistered(path)) => {
+            log::log!(Level::Debug, actor:% = path; "Actor '{}' unregistered", path);
+        }
+        SystemMessage::DeadLetter(letter) => {
+            log::log!(
+                Level::Info,
+                actor:% = letter.recipient(),
+                message_type = letter.message_type();
+                "Dead letter {} to actor '{}': {:?}",
+                letter.message_type(),
+                letter.recipient(),
+                letter.reason()
+            );
+        }
+        SystemMessage::Crash(report) => {
+            log::log!(
+                Level::Error,
+                actor:% = report.path(),
+                message_type = report.message_type();
+                "Actor '{}' crashed handling {}: {}",
+                report.path(),
+                report.message_type(),
+                report.panic()
+            );
+        }
+        SystemMessage::Quota(event) => {
+            log::log!(
+                Level::Warn,
+                actor:% = &event.path;
+                "Quota {:?} of {} exceeded by actor '{}'",
+                event.quota,
+                event.scope,
+                &event.path
+            );
+        }
+        SystemMessage::Flapping(flapping) => {
+            log::log!(
+                Level::Warn,
+                actor:% = &flapping.path;
+                "Actor '{}' is flapping: restarted {} times within {:?}",
+                &flapping.path,
+                flapping.restarts,
+                flapping.within
+            );
+        }
+        SystemMessage::Starvation(warning) => {
+            log::log!(
+                Level::Warn,
+                actor:% = &warning.path,
+                message_type = warning.message_type;
+                "Actor '{}' starved its runner for {:?}, handling {} messages",
+                &warning.path,
+                warning.elapsed,
+                warning.messages
+            );
+        }
+        SystemMessage::BusStats(stats) => {
+            log::debug!(
+                "Event bus has {} subscribers, published {} events of which {} were overwritten",
+                stats.subscribers,
+                stats.published,
+                stats.overwritten
+            );
+        }
+        SystemMessage::Leadership(change) => {
+            log::log!(
+                Level::Info,
+                actor:% = &change.path;
+                "Actor '{}' {} leader for lease '{}'",
+                &change.path,
+                if change.elected { "became" } else { "is no longer" },
+                &change.lease
+            );
+        }
+        SystemMessage::Connection(change) => {
+            log::log!(
+                if change.state == ConnectionState::Disconnected {
+                    Level::Warn
+                } else {
+                    Level::Info
+                },
+                actor:% = &change.path;
+                "Connection of actor '{}' is {:?}",
+                &change.path,
+                change.state
+            );
+        }
+        SystemMessage::SupervisionGroup(event) => {
+            log::log!(
+                Level::Warn,
+                actor:% = &event.failed;
+                "Actor '{}' of supervision group '{}' failed, {} {} other members",
+                &event.failed,
+                &event.group,
+                match event.strategy {
+                    GroupStrategy::RestartAll => "restarted",
+                    GroupStrategy::StopAll => "stopped",
+                },
+                event.members.len()
+            );
+        }
+        SystemMessage::EventsDropped(dropped) => {
+            log::warn!("Event bus overflowed, dropped {} events", dropped);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::marker::PhantomData;
+
+use async_trait::async_trait;
+
+use crate::{
+    Actor, ActorContext, ActorError, ActorRef, Handler, Message, OnTerminate, SystemEvent,
+};
+
+/// An actor merging the messages of several sources into a single stream of
+/// messages of type `M` for the target actor, e.g. to join the streams of
+/// several stages of a pipeline. The inputs it accepts are the variants of
+/// `M`, declared through [`merge_inputs!`](crate::merge_inputs).
+///
+/// Every input is passed on through [`ActorRef::ordered_tell()`], so when
+/// the target has a bounded mailbox that is full, the merge actor waits for
+/// room and its own mailbox fills up in turn. Give it a bounded mailbox as
+/// well (see [`crate::ActorConfig::with_mailbox_capacity()`]), and let the
+/// sources send through `ordered_tell()` too, so that each source waits on
+/// its own send while the target is behind.
+///
+/// The actor stops once its target stops.
+pub struct MergeActor<E: SystemEvent, T: Actor<E>, M> {
+    target: ActorRef<E, T>,
+    _merged: PhantomData<fn() -> M>,
+}
+
+impl<E, T, M> MergeActor<E, T, M>
+where
+    E: SystemEvent,
+    T: Actor<E> + Handler<E, M>,
+    M: Message,
+{
+    /// Merges the inputs into messages for the target.
+    pub fn new(target: ActorRef<E, T>) -> Self {
+        MergeActor {
+            target,
+            _merged: PhantomData,
+        }
+    }
+
+    /// Passes the merged message on to the target, waiting for room in its
+    /// mailbox. Called by the handlers generated through
+    /// [`merge_inputs!`](crate::merge_inputs).
+    pub async fn forward(&mut self, msg: M, ctx: &mut ActorContext<E>) {
+        if let Err(error) = self.target.ordered_tell(msg).await {
+            log::debug!(
+                "Actor '{}' dropped a message for its target '{}': {:?}",
+                &ctx.path,
+                self.target.path(),
+                error
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl<E, T, M> Actor<E> for MergeActor<E, T, M>
+where
+    E: SystemEvent,
+    T: Actor<E> + Handler<E, M>,
+    M: Message,
+{
+    async fn pre_start(&mut self, ctx: &mut ActorContext<E>) -> Result<(), ActorError> {
+        ctx.watch_with(&self.target, OnTerminate::StopSelf);
+        Ok(())
+    }
+}
+
+/// Declares the message type a [`MergeActor`](crate::MergeActor) merges its
+/// inputs into: an enum with a variant per input type, together with the
+/// handlers of the merge actor for each of the inputs. The inputs are
+/// messages without a response. For example:
+/// ```
+/// use tiny_tokio_actor::*;
+///
+/// #[derive(Clone, Debug)]
+/// struct TestEvent;
+///
+/// impl SystemEvent for TestEvent {}
+///
+/// #[derive(Clone, Debug)]
+/// struct Temperature(f64);
+///
+/// impl Message for Temperature {
+///     type Response = ();
+/// }
+///
+/// #[derive(Clone, Debug)]
+/// struct Humidity(f64);
+///
+/// impl Message for Humidity {
+///     type Response = ();
+/// }
+///
+/// merge_inputs! {
+///     /// A reading of any of the sensors
+///     #[derive(Debug)]
+///     pub enum Reading for TestEvent {
+///         Temperature(Temperature),
+///         Humidity(Humidity),
+///     }
+/// }
+///
+/// #[derive(Default)]
+/// struct Dashboard {
+///     readings: Vec<String>,
+/// }
+///
+/// impl Actor<TestEvent> for Dashboard {}
+///
+/// #[async_trait]
+/// impl Handler<TestEvent, Reading> for Dashboard {
+///     async fn handle(&mut self, msg: Reading, _ctx: &mut ActorContext<TestEvent>) {
+///         self.readings.push(format!("{:?}", msg));
+///     }
+/// }
+///
+/// #[derive(Clone, Debug)]
+/// struct Readings;
+///
+/// impl Message for Readings {
+///     type Response = Vec<String>;
+/// }
+///
+/// #[async_trait]
+/// impl Handler<TestEvent, Readings> for Dashboard {
+///     async fn handle(&mut self, _msg: Readings, _ctx: &mut ActorContext<TestEvent>) -> Vec<String> {
+///         self.readings.clone()
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), ActorError> {
+///     let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+///     let dashboard = system.create_actor("dashboard", Dashboard::default()).await?;
+///     let config = ActorConfig::default().with_mailbox_capacity(16);
+///     let merge = MergeActor::new(dashboard.clone());
+///     let readings = system.create_actor_with_config("readings", merge, config).await?;
+///
+///     readings.ordered_tell(Temperature(21.5)).await?;
+///     readings.ordered_tell(Humidity(0.4)).await?;
+///     readings.drain().await?;
+///     assert_eq!(
+///         dashboard.ask(Readings).await?,
+///         vec!["Temperature(Temperature(21.5))", "Humidity(Humidity(0.4))"]
+///     );
+///     Ok(())
+/// }
+/// ```
+#[macro_export]
+macro_rules! merge_inputs {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident for $event:ty {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident($input:ty)
+            ),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone)]
+        $vis enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant($input),
+            )+
+        }
+
+        impl $crate::Message for $name {
+            type Response = ();
+        }
+
+        $(
+            impl ::std::convert::From<$input> for $name {
+                fn from(input: $input) -> Self {
+                    $name::$variant(input)
+                }
+            }
+
+            #[$crate::async_trait]
+            impl<T> $crate::Handler<$event, $input> for $crate::MergeActor<$event, T, $name>
+            where
+                T: $crate::Actor<$event> + $crate::Handler<$event, $name>,
+            {
+                async fn handle(&mut self, msg: $input, ctx: &mut $crate::ActorContext<$event>) {
+                    self.forward($name::$variant(msg), ctx).await
+                }
+            }
+        )+
+    };
+}
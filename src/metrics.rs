@@ -0,0 +1,281 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{
+    instrumentation::Instrumentation, ActorError, ActorPath, DeadLetterReason,
+    MailboxInstrumentation,
+};
+
+/// The upper bounds in seconds of the buckets of the handler duration
+/// histogram.
+const DURATION_BUCKETS: [f64; 10] = [0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+
+/// The metrics of the mailbox of one actor.
+#[derive(Default)]
+struct ActorMetrics {
+    enqueued: u64,
+    dequeued: u64,
+    failures: u64,
+    dropped: u64,
+    /// The number of handled messages per bucket of [`DURATION_BUCKETS`], and
+    /// of those that took longer in the last one.
+    buckets: [u64; DURATION_BUCKETS.len() + 1],
+    handled_seconds: f64,
+}
+
+impl ActorMetrics {
+    fn queued(&self) -> u64 {
+        self.enqueued.saturating_sub(self.dequeued)
+    }
+
+    fn handled(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+}
+
+/// Collects the metrics of all actors of a system for
+/// [`ActorSystem::metrics_text()`](crate::ActorSystem::metrics_text), passing
+/// everything on to the instrumentation registered in the system configuration.
+pub(crate) struct Metrics {
+    actors: Mutex<HashMap<ActorPath, ActorMetrics>>,
+    instrumentation: Option<Instrumentation>,
+}
+
+impl Metrics {
+    pub fn new(instrumentation: Option<Instrumentation>) -> Self {
+        Metrics {
+            actors: Default::default(),
+            instrumentation,
+        }
+    }
+
+    /// The instrumentation to register with the mailboxes of actors.
+    pub fn instrumentation(self: &Arc<Self>) -> Instrumentation {
+        Instrumentation(self.clone())
+    }
+
+    fn update(&self, path: &ActorPath, f: impl FnOnce(&mut ActorMetrics)) {
+        f(self.actors.lock().unwrap().entry(path.clone()).or_default());
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format. Only the
+    /// given actors are reported, the metrics of any other actor are dropped.
+    pub fn render(
+        &self,
+        system: &str,
+        actors: &HashSet<ActorPath>,
+        restarts: &[(ActorPath, u64)],
+    ) -> String {
+        let mut metrics = self.actors.lock().unwrap();
+        metrics.retain(|path, _| actors.contains(path));
+        let system = escape(system);
+        let mut paths: Vec<_> = metrics.keys().cloned().collect();
+        paths.sort_by_key(|path| path.to_string());
+        let mut out = String::new();
+
+        family(
+            &mut out,
+            "tta_actors",
+            "gauge",
+            "The number of actors in the actor system.",
+        );
+        let _ = writeln!(out, "tta_actors{{system=\"{}\"}} {}", system, actors.len());
+
+        family(
+            &mut out,
+            "tta_actor_restarts_total",
+            "counter",
+            "The number of restarts of an actor.",
+        );
+        for (path, count) in restarts.iter().filter(|(path, _)| actors.contains(path)) {
+            sample(
+                &mut out,
+                "tta_actor_restarts_total",
+                &system,
+                path,
+                "",
+                *count,
+            );
+        }
+
+        family(
+            &mut out,
+            "tta_mailbox_messages",
+            "gauge",
+            "The number of messages waiting in the mailbox of an actor.",
+        );
+        for path in &paths {
+            sample(
+                &mut out,
+                "tta_mailbox_messages",
+                &system,
+                path,
+                "",
+                metrics[path].queued(),
+            );
+        }
+
+        family(
+            &mut out,
+            "tta_actor_failures_total",
+            "counter",
+            "The number of times an actor failed to start or restart.",
+        );
+        for path in &paths {
+            sample(
+                &mut out,
+                "tta_actor_failures_total",
+                &system,
+                path,
+                "",
+                metrics[path].failures,
+            );
+        }
+
+        family(
+            &mut out,
+            "tta_dead_letters_total",
+            "counter",
+            "The number of messages to an actor that became dead letters.",
+        );
+        for path in &paths {
+            sample(
+                &mut out,
+                "tta_dead_letters_total",
+                &system,
+                path,
+                "",
+                metrics[path].dropped,
+            );
+        }
+
+        family(
+            &mut out,
+            "tta_handler_duration_seconds",
+            "histogram",
+            "How long an actor took to handle a message.",
+        );
+        for path in &paths {
+            let actor = &metrics[path];
+            let mut count = 0;
+            for (bound, handled) in DURATION_BUCKETS.iter().zip(actor.buckets.iter()) {
+                count += handled;
+                let le = format!(",le=\"{}\"", bound);
+                sample(
+                    &mut out,
+                    "tta_handler_duration_seconds_bucket",
+                    &system,
+                    path,
+                    &le,
+                    count,
+                );
+            }
+            sample(
+                &mut out,
+                "tta_handler_duration_seconds_bucket",
+                &system,
+                path,
+                ",le=\"+Inf\"",
+                actor.handled(),
+            );
+            let _ = writeln!(
+                out,
+                "tta_handler_duration_seconds_sum{{system=\"{}\",actor=\"{}\"}} {}",
+                system,
+                escape(&path.to_string()),
+                actor.handled_seconds
+            );
+            sample(
+                &mut out,
+                "tta_handler_duration_seconds_count",
+                &system,
+                path,
+                "",
+                actor.handled(),
+            );
+        }
+        out
+    }
+}
+
+impl MailboxInstrumentation for Metrics {
+    fn enqueued(&self, path: &ActorPath, message_type: &'static str) {
+        self.update(path, |actor| actor.enqueued += 1);
+        if let Some(instrumentation) = &self.instrumentation {
+            instrumentation.0.enqueued(path, message_type);
+        }
+    }
+
+    fn dequeued(&self, path: &ActorPath, message_type: &'static str, waited: Duration) {
+        self.update(path, |actor| actor.dequeued += 1);
+        if let Some(instrumentation) = &self.instrumentation {
+            instrumentation.0.dequeued(path, message_type, waited);
+        }
+    }
+
+    fn handled(&self, path: &ActorPath, message_type: &'static str, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        let bucket = DURATION_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(DURATION_BUCKETS.len());
+        self.update(path, |actor| {
+            actor.buckets[bucket] += 1;
+            actor.handled_seconds += seconds;
+        });
+        if let Some(instrumentation) = &self.instrumentation {
+            instrumentation.0.handled(path, message_type, elapsed);
+        }
+    }
+
+    fn yielded(&self, path: &ActorPath, messages: usize) {
+        if let Some(instrumentation) = &self.instrumentation {
+            instrumentation.0.yielded(path, messages);
+        }
+    }
+
+    fn failed(&self, path: &ActorPath, error: &ActorError) {
+        self.update(path, |actor| actor.failures += 1);
+        if let Some(instrumentation) = &self.instrumentation {
+            instrumentation.0.failed(path, error);
+        }
+    }
+
+    fn dropped(&self, path: &ActorPath, message_type: &'static str, reason: DeadLetterReason) {
+        self.update(path, |actor| actor.dropped += 1);
+        if let Some(instrumentation) = &self.instrumentation {
+            instrumentation.0.dropped(path, message_type, reason);
+        }
+    }
+}
+
+/// Writes the `HELP` and `TYPE` lines of a metric family.
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Writes a sample of an actor, with any extra labels (starting with a comma).
+fn sample(out: &mut String, name: &str, system: &str, path: &ActorPath, labels: &str, value: u64) {
+    let _ = writeln!(
+        out,
+        "{}{{system=\"{}\",actor=\"{}\"{}}} {}",
+        name,
+        system,
+        escape(&path.to_string()),
+        labels,
+        value
+    );
+}
+
+/// Escapes a label value as required by the text exposition format.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,150 @@
+use crate::{
+    system::validate_name, Actor, ActorConfig, ActorError, ActorPath, ActorRef, ActorSystem,
+    BulkResult, Handler, Message, QuotaScope, Quotas, SystemEvent,
+};
+
+/// A partition of an actor system, e.g. for one tenant among many hosted in
+/// the same process. The actors of a namespace live under '/user/<name>', and
+/// the operations on a namespace only see and affect those actors. Get one
+/// through [`ActorSystem::namespace()`]. For example:
+/// ```
+/// # use tiny_tokio_actor::*;
+/// # #[derive(Clone, Debug)]
+/// # struct TestEvent;
+/// # impl SystemEvent for TestEvent {}
+/// # struct Session;
+/// # impl Actor<TestEvent> for Session {}
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), ActorError> {
+/// let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+/// let tenant = system.namespace("tenant-a")?;
+/// tenant.set_quotas(Quotas::default().with_max_actors(100));
+///
+/// let session = tenant.create_actor("session-1", Session).await?;
+/// assert_eq!(session.path(), &ActorPath::from("/user/tenant-a/session-1"));
+///
+/// tenant.shutdown().await;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Namespace<E: SystemEvent> {
+    system: ActorSystem<E>,
+    root: ActorPath,
+}
+
+impl<E: SystemEvent> Clone for Namespace<E> {
+    fn clone(&self) -> Self {
+        Namespace {
+            system: self.system.clone(),
+            root: self.root.clone(),
+        }
+    }
+}
+
+impl<E: SystemEvent> Namespace<E> {
+    pub(crate) fn new(system: ActorSystem<E>, root: ActorPath) -> Self {
+        Namespace { system, root }
+    }
+
+    /// The name of the namespace
+    pub fn name(&self) -> String {
+        self.root.key()
+    }
+
+    /// The path all actors of the namespace live under
+    pub fn path(&self) -> &ActorPath {
+        &self.root
+    }
+
+    /// The actor system of the namespace
+    pub fn system(&self) -> &ActorSystem<E> {
+        &self.system
+    }
+
+    /// Limits the resources of the namespace, see [`ActorSystem::set_quotas()`].
+    pub fn set_quotas(&self, quotas: Quotas) {
+        self.system.set_quotas(self.scope(), quotas);
+    }
+
+    /// The quotas of the namespace, if any
+    pub fn quotas(&self) -> Option<Quotas> {
+        self.system.quotas(&self.scope())
+    }
+
+    fn scope(&self) -> QuotaScope {
+        QuotaScope::Namespace(self.root.clone())
+    }
+
+    /// Launches a new actor at the top level of the namespace.
+    pub async fn create_actor<A: Actor<E>>(
+        &self,
+        name: &str,
+        actor: A,
+    ) -> Result<ActorRef<E, A>, ActorError> {
+        self.create_actor_with_config(name, actor, ActorConfig::default())
+            .await
+    }
+
+    /// Launches a new actor at the top level of the namespace, using the given
+    /// configuration.
+    pub async fn create_actor_with_config<A: Actor<E>>(
+        &self,
+        name: &str,
+        actor: A,
+        config: ActorConfig,
+    ) -> Result<ActorRef<E, A>, ActorError> {
+        validate_name(name)?;
+        let path = self.root.clone() / name;
+        self.system
+            .create_actor_path_with_config(path, actor, config)
+            .await
+    }
+
+    /// Retrieves an actor at the top level of the namespace.
+    pub async fn get_actor<A: Actor<E>>(&self, name: &str) -> Option<ActorRef<E, A>> {
+        self.system.get_actor(&(self.root.clone() / name)).await
+    }
+
+    /// Retrieves the paths of all actors in the namespace, sorted.
+    pub async fn actor_paths(&self) -> Vec<ActorPath> {
+        let mut paths = self
+            .system
+            .find_paths(|path| path.is_descendant_of(&self.root))
+            .await;
+        paths.sort_unstable();
+        paths
+    }
+
+    /// The pattern relative to the namespace as a pattern of absolute paths.
+    fn pattern(&self, pattern: &str) -> String {
+        format!("{}/{}", self.root, pattern.trim_start_matches('/'))
+    }
+
+    /// Tells the message to all actors of type `A` in the namespace whose path
+    /// relative to the namespace matches the pattern, e.g. `workers/*`. See
+    /// [`ActorSystem::tell_matching()`].
+    pub async fn tell_matching<A, M>(&self, pattern: &str, msg: M) -> BulkResult
+    where
+        A: Actor<E> + Handler<E, M>,
+        M: Message,
+    {
+        self.system
+            .tell_matching::<A, M>(&self.pattern(pattern), msg)
+            .await
+    }
+
+    /// Stops all actors in the namespace whose path relative to the namespace
+    /// matches the pattern. See [`ActorSystem::stop_actors_matching()`].
+    pub async fn stop_actors_matching(&self, pattern: &str) -> Vec<ActorPath> {
+        self.system
+            .stop_actors_matching(&self.pattern(pattern))
+            .await
+    }
+
+    /// Stops all actors in the namespace, and resolves once all of them have
+    /// stopped. Its quotas remain in place.
+    pub async fn shutdown(&self) {
+        log::debug!("Shutting down namespace '{}'...", &self.root);
+        self.system.stop_subtree(&self.root).await;
+    }
+}
@@ -0,0 +1,177 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use async_trait::async_trait;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::sync::CancellationToken;
+
+use crate::{Actor, ActorContext, ActorError, ActorSystem, Handler, Message, SystemEvent};
+
+type ConnectionFactory<A> = Arc<dyn Fn(TcpStream, SocketAddr) -> A + Send + Sync>;
+
+/// An actor that listens on a TCP address and runs an actor per accepted
+/// connection, created from the stream by the given factory. The connection
+/// actors are anonymous children of the acceptor, so stopping the acceptor
+/// also stops all its connections.
+///
+/// The listener is bound in `pre_start`, so binding failures are subject to
+/// the supervision strategy of the acceptor (e.g. set through
+/// [`crate::ActorConfig::with_supervision()`]). If accepting connections
+/// fails, the acceptor stops and the failure is escalated to its parent (see
+/// [`crate::Actor::child_failed()`]).
+pub struct TcpAcceptorActor<A> {
+    addr: String,
+    factory: ConnectionFactory<A>,
+    local_addr: Option<SocketAddr>,
+    accepting: Option<CancellationToken>,
+}
+
+impl<A> TcpAcceptorActor<A> {
+    pub fn new<F>(addr: &str, factory: F) -> Self
+    where
+        F: Fn(TcpStream, SocketAddr) -> A + Send + Sync + 'static,
+    {
+        TcpAcceptorActor {
+            addr: addr.to_string(),
+            factory: Arc::new(factory),
+            local_addr: None,
+            accepting: None,
+        }
+    }
+}
+
+#[async_trait]
+impl<E: SystemEvent, A: Actor<E>> Actor<E> for TcpAcceptorActor<A> {
+    async fn pre_start(&mut self, ctx: &mut ActorContext<E>) -> Result<(), ActorError> {
+        let listener = TcpListener::bind(&self.addr)
+            .await
+            .map_err(ActorError::new)?;
+        self.local_addr = Some(listener.local_addr().map_err(ActorError::new)?);
+        log::debug!(
+            "Actor '{}' listening on {:?}",
+            &ctx.path,
+            self.local_addr.as_ref()
+        );
+
+        let accepting = ctx.cancellation_token().child_token();
+        self.accepting = Some(accepting.clone());
+        tokio::spawn(accept(
+            listener,
+            self.factory.clone(),
+            ctx.system.clone(),
+            ctx.path.clone(),
+            accepting,
+        ));
+        Ok(())
+    }
+
+    async fn post_stop(&mut self, _ctx: &mut ActorContext<E>) {
+        if let Some(accepting) = self.accepting.take() {
+            accepting.cancel();
+        }
+    }
+}
+
+async fn accept<E: SystemEvent, A: Actor<E>>(
+    listener: TcpListener,
+    factory: ConnectionFactory<A>,
+    system: ActorSystem<E>,
+    path: crate::ActorPath,
+    accepting: CancellationToken,
+) {
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = accepting.cancelled() => return,
+        };
+        match accepted {
+            Ok((stream, peer)) => {
+                let child = path.clone() / &system.anonymous_name();
+                if let Err(error) = system.create_actor_path(child, factory(stream, peer)).await {
+                    log::error!(
+                        "Failed to create actor for connection from {}: {:?}",
+                        peer,
+                        error
+                    );
+                }
+            }
+            Err(error) => {
+                log::error!("Actor '{}' failed to accept connection: {}", &path, error);
+                system.escalate(&path, ActorError::new(error)).await;
+                return;
+            }
+        }
+    }
+}
+
+/// Asks the acceptor for the address it is listening on.
+#[derive(Clone, Debug)]
+pub struct LocalAddr;
+
+impl Message for LocalAddr {
+    type Response = Option<SocketAddr>;
+}
+
+#[async_trait]
+impl<E: SystemEvent, A: Actor<E>> Handler<E, LocalAddr> for TcpAcceptorActor<A> {
+    async fn handle(&mut self, _msg: LocalAddr, _ctx: &mut ActorContext<E>) -> Option<SocketAddr> {
+        self.local_addr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use crate::EventBus;
+
+    use super::*;
+
+    #[derive(Clone, Debug)]
+    struct TestEvent;
+
+    impl SystemEvent for TestEvent {}
+
+    struct EchoActor {
+        stream: Option<TcpStream>,
+    }
+
+    #[async_trait]
+    impl Actor<TestEvent> for EchoActor {
+        async fn pre_start(
+            &mut self,
+            _ctx: &mut ActorContext<TestEvent>,
+        ) -> Result<(), ActorError> {
+            let mut stream = self.stream.take().unwrap();
+            tokio::spawn(async move {
+                let mut buffer = [0; 5];
+                stream.read_exact(&mut buffer).await.unwrap();
+                stream.write_all(&buffer).await.unwrap();
+            });
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn tcp_acceptor() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(10));
+        let acceptor = TcpAcceptorActor::new("127.0.0.1:0", |stream, _peer| EchoActor {
+            stream: Some(stream),
+        });
+        let acceptor = system.create_actor("acceptor", acceptor).await.unwrap();
+        let addr = acceptor.ask(LocalAddr).await.unwrap().unwrap();
+
+        for _ in 0..2 {
+            let mut client = TcpStream::connect(addr).await.unwrap();
+            client.write_all(b"hello").await.unwrap();
+            let mut buffer = [0; 5];
+            client.read_exact(&mut buffer).await.unwrap();
+            assert_eq!(&buffer, b"hello");
+        }
+
+        let connections = system
+            .find_paths(|path| path.is_child_of(acceptor.path()))
+            .await;
+        assert_eq!(connections.len(), 2);
+    }
+}
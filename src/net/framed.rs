@@ -0,0 +1,269 @@
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf};
+use tokio_util::{
+    codec::{Decoder, Encoder, FramedRead, FramedWrite},
+    sync::CancellationToken,
+};
+
+use crate::{
+    Actor, ActorContext, ActorError, ActorPath, ActorRef, ActorSystem, Handler, Message,
+    SystemEvent,
+};
+
+/// A frame decoded by a [`FramedActor`], sent to its target actor.
+#[derive(Clone, Debug)]
+pub struct Frame<I> {
+    /// The path of the framed actor that read the frame, e.g. to send replies
+    /// to it as [`Outbound`] messages.
+    pub connection: ActorPath,
+    pub item: I,
+}
+
+impl<I: Clone + Send + Sync + 'static> Message for Frame<I> {
+    type Response = ();
+}
+
+/// Asks a [`FramedActor`] to encode the item and write it to its connection.
+/// The response resolves once the frame has been flushed.
+#[derive(Clone, Debug)]
+pub struct Outbound<O>(pub O);
+
+impl<O: Clone + Send + Sync + 'static> Message for Outbound<O> {
+    type Response = Result<(), ActorError>;
+}
+
+/// An actor that owns a connection and speaks a protocol through the codec
+/// `C`. Every decoded frame is sent to the target actor as a [`Frame`], and
+/// items sent to this actor as [`Outbound`] messages are encoded and written
+/// to the connection. For example, as created by a
+/// [`TcpAcceptorActor`](super::TcpAcceptorActor):
+/// ```no_run
+/// # use tiny_tokio_actor::{*, net::*};
+/// # use tokio_util::codec::LinesCodec;
+/// # #[derive(Clone, Debug)]
+/// # struct TestEvent;
+/// # impl SystemEvent for TestEvent {}
+/// # struct Chat;
+/// # impl Actor<TestEvent> for Chat {}
+/// # #[async_trait]
+/// # impl Handler<TestEvent, Frame<String>> for Chat {
+/// #     async fn handle(&mut self, _msg: Frame<String>, _ctx: &mut ActorContext<TestEvent>) {}
+/// # }
+/// # async fn run(system: ActorSystem<TestEvent>) -> Result<(), ActorError> {
+/// let chat = system.create_actor("chat", Chat).await?;
+/// let acceptor = TcpAcceptorActor::new("127.0.0.1:6000", move |stream, _peer| {
+///     FramedActor::new(stream, LinesCodec::new(), chat.clone())
+/// });
+/// system.create_actor("chat-server", acceptor).await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Frames are read one at a time: the next frame is only read once the target
+/// handled the previous one, so a slow target slows down the peer instead of
+/// buffering frames without bound. Likewise outbound messages queue up in the
+/// mailbox while a write is in progress.
+///
+/// The actor stops when the peer closes the connection or the target stops.
+/// If a frame cannot be decoded, the actor stops and the failure is escalated
+/// to its parent (see [`crate::Actor::child_failed()`]).
+pub struct FramedActor<E: SystemEvent, S, C, T: Actor<E>> {
+    reader: Option<FramedRead<ReadHalf<S>, C>>,
+    writer: FramedWrite<WriteHalf<S>, C>,
+    target: ActorRef<E, T>,
+    reading: Option<CancellationToken>,
+}
+
+impl<E, S, C, T> FramedActor<E, S, C, T>
+where
+    E: SystemEvent,
+    S: AsyncRead + AsyncWrite,
+    C: Clone,
+    T: Actor<E>,
+{
+    pub fn new(io: S, codec: C, target: ActorRef<E, T>) -> Self {
+        let (read, write) = tokio::io::split(io);
+        FramedActor {
+            reader: Some(FramedRead::new(read, codec.clone())),
+            writer: FramedWrite::new(write, codec),
+            target,
+            reading: None,
+        }
+    }
+}
+
+#[async_trait]
+impl<E, S, C, T> Actor<E> for FramedActor<E, S, C, T>
+where
+    E: SystemEvent,
+    S: AsyncRead + AsyncWrite + Send + Sync + 'static,
+    C: Decoder + Send + Sync + 'static,
+    C::Item: Clone + Send + Sync + 'static,
+    C::Error: std::error::Error + Send + Sync + 'static,
+    T: Actor<E> + Handler<E, Frame<C::Item>>,
+{
+    async fn pre_start(&mut self, ctx: &mut ActorContext<E>) -> Result<(), ActorError> {
+        // A restarted actor keeps reading with the task of its first start
+        if let Some(reader) = self.reader.take() {
+            let reading = ctx.cancellation_token().child_token();
+            self.reading = Some(reading.clone());
+            tokio::spawn(read(
+                reader,
+                self.target.clone(),
+                ctx.system.clone(),
+                ctx.path.clone(),
+                reading,
+            ));
+        }
+        Ok(())
+    }
+
+    async fn post_stop(&mut self, ctx: &mut ActorContext<E>) {
+        if let Some(reading) = self.reading.take() {
+            reading.cancel();
+        }
+        if let Err(error) = self.writer.get_mut().shutdown().await {
+            log::debug!(
+                "Actor '{}' failed to close connection: {}",
+                &ctx.path,
+                error
+            );
+        }
+    }
+}
+
+async fn read<E, S, C, T>(
+    mut reader: FramedRead<ReadHalf<S>, C>,
+    target: ActorRef<E, T>,
+    system: ActorSystem<E>,
+    path: ActorPath,
+    reading: CancellationToken,
+) where
+    E: SystemEvent,
+    S: AsyncRead,
+    C: Decoder,
+    C::Item: Clone + Send + Sync + 'static,
+    C::Error: std::error::Error + Send + Sync + 'static,
+    T: Actor<E> + Handler<E, Frame<C::Item>>,
+{
+    loop {
+        let next = tokio::select! {
+            next = reader.next() => next,
+            _ = reading.cancelled() => return,
+        };
+        match next {
+            Some(Ok(item)) => {
+                let frame = Frame {
+                    connection: path.clone(),
+                    item,
+                };
+                if let Err(error) = target.ask_with_timeout(frame, None).await {
+                    log::debug!(
+                        "Actor '{}' stops as its target '{}' failed: {:?}",
+                        &path,
+                        target.path(),
+                        error
+                    );
+                    break;
+                }
+            }
+            Some(Err(error)) => {
+                log::error!("Actor '{}' failed to decode frame: {}", &path, error);
+                system.escalate(&path, ActorError::new(error)).await;
+                return;
+            }
+            None => break,
+        }
+    }
+    system.terminate(&path).await;
+}
+
+#[async_trait]
+impl<E, S, C, T, O> Handler<E, Outbound<O>> for FramedActor<E, S, C, T>
+where
+    E: SystemEvent,
+    S: AsyncRead + AsyncWrite + Send + Sync + 'static,
+    C: Decoder + Encoder<O> + Send + Sync + 'static,
+    C::Item: Clone + Send + Sync + 'static,
+    <C as Decoder>::Error: std::error::Error + Send + Sync + 'static,
+    <C as Encoder<O>>::Error: std::error::Error + Send + Sync + 'static,
+    T: Actor<E> + Handler<E, Frame<C::Item>>,
+    O: Clone + Send + Sync + 'static,
+{
+    async fn handle(
+        &mut self,
+        msg: Outbound<O>,
+        _ctx: &mut ActorContext<E>,
+    ) -> Result<(), ActorError> {
+        self.writer.send(msg.0).await.map_err(ActorError::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use tokio::{
+        io::{AsyncBufReadExt, BufReader},
+        net::TcpStream,
+    };
+    use tokio_util::codec::LinesCodec;
+
+    use crate::{net::LocalAddr, net::TcpAcceptorActor, EventBus};
+
+    use super::*;
+
+    #[derive(Clone, Debug)]
+    struct TestEvent;
+
+    impl SystemEvent for TestEvent {}
+
+    struct ShoutActor;
+
+    impl Actor<TestEvent> for ShoutActor {}
+
+    type Connection = FramedActor<TestEvent, TcpStream, LinesCodec, ShoutActor>;
+
+    #[async_trait]
+    impl Handler<TestEvent, Frame<String>> for ShoutActor {
+        async fn handle(&mut self, msg: Frame<String>, ctx: &mut ActorContext<TestEvent>) {
+            let connection = ctx
+                .system
+                .get_actor::<Connection>(&msg.connection)
+                .await
+                .unwrap();
+            connection.tell(Outbound(msg.item.to_uppercase())).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn framed_actor() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(10));
+        let shout = system.create_actor("shout", ShoutActor).await.unwrap();
+        let acceptor = TcpAcceptorActor::new("127.0.0.1:0", move |stream, _peer| {
+            FramedActor::new(stream, LinesCodec::new(), shout.clone())
+        });
+        let acceptor = system.create_actor("acceptor", acceptor).await.unwrap();
+        let addr = acceptor.ask(LocalAddr).await.unwrap().unwrap();
+
+        let mut client = BufReader::new(TcpStream::connect(addr).await.unwrap());
+        client.write_all(b"hello\nworld\n").await.unwrap();
+        let mut line = String::new();
+        client.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "HELLO\n");
+        line.clear();
+        client.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "WORLD\n");
+
+        // Closing the connection stops the framed actor
+        drop(client);
+        let is_child = |path: &ActorPath| path.is_child_of(acceptor.path());
+        for _ in 0..100 {
+            if system.find_paths(is_child).await.is_empty() {
+                return;
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+        }
+        panic!("framed actor did not stop");
+    }
+}
@@ -0,0 +1,7 @@
+//! Actor based network servers. Requires the `net` feature.
+
+mod acceptor;
+mod framed;
+
+pub use acceptor::{LocalAddr, TcpAcceptorActor};
+pub use framed::{Frame, FramedActor, Outbound};
@@ -0,0 +1,209 @@
+use std::{collections::BTreeMap, future::Future, sync::Arc};
+
+use crate::ActorPath;
+
+tokio::task_local! {
+    /// The path of the actor whose runner is running the current task.
+    static SENDER: ActorPath;
+
+    /// On whose behalf the current task sends messages.
+    static CALLER: Option<Arc<Caller>>;
+}
+
+/// The identity on whose behalf a message is sent, e.g. the user of the
+/// request that a remoting layer or web handler turned into a message. It is
+/// attached to the messages sent within [`Caller::scope()`], and handlers
+/// can read it through [`ActorContext::caller()`](crate::ActorContext::caller)
+/// to make authorization decisions. Messages that a handler sends, and asks
+/// it makes, carry the caller of the message being handled, so the identity
+/// follows the work through the actors. Tasks spawned by a handler do not
+/// inherit it. For example:
+/// ```
+/// # use tiny_tokio_actor::*;
+/// # #[derive(Clone, Debug)]
+/// # struct TestEvent;
+/// # impl SystemEvent for TestEvent {}
+/// #[derive(Clone, Debug)]
+/// struct DeleteAccount;
+///
+/// impl Message for DeleteAccount {
+///     type Response = Result<(), ActorError>;
+/// }
+///
+/// struct Accounts;
+///
+/// impl Actor<TestEvent> for Accounts {}
+///
+/// #[async_trait]
+/// impl Handler<TestEvent, DeleteAccount> for Accounts {
+///     async fn handle(
+///         &mut self,
+///         _msg: DeleteAccount,
+///         ctx: &mut ActorContext<TestEvent>,
+///     ) -> Result<(), ActorError> {
+///         match ctx.caller() {
+///             Some(caller) if caller.attribute("role") == Some("admin") => Ok(()),
+///             _ => Err(ActorError::Unauthorized {
+///                 path: ctx.path.clone(),
+///                 message_type: std::any::type_name::<DeleteAccount>(),
+///             }),
+///         }
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), ActorError> {
+/// let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+/// let accounts = system.create_actor("accounts", Accounts).await?;
+///
+/// let admin = Caller::new("alice").with_attribute("role", "admin");
+/// let deleted = admin.scope(accounts.ask(DeleteAccount)).await?;
+/// assert!(deleted.is_ok());
+/// let denied = accounts.ask(DeleteAccount).await?;
+/// assert!(matches!(denied, Err(ActorError::Unauthorized { .. })));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Caller {
+    principal: String,
+    attributes: BTreeMap<String, String>,
+}
+
+impl Caller {
+    /// A caller identified by the principal, e.g. a user or service name.
+    pub fn new(principal: &str) -> Self {
+        Caller {
+            principal: principal.to_string(),
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    /// Adds an attribute to the identity, e.g. a role or tenant.
+    pub fn with_attribute(mut self, key: &str, value: &str) -> Self {
+        self.attributes.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// The principal of the caller
+    pub fn principal(&self) -> &str {
+        &self.principal
+    }
+
+    /// The value of an attribute of the caller, if it has it
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.get(key).map(String::as_str)
+    }
+
+    /// All attributes of the caller
+    pub fn attributes(&self) -> &BTreeMap<String, String> {
+        &self.attributes
+    }
+
+    /// Runs the future on behalf of the caller: the messages it sends carry
+    /// the caller.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CALLER.scope(Some(Arc::new(self)), future).await
+    }
+
+    /// The caller the current task sends messages on behalf of, if any
+    pub fn current() -> Option<Caller> {
+        current_caller().map(|caller| caller.as_ref().clone())
+    }
+}
+
+/// The caller to attach to the messages sent by the current task.
+pub(crate) fn current_caller() -> Option<Arc<Caller>> {
+    CALLER.try_with(Clone::clone).ok().flatten()
+}
+
+/// Runs the handling of a message on behalf of the caller it carries.
+pub(crate) async fn calling_as<F: Future>(caller: Option<Arc<Caller>>, handler: F) -> F::Output {
+    CALLER.scope(caller, handler).await
+}
+
+/// A message about to enter the mailbox of an actor, as presented to a
+/// [`MessagePolicy`].
+#[derive(Clone, Copy, Debug)]
+pub struct Delivery<'a> {
+    /// The actor sending the message, or `None` if it is sent from outside
+    /// an actor, e.g. from `main` or a task spawned by an actor.
+    pub sender: Option<&'a ActorPath>,
+    /// The actor the message is sent to.
+    pub recipient: &'a ActorPath,
+    /// The type name of the message.
+    pub message_type: &'static str,
+    /// On whose behalf the message is sent, if known, see [`Caller`].
+    pub caller: Option<&'a Caller>,
+}
+
+/// Decides which messages may be delivered, e.g. so that in a multi-tenant
+/// deployment the actors of one tenant cannot command the actors of another.
+/// Register it for all actors of a system through
+/// [`SystemConfig::with_message_policy()`](crate::SystemConfig::with_message_policy).
+/// It is called inline before each message enters a mailbox, including a
+/// message sent through [`ActorRef::tell_latest()`](crate::ActorRef::tell_latest)
+/// that replaces one still queued, so it should return quickly. Rejected
+/// messages fail with an `ActorError::Unauthorized` and become dead letters.
+/// Closures taking a [`Delivery`] are policies too. For example:
+/// ```
+/// use tiny_tokio_actor::*;
+///
+/// let tenants = |delivery: &Delivery<'_>| match delivery.sender {
+///     Some(sender) => sender.parent() == delivery.recipient.parent(),
+///     None => true,
+/// };
+/// let config = SystemConfig::new("test").with_message_policy(tenants);
+/// ```
+pub trait MessagePolicy: Send + Sync + 'static {
+    /// True if the message may be delivered.
+    fn authorize(&self, delivery: &Delivery<'_>) -> bool;
+}
+
+impl<F> MessagePolicy for F
+where
+    F: Fn(&Delivery<'_>) -> bool + Send + Sync + 'static,
+{
+    fn authorize(&self, delivery: &Delivery<'_>) -> bool {
+        self(delivery)
+    }
+}
+
+/// A shared [`MessagePolicy`] as held by the system configuration.
+#[derive(Clone)]
+pub(crate) struct Policy(pub Arc<dyn MessagePolicy>);
+
+impl Policy {
+    /// Checks if the current task may send the message to the recipient.
+    pub fn authorize(&self, recipient: &ActorPath, message_type: &'static str) -> bool {
+        let caller = current_caller();
+        let authorize = |sender: Option<&ActorPath>| {
+            self.0.authorize(&Delivery {
+                sender,
+                recipient,
+                message_type,
+                caller: caller.as_deref(),
+            })
+        };
+        SENDER
+            .try_with(|sender| authorize(Some(sender)))
+            .unwrap_or_else(|_| authorize(None))
+    }
+}
+
+impl std::fmt::Debug for Policy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Policy")
+    }
+}
+
+/// The path of the actor whose runner is running the current task, if any.
+pub(crate) fn current_sender() -> Option<ActorPath> {
+    SENDER.try_with(Clone::clone).ok()
+}
+
+/// Runs the runner of the actor at the path, so the messages it sends are
+/// sent on behalf of the actor.
+pub(crate) async fn sending_as<F: Future>(path: ActorPath, runner: F) -> F::Output {
+    SENDER.scope(path, runner).await
+}
@@ -0,0 +1,212 @@
+/// Defines the public protocol of an actor as a trait, so callers don't have to
+/// know the individual message types.
+///
+/// For every method a message struct is generated, with the method arguments
+/// as public fields, together with a [`Handler`](crate::Handler) that calls the
+/// inherent method of the same name on the actor (taking `&mut self`, the
+/// arguments, and the [`ActorContext`](crate::ActorContext)). The trait itself is
+/// implemented for the [`ActorRef`](crate::ActorRef) of the actor, with every
+/// method sending its message through [`ActorRef::ask()`](crate::ActorRef::ask).
+/// For example:
+/// ```
+/// use tiny_tokio_actor::*;
+///
+/// #[derive(Clone, Debug)]
+/// struct TestEvent(String);
+///
+/// impl SystemEvent for TestEvent {}
+///
+/// #[derive(Default)]
+/// struct CounterActor {
+///     counter: usize,
+/// }
+///
+/// impl Actor<TestEvent> for CounterActor {}
+///
+/// impl CounterActor {
+///     async fn increment(&mut self, n: usize, _ctx: &mut ActorContext<TestEvent>) -> usize {
+///         self.counter += n;
+///         self.counter
+///     }
+///
+///     async fn get(&mut self, _ctx: &mut ActorContext<TestEvent>) -> usize {
+///         self.counter
+///     }
+/// }
+///
+/// actor_protocol! {
+///     /// The protocol of the counter actor.
+///     pub trait Counter for CounterActor, TestEvent {
+///         Increment => async fn increment(&self, n: usize) -> usize;
+///         Get => async fn get(&self) -> usize;
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+///     let counter = system.create_actor("counter", CounterActor::default()).await.unwrap();
+///
+///     assert_eq!(counter.increment(5).await.unwrap(), 5);
+///     assert_eq!(counter.get().await.unwrap(), 5);
+///
+///     // The generated message types can still be used directly.
+///     counter.tell(Increment { n: 1 }).unwrap();
+///     assert_eq!(counter.ask(Get {}).await.unwrap(), 6);
+/// }
+/// ```
+///
+/// A `sealed` protocol also generates a client named after `as`, which wraps
+/// the [`ActorRef`](crate::ActorRef) and only offers the methods of the
+/// protocol. Handing out the client instead of the actor ref makes the
+/// protocol the complete set of messages callers can send:
+/// ```
+/// # use tiny_tokio_actor::*;
+/// # #[derive(Clone, Debug)]
+/// # struct TestEvent;
+/// # impl SystemEvent for TestEvent {}
+/// # #[derive(Default)]
+/// # struct CounterActor {
+/// #     counter: usize,
+/// # }
+/// # impl Actor<TestEvent> for CounterActor {}
+/// # impl CounterActor {
+/// #     async fn get(&mut self, _ctx: &mut ActorContext<TestEvent>) -> usize {
+/// #         self.counter
+/// #     }
+/// # }
+/// actor_protocol! {
+///     pub sealed trait Counter for CounterActor, TestEvent as CounterClient {
+///         Get => async fn get(&self) -> usize;
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), ActorError> {
+/// # let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+/// let counter = system.create_actor("counter", CounterActor::default()).await?;
+/// let client = CounterClient::new(counter);
+/// assert_eq!(client.get().await?, 0);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Sending anything else through the client fails to compile:
+/// ```compile_fail
+/// # use tiny_tokio_actor::*;
+/// # #[derive(Clone, Debug)]
+/// # struct TestEvent;
+/// # impl SystemEvent for TestEvent {}
+/// # #[derive(Default)]
+/// # struct CounterActor;
+/// # impl Actor<TestEvent> for CounterActor {}
+/// # impl CounterActor {
+/// #     async fn get(&mut self, _ctx: &mut ActorContext<TestEvent>) -> usize {
+/// #         0
+/// #     }
+/// # }
+/// # actor_protocol! {
+/// #     pub sealed trait Counter for CounterActor, TestEvent as CounterClient {
+/// #         Get => async fn get(&self) -> usize;
+/// #     }
+/// # }
+/// # async fn reset(client: CounterClient) {
+/// client.ask(Get {}).await;
+/// # }
+/// ```
+#[macro_export]
+macro_rules! actor_protocol {
+    (
+        $(#[$meta:meta])*
+        $vis:vis sealed trait $name:ident for $actor:ty, $event:ty as $client:ident {
+            $(
+                $(#[$msg_meta:meta])*
+                $msg:ident => async fn $method:ident(&self $(, $arg:ident : $arg_ty:ty)* $(,)?) -> $ret:ty;
+            )*
+        }
+    ) => {
+        $crate::actor_protocol! {
+            $(#[$meta])*
+            $vis trait $name for $actor, $event {
+                $(
+                    $(#[$msg_meta])*
+                    $msg => async fn $method(&self $(, $arg: $arg_ty)*) -> $ret;
+                )*
+            }
+        }
+
+        /// A client of the actor that can only send the messages of its
+        /// protocol.
+        #[derive(Clone, Debug)]
+        $vis struct $client {
+            actor_ref: $crate::ActorRef<$event, $actor>,
+        }
+
+        impl $client {
+            /// Wraps the actor ref, so only the protocol can be used.
+            pub fn new(actor_ref: $crate::ActorRef<$event, $actor>) -> Self {
+                $client { actor_ref }
+            }
+
+            /// The path of the actor
+            pub fn path(&self) -> &$crate::ActorPath {
+                self.actor_ref.path()
+            }
+        }
+
+        #[$crate::async_trait]
+        impl $name for $client {
+            $(
+                async fn $method(&self $(, $arg: $arg_ty)*) -> Result<$ret, $crate::ActorError> {
+                    $name::$method(&self.actor_ref $(, $arg)*).await
+                }
+            )*
+        }
+    };
+    (
+        $(#[$meta:meta])*
+        $vis:vis trait $name:ident for $actor:ty, $event:ty {
+            $(
+                $(#[$msg_meta:meta])*
+                $msg:ident => async fn $method:ident(&self $(, $arg:ident : $arg_ty:ty)* $(,)?) -> $ret:ty;
+            )*
+        }
+    ) => {
+        $(
+            $(#[$msg_meta])*
+            #[derive(Clone)]
+            $vis struct $msg {
+                $(pub $arg: $arg_ty,)*
+            }
+
+            impl $crate::Message for $msg {
+                type Response = $ret;
+            }
+
+            #[$crate::async_trait]
+            impl $crate::Handler<$event, $msg> for $actor {
+                async fn handle(&mut self, msg: $msg, ctx: &mut $crate::ActorContext<$event>) -> $ret {
+                    let $msg { $($arg,)* } = msg;
+                    self.$method($($arg,)* ctx).await
+                }
+            }
+        )*
+
+        $(#[$meta])*
+        #[$crate::async_trait]
+        $vis trait $name {
+            $(
+                async fn $method(&self $(, $arg: $arg_ty)*) -> Result<$ret, $crate::ActorError>;
+            )*
+        }
+
+        #[$crate::async_trait]
+        impl $name for $crate::ActorRef<$event, $actor> {
+            $(
+                async fn $method(&self $(, $arg: $arg_ty)*) -> Result<$ret, $crate::ActorError> {
+                    self.ask($msg { $($arg,)* }).await
+                }
+            )*
+        }
+    };
+}
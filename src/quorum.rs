@@ -0,0 +1,600 @@
+use std::{future::Future, time::Duration};
+
+use tokio::task::JoinSet;
+
+use crate::{
+    deadline, policy, Actor, ActorError, ActorRef, Deadline, Handler, Message, SystemEvent,
+};
+
+/// How [`ask_all()`] combines the responses of the actors it asks into one
+/// result, e.g. [`FirstSuccess`], [`Majority`], [`Fold`] or [`Fastest`].
+pub trait Gather<R>: Send {
+    /// The combined result
+    type Output;
+
+    /// Called once before the asks are made, with the number of actors
+    /// asked.
+    fn begin(&mut self, _asked: usize) {}
+
+    /// Takes the outcome of one more ask, in the order they complete.
+    /// Returns the result once it is known, which drops the pending asks.
+    fn gather(
+        &mut self,
+        outcome: Result<R, ActorError>,
+    ) -> Option<Result<Self::Output, ActorError>>;
+
+    /// Returns the result once all asks completed without
+    /// [`Gather::gather()`] returning one.
+    fn finish(self) -> Result<Self::Output, ActorError>;
+}
+
+/// Asks all actors the same message at once, combining their responses as
+/// the strategy sets out, e.g. to read from a quorum of replicas. For
+/// example:
+/// ```
+/// # use tiny_tokio_actor::*;
+/// # #[derive(Clone, Debug)]
+/// # struct TestEvent;
+/// # impl SystemEvent for TestEvent {}
+/// #[derive(Clone, Debug)]
+/// struct Read;
+///
+/// impl Message for Read {
+///     type Response = u64;
+/// }
+///
+/// struct Replica {
+///     version: u64,
+/// }
+///
+/// impl Actor<TestEvent> for Replica {}
+///
+/// #[async_trait]
+/// impl Handler<TestEvent, Read> for Replica {
+///     async fn handle(&mut self, _msg: Read, _ctx: &mut ActorContext<TestEvent>) -> u64 {
+///         self.version
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), ActorError> {
+/// let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+/// let mut replicas = Vec::new();
+/// for (name, version) in [("a", 7), ("b", 7), ("c", 6)] {
+///     replicas.push(system.create_actor(name, Replica { version }).await?);
+/// }
+///
+/// let version = ask_all(&replicas, Read, Majority::new()).await?;
+/// assert_eq!(version, 7);
+/// let latest = Fold::new(0, |latest: u64, version: Result<u64, ActorError>| {
+///     version.map_or(latest, |version| latest.max(version))
+/// });
+/// let latest = ask_all(&replicas, Read, latest).await?;
+/// assert_eq!(latest, 7);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Every ask is made through [`ActorRef::ask()`] in a task of its own, within
+/// the [`Deadline`] and on behalf of the [`crate::Caller`] of the current
+/// task, so both the ask timeouts of the actors and the deadline apply. A
+/// strategy that has its result drops the asks still pending.
+pub async fn ask_all<E, A, M, G>(
+    actors: &[ActorRef<E, A>],
+    msg: M,
+    mut gather: G,
+) -> Result<G::Output, ActorError>
+where
+    E: SystemEvent,
+    A: Actor<E> + Handler<E, M>,
+    M: Message,
+    G: Gather<M::Response>,
+{
+    gather.begin(actors.len());
+    let mut asks = JoinSet::new();
+    for actor in actors {
+        let actor = actor.clone();
+        let msg = msg.clone();
+        asks.spawn(inheriting(async move { actor.ask(msg).await }));
+    }
+    while let Some(asked) = asks.join_next().await {
+        let outcome = asked.unwrap_or_else(|error| Err(ActorError::new(error)));
+        if let Some(output) = gather.gather(outcome) {
+            return output;
+        }
+    }
+    gather.finish()
+}
+
+/// Asks the first actor, and when it did not respond within `hedge_after`
+/// asks the next one as well, and so on, returning the first successful
+/// response and dropping the other asks, e.g. to cut the tail latency of
+/// idempotent queries to replicas. An ask that fails moves on to the next
+/// actor right away. Fails with the error of the last ask if none succeeded.
+/// For example:
+/// ```
+/// # use tiny_tokio_actor::*;
+/// # use std::time::Duration;
+/// # #[derive(Clone, Debug)]
+/// # struct TestEvent;
+/// # impl SystemEvent for TestEvent {}
+/// #[derive(Clone, Debug)]
+/// struct Lookup(String);
+///
+/// impl Message for Lookup {
+///     type Response = Option<String>;
+/// }
+///
+/// struct Replica {
+///     delay: Duration,
+/// }
+///
+/// impl Actor<TestEvent> for Replica {}
+///
+/// #[async_trait]
+/// impl Handler<TestEvent, Lookup> for Replica {
+///     async fn handle(&mut self, msg: Lookup, _ctx: &mut ActorContext<TestEvent>) -> Option<String> {
+///         tokio::time::sleep(self.delay).await;
+///         Some(msg.0)
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), ActorError> {
+/// let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+/// let slow = Replica { delay: Duration::from_secs(5) };
+/// let fast = Replica { delay: Duration::ZERO };
+/// let replicas = vec![
+///     system.create_actor("slow", slow).await?,
+///     system.create_actor("fast", fast).await?,
+/// ];
+///
+/// let lookup = Lookup("key".to_string());
+/// let found = ask_hedged(&replicas, lookup, Duration::from_millis(20)).await?;
+/// assert_eq!(found, Some("key".to_string()));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Like with [`ask_all()`], the asks are made within the [`Deadline`] and on
+/// behalf of the [`crate::Caller`] of the current task. The actors whose ask
+/// was dropped still handle the message, but their response is not waited
+/// for.
+pub async fn ask_hedged<E, A, M>(
+    actors: &[ActorRef<E, A>],
+    msg: M,
+    hedge_after: Duration,
+) -> Result<M::Response, ActorError>
+where
+    E: SystemEvent,
+    A: Actor<E> + Handler<E, M>,
+    M: Message,
+{
+    let mut asks = JoinSet::new();
+    let mut backups = actors.iter();
+    let mut hedge = |asks: &mut JoinSet<_>| match backups.next() {
+        Some(actor) => {
+            let actor = actor.clone();
+            let msg = msg.clone();
+            asks.spawn(inheriting(async move { actor.ask(msg).await }));
+            true
+        }
+        None => false,
+    };
+    let mut hedging = hedge(&mut asks);
+    let mut last_error = None;
+    while !asks.is_empty() {
+        tokio::select! {
+            Some(asked) = asks.join_next() => {
+                match asked.unwrap_or_else(|error| Err(ActorError::new(error))) {
+                    Ok(response) => return Ok(response),
+                    Err(error) => {
+                        last_error = Some(error);
+                        hedging = hedge(&mut asks);
+                    }
+                }
+            }
+            _ = tokio::time::sleep(hedge_after), if hedging => {
+                hedging = hedge(&mut asks);
+            }
+        }
+    }
+    Err(last_error.unwrap_or(ActorError::NoQuorum {
+        asked: 0,
+        needed: 1,
+    }))
+}
+
+/// Runs the future within the deadline, on behalf of the caller and as the
+/// sender of the current task, which a spawned task does not inherit.
+fn inheriting<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let deadline = Deadline::current();
+    let caller = policy::current_caller();
+    let sender = policy::current_sender();
+    async move {
+        let future = deadline::within(deadline, policy::calling_as(caller, future));
+        match sender {
+            Some(sender) => policy::sending_as(sender, future).await,
+            None => future.await,
+        }
+    }
+}
+
+/// The first successful response. Fails with the error of the last ask if
+/// none succeeded.
+pub struct FirstSuccess<R> {
+    last_error: Option<ActorError>,
+    _response: std::marker::PhantomData<fn() -> R>,
+}
+
+impl<R> FirstSuccess<R> {
+    pub fn new() -> Self {
+        FirstSuccess {
+            last_error: None,
+            _response: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<R> Default for FirstSuccess<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: Send> Gather<R> for FirstSuccess<R> {
+    type Output = R;
+
+    fn gather(&mut self, outcome: Result<R, ActorError>) -> Option<Result<R, ActorError>> {
+        match outcome {
+            Ok(response) => Some(Ok(response)),
+            Err(error) => {
+                self.last_error = Some(error);
+                None
+            }
+        }
+    }
+
+    fn finish(self) -> Result<R, ActorError> {
+        Err(self.last_error.unwrap_or(ActorError::NoQuorum {
+            asked: 0,
+            needed: 1,
+        }))
+    }
+}
+
+/// The response of more than half of the actors asked. Fails with an
+/// `ActorError::NoQuorum` as soon as no response can get a majority.
+pub struct Majority<R> {
+    votes: Vec<(R, usize)>,
+    asked: usize,
+    outstanding: usize,
+}
+
+impl<R> Majority<R> {
+    pub fn new() -> Self {
+        Majority {
+            votes: Vec::new(),
+            asked: 0,
+            outstanding: 0,
+        }
+    }
+
+    fn needed(&self) -> usize {
+        self.asked / 2 + 1
+    }
+}
+
+impl<R> Default for Majority<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: PartialEq + Send> Gather<R> for Majority<R> {
+    type Output = R;
+
+    fn begin(&mut self, asked: usize) {
+        self.asked = asked;
+        self.outstanding = asked;
+    }
+
+    fn gather(&mut self, outcome: Result<R, ActorError>) -> Option<Result<R, ActorError>> {
+        self.outstanding -= 1;
+        if let Ok(response) = outcome {
+            let index = match self.votes.iter().position(|(vote, _)| *vote == response) {
+                Some(index) => index,
+                None => {
+                    self.votes.push((response, 0));
+                    self.votes.len() - 1
+                }
+            };
+            self.votes[index].1 += 1;
+            if self.votes[index].1 >= self.needed() {
+                return Some(Ok(self.votes.swap_remove(index).0));
+            }
+        }
+        let leading = self.votes.iter().map(|(_, count)| *count).max();
+        if leading.unwrap_or_default() + self.outstanding < self.needed() {
+            return Some(Err(ActorError::NoQuorum {
+                asked: self.asked,
+                needed: self.needed(),
+            }));
+        }
+        None
+    }
+
+    fn finish(self) -> Result<R, ActorError> {
+        Err(ActorError::NoQuorum {
+            asked: self.asked,
+            needed: self.needed(),
+        })
+    }
+}
+
+/// Folds the outcomes of all asks into a value, starting from `init`.
+pub struct Fold<T, F> {
+    value: Option<T>,
+    f: F,
+}
+
+impl<T, F> Fold<T, F> {
+    pub fn new(init: T, f: F) -> Self {
+        Fold {
+            value: Some(init),
+            f,
+        }
+    }
+}
+
+impl<R, T, F> Gather<R> for Fold<T, F>
+where
+    T: Send,
+    F: FnMut(T, Result<R, ActorError>) -> T + Send,
+{
+    type Output = T;
+
+    fn gather(&mut self, outcome: Result<R, ActorError>) -> Option<Result<T, ActorError>> {
+        self.value = self.value.take().map(|value| (self.f)(value, outcome));
+        None
+    }
+
+    fn finish(self) -> Result<T, ActorError> {
+        Ok(self.value.expect("fold value taken"))
+    }
+}
+
+/// The first `n` successful responses, in the order they arrived. Fails with
+/// an `ActorError::NoQuorum` as soon as fewer than `n` asks can succeed.
+pub struct Fastest<R> {
+    needed: usize,
+    responses: Vec<R>,
+    asked: usize,
+    outstanding: usize,
+}
+
+impl<R> Fastest<R> {
+    pub fn new(n: usize) -> Self {
+        Fastest {
+            needed: n,
+            responses: Vec::with_capacity(n),
+            asked: 0,
+            outstanding: 0,
+        }
+    }
+
+    fn no_quorum(&self) -> ActorError {
+        ActorError::NoQuorum {
+            asked: self.asked,
+            needed: self.needed,
+        }
+    }
+}
+
+impl<R: Send> Gather<R> for Fastest<R> {
+    type Output = Vec<R>;
+
+    fn begin(&mut self, asked: usize) {
+        self.asked = asked;
+        self.outstanding = asked;
+    }
+
+    fn gather(&mut self, outcome: Result<R, ActorError>) -> Option<Result<Vec<R>, ActorError>> {
+        self.outstanding -= 1;
+        if let Ok(response) = outcome {
+            self.responses.push(response);
+        }
+        if self.responses.len() >= self.needed {
+            Some(Ok(std::mem::take(&mut self.responses)))
+        } else if self.responses.len() + self.outstanding < self.needed {
+            Some(Err(self.no_quorum()))
+        } else {
+            None
+        }
+    }
+
+    fn finish(self) -> Result<Vec<R>, ActorError> {
+        if self.responses.len() >= self.needed {
+            Ok(self.responses)
+        } else {
+            Err(self.no_quorum())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::{ActorContext, ActorSystem, Caller, EventBus};
+
+    #[derive(Clone, Debug)]
+    struct TestEvent;
+
+    impl SystemEvent for TestEvent {}
+
+    /// Asks for the principal of the caller
+    #[derive(Clone, Debug)]
+    struct WhoAsks;
+
+    impl Message for WhoAsks {
+        type Response = Option<String>;
+    }
+
+    /// Answers after the delay, or never without one
+    struct Replica(Option<Duration>);
+
+    impl Actor<TestEvent> for Replica {}
+
+    #[async_trait]
+    impl Handler<TestEvent, WhoAsks> for Replica {
+        async fn handle(
+            &mut self,
+            _msg: WhoAsks,
+            ctx: &mut ActorContext<TestEvent>,
+        ) -> Option<String> {
+            match self.0 {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => std::future::pending().await,
+            }
+            ctx.caller().map(|caller| caller.principal().to_string())
+        }
+    }
+
+    async fn replicas(
+        system: &ActorSystem<TestEvent>,
+        delays: &[Option<Duration>],
+    ) -> Vec<ActorRef<TestEvent, Replica>> {
+        let mut replicas = Vec::new();
+        for delay in delays {
+            replicas.push(
+                system
+                    .create_anonymous_actor(Replica(*delay))
+                    .await
+                    .unwrap(),
+            );
+        }
+        replicas
+    }
+
+    /// A replica that has stopped, so asking it fails right away
+    async fn stopped(system: &ActorSystem<TestEvent>) -> ActorRef<TestEvent, Replica> {
+        let replica = system
+            .create_anonymous_actor(Replica(Some(Duration::ZERO)))
+            .await
+            .unwrap();
+        system.stop_subtree(replica.path()).await;
+        replica
+    }
+
+    #[tokio::test]
+    async fn no_quorum_early() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let mut replicas = replicas(&system, &[None]).await;
+        replicas.push(stopped(&system).await);
+        replicas.push(stopped(&system).await);
+
+        // The stuck replica is not waited for once it cannot make a quorum
+        let within = Duration::from_secs(5);
+        let majority = ask_all(&replicas, WhoAsks, Majority::new());
+        let error = tokio::time::timeout(within, majority).await.unwrap();
+        assert!(matches!(
+            error,
+            Err(ActorError::NoQuorum {
+                asked: 3,
+                needed: 2
+            })
+        ));
+        let fastest = ask_all(&replicas, WhoAsks, Fastest::new(2));
+        let error = tokio::time::timeout(within, fastest).await.unwrap();
+        assert!(matches!(
+            error,
+            Err(ActorError::NoQuorum {
+                asked: 3,
+                needed: 2
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn ask_hedged_after_error() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let mut backups = replicas(&system, &[Some(Duration::ZERO)]).await;
+        let replicas = vec![stopped(&system).await, backups.remove(0)];
+
+        // The backup is asked right away rather than after the hedge delay
+        let hedging = ask_hedged(&replicas, WhoAsks, Duration::from_secs(60));
+        let hedging = Caller::new("alice").scope(hedging);
+        let principal = tokio::time::timeout(Duration::from_secs(5), hedging)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(principal.as_deref(), Some("alice"));
+    }
+
+    #[tokio::test]
+    async fn ask_hedged_within_deadline() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let replicas = replicas(&system, &[None, None]).await;
+
+        let deadline = Deadline::after(Duration::from_millis(20));
+        let hedging = ask_hedged(&replicas, WhoAsks, Duration::from_millis(5));
+        let error = tokio::time::timeout(Duration::from_secs(5), deadline.scope(hedging))
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(error, ActorError::Timeout));
+    }
+
+    #[tokio::test]
+    async fn no_actors() {
+        let none: Vec<ActorRef<TestEvent, Replica>> = Vec::new();
+        let no_quorum = |result: Result<_, ActorError>| {
+            matches!(
+                result,
+                Err(ActorError::NoQuorum {
+                    asked: 0,
+                    needed: 1
+                })
+            )
+        };
+        assert!(no_quorum(
+            ask_all(&none, WhoAsks, FirstSuccess::new()).await
+        ));
+        assert!(no_quorum(ask_all(&none, WhoAsks, Majority::new()).await));
+        assert!(no_quorum(
+            ask_all(&none, WhoAsks, Fastest::new(1)).await.map(|_| None)
+        ));
+        assert!(no_quorum(ask_hedged(&none, WhoAsks, Duration::ZERO).await));
+        let fold = Fold::new(0, |asked: usize, _: Result<Option<String>, ActorError>| {
+            asked + 1
+        });
+        assert_eq!(ask_all(&none, WhoAsks, fold).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn ask_all_within_deadline() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let replicas = replicas(&system, &[None, None]).await;
+
+        let deadline = Deadline::after(Duration::from_millis(20));
+        let asking = deadline.scope(ask_all(&replicas, WhoAsks, FirstSuccess::new()));
+        let error = tokio::time::timeout(Duration::from_secs(5), asking)
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(error, ActorError::Timeout));
+    }
+
+    #[tokio::test]
+    async fn ask_all_as_caller() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let replicas = replicas(&system, &[Some(Duration::ZERO)]).await;
+
+        let asking = ask_all(&replicas, WhoAsks, Majority::new());
+        let principal = Caller::new("alice").scope(asking).await.unwrap();
+        assert_eq!(principal.as_deref(), Some("alice"));
+    }
+}
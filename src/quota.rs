@@ -0,0 +1,280 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{builtin::BuiltinBus, ActorConfig, ActorError, ActorPath};
+
+/// The actors a set of [`Quotas`] applies to.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum QuotaScope {
+    /// All actors of a [`Namespace`](crate::Namespace), by its path.
+    Namespace(ActorPath),
+    /// All actors created with the label, by key and value.
+    Label(String, String),
+}
+
+impl QuotaScope {
+    /// Checks if an actor created at the path with the configuration is part
+    /// of the scope.
+    pub(crate) fn contains(&self, path: &ActorPath, config: &ActorConfig) -> bool {
+        match self {
+            QuotaScope::Namespace(root) => path.is_descendant_of(root),
+            QuotaScope::Label(key, value) => config.label(key) == Some(value),
+        }
+    }
+}
+
+impl std::fmt::Display for QuotaScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuotaScope::Namespace(root) => write!(f, "namespace '{}'", root),
+            QuotaScope::Label(key, value) => write!(f, "label '{}={}'", key, value),
+        }
+    }
+}
+
+/// A resource that can be limited through [`Quotas`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quota {
+    /// The number of live actors.
+    Actors,
+    /// The total capacity of the mailboxes of the actors.
+    MailboxCapacity,
+    /// The number of messages sent to the actors per second.
+    MessageRate,
+    /// The total number of messages waiting in the mailboxes of the actors.
+    MailboxDepth,
+}
+
+/// A quota was exceeded, see [`ActorSystem::quota_events()`](crate::ActorSystem::quota_events).
+#[derive(Clone, Debug)]
+pub struct QuotaEvent {
+    /// The actors the quota applies to.
+    pub scope: QuotaScope,
+    /// The quota that was exceeded.
+    pub quota: Quota,
+    /// The actor that could not be created or sent to.
+    pub path: ActorPath,
+    /// When the quota was exceeded.
+    pub timestamp: SystemTime,
+}
+
+/// Limits on the resources used by a group of actors, such as the actors of a
+/// [`Namespace`](crate::Namespace) or all actors with a label. Creating an
+/// actor or sending a message that would exceed a quota fails with an
+/// `Err(ActorError::QuotaExceeded)`, and a [`QuotaEvent`] is published.
+/// Quotas apply to the actors created after they were set. For example:
+/// ```
+/// use tiny_tokio_actor::*;
+///
+/// let quotas = Quotas::default()
+///     .with_max_actors(100)
+///     .with_max_messages_per_second(1000)
+///     .with_max_mailbox_depth(10_000);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Quotas {
+    max_actors: Option<usize>,
+    max_mailbox_capacity: Option<usize>,
+    max_messages_per_second: Option<usize>,
+    max_mailbox_depth: Option<usize>,
+}
+
+impl Quotas {
+    /// Allow at most the given number of live actors.
+    pub fn with_max_actors(mut self, actors: usize) -> Self {
+        self.max_actors = Some(actors);
+        self
+    }
+
+    /// Allow the mailboxes of the actors to hold at most the given number of
+    /// messages in total. The actors must then have a bounded mailbox, see
+    /// [`ActorConfig::with_mailbox_capacity()`].
+    pub fn with_max_mailbox_capacity(mut self, capacity: usize) -> Self {
+        self.max_mailbox_capacity = Some(capacity);
+        self
+    }
+
+    /// Allow at most the given number of messages to be sent to the actors
+    /// within each second.
+    pub fn with_max_messages_per_second(mut self, messages: usize) -> Self {
+        self.max_messages_per_second = Some(messages);
+        self
+    }
+
+    /// Allow at most the given number of messages to wait in the mailboxes of
+    /// the actors in total.
+    pub fn with_max_mailbox_depth(mut self, depth: usize) -> Self {
+        self.max_mailbox_depth = Some(depth);
+        self
+    }
+
+    /// The maximum number of actors, if limited
+    pub fn max_actors(&self) -> Option<usize> {
+        self.max_actors
+    }
+
+    /// The maximum total mailbox capacity, if limited
+    pub fn max_mailbox_capacity(&self) -> Option<usize> {
+        self.max_mailbox_capacity
+    }
+
+    /// The maximum number of messages per second, if limited
+    pub fn max_messages_per_second(&self) -> Option<usize> {
+        self.max_messages_per_second
+    }
+
+    /// The maximum total mailbox depth, if limited
+    pub fn max_mailbox_depth(&self) -> Option<usize> {
+        self.max_mailbox_depth
+    }
+
+    /// Checks that an actor with the given configuration can be added to the
+    /// scope, which holds actors with the `existing` configurations.
+    pub(crate) fn check<'a, I>(&self, existing: I, config: &ActorConfig) -> Result<(), Quota>
+    where
+        I: Iterator<Item = &'a ActorConfig>,
+    {
+        let mut actors = 0;
+        let mut capacity = 0;
+        for existing in existing {
+            actors += 1;
+            capacity += existing.mailbox_capacity().unwrap_or_default();
+        }
+        if self
+            .max_actors
+            .is_some_and(|max_actors| actors >= max_actors)
+        {
+            return Err(Quota::Actors);
+        }
+        if let Some(max_capacity) = self.max_mailbox_capacity {
+            match config.mailbox_capacity() {
+                Some(requested) if capacity + requested <= max_capacity => {}
+                // Unbounded mailboxes do not fit any capacity
+                _ => return Err(Quota::MailboxCapacity),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The quotas of a scope, together with the usage shared by its actors.
+pub(crate) struct ScopeQuotas {
+    pub scope: QuotaScope,
+    pub quotas: Quotas,
+    window: Mutex<(Instant, usize)>,
+    depth: AtomicUsize,
+}
+
+impl ScopeQuotas {
+    pub fn new(scope: QuotaScope, quotas: Quotas) -> Self {
+        ScopeQuotas {
+            scope,
+            quotas,
+            window: Mutex::new((Instant::now(), 0)),
+            depth: AtomicUsize::new(0),
+        }
+    }
+
+    /// Counts a message sent within the current second, if allowed.
+    fn admit_rate(&self) -> bool {
+        let max = match self.quotas.max_messages_per_second {
+            Some(max) => max,
+            None => return true,
+        };
+        let mut window = self.window.lock().unwrap();
+        if window.0.elapsed() >= Duration::from_secs(1) {
+            *window = (Instant::now(), 0);
+        }
+        if window.1 >= max {
+            return false;
+        }
+        window.1 += 1;
+        true
+    }
+
+    /// Counts a message added to a mailbox, if allowed.
+    fn admit_depth(&self) -> bool {
+        let max = match self.quotas.max_mailbox_depth {
+            Some(max) => max,
+            None => return true,
+        };
+        self.depth
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |depth| {
+                (depth < max).then_some(depth + 1)
+            })
+            .is_ok()
+    }
+
+    fn leave(&self) {
+        if self.quotas.max_mailbox_depth.is_some() {
+            self.depth.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+}
+
+/// The quotas that apply to the mailbox of one actor.
+#[derive(Clone)]
+pub(crate) struct MailboxQuotas {
+    scopes: Vec<Arc<ScopeQuotas>>,
+    events: BuiltinBus<QuotaEvent>,
+}
+
+impl MailboxQuotas {
+    pub fn new(scopes: Vec<Arc<ScopeQuotas>>, events: BuiltinBus<QuotaEvent>) -> Self {
+        MailboxQuotas { scopes, events }
+    }
+
+    /// Admits a message to the mailbox of the actor at `path` if all quotas
+    /// allow it. An admitted message must be given back through
+    /// [`MailboxQuotas::leave()`] once it leaves the mailbox.
+    pub fn admit(&self, path: &ActorPath) -> Result<(), ActorError> {
+        for (index, scope) in self.scopes.iter().enumerate() {
+            let exceeded = if !scope.admit_rate() {
+                Some(Quota::MessageRate)
+            } else if !scope.admit_depth() {
+                Some(Quota::MailboxDepth)
+            } else {
+                None
+            };
+            if let Some(quota) = exceeded {
+                for admitted in &self.scopes[..index] {
+                    admitted.leave();
+                }
+                return Err(exceeded_quota(&self.events, &scope.scope, quota, path));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn leave(&self) {
+        for scope in &self.scopes {
+            scope.leave();
+        }
+    }
+}
+
+/// Publishes that the quota was exceeded, returning the error to report.
+pub(crate) fn exceeded_quota(
+    events: &BuiltinBus<QuotaEvent>,
+    scope: &QuotaScope,
+    quota: Quota,
+    path: &ActorPath,
+) -> ActorError {
+    log::debug!("Quota {:?} of {} exceeded by '{}'", quota, scope, path);
+    let event = QuotaEvent {
+        scope: scope.clone(),
+        quota,
+        path: path.clone(),
+        timestamp: SystemTime::now(),
+    };
+    events.send(event);
+    ActorError::QuotaExceeded {
+        scope: scope.clone(),
+        quota,
+    }
+}
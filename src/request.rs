@@ -0,0 +1,123 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, Weak},
+};
+
+use tokio::sync::{broadcast, oneshot};
+
+use crate::EventReceiver;
+
+/// The number of requests of a type held for the slowest responder.
+const REQUEST_CAPACITY: usize = 1024;
+
+type Pending<Resp> = Mutex<HashMap<u64, oneshot::Sender<Resp>>>;
+
+/// A request published through
+/// [`ActorSystem::request()`](crate::ActorSystem::request), received by the
+/// responders subscribed through
+/// [`ActorSystem::responder()`](crate::ActorSystem::responder). Any of them
+/// may answer it; the first answer is returned to the requester.
+pub struct Request<Req, Resp> {
+    correlation_id: u64,
+    request: Req,
+    pending: Weak<Pending<Resp>>,
+}
+
+impl<Req: Clone, Resp> Clone for Request<Req, Resp> {
+    fn clone(&self) -> Self {
+        Request {
+            correlation_id: self.correlation_id,
+            request: self.request.clone(),
+            pending: self.pending.clone(),
+        }
+    }
+}
+
+impl<Req, Resp> Request<Req, Resp> {
+    /// The id matching the answer to the request, unique per actor system
+    pub fn correlation_id(&self) -> u64 {
+        self.correlation_id
+    }
+
+    /// The request itself
+    pub fn request(&self) -> &Req {
+        &self.request
+    }
+
+    /// True while the requester waits for an answer
+    pub fn is_pending(&self) -> bool {
+        self.pending.upgrade().is_some_and(|pending| {
+            pending
+                .lock()
+                .unwrap()
+                .get(&self.correlation_id)
+                .is_some_and(|reply| !reply.is_closed())
+        })
+    }
+
+    /// Answers the request. Returns `false` if another responder answered it
+    /// first, or the requester gave up waiting.
+    pub fn respond(&self, response: Resp) -> bool {
+        let reply = self
+            .pending
+            .upgrade()
+            .and_then(|pending| pending.lock().unwrap().remove(&self.correlation_id));
+        match reply {
+            Some(reply) => reply.send(response).is_ok(),
+            None => false,
+        }
+    }
+}
+
+/// The responders and pending requests of a request type, kept as an
+/// extension of the actor system.
+pub(crate) struct Requests<Req, Resp> {
+    sender: broadcast::Sender<Request<Req, Resp>>,
+    pending: Arc<Pending<Resp>>,
+}
+
+impl<Req, Resp> Requests<Req, Resp>
+where
+    Req: Clone + Send + Sync + 'static,
+    Resp: Send + 'static,
+{
+    pub(crate) fn new() -> Self {
+        let (sender, _) = broadcast::channel(REQUEST_CAPACITY);
+        Requests {
+            sender,
+            pending: Default::default(),
+        }
+    }
+
+    pub(crate) fn subscribe(&self) -> EventReceiver<Request<Req, Resp>> {
+        self.sender.subscribe()
+    }
+
+    /// Publishes the request to the responders, returning where its answer
+    /// arrives, or `None` if there are no responders.
+    pub(crate) fn publish(
+        &self,
+        correlation_id: u64,
+        request: Req,
+    ) -> Option<oneshot::Receiver<Resp>> {
+        let (reply, answer) = oneshot::channel();
+        self.pending.lock().unwrap().insert(correlation_id, reply);
+        let request = Request {
+            correlation_id,
+            request,
+            pending: Arc::downgrade(&self.pending),
+        };
+        match self.sender.send(request) {
+            Ok(_) => Some(answer),
+            Err(_) => {
+                self.forget(correlation_id);
+                None
+            }
+        }
+    }
+
+    /// Drops the request once the requester stopped waiting for its answer.
+    pub(crate) fn forget(&self, correlation_id: u64) {
+        self.pending.lock().unwrap().remove(&correlation_id);
+    }
+}
@@ -0,0 +1,269 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    hash::Hash,
+    pin::Pin,
+    time::{Duration, Instant},
+};
+
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    bus::{EventReceiver, EventRecvError},
+    Actor, ActorError, ActorRef, Handler, Message, SystemEvent,
+};
+
+/// Holds back messages for an actor that is still busy, keeping only the
+/// latest message per key.
+pub(crate) trait Conflation<M>: Send {
+    /// Adds a message, replacing a held back message with the same key.
+    fn push(&mut self, msg: M);
+
+    /// Takes the message whose key was held back the longest.
+    fn pop(&mut self) -> Option<M>;
+}
+
+/// A [`Conflation`] keyed by a function of the message.
+pub(crate) struct KeyedConflation<M, K, F> {
+    latest: HashMap<K, M>,
+    order: VecDeque<K>,
+    key: F,
+}
+
+impl<M, K, F> KeyedConflation<M, K, F> {
+    pub fn new(key: F) -> Self {
+        KeyedConflation {
+            latest: HashMap::new(),
+            order: VecDeque::new(),
+            key,
+        }
+    }
+}
+
+impl<M, K, F> Conflation<M> for KeyedConflation<M, K, F>
+where
+    M: Send,
+    K: Hash + Eq + Clone + Send,
+    F: Fn(&M) -> K + Send,
+{
+    fn push(&mut self, msg: M) {
+        let key = (self.key)(&msg);
+        if self.latest.insert(key.clone(), msg).is_none() {
+            self.order.push_back(key);
+        }
+    }
+
+    fn pop(&mut self) -> Option<M> {
+        let key = self.order.pop_front()?;
+        self.latest.remove(&key)
+    }
+}
+
+/// How an actor consumes the events it is subscribed to through
+/// [`ActorSystem::subscribe_actor()`](crate::ActorSystem::subscribe_actor),
+/// so a slow actor can follow a hot stream of events without lagging behind
+/// and losing events at random. Events are thinned out before they enter the
+/// mailbox of the actor. By default all events are delivered. For example:
+/// ```
+/// # use tiny_tokio_actor::*;
+/// # use std::time::Duration;
+/// #[derive(Clone, Debug)]
+/// struct PriceTick {
+///     symbol: String,
+///     price: u64,
+/// }
+///
+/// impl Message for PriceTick {
+///     type Response = ();
+/// }
+///
+/// // At most 100 ticks per second, of which only the latest per symbol while
+/// // the actor is busy
+/// let policy = EventPolicy::<PriceTick>::all()
+///     .max_rate(100, Duration::from_secs(1))
+///     .keep_latest_by(|tick| tick.symbol.clone());
+/// ```
+pub struct EventPolicy<M> {
+    sample: Option<usize>,
+    rate: Option<(usize, Duration)>,
+    conflation: Option<Box<dyn Conflation<M>>>,
+}
+
+impl<M: Message> EventPolicy<M> {
+    /// Deliver all events.
+    pub fn all() -> Self {
+        EventPolicy {
+            sample: None,
+            rate: None,
+            conflation: None,
+        }
+    }
+
+    /// Only deliver every `n`-th event, starting with the first.
+    pub fn sample_every(mut self, n: usize) -> Self {
+        self.sample = Some(n.max(1));
+        self
+    }
+
+    /// Deliver at most `events` events per period, dropping the rest.
+    pub fn max_rate(mut self, events: usize, per: Duration) -> Self {
+        self.rate = Some((events, per));
+        self
+    }
+
+    /// Deliver one event at a time, waiting until the actor has handled it
+    /// before delivering the next. Events that arrive in the meantime are
+    /// held back, keeping only the latest event per key, e.g. the latest
+    /// reading of each sensor.
+    pub fn keep_latest_by<K, F>(mut self, key: F) -> Self
+    where
+        K: Hash + Eq + Clone + Send + 'static,
+        F: Fn(&M) -> K + Send + 'static,
+    {
+        self.conflation = Some(Box::new(KeyedConflation::new(key)));
+        self
+    }
+}
+
+/// Decides which events get through the rate limit and sampling of a policy.
+struct Admission {
+    sample: Option<usize>,
+    rate: Option<(usize, Duration)>,
+    seen: usize,
+    window: (Instant, usize),
+}
+
+impl Admission {
+    fn admit(&mut self) -> bool {
+        let seen = self.seen;
+        self.seen += 1;
+        if self.sample.is_some_and(|n| !seen.is_multiple_of(n)) {
+            return false;
+        }
+        if let Some((events, per)) = self.rate {
+            let (started, admitted) = &mut self.window;
+            if started.elapsed() >= per {
+                *started = Instant::now();
+                *admitted = 0;
+            }
+            if *admitted >= events {
+                return false;
+            }
+            *admitted += 1;
+        }
+        true
+    }
+}
+
+/// A subscription of an actor to the events of its system, see
+/// [`ActorSystem::subscribe_actor()`](crate::ActorSystem::subscribe_actor).
+/// It ends when the actor stops or it is cancelled; dropping it does not end
+/// it.
+#[derive(Clone)]
+pub struct EventSubscription {
+    stopped: CancellationToken,
+}
+
+impl EventSubscription {
+    /// Stops delivering events to the actor.
+    pub fn cancel(&self) {
+        self.stopped.cancel();
+    }
+
+    /// True if the subscription ended
+    pub fn is_cancelled(&self) -> bool {
+        self.stopped.is_cancelled()
+    }
+}
+
+type Delivery = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Delivers the mapped events to the actor according to the policy until the
+/// actor stops or the subscription is cancelled.
+pub(crate) fn subscribe<E, A, M, F>(
+    mut events: EventReceiver<E>,
+    actor: ActorRef<E, A>,
+    policy: EventPolicy<M>,
+    map: F,
+) -> EventSubscription
+where
+    E: SystemEvent,
+    A: Actor<E> + Handler<E, M>,
+    M: Message,
+    F: Fn(E) -> Option<M> + Send + 'static,
+{
+    let stopped = actor.control().cancellation_token().child_token();
+    let subscription = EventSubscription {
+        stopped: stopped.clone(),
+    };
+    let mut admission = Admission {
+        sample: policy.sample,
+        rate: policy.rate,
+        seen: 0,
+        window: (Instant::now(), 0),
+    };
+    let mut conflation = policy.conflation;
+    tokio::spawn(async move {
+        let mut delivering: Option<Delivery> = None;
+        loop {
+            tokio::select! {
+                biased;
+
+                _ = stopped.cancelled() => break,
+                _ = async { delivering.as_mut().unwrap().await }, if delivering.is_some() => {
+                    delivering = None;
+                }
+                event = events.recv() => match event {
+                    Ok(event) => {
+                        let msg = match map(event) {
+                            Some(msg) if admission.admit() => msg,
+                            _ => continue,
+                        };
+                        match &mut conflation {
+                            Some(conflation) => conflation.push(msg),
+                            None => deliver(&actor, msg),
+                        }
+                    }
+                    Err(EventRecvError::Lagged(skipped)) => {
+                        log::warn!(
+                            "Subscription of actor '{}' dropped {} events",
+                            actor.path(),
+                            skipped
+                        );
+                    }
+                    Err(EventRecvError::Closed) => break,
+                },
+            }
+            if delivering.is_none() {
+                if let Some(msg) = conflation.as_mut().and_then(|conflation| conflation.pop()) {
+                    let actor = actor.clone();
+                    delivering = Some(Box::pin(async move {
+                        if let Err(error) = actor.ask(msg).await {
+                            log_undelivered(&actor, &error);
+                        }
+                    }));
+                }
+            }
+        }
+    });
+    subscription
+}
+
+fn deliver<E, A, M>(actor: &ActorRef<E, A>, msg: M)
+where
+    E: SystemEvent,
+    A: Actor<E> + Handler<E, M>,
+    M: Message,
+{
+    if let Err(error) = actor.tell(msg) {
+        log_undelivered(actor, &error);
+    }
+}
+
+fn log_undelivered<E: SystemEvent, A: Actor<E>>(actor: &ActorRef<E, A>, error: &ActorError) {
+    log::debug!(
+        "Failed to deliver event to subscribed actor '{}': {}",
+        actor.path(),
+        error
+    );
+}
@@ -1,20 +1,386 @@
-use std::{any::Any, collections::HashMap, sync::Arc, thread::spawn};
-use tokio::sync::RwLock;
+use log::LevelFilter;
+use std::{
+    any::{Any, TypeId},
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Weak},
+    time::Duration,
+};
+use tokio::{
+    runtime::Handle,
+    sync::{oneshot, Notify, RwLock},
+};
 
 use crate::{
-    actor::{runner::ActorRunner, Actor, ActorRef},
-    bus::{EventBus, EventReceiver},
-    ActorError, ActorPath,
+    actor::{
+        handler::ControlRef, props::Factory, runner::ActorRunner, unreferenced::RefGuard, Actor,
+        ActorConfig, ActorRef, Props,
+    },
+    bridge::SystemBridge,
+    builtin::{BuiltinBus, BuiltinBuses, BuiltinEvent},
+    bus::{
+        BusStats, EventAdapter, EventBus, EventPublisher, EventReceiver, EventRecvError,
+        GroupReceiver, MappedReceiver, NamedReceiver, SendError, SubscriberStats,
+    },
+    connection::ConnectionChange,
+    dead_letters::{DeadLetter, DeadLetterActor, DeadLetterConfig, DeadLetterReason, DeadLetters},
+    deadline::Deadline,
+    election::LeadershipChange,
+    group::{GroupEvent, GroupMembers, GroupStrategy, SupervisionGroup},
+    ids::{IdGenerator, IdKind, SequentialIds},
+    instrumentation::{Activity, Instrumentation, MailboxMonitor, StarvationWarning},
+    logger::LoggerActor,
+    namespace::Namespace,
+    quota::{exceeded_quota, MailboxQuotas, QuotaEvent, QuotaScope, Quotas, ScopeQuotas},
+    request::{Request, Requests},
+    subscription::{self, EventPolicy, EventSubscription},
+    ActorError, ActorPath, ChildFailed, CrashReport, FlappingActor, Handler, Message, PanicPolicy,
+    PathPattern, RestartStats, SystemConfig,
 };
 
 /// Events that this actor system will send
 pub trait SystemEvent: Clone + Send + Sync + 'static {}
 
+/// The event type of an actor system whose actors publish no events, see
+/// [`ActorSystem::without_events()`]. It is the default event type of
+/// [`ActorSystem`], [`ActorContext`](crate::ActorContext) and
+/// [`Actor`], so actors that only exchange messages need not name one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoEvent {}
+
+impl SystemEvent for NoEvent {}
+
+/// Guardian root of all actors created by user code.
+const USER_ROOT: &str = "/user";
+/// Guardian root of the actors run internally by the actor system. User code
+/// cannot create actors here.
+const SYSTEM_ROOT: &str = "/system";
+
+/// Name of the dead letter actor under the system root.
+const DEAD_LETTERS: &str = "deadletters";
+/// Name of the logger actor under the system root.
+const LOGGER: &str = "logger";
+
+/// Prefix of generated actor names. User provided names cannot start with it.
+const ANONYMOUS_PREFIX: char = '$';
+
+thread_local! {
+    /// On the dedicated thread of an actor, the runtime the other actors run
+    /// on.
+    static SHARED_RUNTIME: RefCell<Option<Handle>> = const { RefCell::new(None) };
+}
+
+/// The runtime actors without a dedicated thread run on.
+fn shared_runtime() -> Handle {
+    SHARED_RUNTIME
+        .with(|shared| shared.borrow().clone())
+        .unwrap_or_else(Handle::current)
+}
+
+/// Starts a registered actor, see [`ActorSystem::register_actor()`].
+type StartFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// An actor that is registered but not started yet.
+struct PendingActor {
+    start: StartFuture,
+    /// The dedicated thread of the actor waiting for it to start, if it has
+    /// one, see [`ActorConfig::with_dedicated_thread()`]. The thread ends
+    /// without running the actor if it is dropped instead.
+    thread: Option<std::sync::mpsc::SyncSender<StartFuture>>,
+}
+
+impl PendingActor {
+    /// Sets up everything the actor needs to run, including its dedicated
+    /// thread, so that launching it cannot fail.
+    fn new(path: &ActorPath, start: StartFuture, config: &ActorConfig) -> Result<Self, ActorError> {
+        if !config.dedicated_thread() {
+            return Ok(PendingActor {
+                start,
+                thread: None,
+            });
+        }
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|error| thread_error(path, error))?;
+        let shared = shared_runtime();
+        let (sender, launched) = std::sync::mpsc::sync_channel::<StartFuture>(1);
+        std::thread::Builder::new()
+            .name(format!("actor {}", path))
+            .spawn(move || {
+                if let Ok(start) = launched.recv() {
+                    SHARED_RUNTIME.with(|runtime| *runtime.borrow_mut() = Some(shared));
+                    runtime.block_on(start);
+                }
+            })
+            .map_err(|error| thread_error(path, error))?;
+        Ok(PendingActor {
+            start,
+            thread: Some(sender),
+        })
+    }
+
+    fn launch(self) {
+        match self.thread {
+            Some(thread) => thread
+                .send(self.start)
+                .expect("the thread of a pending actor waits for it"),
+            None => {
+                shared_runtime().spawn(self.start);
+            }
+        }
+    }
+}
+
+fn thread_error(path: &ActorPath, error: std::io::Error) -> ActorError {
+    ActorError::CreateError(format!("Failed to start thread for '{}': {}", path, error))
+}
+
+/// Checks that a name can be used as a single segment of an actor path.
+pub(crate) fn validate_name(name: &str) -> Result<(), ActorError> {
+    if name.trim().is_empty() || name.contains('/') || name.starts_with(ANONYMOUS_PREFIX) {
+        Err(ActorError::CreateError(format!(
+            "'{}' is not a valid actor name",
+            name
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks that an actor can be created at the path by users of the system,
+/// i.e. that it is under '/user' and its name is valid.
+fn validate_user_path(path: &ActorPath) -> Result<(), ActorError> {
+    if !path.is_descendant_of(&ActorPath::from(USER_ROOT)) {
+        return Err(ActorError::CreateError(format!(
+            "Actor path '{}' is not under '{}'",
+            path, USER_ROOT
+        )));
+    }
+    validate_name(&path.key())
+}
+
+/// A registered actor. The typed `ActorRef` is type erased so actors of
+/// different types can live in the same registry, while the control handle
+/// allows system wide operations without knowing the actor type.
+struct ActorEntry {
+    actor_ref: Box<dyn Any + Send + Sync + 'static>,
+    control: ControlRef,
+    config: ActorConfig,
+    /// The guard of an actor that stops when unreferenced, see
+    /// [`ActorConfig::with_stop_when_unreferenced()`].
+    guard: Option<Weak<dyn Any + Send + Sync>>,
+}
+
+/// The registered actors that have started, which actors depending on them
+/// wait for, see [`ActorConfig::with_dependency()`].
+#[derive(Default)]
+struct StartedActors {
+    paths: std::sync::Mutex<HashSet<ActorPath>>,
+    changed: Notify,
+}
+
+impl StartedActors {
+    fn insert(&self, path: &ActorPath) {
+        if self.paths.lock().unwrap().insert(path.clone()) {
+            self.changed.notify_waiters();
+        }
+    }
+
+    fn remove(&self, path: &ActorPath) {
+        self.paths.lock().unwrap().remove(path);
+    }
+
+    /// Resolves once all actors at the paths have started.
+    async fn wait_for(&self, paths: &[ActorPath]) {
+        loop {
+            let changed = self.changed.notified();
+            {
+                let started = self.paths.lock().unwrap();
+                if paths.iter().all(|path| started.contains(path)) {
+                    return;
+                }
+            }
+            changed.await;
+        }
+    }
+}
+
+/// Changes to the actors registered in an actor system, see
+/// [`ActorSystem::registry_events()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RegistryEvent {
+    /// An actor was created at the path.
+    ActorRegistered(ActorPath),
+    /// The actor at the path was stopped or failed, and can no longer be
+    /// looked up.
+    ActorUnregistered(ActorPath),
+}
+
+/// Everything published on an actor system: the events of the user together
+/// with the built-in events, as received through
+/// [`ActorSystem::system_messages()`].
+#[derive(Clone, Debug)]
+pub enum SystemMessage<E: SystemEvent> {
+    /// An event published on the event bus.
+    Event(E),
+    /// An actor was registered or unregistered.
+    Registry(RegistryEvent),
+    /// An actor panicked while handling a message.
+    Crash(CrashReport),
+    /// A message could not be delivered.
+    DeadLetter(DeadLetter),
+    /// A quota was exceeded.
+    Quota(QuotaEvent),
+    /// An actor restarted too often.
+    Flapping(FlappingActor),
+    /// An actor kept its runner busy for too long.
+    Starvation(StarvationWarning),
+    /// The periodic stats of the event bus.
+    BusStats(BusStats),
+    /// An actor became or stopped being a leader.
+    Leadership(LeadershipChange),
+    /// The connection of a managed connection actor changed state.
+    Connection(ConnectionChange),
+    /// A member of a supervision group failed.
+    SupervisionGroup(GroupEvent),
+    /// The event bus overflowed, and the given number of events were dropped
+    /// before they could be received.
+    EventsDropped(u64),
+}
+
+/// Receives the [`SystemMessage`]s of an actor system, see
+/// [`ActorSystem::system_messages()`].
+pub struct SystemMessageReceiver<E: SystemEvent> {
+    events: EventReceiver<E>,
+    builtin: EventReceiver<BuiltinEvent>,
+    events_closed: bool,
+    builtin_closed: bool,
+}
+
+impl<E: SystemEvent> SystemMessageReceiver<E> {
+    /// Receives the next message. Built-in events are received in the order
+    /// they were published, but there is no ordering between them and the
+    /// events of the event bus. Built-in events that could not be received in
+    /// time are skipped. Returns `Err(EventRecvError::Closed)` once all buses
+    /// of the system were closed, see [`ActorSystem::shutdown()`].
+    pub async fn recv(&mut self) -> Result<SystemMessage<E>, EventRecvError> {
+        loop {
+            tokio::select! {
+                event = self.events.recv(), if !self.events_closed => match event {
+                    Ok(event) => return Ok(SystemMessage::Event(event)),
+                    Err(EventRecvError::Lagged(dropped)) => {
+                        return Ok(SystemMessage::EventsDropped(dropped))
+                    }
+                    Err(EventRecvError::Closed) => self.events_closed = true,
+                },
+                event = self.builtin.recv(), if !self.builtin_closed => match event {
+                    Ok(event) => return Ok(event.into_message()),
+                    Err(EventRecvError::Lagged(_)) => {}
+                    Err(EventRecvError::Closed) => self.builtin_closed = true,
+                },
+                else => return Err(EventRecvError::Closed),
+            }
+        }
+    }
+}
+
+/// The outcome of an operation on all actors matching a path pattern.
+#[derive(Debug, Default)]
+pub struct BulkResult {
+    /// The actors the operation succeeded for.
+    pub succeeded: Vec<ActorPath>,
+    /// The actors the operation failed for, with the reason.
+    pub failed: Vec<(ActorPath, ActorError)>,
+}
+
+/// The outcome of [`ActorSystem::shutdown_within()`].
+#[derive(Debug, Default)]
+pub struct ShutdownReport {
+    /// The actors that did not stop in time, e.g. because a handler or their
+    /// `post_stop` was still running. They were asked to stop, but may still
+    /// be running.
+    pub timed_out: Vec<ActorPath>,
+}
+
+impl ShutdownReport {
+    /// True if all actors stopped in time
+    pub fn is_clean(&self) -> bool {
+        self.timed_out.is_empty()
+    }
+}
+
+/// Creates the virtual actor for a path, see [`ActorSystem::register_virtual()`].
+type VirtualFactory<A> = Arc<dyn Fn(&ActorPath) -> A + Send + Sync>;
+
 #[derive(Clone)]
-pub struct ActorSystem<E: SystemEvent> {
+pub struct ActorSystem<E: SystemEvent = NoEvent> {
     name: String,
-    actors: Arc<RwLock<HashMap<ActorPath, Box<dyn Any + Send + Sync + 'static>>>>,
+    actors: Arc<RwLock<HashMap<ActorPath, ActorEntry>>>,
     bus: EventBus<E>,
+    builtins: BuiltinBuses,
+    registry: BuiltinBus<RegistryEvent>,
+    crashes: BuiltinBus<CrashReport>,
+    config: Arc<SystemConfig>,
+    ids: Arc<dyn IdGenerator>,
+    dead_letters: DeadLetters,
+    virtuals: Arc<std::sync::RwLock<HashMap<ActorPath, Box<dyn Any + Send + Sync>>>>,
+    extensions: Arc<std::sync::RwLock<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>>,
+    quotas: Arc<std::sync::RwLock<HashMap<QuotaScope, Arc<ScopeQuotas>>>>,
+    quota_events: BuiltinBus<QuotaEvent>,
+    restarts: Arc<std::sync::Mutex<HashMap<ActorPath, RestartStats>>>,
+    flapping: BuiltinBus<FlappingActor>,
+    starvation: BuiltinBus<StarvationWarning>,
+    bus_stats: BuiltinBus<BusStats>,
+    leadership: BuiltinBus<LeadershipChange>,
+    connections: BuiltinBus<ConnectionChange>,
+    groups: Arc<std::sync::RwLock<HashMap<ActorPath, Arc<GroupMembers>>>>,
+    group_events: BuiltinBus<GroupEvent>,
+    activity: Arc<Activity>,
+    started: Arc<StartedActors>,
+    #[cfg(feature = "prometheus")]
+    metrics: Arc<crate::metrics::Metrics>,
+}
+
+impl ActorSystem<NoEvent> {
+    /// Creates a new actor system (of type `ActorSystem<NoEvent>`) for actors
+    /// that only exchange messages and publish no events, with an event bus
+    /// of minimal capacity. For example:
+    /// ```
+    /// use tiny_tokio_actor::*;
+    ///
+    /// #[derive(Clone, Debug)]
+    /// struct Increment;
+    ///
+    /// impl Message for Increment {
+    ///     type Response = usize;
+    /// }
+    ///
+    /// struct Counter(usize);
+    ///
+    /// impl Actor for Counter {}
+    ///
+    /// #[async_trait]
+    /// impl Handler<NoEvent, Increment> for Counter {
+    ///     async fn handle(&mut self, _msg: Increment, _ctx: &mut ActorContext) -> usize {
+    ///         self.0 += 1;
+    ///         self.0
+    ///     }
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), ActorError> {
+    /// let system = ActorSystem::without_events("counters");
+    /// let counter = system.create_actor("counter", Counter(0)).await?;
+    /// assert_eq!(counter.ask(Increment).await?, 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn without_events(name: &str) -> Self {
+        ActorSystem::from_config(SystemConfig::new(name).with_event_bus_capacity(1))
+    }
 }
 
 impl<E: SystemEvent> ActorSystem<E> {
@@ -26,13 +392,50 @@ impl<E: SystemEvent> ActorSystem<E> {
     /// Publish an event on the actor system's event bus. These events can be
     /// received by other actors in the same actor system.
     pub fn publish(&self, event: E) {
-        self.bus.send(event).unwrap_or_else(|error| {
-            log::warn!(
-                "No listeners active on event bus. Dropping event: {:?}",
-                &error.to_string(),
+        if self.try_publish(event).is_err() {
+            log::debug!(
+                "Event bus of actor system '{}' is closed, dropping event",
+                self.name
             );
-            0
-        });
+        }
+    }
+
+    /// Publish an event on the actor system's event bus, returning the number
+    /// of subscribers that received it. Having no subscribers is not an
+    /// error: the event is dropped, or held back for the first subscriber
+    /// when the system is configured with
+    /// [`SystemConfig::with_event_buffering()`], and `Ok(0)` is returned.
+    /// Fails only when the bus was closed, see [`ActorSystem::shutdown()`].
+    pub fn try_publish(&self, event: E) -> Result<usize, SendError<E>> {
+        match self.bus.send(event) {
+            Err(_) if !self.bus.is_closed() => Ok(0),
+            sent => sent,
+        }
+    }
+
+    /// Publish an event on the actor system's event bus, waiting until all
+    /// subscribers have room for it so that none of them misses the event.
+    /// Resolves to the number of subscribers that received it. See
+    /// [`EventBus::send_lossless()`].
+    pub async fn publish_async(&self, event: E) -> usize {
+        self.bus.send_lossless(event).await.unwrap_or_default()
+    }
+
+    /// A publisher of events of type `T` on the event bus of this actor system,
+    /// converting them into its event type. See [`EventPublisher`].
+    pub fn publisher<T: Into<E> + 'static>(&self) -> EventPublisher<T> {
+        self.publisher_with(Into::into)
+    }
+
+    /// A publisher of events of type `T` on the event bus of this actor system,
+    /// converting them into its event type with `map`.
+    pub fn publisher_with<T, M>(&self, map: M) -> EventPublisher<T>
+    where
+        T: 'static,
+        M: Fn(T) -> E + Send + Sync + 'static,
+    {
+        let bus = self.bus.clone();
+        EventPublisher::new(move |event| bus.send(map(event)).unwrap_or_default())
     }
 
     /// Subscribe to events of this actor system.
@@ -40,379 +443,6036 @@ impl<E: SystemEvent> ActorSystem<E> {
         self.bus.subscribe()
     }
 
-    /// Retrieves an actor running in this actor system. If actor does not exist, a None
-    /// is returned instead.
-    pub async fn get_actor<A: Actor<E>>(&self, path: &ActorPath) -> Option<ActorRef<E, A>> {
-        let actors = self.actors.read().await;
-        actors
-            .get(path)
-            .and_then(|any| any.downcast_ref::<ActorRef<E, A>>().cloned())
+    /// Subscribe to events of this actor system converted by `map`, skipping
+    /// the events it maps to `None`. See [`EventAdapter`].
+    pub fn events_mapped<F, M>(&self, map: M) -> MappedReceiver<F>
+    where
+        F: Send + 'static,
+        M: Fn(E) -> Option<F> + Send + Sync + 'static,
+    {
+        self.bus.subscribe_mapped(map)
     }
 
-    pub(crate) async fn create_actor_path<A: Actor<E>>(
+    /// Views the event bus of this actor system as a bus of events converted
+    /// by `map`, e.g. to hand to a library written against its own event type.
+    /// See [`EventAdapter`].
+    pub fn event_adapter<F, M>(&self, map: M) -> EventAdapter<F>
+    where
+        F: Send + 'static,
+        M: Fn(E) -> Option<F> + Send + Sync + 'static,
+    {
+        self.bus.adapter(map)
+    }
+
+    /// Join a consumer group on the event bus of this actor system, where each
+    /// event is received by only one member of the group. See
+    /// [`EventBus::subscribe_group()`].
+    pub fn group_events(&self, group: &str) -> GroupReceiver<E> {
+        self.bus.subscribe_group(group)
+    }
+
+    /// Delivers the events of this actor system to the actor as messages,
+    /// converted by `map` and thinned out according to the policy, e.g. to
+    /// keep a slow actor from lagging behind a hot stream of events. Events
+    /// mapped to `None` are skipped. Only events published after this call
+    /// are delivered, until the actor stops or the subscription is cancelled.
+    pub fn subscribe_actor<A, M, F>(
         &self,
-        path: ActorPath,
-        actor: A,
-    ) -> Result<ActorRef<E, A>, ActorError> {
-        log::debug!("Creating actor '{}' on system '{}'...", &path, &self.name);
+        actor: &ActorRef<E, A>,
+        policy: EventPolicy<M>,
+        map: F,
+    ) -> EventSubscription
+    where
+        A: Actor<E> + Handler<E, M>,
+        M: Message,
+        F: Fn(E) -> Option<M> + Send + 'static,
+    {
+        subscription::subscribe(self.events(), actor.clone(), policy, map)
+    }
 
-        let mut actors = self.actors.write().await;
-        if actors.contains_key(&path) {
-            return Err(ActorError::Exists(path));
+    /// Subscribe to actors being added to and removed from this actor system,
+    /// e.g. to maintain a routing table without polling
+    /// [`ActorSystem::get_actor()`]. Only changes made after subscribing are
+    /// received.
+    pub fn registry_events(&self) -> EventReceiver<RegistryEvent> {
+        self.registry.subscribe()
+    }
+
+    /// Subscribe to reports of actors on this actor system that panicked while
+    /// handling a message.
+    pub fn crash_reports(&self) -> EventReceiver<CrashReport> {
+        self.crashes.subscribe()
+    }
+
+    /// Subscribe to the events of this actor system together with its built-in
+    /// events: registry changes, crash reports, dead letters, exceeded quotas,
+    /// flapping and starving actors, overflows and stats of the event bus,
+    /// changes of leadership and of connections, and failures within
+    /// supervision groups. See [`SystemMessage`].
+    pub fn system_messages(&self) -> SystemMessageReceiver<E> {
+        SystemMessageReceiver {
+            events: self.bus.subscribe(),
+            builtin: self.builtins.subscribe(),
+            events_closed: false,
+            builtin_closed: false,
+        }
+    }
+
+    /// Subscribe to actors that restart too often, see
+    /// [`SystemConfig::with_flap_detection()`].
+    pub fn flapping_actors(&self) -> EventReceiver<FlappingActor> {
+        self.flapping.subscribe()
+    }
+
+    /// How often the actor at the path restarted, if ever.
+    pub fn restart_stats(&self, path: &ActorPath) -> Option<RestartStats> {
+        self.restarts.lock().unwrap().get(path).cloned()
+    }
+
+    /// Renders the metrics of the actors of this system in the Prometheus text
+    /// exposition format, ready to be served from any HTTP server: the number
+    /// of actors, and per actor its restarts, the messages waiting in its
+    /// mailbox, its failures to start, its dead letters and a histogram of how
+    /// long it took to handle messages. The metrics are collected through the
+    /// mailbox instrumentation, which still reports to the instrumentation
+    /// registered through [`SystemConfig::with_instrumentation()`]. Requires
+    /// the `prometheus` feature.
+    #[cfg(feature = "prometheus")]
+    pub async fn metrics_text(&self) -> String {
+        let actors: HashSet<ActorPath> = self.actors.read().await.keys().cloned().collect();
+        let restarts: Vec<(ActorPath, u64)> = self
+            .restarts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(path, stats)| (path.clone(), stats.restarts()))
+            .collect();
+        self.metrics.render(&self.name, &actors, &restarts)
+    }
+
+    /// The instrumentation to report the mailbox activity of actors to.
+    #[cfg(feature = "prometheus")]
+    fn instrumentation(&self) -> Option<Instrumentation> {
+        Some(self.metrics.instrumentation())
+    }
+
+    /// The instrumentation to report the mailbox activity of actors to.
+    #[cfg(not(feature = "prometheus"))]
+    fn instrumentation(&self) -> Option<Instrumentation> {
+        self.config.instrumentation().cloned()
+    }
+
+    pub(crate) fn record_restart(&self, path: &ActorPath) {
+        let flap_detection = self.config.flap_detection();
+        let recent = self
+            .restarts
+            .lock()
+            .unwrap()
+            .entry(path.clone())
+            .or_default()
+            .record(flap_detection.map(|(_, within)| within));
+        if let Some((max_restarts, within)) = flap_detection {
+            if recent == max_restarts + 1 {
+                log::warn!(
+                    actor:% = path;
+                    "Actor '{}' restarted {} times within {:?}",
+                    path,
+                    recent,
+                    within
+                );
+                let flapping = FlappingActor {
+                    path: path.clone(),
+                    restarts: recent,
+                    within,
+                    timestamp: std::time::SystemTime::now(),
+                };
+                self.flapping.send(flapping);
+            }
+        }
+    }
+
+    /// Subscribe to actors that keep their runner busy for too long, see
+    /// [`SystemConfig::with_starvation_threshold()`].
+    pub fn starvation_warnings(&self) -> EventReceiver<StarvationWarning> {
+        self.starvation.subscribe()
+    }
+
+    /// Subscribe to the events of this actor system under a name, so that
+    /// diagnostics identify the subscriber, see [`EventBus::subscribe_named()`].
+    pub fn events_named(&self, name: &str) -> NamedReceiver<E> {
+        self.bus.subscribe_named(name)
+    }
+
+    /// The stats of the named subscribers to the events of this actor system,
+    /// see [`ActorSystem::events_named()`].
+    pub fn subscriber_stats(&self) -> Vec<SubscriberStats> {
+        self.bus.subscriber_stats()
+    }
+
+    /// The stats of the event bus of this actor system, see [`BusStats`].
+    pub fn event_bus_stats(&self) -> BusStats {
+        self.bus.stats()
+    }
+
+    /// Subscribe to the stats of the event bus, published at the interval set
+    /// through [`SystemConfig::with_bus_stats_interval()`].
+    pub fn bus_stats_events(&self) -> EventReceiver<BusStats> {
+        self.bus_stats.subscribe()
+    }
+
+    /// Subscribe to the changes of leadership of the actors campaigning for a
+    /// lease, see [`ActorContext::campaign()`](crate::ActorContext::campaign).
+    pub fn leadership_changes(&self) -> EventReceiver<LeadershipChange> {
+        self.leadership.subscribe()
+    }
+
+    pub(crate) fn report_leadership(&self, change: LeadershipChange) {
+        self.leadership.send(change);
+    }
+
+    /// Subscribe to the changes of state of the connections of
+    /// [`ManagedConnection`](crate::ManagedConnection) actors.
+    pub fn connection_changes(&self) -> EventReceiver<ConnectionChange> {
+        self.connections.subscribe()
+    }
+
+    pub(crate) fn report_connection(&self, change: ConnectionChange) {
+        self.connections.send(change);
+    }
+
+    /// Creates a group of actors that are restarted or stopped together once
+    /// one of them fails, see [`SupervisionGroup`].
+    pub fn supervision_group(&self, name: &str, strategy: GroupStrategy) -> SupervisionGroup<E> {
+        SupervisionGroup::new(self.clone(), name, strategy)
+    }
+
+    /// Subscribe to the failures of members of supervision groups, see
+    /// [`SupervisionGroup`].
+    pub fn supervision_group_events(&self) -> EventReceiver<GroupEvent> {
+        self.group_events.subscribe()
+    }
+
+    /// Makes the actor at the path a member of the group, returning the
+    /// group it was a member of before, if any.
+    pub(crate) fn join_group(
+        &self,
+        path: &ActorPath,
+        members: Arc<GroupMembers>,
+    ) -> Option<Arc<GroupMembers>> {
+        let mut groups = self.groups.write().unwrap();
+        groups
+            .insert(path.clone(), members)
+            .filter(|previous| !Arc::ptr_eq(previous, &groups[path]))
+    }
+
+    pub(crate) fn leave_group(&self, path: &ActorPath, members: &Arc<GroupMembers>) {
+        let mut groups = self.groups.write().unwrap();
+        if groups
+            .get(path)
+            .is_some_and(|current| Arc::ptr_eq(current, members))
+        {
+            groups.remove(path);
         }
+    }
 
+    /// Applies the strategy of the group to its other members once the
+    /// member at the path failed.
+    fn fail_group(&self, path: &ActorPath, members: Arc<GroupMembers>) {
+        let others: Vec<ActorPath> = members
+            .paths
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|member| *member != path && !member.is_descendant_of(path))
+            .cloned()
+            .collect();
+        log::warn!(
+            actor:% = path;
+            "Actor '{}' of supervision group '{}' failed, applying {:?} to {} other members",
+            path,
+            &members.name,
+            members.strategy,
+            others.len()
+        );
         let system = self.clone();
-        let (mut runner, actor_ref) = ActorRunner::create(path, actor);
+        let failed = path.clone();
+        // The failed member escalates from its own runner, so do not await
+        // the other members here
         tokio::spawn(async move {
-            runner.start(system).await;
+            for member in &others {
+                match members.strategy {
+                    GroupStrategy::RestartAll => {
+                        if let Err(error) = system.restart_subtree(member).await {
+                            log::error!(
+                                "Member '{}' of supervision group '{}' failed to restart: {}",
+                                member,
+                                &members.name,
+                                error
+                            );
+                        }
+                    }
+                    GroupStrategy::StopAll => system.stop_subtree(member).await,
+                }
+            }
+            let event = GroupEvent {
+                group: members.name.clone(),
+                failed,
+                strategy: members.strategy,
+                members: others,
+            };
+            system.group_events.send(event);
         });
+    }
 
-        let path = actor_ref.path().clone();
-        let any = Box::new(actor_ref.clone());
+    /// Publishes the message as a dead letter, as its deadline passed before
+    /// the actor at the path could handle it.
+    pub(crate) fn report_expired<M: Message>(&self, path: &ActorPath, msg: M) {
+        self.dead_letters
+            .publish_message(path, msg, DeadLetterReason::Expired);
+    }
 
-        actors.insert(path, any);
+    /// Publishes the message as a dead letter, as the caller that asked it
+    /// went away before the actor at the path responded.
+    pub(crate) fn report_orphaned_response<M: Message>(&self, path: &ActorPath, msg: M) {
+        self.dead_letters
+            .publish_message(path, msg, DeadLetterReason::CallerGone);
+    }
 
-        Ok(actor_ref)
+    pub(crate) fn report_starvation(&self, warning: StarvationWarning) {
+        log::warn!(
+            actor:% = &warning.path,
+            message_type = warning.message_type;
+            "Actor '{}' has been busy for {:?} handling {} messages, now {}",
+            &warning.path,
+            warning.elapsed,
+            warning.messages,
+            warning.message_type
+        );
+        self.starvation.send(warning);
     }
 
-    /// Launches a new top level actor on this actor system at the '/user' actor path. If another actor with
-    /// the same name already exists, an `Err(ActorError::Exists(ActorPath))` is returned instead.
-    pub async fn create_actor<A: Actor<E>>(
-        &self,
-        name: &str,
-        actor: A,
-    ) -> Result<ActorRef<E, A>, ActorError> {
-        let path = ActorPath::from("/user") / name;
-        self.create_actor_path(path, actor).await
+    /// Reports the panic of an actor, and applies the panic policy of the
+    /// system to it.
+    pub(crate) fn report_crash(&self, report: CrashReport) {
+        let path = report.path().clone();
+        self.crashes.send(report);
+        match self.config.panic_policy() {
+            PanicPolicy::Supervise => {}
+            PanicPolicy::Shutdown => {
+                log::error!(
+                    actor:% = path;
+                    "Shutting down actor system '{}' as actor '{}' panicked",
+                    &self.name,
+                    &path
+                );
+                // The runner of the actor must keep going for it to stop
+                let system = self.clone();
+                tokio::spawn(async move { system.shutdown().await });
+            }
+            PanicPolicy::Abort => {
+                log::error!(
+                    actor:% = path;
+                    "Aborting the process as actor '{}' panicked",
+                    &path
+                );
+                std::process::abort();
+            }
+        }
     }
 
-    /// Retrieve or create a new actor on this actor system if it does not exist yet.
-    pub async fn get_or_create_actor<A, F>(
+    fn registry_changed(&self, event: RegistryEvent) {
+        // Without subscribers there is nobody to tell
+        self.registry.send(event);
+    }
+
+    /// Removes the actor at the path from the registry.
+    fn unregister(
         &self,
-        name: &str,
-        actor_fn: F,
-    ) -> Result<ActorRef<E, A>, ActorError>
+        actors: &mut HashMap<ActorPath, ActorEntry>,
+        path: &ActorPath,
+    ) -> Option<ActorEntry> {
+        let entry = actors.remove(path)?;
+        self.started.remove(path);
+        self.registry_changed(RegistryEvent::ActorUnregistered(path.clone()));
+        Some(entry)
+    }
+
+    /// Retrieves an actor running in this actor system. If actor does not exist, a None
+    /// is returned instead.
+    pub async fn get_actor<A: Actor<E>>(&self, path: &ActorPath) -> Option<ActorRef<E, A>> {
+        let actors = self.actors.read().await;
+        let entry = actors.get(path)?;
+        let actor_ref = entry.actor_ref.downcast_ref::<ActorRef<E, A>>()?.clone();
+        match &entry.guard {
+            // An actor that is no longer referenced is being stopped
+            Some(guard) => {
+                let guard = guard.upgrade()?.downcast::<RefGuard<E>>().ok()?;
+                Some(actor_ref.with_guard(guard))
+            }
+            None => Some(actor_ref),
+        }
+    }
+
+    /// Retrieves the paths of all actors matching the predicate.
+    pub(crate) async fn find_paths<P>(&self, predicate: P) -> Vec<ActorPath>
     where
-        A: Actor<E>,
-        F: FnOnce() -> A,
+        P: Fn(&ActorPath) -> bool,
     {
-        let path = ActorPath::from("/user") / name;
-        self.get_or_create_actor_path(&path, actor_fn).await
+        let actors = self.actors.read().await;
+        actors
+            .keys()
+            .filter(|path| predicate(path))
+            .cloned()
+            .collect()
     }
 
-    pub(crate) async fn get_or_create_actor_path<A, F>(
-        &self,
-        path: &ActorPath,
-        actor_fn: F,
-    ) -> Result<ActorRef<E, A>, ActorError>
+    /// Retrieves all actors of type `A` whose path matches the predicate.
+    pub(crate) async fn find_actors<A, P>(&self, predicate: P) -> Vec<ActorRef<E, A>>
     where
         A: Actor<E>,
-        F: FnOnce() -> A,
+        P: Fn(&ActorPath) -> bool,
     {
         let actors = self.actors.read().await;
-        match self.get_actor(path).await {
-            Some(actor) => Ok(actor),
-            None => {
-                drop(actors);
-                self.create_actor_path(path.clone(), actor_fn()).await
-            }
-        }
+        actors
+            .iter()
+            .filter(|(path, _)| predicate(path))
+            .filter_map(|(_, entry)| entry.actor_ref.downcast_ref::<ActorRef<E, A>>().cloned())
+            .collect()
     }
 
-    /// Stops the actor on this actor system. All its children will also be stopped.
-    pub async fn stop_actor(&self, path: &ActorPath) {
-        log::debug!("Stopping actor '{}' on system '{}'...", &path, &self.name);
-        let mut paths: Vec<ActorPath> = vec![path.clone()];
-        {
-            let running_actors = self.actors.read().await;
-            for running in running_actors.keys() {
-                if running.is_descendant_of(path) {
-                    paths.push(running.clone());
-                }
-            }
-        }
+    /// Retrieves all actors of type `A` that were created with the given label.
+    pub async fn actors_with_label<A: Actor<E>>(
+        &self,
+        key: &str,
+        value: &str,
+    ) -> Vec<ActorRef<E, A>> {
+        let actors = self.actors.read().await;
+        actors
+            .values()
+            .filter(|entry| entry.config.label(key) == Some(value))
+            .filter_map(|entry| entry.actor_ref.downcast_ref::<ActorRef<E, A>>().cloned())
+            .collect()
+    }
+
+    /// Retrieves the paths of all actors that were created with the given label.
+    pub async fn paths_with_label(&self, key: &str, value: &str) -> Vec<ActorPath> {
+        let actors = self.actors.read().await;
+        let mut paths: Vec<ActorPath> = actors
+            .iter()
+            .filter(|(_, entry)| entry.config.label(key) == Some(value))
+            .map(|(path, _)| path.clone())
+            .collect();
         paths.sort_unstable();
-        paths.reverse();
-        let mut actors = self.actors.write().await;
-        for path in &paths {
-            actors.remove(path);
-        }
+        paths
     }
 
-    /// Creats a new actor system on which you can create actors.
-    pub fn new(name: &str, bus: EventBus<E>) -> Self {
-        let name = name.to_string();
-        let actors = Arc::new(RwLock::new(HashMap::new()));
-        ActorSystem { name, actors, bus }
+    pub(crate) async fn create_actor_path<A: Actor<E>>(
+        &self,
+        path: ActorPath,
+        actor: A,
+    ) -> Result<ActorRef<E, A>, ActorError> {
+        self.create_actor_path_with_config(path, actor, ActorConfig::default())
+            .await
     }
-}
 
-#[cfg(test)]
-mod tests {
+    pub(crate) async fn create_actor_path_with_config<A: Actor<E>>(
+        &self,
+        path: ActorPath,
+        actor: A,
+        config: ActorConfig,
+    ) -> Result<ActorRef<E, A>, ActorError> {
+        self.spawn_actor(path, actor, config, None, None).await
+    }
+
+    pub(crate) async fn create_actor_path_from_props<A: Actor<E>>(
+        &self,
+        path: ActorPath,
+        props: Props<A>,
+    ) -> Result<ActorRef<E, A>, ActorError> {
+        let actor = props.create();
+        let (factory, config) = props.into_parts();
+        self.spawn_actor(path, actor, config, None, Some(factory))
+            .await
+    }
+
+    /// Registers the actor and starts it. If `ready` is given, it receives the
+    /// outcome of starting the actor instead of the parent being informed of
+    /// a failure. If `factory` is given, the actor is restarted with fresh
+    /// instances from it.
+    async fn spawn_actor<A: Actor<E>>(
+        &self,
+        path: ActorPath,
+        actor: A,
+        config: ActorConfig,
+        ready: Option<oneshot::Sender<Result<(), ActorError>>>,
+        factory: Option<Factory<A>>,
+    ) -> Result<ActorRef<E, A>, ActorError> {
+        log::debug!("Creating actor '{}' on system '{}'...", &path, &self.name);
+        let mut actors = self.actors.write().await;
+        let (actor_ref, pending) =
+            self.register_actor(&mut actors, path, actor, config, ready, factory)?;
+        self.start_actor(actor_ref.path(), pending);
+        Ok(actor_ref)
+    }
+
+    /// Adds the actor to the registry without starting it yet, so a failure to
+    /// create it can still be rolled back by removing it again.
+    fn register_actor<A: Actor<E>>(
+        &self,
+        actors: &mut HashMap<ActorPath, ActorEntry>,
+        path: ActorPath,
+        actor: A,
+        config: ActorConfig,
+        ready: Option<oneshot::Sender<Result<(), ActorError>>>,
+        factory: Option<Factory<A>>,
+    ) -> Result<(ActorRef<E, A>, PendingActor), ActorError> {
+        let user_root = ActorPath::from(USER_ROOT);
+        let system_root = ActorPath::from(SYSTEM_ROOT);
+        if !path.is_descendant_of(&user_root) && !path.is_descendant_of(&system_root) {
+            return Err(ActorError::CreateError(format!(
+                "Actor path '{}' is not under '{}' or '{}'",
+                &path, USER_ROOT, SYSTEM_ROOT
+            )));
+        }
+
+        if actors.contains_key(&path) {
+            return Err(ActorError::Exists(path));
+        }
+
+        let config = self.config.actor_config(&path, config);
+        Self::check_dependencies(actors, &path, &config)?;
+        let quotas = self.check_quotas(actors, &path, &config)?;
+
+        let system = self.clone();
+        let monitor = MailboxMonitor::new(
+            path.clone(),
+            self.dead_letters.clone(),
+            self.instrumentation(),
+        )
+        .with_log_level(config.log_level().unwrap_or(LevelFilter::Trace))
+        .with_mailbox_bytes(config.mailbox_bytes())
+        .with_quotas(quotas)
+        .with_policy(self.config.message_policy().cloned())
+        .with_activity(self.activity.clone());
+        let incarnation = self.next_id(IdKind::Incarnation);
+        let (mut runner, actor_ref) =
+            ActorRunner::create(path, incarnation, actor, &config, monitor);
+        if let Some(ready) = ready {
+            runner.notify_ready(ready);
+        }
+        if let Some(factory) = factory {
+            runner.restart_from(factory);
+        }
+        #[cfg(feature = "chaos")]
+        if let Some(chaos) = self.config.chaos() {
+            runner.inject_chaos(crate::chaos::Chaos::new(chaos, actor_ref.path()));
+        }
+        let sender = actor_ref.path().clone();
+        let start = Box::pin(crate::policy::sending_as(sender, async move {
+            runner.start(system).await;
+        }));
+
+        let path = actor_ref.path().clone();
+        let pending = PendingActor::new(&path, start, &config)?;
+        let guard = config.stop_when_unreferenced().then(|| {
+            Arc::new(RefGuard::new(
+                self.clone(),
+                path.clone(),
+                actor_ref.control().clone(),
+            ))
+        });
+        let entry = ActorEntry {
+            actor_ref: Box::new(actor_ref.clone()),
+            control: actor_ref.control().clone(),
+            config,
+            guard: guard.as_ref().map(|guard| {
+                let guard: Arc<dyn Any + Send + Sync> = guard.clone();
+                Arc::downgrade(&guard)
+            }),
+        };
+        actors.insert(path, entry);
+
+        match guard {
+            Some(guard) => Ok((actor_ref.with_guard(guard), pending)),
+            None => Ok((actor_ref, pending)),
+        }
+    }
+
+    /// Starts a registered actor.
+    fn start_actor(&self, path: &ActorPath, pending: PendingActor) {
+        pending.launch();
+        self.registry_changed(RegistryEvent::ActorRegistered(path.clone()));
+    }
+
+    /// Launches new actors at the paths under '/user' at once, taking the lock
+    /// on the registry only once. Either all actors are created, or none of
+    /// them are: if any actor cannot be created, e.g. because its path is
+    /// invalid or already taken, or its dedicated thread cannot be started,
+    /// the error is returned and no actor is started or left registered, so
+    /// none of them count towards the quotas. The references to the actors
+    /// are returned in the order of the given actors.
+    pub async fn create_actors<A: Actor<E>>(
+        &self,
+        actors: Vec<(ActorPath, A)>,
+    ) -> Result<Vec<ActorRef<E, A>>, ActorError> {
+        log::debug!(
+            "Creating {} actors on system '{}'...",
+            actors.len(),
+            &self.name
+        );
+        let mut registry = self.actors.write().await;
+        let mut created = Vec::with_capacity(actors.len());
+        for (path, actor) in actors {
+            let registered = validate_user_path(&path).and_then(|_| {
+                self.register_actor(
+                    &mut registry,
+                    path,
+                    actor,
+                    ActorConfig::default(),
+                    None,
+                    None,
+                )
+            });
+            match registered {
+                Ok(registered) => created.push(registered),
+                Err(error) => {
+                    for (actor_ref, _) in created {
+                        registry.remove(actor_ref.path());
+                    }
+                    return Err(error);
+                }
+            }
+        }
+
+        let mut actor_refs = Vec::with_capacity(created.len());
+        for (actor_ref, pending) in created {
+            self.start_actor(actor_ref.path(), pending);
+            actor_refs.push(actor_ref);
+        }
+        Ok(actor_refs)
+    }
+
+    /// Checks that the dependencies of an actor to be created at the path do
+    /// not depend on it in turn, directly or through other actors.
+    fn check_dependencies(
+        actors: &HashMap<ActorPath, ActorEntry>,
+        path: &ActorPath,
+        config: &ActorConfig,
+    ) -> Result<(), ActorError> {
+        let mut visited = HashSet::new();
+        let mut pending: Vec<&ActorPath> = config.dependencies().iter().collect();
+        while let Some(dependency) = pending.pop() {
+            if dependency == path {
+                return Err(ActorError::CreateError(format!(
+                    "Actor '{}' depends on itself through its dependencies",
+                    path
+                )));
+            }
+            if visited.insert(dependency) {
+                if let Some(entry) = actors.get(dependency) {
+                    pending.extend(entry.config.dependencies());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks the actor at the path as started, releasing the actors that
+    /// depend on it.
+    pub(crate) fn actor_started(&self, path: &ActorPath) {
+        self.started.insert(path);
+    }
+
+    /// Resolves once all actors at the paths have started.
+    pub(crate) async fn wait_for_start(&self, paths: &[ActorPath]) {
+        self.started.wait_for(paths).await
+    }
+
+    /// Checks the quotas of the scopes the actor would be created in,
+    /// returning the quotas that apply to its mailbox.
+    fn check_quotas(
+        &self,
+        actors: &HashMap<ActorPath, ActorEntry>,
+        path: &ActorPath,
+        config: &ActorConfig,
+    ) -> Result<Option<MailboxQuotas>, ActorError> {
+        let quotas = self.quotas.read().unwrap();
+        let mut scopes = vec![];
+        for (scope, quotas) in quotas.iter() {
+            if !scope.contains(path, config) {
+                continue;
+            }
+            let existing = actors
+                .iter()
+                .filter(|(path, entry)| scope.contains(path, &entry.config))
+                .map(|(_, entry)| &entry.config);
+            if let Err(quota) = quotas.quotas.check(existing, config) {
+                return Err(exceeded_quota(&self.quota_events, scope, quota, path));
+            }
+            scopes.push(quotas.clone());
+        }
+        if scopes.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(MailboxQuotas::new(scopes, self.quota_events.clone())))
+    }
+
+    /// Limits the resources used by the actors in the scope, replacing any
+    /// previous quotas of the scope. The quotas apply to the actors created from
+    /// then on; actors that already exist count towards them, but keep the
+    /// quotas (if any) they were created with.
+    pub fn set_quotas(&self, scope: QuotaScope, quotas: Quotas) {
+        let scope_quotas = Arc::new(ScopeQuotas::new(scope.clone(), quotas));
+        self.quotas.write().unwrap().insert(scope, scope_quotas);
+    }
+
+    /// The quotas of the scope, if any
+    pub fn quotas(&self, scope: &QuotaScope) -> Option<Quotas> {
+        let quotas = self.quotas.read().unwrap();
+        quotas.get(scope).map(|quotas| quotas.quotas.clone())
+    }
+
+    /// Subscribe to quotas being exceeded on this actor system, see
+    /// [`ActorSystem::set_quotas()`].
+    pub fn quota_events(&self) -> EventReceiver<QuotaEvent> {
+        self.quota_events.subscribe()
+    }
+
+    /// Links this actor system to another one in the same process, e.g. to
+    /// forward events between them. See [`SystemBridge`].
+    pub fn bridge<F: SystemEvent>(&self, other: &ActorSystem<F>) -> SystemBridge<E, F> {
+        SystemBridge::new(self.clone(), other.clone())
+    }
+
+    /// Get the namespace with the given name, whose actors live under
+    /// '/user/<name>'. See [`Namespace`].
+    pub fn namespace(&self, name: &str) -> Result<Namespace<E>, ActorError> {
+        validate_name(name)?;
+        Ok(Namespace::new(
+            self.clone(),
+            ActorPath::from(USER_ROOT) / name,
+        ))
+    }
+
+    /// Launches a new top level actor on this actor system at the '/user' actor path. If another actor with
+    /// the same name already exists, an `Err(ActorError::Exists(ActorPath))` is returned instead.
+    pub async fn create_actor<A: Actor<E>>(
+        &self,
+        name: &str,
+        actor: A,
+    ) -> Result<ActorRef<E, A>, ActorError> {
+        validate_name(name)?;
+        let path = ActorPath::from(USER_ROOT) / name;
+        self.create_actor_path(path, actor).await
+    }
+
+    /// Launches a new top level actor on this actor system at the '/user' actor
+    /// path like [`ActorSystem::create_actor()`], but only resolves once the
+    /// actor has started, including any retries of its supervision strategy.
+    /// If the actor fails to start it is removed again and the error of its
+    /// `pre_start` is returned.
+    pub async fn create_actor_ready<A: Actor<E>>(
+        &self,
+        name: &str,
+        actor: A,
+    ) -> Result<ActorRef<E, A>, ActorError> {
+        validate_name(name)?;
+        let path = ActorPath::from(USER_ROOT) / name;
+        let (ready, started) = oneshot::channel();
+        let actor_ref = self
+            .spawn_actor(path, actor, ActorConfig::default(), Some(ready), None)
+            .await?;
+        started
+            .await
+            .map_err(|error| ActorError::SendError(error.to_string()))??;
+        Ok(actor_ref)
+    }
+
+    /// Launches a new top level actor on this actor system at the '/user' actor
+    /// path from its props, see [`Props`].
+    pub async fn create_actor_from_props<A: Actor<E>>(
+        &self,
+        name: &str,
+        props: Props<A>,
+    ) -> Result<ActorRef<E, A>, ActorError> {
+        validate_name(name)?;
+        let path = ActorPath::from(USER_ROOT) / name;
+        self.create_actor_path_from_props(path, props).await
+    }
+
+    /// Launches a new top level actor on this actor system at the '/user' actor path,
+    /// using the given configuration.
+    pub async fn create_actor_with_config<A: Actor<E>>(
+        &self,
+        name: &str,
+        actor: A,
+        config: ActorConfig,
+    ) -> Result<ActorRef<E, A>, ActorError> {
+        validate_name(name)?;
+        let path = ActorPath::from(USER_ROOT) / name;
+        self.create_actor_path_with_config(path, actor, config)
+            .await
+    }
+
+    /// Launches a new top level actor on this actor system under a generated
+    /// unique name, e.g. '/user/$1f'.
+    pub async fn create_anonymous_actor<A: Actor<E>>(
+        &self,
+        actor: A,
+    ) -> Result<ActorRef<E, A>, ActorError> {
+        let path = ActorPath::from(USER_ROOT) / &self.anonymous_name();
+        self.create_actor_path(path, actor).await
+    }
+
+    /// Generates an id of the kind through the id generator of this actor
+    /// system, see [`SystemConfig::with_id_generator()`].
+    pub(crate) fn next_id(&self, kind: IdKind) -> u64 {
+        self.ids.next_id(kind)
+    }
+
+    /// Generates an actor name that is unique within this actor system.
+    pub(crate) fn anonymous_name(&self) -> String {
+        let id = self.next_id(IdKind::Anonymous);
+        format!("{}{:x}", ANONYMOUS_PREFIX, id)
+    }
+
+    /// Launches a new internal actor at the '/system' actor path.
+    pub(crate) async fn create_system_actor<A: Actor<E>>(
+        &self,
+        name: &str,
+        actor: A,
+    ) -> Result<ActorRef<E, A>, ActorError> {
+        validate_name(name)?;
+        let path = ActorPath::from(SYSTEM_ROOT) / name;
+        self.create_actor_path(path, actor).await
+    }
+
+    /// Starts the dead letter actor at '/system/deadletters'. From then on,
+    /// messages that cannot be delivered because the mailbox of the recipient
+    /// is full or the recipient is not running anymore (including messages
+    /// left in the mailbox of a stopped actor) are sent to it. Its dead letters
+    /// can be retrieved by asking it [`GetDeadLetters`](crate::GetDeadLetters),
+    /// and sent again through [`ReplayDeadLetters`](crate::ReplayDeadLetters).
+    pub async fn start_dead_letters(
+        &self,
+        config: DeadLetterConfig,
+    ) -> Result<ActorRef<E, DeadLetterActor>, ActorError> {
+        let actor_ref = self
+            .create_system_actor(DEAD_LETTERS, DeadLetterActor::new(config))
+            .await?;
+        self.dead_letters.subscribe(actor_ref.clone());
+        Ok(actor_ref)
+    }
+
+    /// Starts the logger actor at '/system/logger', which logs the built-in
+    /// events of the actor system, see [`LoggerActor`]. Registrations are
+    /// logged at debug level, dead letters at info level, exceeded quotas,
+    /// flapping and starving actors and overflows of the event bus as
+    /// warnings, and crashes as errors.
+    pub async fn start_logger(&self) -> Result<ActorRef<E, LoggerActor>, ActorError> {
+        self.create_system_actor(LOGGER, LoggerActor::new()).await
+    }
+
+    /// Registers virtual actors of type `A` under the path prefix: an actor at a
+    /// path directly under the prefix is created by the factory when it is
+    /// first looked up through [`ActorSystem::virtual_actor()`], so callers can
+    /// send to it without managing its lifecycle. Registering a factory for the
+    /// same prefix again replaces the previous one. The prefix must be '/user'
+    /// or a path under it, otherwise an `Err(ActorError::CreateError)` is
+    /// returned.
+    pub fn register_virtual<A, F>(&self, prefix: &str, factory: F) -> Result<(), ActorError>
+    where
+        A: Actor<E>,
+        F: Fn(&ActorPath) -> A + Send + Sync + 'static,
+    {
+        let prefix = ActorPath::from(prefix);
+        let user_root = ActorPath::from(USER_ROOT);
+        if prefix != user_root && !prefix.is_descendant_of(&user_root) {
+            return Err(ActorError::CreateError(format!(
+                "Virtual actor prefix '{}' is not under '{}'",
+                prefix, USER_ROOT
+            )));
+        }
+        let factory: VirtualFactory<A> = Arc::new(factory);
+        self.virtuals
+            .write()
+            .unwrap()
+            .insert(prefix, Box::new(factory));
+        Ok(())
+    }
+
+    /// Retrieves the actor of type `A` at the path, creating it if a virtual
+    /// actor factory for `A` is registered for its parent path (see
+    /// [`ActorSystem::register_virtual()`]). If the actor does not exist and
+    /// cannot be created, an `Err(ActorError::NotFound)` is returned. A path
+    /// that is not a valid user actor path is rejected with an
+    /// `Err(ActorError::CreateError)` before the factory is invoked.
+    pub async fn virtual_actor<A: Actor<E>>(
+        &self,
+        path: &ActorPath,
+    ) -> Result<ActorRef<E, A>, ActorError> {
+        if let Some(actor_ref) = self.get_actor(path).await {
+            return Ok(actor_ref);
+        }
+        validate_user_path(path)?;
+        let factory = self
+            .virtuals
+            .read()
+            .unwrap()
+            .get(&path.parent())
+            .and_then(|factory| factory.downcast_ref::<VirtualFactory<A>>().cloned())
+            .ok_or_else(|| ActorError::NotFound(path.clone()))?;
+        match self.create_actor_path(path.clone(), factory(path)).await {
+            // Another caller created it in the meantime
+            Err(ActorError::Exists(_)) => self
+                .get_actor(path)
+                .await
+                .ok_or_else(|| ActorError::NotFound(path.clone())),
+            result => result,
+        }
+    }
+
+    /// Registers an extension: state shared by everything running on this actor
+    /// system, such as the connection pool of a persistence plugin, instead of
+    /// a global static. There is one extension per type, so if an extension of
+    /// type `T` is registered already, that one is kept and returned. For
+    /// example:
+    /// ```
+    /// # use tiny_tokio_actor::*;
+    /// # #[derive(Clone, Debug)]
+    /// # struct TestEvent;
+    /// # impl SystemEvent for TestEvent {}
+    /// struct Metrics {
+    ///     prefix: String,
+    /// }
+    ///
+    /// let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+    /// system.register_extension(Metrics { prefix: "app".to_string() });
+    ///
+    /// let metrics = system.extension::<Metrics>().unwrap();
+    /// assert_eq!(metrics.prefix, "app");
+    /// ```
+    pub fn register_extension<T: Send + Sync + 'static>(&self, extension: T) -> Arc<T> {
+        let mut extensions = self.extensions.write().unwrap();
+        let extension = extensions
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Arc::new(extension))
+            .clone();
+        extension
+            .downcast()
+            .expect("extension registered under the id of another type")
+    }
+
+    /// Retrieves the extension of type `T`, if registered through
+    /// [`ActorSystem::register_extension()`].
+    pub fn extension<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        let extensions = self.extensions.read().unwrap();
+        let extension = extensions.get(&TypeId::of::<T>())?.clone();
+        extension.downcast().ok()
+    }
+
+    /// Subscribes a responder to the requests of type `Req` made through
+    /// [`ActorSystem::request()`], which it answers with a `Resp` through
+    /// [`Request::respond()`]. For example:
+    /// ```
+    /// # use tiny_tokio_actor::*;
+    /// #[derive(Clone, Debug)]
+    /// struct Lookup(String);
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), ActorError> {
+    /// let system = ActorSystem::without_events("test");
+    ///
+    /// let mut lookups = system.responder::<Lookup, Option<u64>>();
+    /// tokio::spawn(async move {
+    ///     while let Ok(lookup) = lookups.recv().await {
+    ///         let found = (lookup.request().0 == "answer").then_some(42);
+    ///         lookup.respond(found);
+    ///     }
+    /// });
+    ///
+    /// let found = system.request::<Lookup, Option<u64>>(Lookup("answer".to_string())).await?;
+    /// assert_eq!(found, Some(42));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Every responder receives every request; the first to answer wins.
+    /// A responder that lags too far behind misses requests, as with
+    /// [`ActorSystem::events()`].
+    pub fn responder<Req, Resp>(&self) -> EventReceiver<Request<Req, Resp>>
+    where
+        Req: Clone + Send + Sync + 'static,
+        Resp: Send + 'static,
+    {
+        self.requests::<Req, Resp>().subscribe()
+    }
+
+    /// Makes a request to whichever responder subscribed through
+    /// [`ActorSystem::responder()`] answers it first, without knowing the
+    /// path of any of them. When the actor system is configured with an ask
+    /// timeout, an `ActorError::Timeout` is returned if no answer arrived in
+    /// time; without one, a request no responder answers waits forever.
+    /// Returns an `ActorError::NoResponder` if no responder is subscribed.
+    pub async fn request<Req, Resp>(&self, request: Req) -> Result<Resp, ActorError>
+    where
+        Req: Clone + Send + Sync + 'static,
+        Resp: Send + 'static,
+    {
+        self.request_within(request, self.config.ask_timeout())
+            .await
+    }
+
+    /// Same as [`ActorSystem::request()`], with a specific timeout.
+    pub async fn request_with_timeout<Req, Resp>(
+        &self,
+        request: Req,
+        timeout: Duration,
+    ) -> Result<Resp, ActorError>
+    where
+        Req: Clone + Send + Sync + 'static,
+        Resp: Send + 'static,
+    {
+        self.request_within(request, Some(timeout)).await
+    }
+
+    async fn request_within<Req, Resp>(
+        &self,
+        request: Req,
+        timeout: Option<Duration>,
+    ) -> Result<Resp, ActorError>
+    where
+        Req: Clone + Send + Sync + 'static,
+        Resp: Send + 'static,
+    {
+        let requests = self.requests::<Req, Resp>();
+        let correlation_id = self.next_id(IdKind::Correlation);
+        let answer = requests
+            .publish(correlation_id, request)
+            .ok_or(ActorError::NoResponder(std::any::type_name::<Req>()))?;
+        // Requests within a deadline give up once it passes
+        let timeout = match (timeout, Deadline::current()) {
+            (Some(timeout), Some(deadline)) => Some(timeout.min(deadline.remaining())),
+            (timeout, deadline) => timeout.or(deadline.map(|deadline| deadline.remaining())),
+        };
+        let answered = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, answer)
+                .await
+                .map_err(|_elapsed| {
+                    log::warn!(
+                        "Request {} #{} timed out",
+                        std::any::type_name::<Req>(),
+                        correlation_id
+                    );
+                    ActorError::Timeout
+                }),
+            None => Ok(answer.await),
+        };
+        requests.forget(correlation_id);
+        answered?.map_err(|_| ActorError::NoResponder(std::any::type_name::<Req>()))
+    }
+
+    fn requests<Req, Resp>(&self) -> Arc<Requests<Req, Resp>>
+    where
+        Req: Clone + Send + Sync + 'static,
+        Resp: Send + 'static,
+    {
+        match self.extension::<Requests<Req, Resp>>() {
+            Some(requests) => requests,
+            None => self.register_extension(Requests::new()),
+        }
+    }
+
+    /// Retrieve or create a new actor on this actor system if it does not exist yet.
+    pub async fn get_or_create_actor<A, F>(
+        &self,
+        name: &str,
+        actor_fn: F,
+    ) -> Result<ActorRef<E, A>, ActorError>
+    where
+        A: Actor<E>,
+        F: FnOnce() -> A,
+    {
+        validate_name(name)?;
+        let path = ActorPath::from(USER_ROOT) / name;
+        self.get_or_create_actor_path(&path, actor_fn).await
+    }
+
+    pub(crate) async fn get_or_create_actor_path<A, F>(
+        &self,
+        path: &ActorPath,
+        actor_fn: F,
+    ) -> Result<ActorRef<E, A>, ActorError>
+    where
+        A: Actor<E>,
+        F: FnOnce() -> A,
+    {
+        let actors = self.actors.read().await;
+        match self.get_actor(path).await {
+            Some(actor) => Ok(actor),
+            None => {
+                drop(actors);
+                self.create_actor_path(path.clone(), actor_fn()).await
+            }
+        }
+    }
+
+    /// Replaces the running actor at the given path with a new instance of the
+    /// same type. The existing instance is stopped (running its `post_stop`)
+    /// once it has finished handling its current message, after which the new
+    /// instance is started and takes over the mailbox, including any messages
+    /// still pending. Existing `ActorRef`s to the path remain valid.
+    ///
+    /// If no actor of type `A` runs at the path an `Err(ActorError::NotFound)`
+    /// is returned. If the new instance fails to start the actor is stopped
+    /// together with its descendants, its parent is informed like of any other
+    /// failed child (see [`Actor::child_failed()`]) and the startup error is
+    /// returned.
+    pub async fn replace_actor<A: Actor<E>>(
+        &self,
+        path: &ActorPath,
+        actor: A,
+    ) -> Result<ActorRef<E, A>, ActorError> {
+        log::debug!("Replacing actor '{}' on system '{}'...", &path, &self.name);
+        let actor_ref = self
+            .get_actor::<A>(path)
+            .await
+            .ok_or_else(|| ActorError::NotFound(path.clone()))?;
+        if let Err(error) = actor_ref.control().replace(actor).await {
+            let reason = ActorError::CreateError(format!(
+                "Replacement of actor '{}' failed to start: {:?}",
+                path, error
+            ));
+            self.escalate_runner(path, actor_ref.control(), reason)
+                .await;
+            return Err(error);
+        }
+        Ok(actor_ref)
+    }
+
+    /// Stops the actor on this actor system. All its children will also be stopped.
+    /// The actors are removed from the system right away, and stop once no
+    /// [`ActorRef`] to them is left and the messages in their mailboxes have
+    /// been handled. To stop them without handling their queued messages, and
+    /// wait until they have stopped, see [`ActorSystem::stop_subtree`].
+    pub async fn stop_actor(&self, path: &ActorPath) {
+        log::debug!("Stopping actor '{}' on system '{}'...", &path, &self.name);
+        self.remove_subtree(path).await;
+    }
+
+    /// Removes the actor and its descendants from the system and has them stop
+    /// once they have finished handling their current message, without
+    /// waiting for them. Messages still in their mailboxes are dropped.
+    pub(crate) async fn terminate(&self, path: &ActorPath) {
+        log::debug!(
+            "Terminating actor '{}' on system '{}'...",
+            &path,
+            &self.name
+        );
+        for control in self.remove_subtree(path).await {
+            drop(control.stop());
+        }
+    }
+
+    /// Stops all actors whose path matches the pattern (see [`PathPattern`]),
+    /// together with their descendants, and resolves once all of them have
+    /// stopped. The actors are stopped concurrently. Returns the paths of the
+    /// matching actors.
+    ///
+    /// Do not await this from within an actor that matches the pattern.
+    pub async fn stop_actors_matching(&self, pattern: &str) -> Vec<ActorPath> {
+        log::debug!(
+            "Stopping actors matching '{}' on system '{}'...",
+            pattern,
+            &self.name
+        );
+        let pattern = PathPattern::from(pattern);
+        let mut paths = self.find_paths(|path| pattern.matches(path)).await;
+        paths.sort_unstable();
+        let mut stopping = vec![];
+        for path in &paths {
+            for control in self.remove_subtree(path).await {
+                stopping.push(control.stop());
+            }
+        }
+        for stopped in stopping {
+            stopped.await.unwrap_or_default();
+        }
+        paths
+    }
+
+    /// Tells the message to all actors of type `A` whose path matches the
+    /// pattern (see [`PathPattern`]), reporting which of them it could be sent
+    /// to.
+    pub async fn tell_matching<A, M>(&self, pattern: &str, msg: M) -> BulkResult
+    where
+        A: Actor<E> + Handler<E, M>,
+        M: Message,
+    {
+        let pattern = PathPattern::from(pattern);
+        let mut actors = self.find_actors::<A, _>(|path| pattern.matches(path)).await;
+        actors.sort_unstable();
+        let mut result = BulkResult::default();
+        for actor_ref in actors {
+            match actor_ref.tell(msg.clone()) {
+                Ok(()) => result.succeeded.push(actor_ref.path().clone()),
+                Err(error) => result.failed.push((actor_ref.path().clone(), error)),
+            }
+        }
+        result
+    }
+
+    /// Removes an actor that failed, stopping its descendants, and informs its
+    /// parent (see [`Actor::child_failed()`]).
+    pub(crate) async fn escalate(&self, path: &ActorPath, reason: ActorError) {
+        for control in self.remove_subtree(path).await {
+            drop(control.stop());
+        }
+        self.notify_parent(path, reason).await;
+    }
+
+    /// Like [`ActorSystem::escalate()`], unless the actor at the path is not
+    /// the incarnation of the control reference anymore, e.g. as it was
+    /// stopped and created again in the meantime.
+    async fn escalate_runner(&self, path: &ActorPath, control: &ControlRef, reason: ActorError) {
+        let removed = {
+            let mut actors = self.actors.write().await;
+            match actors.get(path) {
+                Some(entry) if entry.control.same_runner(control) => {
+                    self.remove_subtree_from(&mut actors, path)
+                }
+                _ => return,
+            }
+        };
+        for (_, control) in removed {
+            drop(control.stop());
+        }
+        self.notify_parent(path, reason).await;
+    }
+
+    /// Informs the parent of the removed actor at the path that it failed.
+    async fn notify_parent(&self, path: &ActorPath, reason: ActorError) {
+        {
+            let actors = self.actors.read().await;
+            match actors.get(&path.parent()) {
+                Some(parent) => parent.control.child_failed(ChildFailed {
+                    path: path.clone(),
+                    reason,
+                }),
+                None => log::error!("Actor '{}' failed: {:?}", path, reason),
+            }
+        }
+        let group = self.groups.read().unwrap().get(path).cloned();
+        if let Some(members) = group {
+            self.fail_group(path, members);
+        }
+    }
+
+    /// Stops the actor and all its descendants, children before their parents,
+    /// and resolves once all of them have stopped.
+    ///
+    /// Do not await this from within an actor that is part of the subtree, as
+    /// that actor can only stop once its current handler completes.
+    pub async fn stop_subtree(&self, path: &ActorPath) {
+        log::debug!("Stopping subtree '{}' on system '{}'...", &path, &self.name);
+        for control in self.remove_subtree(path).await {
+            control.stop().await.unwrap_or_default();
+        }
+    }
+
+    /// Shuts the actor system down: stops the actors of the user, then all
+    /// remaining ones such as the built-in actors, and resolves once all of
+    /// them have stopped. It then closes the event bus of the system and its
+    /// internal buses, so subscribers receive `Err(EventRecvError::Closed)`
+    /// once they received the events sent before, instead of waiting for
+    /// events forever. The system should not be used afterwards.
+    ///
+    /// Do not await this from within an actor of the system.
+    pub async fn shutdown(&self) {
+        self.shutdown_until(None).await;
+    }
+
+    /// Shuts the actor system down like [`ActorSystem::shutdown()`], but
+    /// waits at most the timeout for the actors to stop. Actors that did not
+    /// stop in time are reported, and the buses are closed regardless.
+    pub async fn shutdown_within(&self, timeout: Duration) -> ShutdownReport {
+        let deadline = tokio::time::Instant::now() + timeout;
+        self.shutdown_until(Some(deadline)).await
+    }
+
+    /// Waits for a ctrl-c, or a `SIGTERM` on unix, and then shuts the actor
+    /// system down through [`ActorSystem::shutdown_within()`], resolving once
+    /// the shutdown completed. For example, at the end of `main`:
+    /// ```no_run
+    /// # use tiny_tokio_actor::*;
+    /// # use std::time::Duration;
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let system = ActorSystem::without_events("service");
+    /// // ... create the actors of the service
+    /// let report = system.run_until_signal(Duration::from_secs(10)).await;
+    /// if !report.is_clean() {
+    ///     eprintln!("Actors that did not stop in time: {:?}", report.timed_out);
+    /// }
+    /// # }
+    /// ```
+    /// Requires the `signal` feature.
+    #[cfg(feature = "signal")]
+    pub async fn run_until_signal(&self, timeout: Duration) -> ShutdownReport {
+        wait_for_signal().await;
+        log::info!("Received shutdown signal for system '{}'", &self.name);
+        self.shutdown_within(timeout).await
+    }
+
+    async fn shutdown_until(&self, deadline: Option<tokio::time::Instant>) -> ShutdownReport {
+        log::debug!("Shutting down system '{}'...", &self.name);
+        let mut report = ShutdownReport::default();
+        // Ask all actors to stop before waiting on any of them, so a slow actor
+        // does not use up the time of the others
+        let stopping: Vec<_> = self
+            .remove_subtree_entries(&ActorPath::from(USER_ROOT))
+            .await
+            .into_iter()
+            .map(|(path, control)| (path, control.stop()))
+            .collect();
+        for (path, stopped) in stopping {
+            if !Self::stopped_by(stopped, deadline).await {
+                report.timed_out.push(path);
+            }
+        }
+        let mut paths = self.find_paths(|_| true).await;
+        paths.sort_unstable();
+        let mut stopping = vec![];
+        for path in &paths {
+            for (path, control) in self.remove_subtree_entries(path).await {
+                stopping.push((path, control.stop()));
+            }
+        }
+        for (path, stopped) in stopping {
+            if !Self::stopped_by(stopped, deadline).await {
+                report.timed_out.push(path);
+            }
+        }
+        if !report.is_clean() {
+            log::warn!(
+                "Actors {:?} of system '{}' did not stop in time",
+                &report.timed_out,
+                &self.name
+            );
+        }
+        self.bus.close();
+        self.builtins.close();
+        report
+    }
+
+    /// Waits for the actor to acknowledge it stopped, returning `false` if it
+    /// did not before the deadline.
+    async fn stopped_by(
+        stopped: oneshot::Receiver<()>,
+        deadline: Option<tokio::time::Instant>,
+    ) -> bool {
+        match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline, stopped).await.is_ok(),
+            None => {
+                stopped.await.unwrap_or_default();
+                true
+            }
+        }
+    }
+
+    /// Resolves once none of the actors at the paths is running anymore,
+    /// immediately for paths without an actor. For example, to wait until a
+    /// batch of workers that stop themselves has finished.
+    pub async fn wait_for_stop_all(&self, paths: &[ActorPath]) {
+        let mut registry = self.registry.subscribe();
+        let mut running: HashSet<ActorPath> = {
+            let actors = self.actors.read().await;
+            paths
+                .iter()
+                .filter(|path| actors.contains_key(path))
+                .cloned()
+                .collect()
+        };
+        while !running.is_empty() {
+            match registry.recv().await {
+                Ok(RegistryEvent::ActorUnregistered(path)) => {
+                    running.remove(&path);
+                }
+                Ok(RegistryEvent::ActorRegistered(_)) => {}
+                Err(EventRecvError::Lagged(_)) => {
+                    let actors = self.actors.read().await;
+                    running.retain(|path| actors.contains_key(path));
+                }
+                Err(EventRecvError::Closed) => break,
+            }
+        }
+    }
+
+    /// Resolves once no actor of the system has a message in its mailbox or
+    /// is handling one, e.g. to wait until all work of a batch has drained
+    /// before exiting. A message that an actor sends while handling another
+    /// one is counted before the handling one is done, so work passed along
+    /// between actors keeps the system busy. Messages sent from outside the
+    /// system afterwards, e.g. by timers, make it busy again.
+    pub async fn wait_until_idle(&self) {
+        self.activity.idle().await
+    }
+
+    /// Restarts the actor and all its descendants, children before their parents,
+    /// and resolves once all of them have restarted. Each actor runs its
+    /// `post_stop` followed by its `pre_restart` (with no error), keeping the
+    /// messages in its mailbox. Actors that fail to restart are stopped together
+    /// with their descendants, their parents are informed like of any other
+    /// failed child (see [`Actor::child_failed()`]) and the error of the first
+    /// one is returned.
+    ///
+    /// Do not await this from within an actor that is part of the subtree.
+    pub async fn restart_subtree(&self, path: &ActorPath) -> Result<(), ActorError> {
+        log::debug!(
+            "Restarting subtree '{}' on system '{}'...",
+            &path,
+            &self.name
+        );
+        let entries: Vec<(ActorPath, ControlRef)> = {
+            let actors = self.actors.read().await;
+            Self::subtree_paths(path, actors.keys())
+                .into_iter()
+                .filter_map(|path| {
+                    let control = actors.get(&path)?.control.clone();
+                    Some((path, control))
+                })
+                .collect()
+        };
+        let mut result = Ok(());
+        for (path, control) in entries {
+            if let Err(error) = control.restart().await {
+                log::error!("Actor '{}' failed to restart: {}", &path, &error);
+                let reason = ActorError::CreateError(format!(
+                    "Actor '{}' failed to restart: {:?}",
+                    path, error
+                ));
+                self.escalate_runner(&path, &control, reason).await;
+                if result.is_ok() {
+                    result = Err(error);
+                }
+            }
+        }
+        result
+    }
+
+    /// The given path and all its descendants, children ordered before their
+    /// parents.
+    fn subtree_paths<'a, I>(path: &ActorPath, paths: I) -> Vec<ActorPath>
+    where
+        I: Iterator<Item = &'a ActorPath>,
+    {
+        let mut subtree: Vec<ActorPath> = paths
+            .filter(|running| *running == path || running.is_descendant_of(path))
+            .cloned()
+            .collect();
+        subtree.sort_unstable();
+        subtree.reverse();
+        subtree
+    }
+
+    /// Removes the actor and all its descendants from the registry, returning
+    /// their control handles with children ordered before their parents.
+    async fn remove_subtree(&self, path: &ActorPath) -> Vec<ControlRef> {
+        self.remove_subtree_entries(path)
+            .await
+            .into_iter()
+            .map(|(_, control)| control)
+            .collect()
+    }
+
+    /// Like [`ActorSystem::remove_subtree()`], together with the paths.
+    async fn remove_subtree_entries(&self, path: &ActorPath) -> Vec<(ActorPath, ControlRef)> {
+        let mut actors = self.actors.write().await;
+        self.remove_subtree_from(&mut actors, path)
+    }
+
+    fn remove_subtree_from(
+        &self,
+        actors: &mut HashMap<ActorPath, ActorEntry>,
+        path: &ActorPath,
+    ) -> Vec<(ActorPath, ControlRef)> {
+        Self::subtree_paths(path, actors.keys())
+            .into_iter()
+            .filter_map(|path| {
+                let entry = self.unregister(actors, &path)?;
+                Some((path, entry.control))
+            })
+            .collect()
+    }
+
+    /// Suspends message processing of all actors on this actor system. Handlers
+    /// that are already running are allowed to complete first, so once this
+    /// resolves no actor is processing a message. Use
+    /// [`ActorSystem::resume_all`] to continue processing.
+    ///
+    /// A handler that waits on another actor, e.g. asks it, cannot complete
+    /// once that actor is paused, so with asks between actors this may not
+    /// resolve until the actors are resumed; see
+    /// [`ActorSystem::pause_all_within`] to bound the wait. Actors created
+    /// while the system is paused are not paused.
+    pub async fn pause_all(&self) {
+        self.pause_all_until(None).await;
+    }
+
+    /// Like [`ActorSystem::pause_all`], but waits at most for the timeout for
+    /// running handlers to complete. Returns the actors whose handlers did not
+    /// complete in time: they are paused once their current handler
+    /// completes.
+    pub async fn pause_all_within(&self, timeout: Duration) -> Vec<ActorPath> {
+        self.pause_all_until(Some(tokio::time::Instant::now() + timeout))
+            .await
+    }
+
+    async fn pause_all_until(&self, deadline: Option<tokio::time::Instant>) -> Vec<ActorPath> {
+        log::debug!("Pausing all actors on system '{}'...", &self.name);
+        let controls: Vec<(ActorPath, ControlRef)> = {
+            let actors = self.actors.read().await;
+            actors
+                .iter()
+                .map(|(path, entry)| (path.clone(), entry.control.clone()))
+                .collect()
+        };
+        // Suspend all actors before waiting on any of them, so the handlers
+        // complete concurrently
+        let acks: Vec<_> = controls
+            .into_iter()
+            .filter_map(|(path, control)| Some((path, control.request_suspend().ok()?)))
+            .collect();
+        let mut busy = Vec::new();
+        for (path, ack) in acks {
+            match deadline {
+                Some(deadline) => {
+                    if tokio::time::timeout_at(deadline, ack).await.is_err() {
+                        busy.push(path);
+                    }
+                }
+                None => ack.await.unwrap_or_default(),
+            }
+        }
+        busy.sort_unstable();
+        busy
+    }
+
+    /// Resumes message processing of all actors on this actor system.
+    pub async fn resume_all(&self) {
+        log::debug!("Resuming all actors on system '{}'...", &self.name);
+        let actors = self.actors.read().await;
+        for entry in actors.values() {
+            entry.control.resume().unwrap_or_default();
+        }
+    }
+
+    /// Publishes the stats of the event bus at the interval until the system
+    /// shuts down.
+    fn report_bus_stats(bus: EventBus<E>, reports: BuiltinBus<BusStats>, interval: Duration) {
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            ticks.tick().await;
+            loop {
+                ticks.tick().await;
+                if reports.is_closed() {
+                    break;
+                }
+                reports.send(bus.stats());
+            }
+        });
+    }
+
+    /// The configuration of this actor system
+    pub fn config(&self) -> &SystemConfig {
+        &self.config
+    }
+
+    /// Creats a new actor system on which you can create actors.
+    pub fn new(name: &str, bus: EventBus<E>) -> Self {
+        Self::with_bus(SystemConfig::new(name), bus)
+    }
+
+    /// Creates a new actor system from the given configuration, including its
+    /// event bus.
+    pub fn from_config(config: SystemConfig) -> Self {
+        let mut bus = EventBus::new(config.event_bus_capacity());
+        if let Some(limit) = config.event_buffering() {
+            bus = bus.buffer_until_subscribed(limit);
+        }
+        Self::with_bus(config, bus)
+    }
+
+    fn with_bus(config: SystemConfig, bus: EventBus<E>) -> Self {
+        let name = config.name().to_string();
+        let actors = Arc::new(RwLock::new(HashMap::new()));
+        let builtins = BuiltinBuses::new(config.event_bus_capacity());
+        let registry = builtins.bus();
+        let crashes = builtins.bus();
+        let ids = match config.id_generator() {
+            Some(ids) => ids.0.clone(),
+            None => Arc::new(SequentialIds::default()),
+        };
+        let dead_letters = DeadLetters::new(builtins.bus());
+        let quota_events = builtins.bus();
+        let flapping = builtins.bus();
+        let starvation = builtins.bus();
+        let bus_stats = builtins.bus();
+        let leadership = builtins.bus();
+        let connections = builtins.bus();
+        let group_events = builtins.bus();
+        if let Some(interval) = config.bus_stats_interval() {
+            Self::report_bus_stats(bus.clone(), bus_stats.clone(), interval);
+        }
+        #[cfg(feature = "prometheus")]
+        let metrics = Arc::new(crate::metrics::Metrics::new(
+            config.instrumentation().cloned(),
+        ));
+        ActorSystem {
+            name,
+            actors,
+            bus,
+            builtins,
+            registry,
+            crashes,
+            config: Arc::new(config),
+            ids,
+            dead_letters,
+            virtuals: Default::default(),
+            extensions: Default::default(),
+            quotas: Default::default(),
+            quota_events,
+            restarts: Default::default(),
+            flapping,
+            starvation,
+            bus_stats,
+            leadership,
+            connections,
+            groups: Default::default(),
+            group_events,
+            activity: Default::default(),
+            started: Default::default(),
+            #[cfg(feature = "prometheus")]
+            metrics,
+        }
+    }
+}
+
+/// Resolves once the process received a ctrl-c, or a `SIGTERM` on unix.
+#[cfg(feature = "signal")]
+async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(error) => log::error!("Failed to listen for SIGTERM: {}", error),
+        }
+    }
+    if let Err(error) = tokio::signal::ctrl_c().await {
+        log::error!("Failed to listen for ctrl-c: {}", error);
+        std::future::pending::<()>().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::actor::{Actor, ActorContext, Handler, Handoff, Message};
+    use crate::ids::SeededIds;
+    use crate::quota::Quota;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use super::*;
+
+    #[allow(dead_code)]
+    #[derive(Clone, Debug)]
+    struct TestEvent(String);
+
+    impl SystemEvent for TestEvent {}
+
+    #[derive(Default)]
+    struct TestActor {
+        counter: usize,
+    }
+
+    #[async_trait]
+    impl Actor<TestEvent> for TestActor {
+        async fn pre_start(
+            &mut self,
+            _ctx: &mut ActorContext<TestEvent>,
+        ) -> Result<(), ActorError> {
+            log::debug!("Starting actor TestActor!");
+            Ok(())
+        }
+
+        async fn post_stop(&mut self, _ctx: &mut ActorContext<TestEvent>) {
+            log::debug!("Stopped actor TestActor!");
+        }
+    }
+
+    #[allow(dead_code)]
+    #[derive(Clone, Debug)]
+    struct TestMessage(usize);
+
+    impl Message for TestMessage {
+        type Response = usize;
+    }
+
+    impl SystemEvent for TestMessage {}
+
+    #[async_trait]
+    impl Handler<TestEvent, TestMessage> for TestActor {
+        async fn handle(&mut self, msg: TestMessage, ctx: &mut ActorContext<TestEvent>) -> usize {
+            log::debug!("received message! {:?}", &msg);
+            self.counter += 1;
+            log::debug!("counter is now {}", &self.counter);
+            log::debug!("{} on system {}", &ctx.path, ctx.system.name());
+            ctx.system
+                .publish(TestEvent("Message received!".to_string()));
+            self.counter
+        }
+    }
+
+    #[derive(Default, Clone)]
+    struct OtherActor {
+        message: String,
+        child: Option<ActorRef<TestEvent, TestActor>>,
+    }
+
+    #[async_trait]
+    impl Actor<TestEvent> for OtherActor {
+        async fn pre_start(&mut self, ctx: &mut ActorContext<TestEvent>) -> Result<(), ActorError> {
+            log::debug!("OtherActor initial message: {}", &self.message);
+            let child = TestActor { counter: 0 };
+            self.child = ctx.create_child("child", child).await.ok();
+            Ok(())
+        }
+
+        async fn post_stop(&mut self, _ctx: &mut ActorContext<TestEvent>) {
+            log::debug!("OtherActor stopped.");
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct OtherMessage(String);
+
+    impl Message for OtherMessage {
+        type Response = String;
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, OtherMessage> for OtherActor {
+        async fn handle(&mut self, msg: OtherMessage, ctx: &mut ActorContext<TestEvent>) -> String {
+            log::debug!("OtherActor received message! {:?}", &msg);
+            log::debug!("original message is {}", &self.message);
+            self.message = msg.0;
+            log::debug!("message is now {}", &self.message);
+            log::debug!("{} on system {}", &ctx.path, ctx.system.name());
+            ctx.system
+                .publish(TestEvent("Received message!".to_string()));
+            self.message.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_create() {
+        if std::env::var("RUST_LOG").is_err() {
+            std::env::set_var("RUST_LOG", "trace");
+        }
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let actor = TestActor { counter: 0 };
+        let msg = TestMessage(10);
+
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+        let actor_ref = system.create_actor("test-actor", actor).await.unwrap();
+        let result = actor_ref.ask(msg).await.unwrap();
+
+        assert_eq!(result, 1);
+    }
+
+    fn create_other(message: String) -> OtherActor {
+        OtherActor {
+            message,
+            child: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_get_or_create() {
+        if std::env::var("RUST_LOG").is_err() {
+            std::env::set_var("RUST_LOG", "trace");
+        }
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+
+        let initial_message = "hello world!".to_string();
+        let actor_fn = || create_other(initial_message);
+        let actor_ref = system
+            .get_or_create_actor("test-actor", actor_fn)
+            .await
+            .unwrap();
+
+        let msg = OtherMessage("Updated message.".to_string());
+        let result = actor_ref.ask(msg).await.unwrap();
+
+        assert_eq!(result, "Updated message.".to_string());
+    }
+
+    #[tokio::test]
+    async fn actor_stop() {
+        if std::env::var("RUST_LOG").is_err() {
+            std::env::set_var("RUST_LOG", "trace");
+        }
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let actor = TestActor { counter: 0 };
+        let msg = TestMessage(10);
+
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+
+        {
+            let actor_ref = system.create_actor("test-actor", actor).await.unwrap();
+            let result = actor_ref.ask(msg).await.unwrap();
+
+            assert_eq!(result, 1);
+
+            system.stop_actor(actor_ref.path()).await;
+        }
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+    }
+
+    #[tokio::test]
+    async fn actor_stop_handles_queued_messages() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let actor_ref = system
+            .create_actor("test-actor", TestActor::default())
+            .await
+            .unwrap();
+        actor_ref.suspend().await.unwrap();
+        actor_ref.tell(TestMessage(1)).unwrap();
+
+        system.stop_actor(actor_ref.path()).await;
+        assert!(system
+            .get_actor::<TestActor>(actor_ref.path())
+            .await
+            .is_none());
+        // The actor keeps handling messages while it is still referenced
+        actor_ref.resume().unwrap();
+        assert_eq!(actor_ref.ask(TestMessage(1)).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn actor_events() {
+        if std::env::var("RUST_LOG").is_err() {
+            std::env::set_var("RUST_LOG", "trace");
+        }
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let actor = TestActor { counter: 0 };
+        let msg = TestMessage(10);
+
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+        let actor_ref = system.create_actor("test-actor", actor).await.unwrap();
+
+        let mut events = system.events();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => println!("Received event! {:?}", event),
+                    Err(err) => println!("Error receivng event!!! {:?}", err),
+                }
+            }
+        });
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let result = actor_ref.ask(msg).await.unwrap();
+
+        assert_eq!(result, 1);
+    }
+
+    #[tokio::test]
+    async fn actor_get() {
+        if std::env::var("RUST_LOG").is_err() {
+            std::env::set_var("RUST_LOG", "trace");
+        }
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let actor = TestActor { counter: 0 };
+
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+        let original = system.create_actor("test-actor", actor).await.unwrap();
+
+        if let Some(actor_ref) = system.get_actor::<TestActor>(original.path()).await {
+            let msg = TestMessage(10);
+            let result = actor_ref.ask(msg).await.unwrap();
+            assert_eq!(result, 1);
+        } else {
+            panic!("It should have retrieved the actor!")
+        }
+
+        if let Some(actor_ref) = system.get_actor::<OtherActor>(original.path()).await {
+            let msg = OtherMessage("Hello world!".to_string());
+            let result = actor_ref.ask(msg).await.unwrap();
+            println!("Result is: {}", result);
+            panic!("It should not go here!");
+        }
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    }
+
+    #[tokio::test]
+    async fn actor_parent_child() {
+        if std::env::var("RUST_LOG").is_err() {
+            std::env::set_var("RUST_LOG", "trace");
+        }
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let actor = OtherActor {
+            message: "Initial".to_string(),
+            child: None,
+        };
+
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+
+        {
+            let actor_ref = system.create_actor("test-actor", actor).await.unwrap();
+            let msg = OtherMessage("new message!".to_string());
+            let result = actor_ref.ask(msg).await.unwrap();
+            assert_eq!(result, "new message!".to_string());
+
+            tokio::time::sleep(tokio::time::Duration::from_millis(2000)).await;
+            system.stop_actor(actor_ref.path()).await;
+        }
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(2000)).await;
+        let actors = system.actors.read().await;
+        for actor in actors.keys() {
+            println!("Still active!: {:?}", actor);
+        }
+        assert_eq!(actors.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn actor_pause_resume_all() {
+        if std::env::var("RUST_LOG").is_err() {
+            std::env::set_var("RUST_LOG", "trace");
+        }
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+        let actor_ref = system
+            .create_actor("test-actor", TestActor { counter: 0 })
+            .await
+            .unwrap();
+
+        system.pause_all().await;
+
+        let paused = tokio::time::timeout(
+            tokio::time::Duration::from_millis(100),
+            actor_ref.ask(TestMessage(10)),
+        )
+        .await;
+        assert!(paused.is_err());
+
+        system.resume_all().await;
+
+        let result = actor_ref.ask(TestMessage(10)).await.unwrap();
+        assert_eq!(result, 2);
+    }
+
+    /// Asks a [`TestActor`] from within its handler, after a while.
+    struct LateRelayActor {
+        target: ActorRef<TestEvent, TestActor>,
+    }
+
+    impl Actor<TestEvent> for LateRelayActor {}
+
+    #[async_trait]
+    impl Handler<TestEvent, TestMessage> for LateRelayActor {
+        async fn handle(&mut self, msg: TestMessage, _ctx: &mut ActorContext<TestEvent>) -> usize {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            self.target.ask(msg).await.unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn pause_all_busy() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let target = system
+            .create_actor("target", TestActor::default())
+            .await
+            .unwrap();
+        let relay = LateRelayActor {
+            target: target.clone(),
+        };
+        let relay = system.create_actor("relay", relay).await.unwrap();
+        let relayed = tokio::spawn({
+            let relay = relay.clone();
+            async move { relay.ask(TestMessage(1)).await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        // The relay asks the target once that is paused already
+        let busy = system.pause_all_within(Duration::from_millis(200)).await;
+        assert_eq!(busy, vec![relay.path().clone()]);
+        assert!(!relayed.is_finished());
+
+        system.resume_all().await;
+        assert_eq!(relayed.await.unwrap().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn actor_replace() {
+        if std::env::var("RUST_LOG").is_err() {
+            std::env::set_var("RUST_LOG", "trace");
+        }
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+        let actor_ref = system
+            .create_actor("test-actor", TestActor { counter: 0 })
+            .await
+            .unwrap();
+
+        // Queue up a message that should be handled by the replacement
+        actor_ref.suspend().await.unwrap();
+        actor_ref.tell(TestMessage(10)).unwrap();
+
+        let replaced = system
+            .replace_actor(actor_ref.path(), TestActor { counter: 10 })
+            .await
+            .unwrap();
+        actor_ref.resume().unwrap();
+
+        let result = replaced.ask(TestMessage(10)).await.unwrap();
+        assert_eq!(result, 12);
+
+        let missing = system
+            .replace_actor(&ActorPath::from("/user/missing"), TestActor::default())
+            .await;
+        assert!(matches!(missing, Err(ActorError::NotFound(_))));
+    }
+
+    #[derive(Default)]
+    struct HandoffActor {
+        counter: usize,
+    }
+
+    #[async_trait]
+    impl Actor<TestEvent> for HandoffActor {
+        async fn pre_start(&mut self, ctx: &mut ActorContext<TestEvent>) -> Result<(), ActorError> {
+            if let Some(counter) = ctx.take_handoff::<usize>() {
+                log::debug!("Received counter {} from previous instance", counter);
+                self.counter = counter;
+            }
+            Ok(())
+        }
+
+        fn handoff(&mut self) -> Option<Handoff> {
+            Some(Box::new(self.counter))
+        }
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, TestMessage> for HandoffActor {
+        async fn handle(&mut self, msg: TestMessage, _ctx: &mut ActorContext<TestEvent>) -> usize {
+            self.counter += msg.0;
+            self.counter
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_replace_handoff() {
+        if std::env::var("RUST_LOG").is_err() {
+            std::env::set_var("RUST_LOG", "trace");
+        }
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+        let actor_ref = system
+            .create_actor("handoff-actor", HandoffActor::default())
+            .await
+            .unwrap();
+        assert_eq!(actor_ref.ask(TestMessage(5)).await.unwrap(), 5);
+
+        system
+            .replace_actor(actor_ref.path(), HandoffActor::default())
+            .await
+            .unwrap();
+        assert_eq!(actor_ref.ask(TestMessage(1)).await.unwrap(), 6);
+    }
+
+    /// Counts handled messages in an extension instead of in the actor.
+    struct ExtensionActor;
+
+    impl Actor<TestEvent> for ExtensionActor {}
+
+    struct Handled(usize);
+
+    #[async_trait]
+    impl Handler<TestEvent, TestMessage> for ExtensionActor {
+        async fn handle(&mut self, msg: TestMessage, ctx: &mut ActorContext<TestEvent>) -> usize {
+            let handled = ctx.extensions_mut().get_or_insert_with(|| Handled(0));
+            handled.0 += msg.0;
+            handled.0
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_context_extensions() {
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+        let actor_ref = system
+            .create_actor("extension-actor", ExtensionActor)
+            .await
+            .unwrap();
+        assert_eq!(actor_ref.ask(TestMessage(2)).await.unwrap(), 2);
+        assert_eq!(actor_ref.ask(TestMessage(3)).await.unwrap(), 5);
+
+        // Extensions belong to the instance, so a replacement starts afresh
+        system
+            .replace_actor(actor_ref.path(), ExtensionActor)
+            .await
+            .unwrap();
+        assert_eq!(actor_ref.ask(TestMessage(1)).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn system_extensions() {
+        struct Registered(usize);
+
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        assert!(system.extension::<Registered>().is_none());
+
+        assert_eq!(system.register_extension(Registered(1)).0, 1);
+        // The first registration is kept
+        assert_eq!(system.register_extension(Registered(2)).0, 1);
+
+        let clone = system.clone();
+        assert_eq!(clone.extension::<Registered>().unwrap().0, 1);
+    }
+
+    #[tokio::test]
+    async fn actor_guardian_roots() {
+        if std::env::var("RUST_LOG").is_err() {
+            std::env::set_var("RUST_LOG", "trace");
+        }
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+
+        let user = system
+            .create_actor("test-actor", TestActor::default())
+            .await
+            .unwrap();
+        assert!(user.path().is_descendant_of(&ActorPath::from(USER_ROOT)));
+
+        let internal = system
+            .create_system_actor("test-actor", TestActor::default())
+            .await
+            .unwrap();
+        assert!(internal
+            .path()
+            .is_descendant_of(&ActorPath::from(SYSTEM_ROOT)));
+
+        let escaped = system
+            .create_actor("../system/test-actor", TestActor::default())
+            .await;
+        assert!(matches!(escaped, Err(ActorError::CreateError(_))));
+
+        let outside = system
+            .create_actor_path(ActorPath::from("/other/test-actor"), TestActor::default())
+            .await;
+        assert!(matches!(outside, Err(ActorError::CreateError(_))));
+    }
+
+    #[derive(Clone, Debug)]
+    struct ChildrenMessage;
+
+    impl Message for ChildrenMessage {
+        type Response = (Vec<ActorPath>, usize);
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, ChildrenMessage> for OtherActor {
+        async fn handle(
+            &mut self,
+            _msg: ChildrenMessage,
+            ctx: &mut ActorContext<TestEvent>,
+        ) -> (Vec<ActorPath>, usize) {
+            let children = ctx.children().await;
+            let sent = ctx.broadcast_children::<TestActor, _>(TestMessage(1)).await;
+            (children, sent)
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_broadcast_children() {
+        if std::env::var("RUST_LOG").is_err() {
+            std::env::set_var("RUST_LOG", "trace");
+        }
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+        let actor_ref = system
+            .create_actor("test-actor", create_other("parent".to_string()))
+            .await
+            .unwrap();
+
+        let (children, sent) = actor_ref.ask(ChildrenMessage).await.unwrap();
+        assert_eq!(children, vec![ActorPath::from("/user/test-actor/child")]);
+        assert_eq!(sent, 1);
+
+        let child = system.get_actor::<TestActor>(&children[0]).await.unwrap();
+        assert_eq!(child.ask(TestMessage(1)).await.unwrap(), 2);
+    }
+
+    #[derive(Default)]
+    struct StartCountActor {
+        starts: usize,
+    }
+
+    #[async_trait]
+    impl Actor<TestEvent> for StartCountActor {
+        async fn pre_start(
+            &mut self,
+            _ctx: &mut ActorContext<TestEvent>,
+        ) -> Result<(), ActorError> {
+            self.starts += 1;
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct GetStarts;
+
+    impl Message for GetStarts {
+        type Response = usize;
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, GetStarts> for StartCountActor {
+        async fn handle(&mut self, _msg: GetStarts, _ctx: &mut ActorContext<TestEvent>) -> usize {
+            self.starts
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_restart_stop_subtree() {
+        if std::env::var("RUST_LOG").is_err() {
+            std::env::set_var("RUST_LOG", "trace");
+        }
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+        let parent = system
+            .create_actor("parent", StartCountActor::default())
+            .await
+            .unwrap();
+        let child = system
+            .create_actor_path(parent.path().clone() / "child", StartCountActor::default())
+            .await
+            .unwrap();
+        let other = system
+            .create_actor("other", StartCountActor::default())
+            .await
+            .unwrap();
+
+        system.restart_subtree(parent.path()).await.unwrap();
+
+        assert_eq!(parent.ask(GetStarts).await.unwrap(), 2);
+        assert_eq!(child.ask(GetStarts).await.unwrap(), 2);
+        assert_eq!(other.ask(GetStarts).await.unwrap(), 1);
+
+        system.stop_subtree(parent.path()).await;
+
+        assert!(parent.is_closed());
+        assert!(child.is_closed());
+        assert!(!other.is_closed());
+        let actors = system.actors.read().await;
+        assert_eq!(actors.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn actor_anonymous() {
+        if std::env::var("RUST_LOG").is_err() {
+            std::env::set_var("RUST_LOG", "trace");
+        }
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+
+        let first = system
+            .create_anonymous_actor(TestActor::default())
+            .await
+            .unwrap();
+        let second = system
+            .create_anonymous_actor(TestActor::default())
+            .await
+            .unwrap();
+
+        assert_ne!(first.path(), second.path());
+        assert!(first.path().is_child_of(&ActorPath::from(USER_ROOT)));
+        assert!(first.path().key().starts_with(ANONYMOUS_PREFIX));
+        assert_eq!(first.ask(TestMessage(1)).await.unwrap(), 1);
+
+        let reserved = system
+            .create_actor(&second.path().key(), TestActor::default())
+            .await;
+        assert!(matches!(reserved, Err(ActorError::CreateError(_))));
+    }
+
+    #[tokio::test]
+    async fn actor_labels() {
+        if std::env::var("RUST_LOG").is_err() {
+            std::env::set_var("RUST_LOG", "trace");
+        }
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+
+        let acme = ActorConfig::default().with_label("tenant", "acme");
+        let other = ActorConfig::default().with_label("tenant", "other");
+        system
+            .create_actor_with_config("acme-1", TestActor::default(), acme.clone())
+            .await
+            .unwrap();
+        system
+            .create_actor_with_config("acme-2", TestActor::default(), acme)
+            .await
+            .unwrap();
+        system
+            .create_actor_with_config("other-1", TestActor::default(), other)
+            .await
+            .unwrap();
+
+        let paths = system.paths_with_label("tenant", "acme").await;
+        assert_eq!(
+            paths,
+            vec![
+                ActorPath::from("/user/acme-1"),
+                ActorPath::from("/user/acme-2")
+            ]
+        );
+
+        let refs = system
+            .actors_with_label::<TestActor>("tenant", "acme")
+            .await;
+        assert_eq!(refs.len(), 2);
+        for actor_ref in refs {
+            assert_eq!(actor_ref.ask(TestMessage(1)).await.unwrap(), 1);
+        }
+
+        let wrong_type = system
+            .actors_with_label::<OtherActor>("tenant", "acme")
+            .await;
+        assert!(wrong_type.is_empty());
+    }
+
+    #[tokio::test]
+    async fn actor_system_from_config() {
+        if std::env::var("RUST_LOG").is_err() {
+            std::env::set_var("RUST_LOG", "trace");
+        }
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let config = SystemConfig::new("configured")
+            .with_mailbox_capacity(1)
+            .with_ask_timeout(tokio::time::Duration::from_millis(100));
+        let system = ActorSystem::<TestEvent>::from_config(config);
+        assert_eq!(system.name(), "configured");
+
+        let actor_ref = system
+            .create_actor("test-actor", TestActor::default())
+            .await
+            .unwrap();
+        actor_ref.suspend().await.unwrap();
+
+        actor_ref.tell(TestMessage(1)).unwrap();
+        let full = actor_ref.tell(TestMessage(1));
+        assert!(matches!(full, Err(ActorError::MailboxFull)));
+
+        let timeout = actor_ref.ask(TestMessage(1)).await;
+        assert!(matches!(timeout, Err(ActorError::Timeout)));
+
+        actor_ref.resume().unwrap();
+        assert_eq!(actor_ref.ask(TestMessage(1)).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn actor_config_overrides() {
+        if std::env::var("RUST_LOG").is_err() {
+            std::env::set_var("RUST_LOG", "trace");
+        }
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let config = SystemConfig::new("test").with_override(
+            "/user/ingest/*",
+            ActorConfig::default()
+                .with_mailbox_capacity(1)
+                .with_label("tier", "ingest"),
+        );
+        let system = ActorSystem::<TestEvent>::from_config(config);
+
+        let ingest = system
+            .create_actor_path(ActorPath::from("/user/ingest/worker"), TestActor::default())
+            .await
+            .unwrap();
+        let other = system
+            .create_actor("other", TestActor::default())
+            .await
+            .unwrap();
+        ingest.suspend().await.unwrap();
+        other.suspend().await.unwrap();
+
+        ingest.tell(TestMessage(1)).unwrap();
+        let full = ingest.tell(TestMessage(1));
+        assert!(matches!(full, Err(ActorError::MailboxFull)));
+        other.tell(TestMessage(1)).unwrap();
+        other.tell(TestMessage(1)).unwrap();
+
+        let labelled = system.paths_with_label("tier", "ingest").await;
+        assert_eq!(labelled, vec![ActorPath::from("/user/ingest/worker")]);
+    }
+
+    #[tokio::test]
+    #[allow(clippy::mutable_key_type)]
+    async fn actor_ref_eq_hash() {
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+        let a = system
+            .create_actor("a", TestActor::default())
+            .await
+            .unwrap();
+        let b = system
+            .create_actor("b", TestActor::default())
+            .await
+            .unwrap();
+        let again = system.get_actor::<TestActor>(a.path()).await.unwrap();
+
+        assert_eq!(a, again);
+        assert_ne!(a, b);
+        assert!(a < b);
+
+        let refs: std::collections::HashSet<_> = vec![a.clone(), b, again].into_iter().collect();
+        assert_eq!(refs.len(), 2);
+        assert!(refs.contains(&a));
+    }
+
+    #[tokio::test]
+    async fn actor_incarnation() {
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+        let original = system
+            .create_actor("test-actor", TestActor::default())
+            .await
+            .unwrap();
+        system.stop_subtree(original.path()).await;
+
+        let recreated = system
+            .create_actor("test-actor", TestActor::default())
+            .await
+            .unwrap();
+        assert_eq!(original.path(), recreated.path());
+        assert_ne!(original.incarnation(), recreated.incarnation());
+        assert_ne!(original, recreated);
+
+        let stale = original.tell(TestMessage(1));
+        assert!(matches!(
+            stale,
+            Err(ActorError::Terminated(_, incarnation)) if incarnation == original.incarnation()
+        ));
+        assert_eq!(recreated.ask(TestMessage(1)).await.unwrap(), 1);
+    }
+
+    #[derive(Default)]
+    struct SlowActor {
+        cancelled: bool,
+    }
+
+    impl Actor<TestEvent> for SlowActor {}
+
+    #[derive(Clone, Debug)]
+    struct SlowQuery;
+
+    impl Message for SlowQuery {
+        type Response = ();
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, SlowQuery> for SlowActor {
+        async fn handle(&mut self, _msg: SlowQuery, ctx: &mut ActorContext<TestEvent>) {
+            for _ in 0..100 {
+                if ctx.is_cancelled() {
+                    self.cancelled = true;
+                    return;
+                }
+                tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+            }
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct WasCancelled;
+
+    impl Message for WasCancelled {
+        type Response = bool;
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, WasCancelled> for SlowActor {
+        async fn handle(&mut self, _msg: WasCancelled, ctx: &mut ActorContext<TestEvent>) -> bool {
+            assert!(!ctx.is_cancelled());
+            self.cancelled
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_ask_cancelled() {
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+        let actor_ref = system
+            .create_actor("slow", SlowActor::default())
+            .await
+            .unwrap();
+
+        let query = actor_ref.ask(SlowQuery);
+        let gave_up = tokio::time::timeout(tokio::time::Duration::from_millis(50), query).await;
+        assert!(gave_up.is_err());
+
+        let start = tokio::time::Instant::now();
+        assert!(actor_ref.ask(WasCancelled).await.unwrap());
+        assert!(start.elapsed() < tokio::time::Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn actor_orphaned_response() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let mut messages = system.system_messages();
+        let actor_ref = system
+            .create_actor("slow", SlowActor::default())
+            .await
+            .unwrap();
+
+        let query = actor_ref.ask(SlowQuery);
+        let gave_up = tokio::time::timeout(tokio::time::Duration::from_millis(20), query).await;
+        assert!(gave_up.is_err());
+
+        let letter = loop {
+            match messages.recv().await.unwrap() {
+                SystemMessage::DeadLetter(letter) => break letter,
+                _ => continue,
+            }
+        };
+        assert_eq!(letter.reason(), DeadLetterReason::CallerGone);
+        assert_eq!(letter.recipient(), actor_ref.path());
+        assert!(letter.message_type().ends_with("SlowQuery"));
+        assert!(letter.payload::<SlowQuery>().is_some());
+    }
+
+    #[derive(Clone, Debug)]
+    struct WaitForStop;
+
+    impl Message for WaitForStop {
+        type Response = ();
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, WaitForStop> for SlowActor {
+        async fn handle(&mut self, _msg: WaitForStop, ctx: &mut ActorContext<TestEvent>) {
+            let token = ctx.cancellation_token().clone();
+            tokio::select! {
+                _ = token.cancelled() => ctx.system.publish(TestEvent("cancelled".to_string())),
+                _ = tokio::time::sleep(tokio::time::Duration::from_secs(60)) => {}
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_cancellation_token() {
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+        let mut events = system.events();
+        let actor_ref = system
+            .create_actor("slow", SlowActor::default())
+            .await
+            .unwrap();
+        actor_ref.tell(WaitForStop).unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+
+        let stopped = tokio::time::timeout(
+            tokio::time::Duration::from_secs(1),
+            system.stop_subtree(actor_ref.path()),
+        )
+        .await;
+        assert!(stopped.is_ok());
+        assert_eq!(events.recv().await.unwrap().0, "cancelled");
+        assert!(actor_ref.is_closed());
+    }
+
+    #[tokio::test]
+    async fn actor_dead_letters() {
+        let config = SystemConfig::new("test").with_mailbox_capacity(1);
+        let system = ActorSystem::<TestEvent>::from_config(config);
+        let dead_letters = system
+            .start_dead_letters(crate::DeadLetterConfig::new(10))
+            .await
+            .unwrap();
+        assert_eq!(dead_letters.path(), &ActorPath::from("/system/deadletters"));
+
+        let actor_ref = system
+            .create_actor("test-actor", TestActor::default())
+            .await
+            .unwrap();
+        actor_ref.suspend().await.unwrap();
+        actor_ref.tell(TestMessage(1)).unwrap();
+        assert!(actor_ref.tell(TestMessage(2)).is_err());
+        system.stop_subtree(actor_ref.path()).await;
+        assert!(actor_ref.tell(TestMessage(3)).is_err());
+
+        let letters = dead_letters.ask(crate::GetDeadLetters).await.unwrap();
+        let summary: Vec<_> = letters
+            .iter()
+            .map(|letter| {
+                assert_eq!(letter.recipient(), actor_ref.path());
+                (letter.payload::<TestMessage>().unwrap().0, letter.reason())
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (2, crate::DeadLetterReason::MailboxFull),
+                (1, crate::DeadLetterReason::Terminated),
+                (3, crate::DeadLetterReason::Terminated),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn system_messages() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(2));
+        let mut messages = system.system_messages();
+
+        let actor_ref = system
+            .create_actor("test-actor", TestActor::default())
+            .await
+            .unwrap();
+        match messages.recv().await.unwrap() {
+            SystemMessage::Registry(RegistryEvent::ActorRegistered(path)) => {
+                assert_eq!(&path, actor_ref.path())
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+
+        system.stop_subtree(actor_ref.path()).await;
+        match messages.recv().await.unwrap() {
+            SystemMessage::Registry(RegistryEvent::ActorUnregistered(path)) => {
+                assert_eq!(&path, actor_ref.path())
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+        assert!(actor_ref.tell(TestMessage(1)).is_err());
+        match messages.recv().await.unwrap() {
+            SystemMessage::DeadLetter(letter) => {
+                assert_eq!(letter.payload::<TestMessage>().unwrap().0, 1)
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+
+        for event in ["one", "two", "three"] {
+            system.publish(TestEvent(event.to_string()));
+        }
+        assert!(matches!(
+            messages.recv().await.unwrap(),
+            SystemMessage::EventsDropped(1)
+        ));
+        for expected in ["two", "three"] {
+            match messages.recv().await.unwrap() {
+                SystemMessage::Event(event) => assert_eq!(event.0, expected),
+                other => panic!("unexpected message {:?}", other),
+            }
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct RecordingInstrumentation(Arc<std::sync::Mutex<Vec<&'static str>>>);
+
+    impl crate::MailboxInstrumentation for RecordingInstrumentation {
+        fn enqueued(&self, _path: &ActorPath, _message_type: &'static str) {
+            self.0.lock().unwrap().push("enqueued");
+        }
+
+        fn dequeued(
+            &self,
+            _path: &ActorPath,
+            _message_type: &'static str,
+            _waited: tokio::time::Duration,
+        ) {
+            self.0.lock().unwrap().push("dequeued");
+        }
+
+        fn handled(
+            &self,
+            _path: &ActorPath,
+            message_type: &'static str,
+            _elapsed: tokio::time::Duration,
+        ) {
+            assert_eq!(message_type, std::any::type_name::<TestMessage>());
+            self.0.lock().unwrap().push("handled");
+        }
+
+        fn failed(&self, _path: &ActorPath, _error: &ActorError) {
+            self.0.lock().unwrap().push("failed");
+        }
+
+        fn dropped(
+            &self,
+            _path: &ActorPath,
+            _message_type: &'static str,
+            reason: crate::DeadLetterReason,
+        ) {
+            assert_eq!(reason, crate::DeadLetterReason::MailboxFull);
+            self.0.lock().unwrap().push("dropped");
+        }
+    }
+
+    struct FailingActor;
+
+    #[async_trait]
+    impl Actor<TestEvent> for FailingActor {
+        async fn pre_start(
+            &mut self,
+            _ctx: &mut ActorContext<TestEvent>,
+        ) -> Result<(), ActorError> {
+            Err(ActorError::CreateError("failing".to_string()))
+        }
+    }
+
+    /// Starts once, failing to start again when restarted or when replaced
+    /// by an instance that has started before.
+    #[derive(Default)]
+    struct OnceStartingActor {
+        started: bool,
+    }
+
+    #[async_trait]
+    impl Actor<TestEvent> for OnceStartingActor {
+        async fn pre_start(
+            &mut self,
+            _ctx: &mut ActorContext<TestEvent>,
+        ) -> Result<(), ActorError> {
+            if self.started {
+                return Err(ActorError::CreateError("started before".to_string()));
+            }
+            self.started = true;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_instrumentation() {
+        let instrumentation = RecordingInstrumentation::default();
+        let config = SystemConfig::new("test")
+            .with_mailbox_capacity(1)
+            .with_instrumentation(instrumentation.clone());
+        let system = ActorSystem::<TestEvent>::from_config(config);
+
+        let actor_ref = system
+            .create_actor("test-actor", TestActor::default())
+            .await
+            .unwrap();
+        actor_ref.suspend().await.unwrap();
+        actor_ref.tell(TestMessage(1)).unwrap();
+        assert!(actor_ref.tell(TestMessage(2)).is_err());
+        actor_ref.resume().unwrap();
+        assert_eq!(actor_ref.ask(TestMessage(3)).await.unwrap(), 2);
+
+        let failing = system.create_actor("failing", FailingActor).await.unwrap();
+        while !failing.is_closed() {
+            tokio::task::yield_now().await;
+        }
+
+        let events = instrumentation.0.lock().unwrap().clone();
+        let count = |name| events.iter().filter(|event| **event == name).count();
+        assert_eq!(count("enqueued"), 2);
+        assert_eq!(count("dequeued"), 2);
+        assert_eq!(count("handled"), 2);
+        assert_eq!(count("dropped"), 1);
+        assert_eq!(count("failed"), 1);
+    }
+
+    #[cfg(feature = "prometheus")]
+    #[tokio::test]
+    async fn metrics_text() {
+        let instrumentation = RecordingInstrumentation::default();
+        let config = SystemConfig::new("test")
+            .with_mailbox_capacity(1)
+            .with_instrumentation(instrumentation.clone());
+        let system = ActorSystem::<TestEvent>::from_config(config);
+
+        let actor_ref = system
+            .create_actor("test-actor", TestActor::default())
+            .await
+            .unwrap();
+        actor_ref.suspend().await.unwrap();
+        actor_ref.tell(TestMessage(1)).unwrap();
+        assert!(actor_ref.tell(TestMessage(2)).is_err());
+        let text = system.metrics_text().await;
+        assert!(text.contains("# TYPE tta_actors gauge\ntta_actors{system=\"test\"} 1\n"));
+        assert!(
+            text.contains("tta_mailbox_messages{system=\"test\",actor=\"/user/test-actor\"} 1\n")
+        );
+        assert!(
+            text.contains("tta_dead_letters_total{system=\"test\",actor=\"/user/test-actor\"} 1\n")
+        );
+
+        actor_ref.resume().unwrap();
+        assert_eq!(actor_ref.ask(TestMessage(3)).await.unwrap(), 2);
+        let text = system.metrics_text().await;
+        assert!(
+            text.contains("tta_mailbox_messages{system=\"test\",actor=\"/user/test-actor\"} 0\n")
+        );
+        assert!(text.contains(
+            "tta_handler_duration_seconds_bucket{system=\"test\",actor=\"/user/test-actor\",le=\"+Inf\"} 2\n"
+        ));
+        assert!(text.contains(
+            "tta_handler_duration_seconds_count{system=\"test\",actor=\"/user/test-actor\"} 2\n"
+        ));
+        // The registered instrumentation still sees everything
+        let events = instrumentation.0.lock().unwrap().clone();
+        assert_eq!(
+            events.iter().filter(|event| **event == "handled").count(),
+            2
+        );
+
+        system.stop_actor(actor_ref.path()).await;
+        let text = system.metrics_text().await;
+        assert!(text.contains("tta_actors{system=\"test\"} 0\n"));
+        assert!(!text.contains("/user/test-actor"));
+    }
+
+    #[cfg(feature = "inspect")]
+    #[tokio::test]
+    async fn actor_peek_mailbox() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let actor_ref = system
+            .create_actor("test-actor", TestActor::default())
+            .await
+            .unwrap();
+        assert!(actor_ref.peek_mailbox(10).is_empty());
+
+        actor_ref.suspend().await.unwrap();
+        for index in 0..3 {
+            actor_ref.tell(TestMessage(index)).unwrap();
+        }
+        let queued = actor_ref.peek_mailbox(2);
+        assert_eq!(queued.len(), 2);
+        assert!(queued[0].message_type.ends_with("TestMessage"));
+        assert!(queued[0].enqueued_at <= queued[1].enqueued_at);
+
+        actor_ref.resume().unwrap();
+        assert_eq!(actor_ref.ask(TestMessage(3)).await.unwrap(), 4);
+        assert!(actor_ref.peek_mailbox(10).is_empty());
+    }
+
+    struct PanickingActor;
+
+    impl Actor<TestEvent> for PanickingActor {}
+
+    #[async_trait]
+    impl Handler<TestEvent, TestMessage> for PanickingActor {
+        async fn handle(&mut self, msg: TestMessage, _ctx: &mut ActorContext<TestEvent>) -> usize {
+            panic!("cannot handle {}", msg.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_crash_report() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let mut reports = system.crash_reports();
+        let supervisor = system
+            .create_actor("supervisor", SupervisorActor::default())
+            .await
+            .unwrap();
+        let path = supervisor.path().clone() / "panicking";
+        let actor_ref = system
+            .create_actor_path(path.clone(), PanickingActor)
+            .await
+            .unwrap();
+
+        assert!(actor_ref.ask(TestMessage(7)).await.is_err());
+        let report = reports.recv().await.unwrap();
+        assert_eq!(report.path(), &path);
+        assert!(report.message_type().ends_with("TestMessage"));
+        assert_eq!(report.panic(), "cannot handle 7");
+
+        loop {
+            let failures = supervisor.ask(GetFailures).await.unwrap();
+            if !failures.is_empty() {
+                assert_eq!(failures, vec![path]);
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn panic_policy_shutdown() {
+        let config = SystemConfig::new("test").with_panic_policy(PanicPolicy::Shutdown);
+        assert_eq!(config.panic_policy(), PanicPolicy::Shutdown);
+        let system = ActorSystem::<TestEvent>::from_config(config);
+        let bystander = system
+            .create_actor("bystander", TestActor::default())
+            .await
+            .unwrap();
+        let actor_ref = system
+            .create_actor("panicking", PanickingActor)
+            .await
+            .unwrap();
+
+        assert!(actor_ref.ask(TestMessage(7)).await.is_err());
+        // The buses of the system are closed once it shut down
+        let shut_down = async {
+            while system.try_publish(TestEvent("late".to_string())).is_ok() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(1), shut_down)
+            .await
+            .unwrap();
+        assert!(bystander.is_closed());
+    }
+
+    #[cfg(feature = "chaos")]
+    #[tokio::test]
+    async fn actor_chaos() {
+        let chaos = crate::ChaosConfig::new(1).with_drops(1.0);
+        let system =
+            ActorSystem::<TestEvent>::from_config(SystemConfig::new("test").with_chaos(chaos));
+        let mut messages = system.system_messages();
+        let actor_ref = system
+            .create_actor("dropping", TestActor::default())
+            .await
+            .unwrap();
+        assert!(actor_ref.ask(TestMessage(1)).await.is_err());
+        loop {
+            if let SystemMessage::DeadLetter(letter) = messages.recv().await.unwrap() {
+                assert_eq!(letter.reason(), crate::DeadLetterReason::Injected);
+                break;
+            }
+        }
+
+        let chaos = crate::ChaosConfig::new(1).with_failures(1.0);
+        let system =
+            ActorSystem::<TestEvent>::from_config(SystemConfig::new("test").with_chaos(chaos));
+        let mut reports = system.crash_reports();
+        let actor_ref = system
+            .create_actor("failing", TestActor::default())
+            .await
+            .unwrap();
+        assert!(actor_ref.ask(TestMessage(1)).await.is_err());
+        let report = reports.recv().await.unwrap();
+        assert_eq!(report.path(), actor_ref.path());
+        assert_eq!(report.panic(), "injected failure");
+    }
+
+    /// Asks a [`TestActor`] on behalf of others, piping the responses back to
+    /// itself.
+    struct ForwardActor {
+        target: ActorRef<TestEvent, TestActor>,
+        piped: Vec<usize>,
+    }
+
+    impl Actor<TestEvent> for ForwardActor {}
+
+    #[derive(Clone, Debug)]
+    struct AskSelf;
+
+    impl Message for AskSelf {
+        type Response = bool;
+    }
+
+    #[derive(Clone, Debug)]
+    struct Forward(usize);
+
+    impl Message for Forward {
+        type Response = ();
+    }
+
+    #[derive(Clone, Debug)]
+    struct Piped(usize);
+
+    impl Message for Piped {
+        type Response = ();
+    }
+
+    #[derive(Clone, Debug)]
+    struct GetPiped;
+
+    impl Message for GetPiped {
+        type Response = Vec<usize>;
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, AskSelf> for ForwardActor {
+        async fn handle(&mut self, _msg: AskSelf, ctx: &mut ActorContext<TestEvent>) -> bool {
+            let me = ctx.system.get_actor::<Self>(&ctx.path).await.unwrap();
+            matches!(ctx.ask(&me, GetPiped).await, Err(ActorError::SelfAsk(_)))
+        }
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, Forward> for ForwardActor {
+        async fn handle(&mut self, msg: Forward, ctx: &mut ActorContext<TestEvent>) {
+            let me = ctx.system.get_actor::<Self>(&ctx.path).await.unwrap();
+            let target = self.target.clone();
+            ctx.pipe_to(
+                async move { Piped(target.ask(TestMessage(msg.0)).await.unwrap()) },
+                &me,
+            );
+        }
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, Piped> for ForwardActor {
+        async fn handle(&mut self, msg: Piped, _ctx: &mut ActorContext<TestEvent>) {
+            self.piped.push(msg.0);
+        }
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, GetPiped> for ForwardActor {
+        async fn handle(
+            &mut self,
+            _msg: GetPiped,
+            _ctx: &mut ActorContext<TestEvent>,
+        ) -> Vec<usize> {
+            self.piped.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_context_ask() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let target = system
+            .create_actor("target", TestActor::default())
+            .await
+            .unwrap();
+        let forward = ForwardActor {
+            target: target.clone(),
+            piped: vec![],
+        };
+        let actor_ref = system.create_actor("forward", forward).await.unwrap();
+
+        assert!(actor_ref.ask(AskSelf).await.unwrap());
+
+        actor_ref.tell(Forward(1)).unwrap();
+        actor_ref.tell(Forward(1)).unwrap();
+        loop {
+            let mut piped = actor_ref.ask(GetPiped).await.unwrap();
+            if piped.len() == 2 {
+                piped.sort_unstable();
+                assert_eq!(piped, vec![1, 2]);
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[derive(Default)]
+    struct BlobActor {
+        received: usize,
+    }
+
+    impl Actor<TestEvent> for BlobActor {}
+
+    #[derive(Clone, Debug)]
+    struct Blob(Vec<u8>);
+
+    impl Message for Blob {
+        type Response = usize;
+
+        fn size_hint(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, Blob> for BlobActor {
+        async fn handle(&mut self, msg: Blob, _ctx: &mut ActorContext<TestEvent>) -> usize {
+            self.received += msg.0.len();
+            self.received
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_mailbox_bytes() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let actor_ref = system
+            .create_actor_with_config(
+                "blob-actor",
+                BlobActor::default(),
+                ActorConfig::default().with_mailbox_bytes(1000),
+            )
+            .await
+            .unwrap();
+
+        actor_ref.suspend().await.unwrap();
+        actor_ref.tell(Blob(vec![0; 600])).unwrap();
+        assert!(matches!(
+            actor_ref.tell(Blob(vec![0; 600])),
+            Err(ActorError::MailboxFull)
+        ));
+        actor_ref.tell(Blob(vec![0; 300])).unwrap();
+
+        // An ask waits until the mailbox has room again
+        let asking = actor_ref.clone();
+        let asked = tokio::spawn(async move { asking.ask(Blob(vec![0; 600])).await.unwrap() });
+        tokio::time::sleep(tokio::time::Duration::from_millis(20)).await;
+        assert!(!asked.is_finished());
+        actor_ref.resume().unwrap();
+        assert_eq!(asked.await.unwrap(), 1500);
+
+        // A message larger than the limit still fits an empty mailbox
+        assert_eq!(actor_ref.ask(Blob(vec![0; 2000])).await.unwrap(), 3500);
+    }
+
+    #[tokio::test]
+    async fn namespaces() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let tenant = system.namespace("tenant-a").unwrap();
+        let other = system.namespace("tenant-b").unwrap();
+        tenant.set_quotas(Quotas::default().with_max_actors(2));
+
+        let first = tenant
+            .create_actor("first", OtherActor::default())
+            .await
+            .unwrap();
+        assert_eq!(first.path(), &ActorPath::from("/user/tenant-a/first"));
+        // The child of the first actor counts as well
+        while tenant.actor_paths().await.len() < 2 {
+            tokio::task::yield_now().await;
+        }
+        let exceeded = tenant.create_actor("second", TestActor::default()).await;
+        assert!(matches!(exceeded, Err(ActorError::QuotaExceeded { .. })));
+        other
+            .create_actor("first", TestActor::default())
+            .await
+            .unwrap();
+
+        let result = tenant
+            .tell_matching::<TestActor, _>("*/child", TestMessage(1))
+            .await;
+        assert_eq!(
+            result.succeeded,
+            vec![ActorPath::from("/user/tenant-a/first/child")]
+        );
+
+        tenant.shutdown().await;
+        assert!(tenant.actor_paths().await.is_empty());
+        assert_eq!(
+            other.actor_paths().await,
+            vec![ActorPath::from("/user/tenant-b/first")]
+        );
+
+        let bounded = system.namespace("bounded").unwrap();
+        bounded.set_quotas(Quotas::default().with_max_mailbox_capacity(10));
+        let unbounded = bounded
+            .create_actor("unbounded", TestActor::default())
+            .await;
+        assert!(matches!(unbounded, Err(ActorError::QuotaExceeded { .. })));
+        let config = ActorConfig::default().with_mailbox_capacity(6);
+        bounded
+            .create_actor_with_config("first", TestActor::default(), config.clone())
+            .await
+            .unwrap();
+        let exceeded = bounded
+            .create_actor_with_config("second", TestActor::default(), config)
+            .await;
+        assert!(matches!(exceeded, Err(ActorError::QuotaExceeded { .. })));
+    }
+
+    #[tokio::test]
+    async fn quotas() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let mut events = system.quota_events();
+        let scope = QuotaScope::Label("tenant".into(), "acme".into());
+        system.set_quotas(scope.clone(), Quotas::default().with_max_actors(1));
+        assert_eq!(
+            system.quotas(&scope),
+            Some(Quotas::default().with_max_actors(1))
+        );
+
+        let config = ActorConfig::default().with_label("tenant", "acme");
+        system
+            .create_actor_with_config("first", TestActor::default(), config.clone())
+            .await
+            .unwrap();
+        let exceeded = system
+            .create_actor_with_config("second", TestActor::default(), config)
+            .await;
+        assert!(matches!(
+            exceeded,
+            Err(ActorError::QuotaExceeded {
+                quota: Quota::Actors,
+                ..
+            })
+        ));
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.scope, scope);
+        assert_eq!(event.quota, Quota::Actors);
+        assert_eq!(event.path, ActorPath::from("/user/second"));
+        system
+            .create_actor("unlabeled", TestActor::default())
+            .await
+            .unwrap();
+
+        let tenant = system.namespace("tenant-b").unwrap();
+        tenant.set_quotas(Quotas::default().with_max_messages_per_second(2));
+        let actor_ref = tenant
+            .create_actor("actor", TestActor::default())
+            .await
+            .unwrap();
+        actor_ref.tell(TestMessage(1)).unwrap();
+        actor_ref.tell(TestMessage(1)).unwrap();
+        let exceeded = actor_ref.tell(TestMessage(1));
+        assert!(matches!(
+            exceeded,
+            Err(ActorError::QuotaExceeded {
+                quota: Quota::MessageRate,
+                ..
+            })
+        ));
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.scope, QuotaScope::Namespace(tenant.path().clone()));
+        assert_eq!(event.quota, Quota::MessageRate);
+    }
+
+    #[tokio::test]
+    async fn bridge() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct OtherEvent(usize);
+        impl SystemEvent for OtherEvent {}
+
+        struct Echo;
+        impl Actor<OtherEvent> for Echo {}
+        #[async_trait]
+        impl Handler<OtherEvent, TestMessage> for Echo {
+            async fn handle(
+                &mut self,
+                msg: TestMessage,
+                _ctx: &mut ActorContext<OtherEvent>,
+            ) -> usize {
+                msg.0
+            }
+        }
+
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let other = ActorSystem::new("other", EventBus::<OtherEvent>::new(1000));
+        let bridge = system.bridge(&other);
+        bridge.forward_events(|TestEvent(text)| Some(OtherEvent(text.len())));
+        bridge.forward_events_back(|OtherEvent(len)| {
+            (len > 10).then(|| TestEvent(format!("long {}", len)))
+        });
+        let mut events = system.events();
+        let mut other_events = other.events();
+
+        system.publish(TestEvent("hello".into()));
+        assert_eq!(other_events.recv().await.unwrap(), OtherEvent(5));
+        // The forwarded event is received locally as well
+        assert_eq!(events.recv().await.unwrap().0, "hello");
+        other.publish(OtherEvent(20));
+        assert_eq!(events.recv().await.unwrap().0, "long 20");
+        assert_eq!(other_events.recv().await.unwrap(), OtherEvent(20));
+        // Forwarded again, but mapped to None on the way back
+        assert_eq!(other_events.recv().await.unwrap(), OtherEvent(7));
+
+        let actor_ref = other.create_actor("actor", Echo).await.unwrap();
+        let path = actor_ref.path().clone();
+        let remote = bridge.get_remote_actor::<Echo>(&path).await.unwrap();
+        assert_eq!(remote.ask(TestMessage(7)).await.unwrap(), 7);
+        assert!(bridge.get_local_actor::<TestActor>(&path).await.is_none());
+
+        bridge.clone().close();
+        assert!(bridge.is_closed());
+        tokio::task::yield_now().await;
+        system.publish(TestEvent("dropped".into()));
+        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+        assert!(other_events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn events_mapped() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let mut lengths = system.events_mapped(|TestEvent(text)| Some(text.len()));
+        let adapter =
+            system.event_adapter(|TestEvent(text)| text.strip_prefix("!").map(String::from));
+        let mut commands = adapter.clone().subscribe();
+
+        system.publish(TestEvent("hello".into()));
+        system.publish(TestEvent("!stop".into()));
+        assert_eq!(lengths.recv().await.unwrap(), 5);
+        assert_eq!(lengths.recv().await.unwrap(), 5);
+        assert_eq!(commands.recv().await.unwrap(), "stop");
+
+        let mut late = adapter.subscribe();
+        system.publish(TestEvent("!go".into()));
+        assert_eq!(late.recv().await.unwrap(), "go");
+        assert_eq!(commands.recv().await.unwrap(), "go");
+    }
+
+    #[tokio::test]
+    async fn generic_actor_publisher() {
+        #[derive(Clone, Debug)]
+        struct Tick(usize);
+
+        impl From<Tick> for TestEvent {
+            fn from(Tick(count): Tick) -> Self {
+                TestEvent(format!("tick {}", count))
+            }
+        }
+
+        #[derive(Default)]
+        struct Ticker(usize);
+
+        impl<E: SystemEvent + From<Tick>> Actor<E> for Ticker {}
+
+        #[async_trait]
+        impl<E: SystemEvent + From<Tick>> Handler<E, TestMessage> for Ticker {
+            async fn handle(&mut self, msg: TestMessage, ctx: &mut ActorContext<E>) -> usize {
+                self.0 += msg.0;
+                ctx.publisher().publish(Tick(self.0));
+                self.0
+            }
+        }
+
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let mut events = system.events();
+        let ticker = system
+            .create_actor("ticker", Ticker::default())
+            .await
+            .unwrap();
+        assert_eq!(ticker.ask(TestMessage(2)).await.unwrap(), 2);
+        assert_eq!(events.recv().await.unwrap().0, "tick 2");
+
+        let publisher = system.publisher_with(|count: usize| TestEvent(count.to_string()));
+        assert_eq!(publisher.clone().publish(5), 1);
+        assert_eq!(events.recv().await.unwrap().0, "5");
+    }
+
+    #[tokio::test]
+    async fn logger() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let logger = system.start_logger().await.unwrap();
+        assert_eq!(logger.path(), &ActorPath::from("/system/logger"));
+        assert!(system.start_logger().await.is_err());
+
+        let actor_ref = system
+            .create_actor("panicking", PanickingActor)
+            .await
+            .unwrap();
+        assert!(actor_ref.ask(TestMessage(1)).await.is_err());
+
+        system.stop_actor(logger.path()).await;
+        assert!(system.start_logger().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn actor_requester() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let actor_ref = system
+            .create_actor("test-actor", TestActor::default())
+            .await
+            .unwrap();
+        let requester = actor_ref.requester::<TestMessage>();
+        assert_eq!(requester.path(), actor_ref.path());
+        assert_eq!(requester.ask(TestMessage(1)).await.unwrap(), 1);
+
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let requester = requester.clone();
+                tokio::spawn(async move {
+                    for _ in 0..10 {
+                        requester.ask(TestMessage(1)).await.unwrap();
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(requester.ask(TestMessage(1)).await.unwrap(), 82);
+
+        system.stop_subtree(actor_ref.path()).await;
+        assert!(requester.ask(TestMessage(1)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn actor_stop_when_unreferenced() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let config = ActorConfig::default().with_stop_when_unreferenced();
+        let actor_ref = system
+            .create_actor_with_config("session", TestActor::default(), config)
+            .await
+            .unwrap();
+        let path = actor_ref.path().clone();
+
+        // A reference looked up through the system keeps the actor running
+        let looked_up = system.get_actor::<TestActor>(&path).await.unwrap();
+        drop(actor_ref);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(looked_up.ask(TestMessage(1)).await.unwrap(), 1);
+
+        let stopped = looked_up.control().cancellation_token().clone();
+        drop(looked_up);
+        tokio::time::timeout(Duration::from_secs(1), stopped.cancelled())
+            .await
+            .unwrap();
+        assert!(system.get_actor::<TestActor>(&path).await.is_none());
+
+        // Actors without the setting are kept running by the registry
+        let actor_ref = system
+            .create_actor("kept", TestActor::default())
+            .await
+            .unwrap();
+        let path = actor_ref.path().clone();
+        drop(actor_ref);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(system.get_actor::<TestActor>(&path).await.is_some());
+    }
+
+    #[derive(Clone, Debug)]
+    struct ThreadName;
+
+    impl Message for ThreadName {
+        type Response = Option<String>;
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, ThreadName> for OtherActor {
+        async fn handle(
+            &mut self,
+            _msg: ThreadName,
+            _ctx: &mut ActorContext<TestEvent>,
+        ) -> Option<String> {
+            std::thread::current().name().map(String::from)
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_dedicated_thread() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let config = ActorConfig::default().with_dedicated_thread();
+        assert!(config.dedicated_thread());
+        let actor_ref = system
+            .create_actor_with_config("dedicated", OtherActor::default(), config)
+            .await
+            .unwrap();
+        assert_eq!(
+            actor_ref.ask(ThreadName).await.unwrap().as_deref(),
+            Some("actor /user/dedicated")
+        );
+
+        // The child created in pre_start runs on the shared runtime
+        let child_path = actor_ref.path().clone() / "child";
+        let child = loop {
+            match system.get_actor::<TestActor>(&child_path).await {
+                Some(child) => break child,
+                None => tokio::task::yield_now().await,
+            }
+        };
+        assert_eq!(child.ask(TestMessage(1)).await.unwrap(), 1);
+
+        system.stop_subtree(actor_ref.path()).await;
+        assert!(actor_ref.ask(ThreadName).await.is_err());
+        assert!(system.get_actor::<TestActor>(&child_path).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn create_actors() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let mut registry = system.registry_events();
+        let path = |path: &str| ActorPath::from(path);
+        let actor_refs = system
+            .create_actors(vec![
+                (path("/user/first"), TestActor::default()),
+                (path("/user/second"), TestActor::default()),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(actor_refs[0].path(), &ActorPath::from("/user/first"));
+        assert_eq!(actor_refs[1].ask(TestMessage(1)).await.unwrap(), 1);
+        assert_eq!(
+            registry.recv().await.unwrap(),
+            RegistryEvent::ActorRegistered(ActorPath::from("/user/first"))
+        );
+
+        let exists = system
+            .create_actors(vec![
+                (path("/user/third"), TestActor::default()),
+                (path("/user/first"), TestActor::default()),
+            ])
+            .await;
+        assert!(matches!(exists, Err(ActorError::Exists(_))));
+        let duplicate = system
+            .create_actors(vec![
+                (path("/user/third"), TestActor::default()),
+                (path("/user/third"), TestActor::default()),
+            ])
+            .await;
+        assert!(matches!(duplicate, Err(ActorError::Exists(_))));
+        for invalid in ["/system/third", "/user/$third"] {
+            let invalid = system
+                .create_actors(vec![
+                    (path("/user/third"), TestActor::default()),
+                    (path(invalid), TestActor::default()),
+                ])
+                .await;
+            assert!(matches!(invalid, Err(ActorError::CreateError(_))));
+        }
+        let third = ActorPath::from("/user/third");
+        assert!(system.get_actor::<TestActor>(&third).await.is_none());
+
+        assert_eq!(
+            registry.recv().await.unwrap(),
+            RegistryEvent::ActorRegistered(ActorPath::from("/user/second"))
+        );
+        assert!(registry.try_recv().is_err());
+
+        // Actors below other actors are created along with them
+        let nested = system
+            .create_actors(vec![
+                (path("/user/first/child"), TestActor::default()),
+                (path("/user/shard/entity"), TestActor::default()),
+            ])
+            .await
+            .unwrap();
+        assert!(nested[0].path().is_child_of(actor_refs[0].path()));
+        assert_eq!(nested[1].ask(TestMessage(1)).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn create_actor_ready() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let actor_ref = system
+            .create_actor_ready("test-actor", TestActor::default())
+            .await
+            .unwrap();
+        assert_eq!(actor_ref.ask(TestMessage(1)).await.unwrap(), 1);
+
+        let failing = system.create_actor_ready("failing", FailingActor).await;
+        assert!(matches!(failing, Err(ActorError::CreateError(ref reason)) if reason == "failing"));
+        assert!(system
+            .get_actor::<FailingActor>(&ActorPath::from("/user/failing"))
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn virtual_actors() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        system
+            .register_virtual("/user/counters", |path| {
+                let counter = path.key().parse().unwrap();
+                TestActor { counter }
+            })
+            .unwrap();
+
+        let path = ActorPath::from("/user/counters/10");
+        let counter = system.virtual_actor::<TestActor>(&path).await.unwrap();
+        assert_eq!(counter.ask(TestMessage(1)).await.unwrap(), 11);
+        let same = system.virtual_actor::<TestActor>(&path).await.unwrap();
+        assert_eq!(same, counter);
+        assert_eq!(same.ask(TestMessage(1)).await.unwrap(), 12);
+
+        let nested = ActorPath::from("/user/counters/10/nested");
+        let unknown = system.virtual_actor::<TestActor>(&nested).await;
+        assert!(matches!(unknown, Err(ActorError::NotFound(_))));
+        let path = ActorPath::from("/user/counters/20");
+        let wrong_type = system.virtual_actor::<OtherActor>(&path).await;
+        assert!(matches!(wrong_type, Err(ActorError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn virtual_actors_outside_user() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let registered = system.register_virtual("/system", |_| TestActor { counter: 0 });
+        assert!(matches!(registered, Err(ActorError::CreateError(_))));
+        let logger = system
+            .virtual_actor::<TestActor>(&ActorPath::from("/system/logger"))
+            .await;
+        assert!(logger.is_err());
+        assert!(system
+            .get_actor::<TestActor>(&ActorPath::from("/system/logger"))
+            .await
+            .is_none());
+
+        system
+            .register_virtual("/user", |_| TestActor { counter: 0 })
+            .unwrap();
+        let anonymous = system
+            .virtual_actor::<TestActor>(&ActorPath::from("/user/$anonymous"))
+            .await;
+        assert!(matches!(anonymous, Err(ActorError::CreateError(_))));
+        let named = system
+            .virtual_actor::<TestActor>(&ActorPath::from("/user/named"))
+            .await;
+        assert!(named.is_ok());
+    }
+
+    #[derive(Default)]
+    struct SupervisorActor {
+        escalate: bool,
+        failures: Vec<ActorPath>,
+    }
+
+    #[async_trait]
+    impl Actor<TestEvent> for SupervisorActor {
+        async fn child_failed(
+            &mut self,
+            _ctx: &mut ActorContext<TestEvent>,
+            failure: ChildFailed,
+        ) -> Result<(), ActorError> {
+            self.failures.push(failure.path);
+            if self.escalate {
+                Err(failure.reason)
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct GetFailures;
+
+    impl Message for GetFailures {
+        type Response = Vec<ActorPath>;
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, GetFailures> for SupervisorActor {
+        async fn handle(
+            &mut self,
+            _msg: GetFailures,
+            _ctx: &mut ActorContext<TestEvent>,
+        ) -> Vec<ActorPath> {
+            self.failures.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_child_failed_escalation() {
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+        let top = system
+            .create_actor("top", SupervisorActor::default())
+            .await
+            .unwrap();
+        let mid = system
+            .create_actor_path(
+                ActorPath::from("/user/top/mid"),
+                SupervisorActor {
+                    escalate: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        let leaf_path = ActorPath::from("/user/top/mid/leaf");
+        system
+            .create_actor_path(leaf_path.clone(), FailingActor)
+            .await
+            .unwrap();
+
+        let mut failures = Vec::new();
+        for _ in 0..100 {
+            failures = top.ask(GetFailures).await.unwrap();
+            if !failures.is_empty() {
+                break;
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(failures, vec![mid.path().clone()]);
+        assert!(system.get_actor::<FailingActor>(&leaf_path).await.is_none());
+        assert!(system
+            .get_actor::<SupervisorActor>(mid.path())
+            .await
+            .is_none());
+    }
+
+    /// Creates a supervisor with a child that starts once, which has a child
+    /// of its own.
+    async fn create_once_starting(
+        system: &ActorSystem<TestEvent>,
+    ) -> (
+        ActorRef<TestEvent, SupervisorActor>,
+        ActorRef<TestEvent, OnceStartingActor>,
+        ActorPath,
+    ) {
+        let top = system
+            .create_actor("top", SupervisorActor::default())
+            .await
+            .unwrap();
+        let mid = system
+            .create_actor_path(
+                ActorPath::from("/user/top/mid"),
+                OnceStartingActor::default(),
+            )
+            .await
+            .unwrap();
+        let leaf_path = ActorPath::from("/user/top/mid/leaf");
+        system
+            .create_actor_path(leaf_path.clone(), TestActor::default())
+            .await
+            .unwrap();
+        (top, mid, leaf_path)
+    }
+
+    async fn wait_for_failures(top: &ActorRef<TestEvent, SupervisorActor>) -> Vec<ActorPath> {
+        let mut failures = Vec::new();
+        for _ in 0..100 {
+            failures = top.ask(GetFailures).await.unwrap();
+            if !failures.is_empty() {
+                break;
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+        }
+        failures
+    }
+
+    #[tokio::test]
+    async fn actor_replace_failed() {
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+        let (top, mid, leaf_path) = create_once_starting(&system).await;
+
+        let replaced = system
+            .replace_actor(mid.path(), OnceStartingActor { started: true })
+            .await;
+        assert!(matches!(replaced, Err(ActorError::CreateError(_))));
+
+        assert_eq!(wait_for_failures(&top).await, vec![mid.path().clone()]);
+        assert!(system
+            .get_actor::<OnceStartingActor>(mid.path())
+            .await
+            .is_none());
+        assert!(system.get_actor::<TestActor>(&leaf_path).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn actor_replace_failed_recreated() {
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+        let (top, mid, _) = create_once_starting(&system).await;
+
+        // The actor is created again while the replacement is starting
+        let replacing = system.replace_actor(mid.path(), OnceStartingActor { started: true });
+        let recreating = async {
+            system.stop_actor(mid.path()).await;
+            system
+                .create_actor_path(mid.path().clone(), OnceStartingActor::default())
+                .await
+                .unwrap()
+        };
+        let (replaced, recreated) = tokio::join!(replacing, recreating);
+        assert!(replaced.is_err());
+
+        // The newer actor at the path is left alone
+        let current = system
+            .get_actor::<OnceStartingActor>(mid.path())
+            .await
+            .unwrap();
+        assert_eq!(current.incarnation(), recreated.incarnation());
+        assert!(top.ask(GetFailures).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn actor_restart_subtree_failed() {
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+        let (top, mid, leaf_path) = create_once_starting(&system).await;
+
+        let restarted = system.restart_subtree(mid.path()).await;
+        assert!(matches!(restarted, Err(ActorError::CreateError(_))));
+
+        assert_eq!(wait_for_failures(&top).await, vec![mid.path().clone()]);
+        assert!(system
+            .get_actor::<OnceStartingActor>(mid.path())
+            .await
+            .is_none());
+        assert!(system.get_actor::<TestActor>(&leaf_path).await.is_none());
+    }
+
+    struct WatchingActor {
+        target: ActorRef<TestEvent, TestActor>,
+    }
+
+    #[async_trait]
+    impl Actor<TestEvent> for WatchingActor {
+        async fn pre_start(&mut self, ctx: &mut ActorContext<TestEvent>) -> Result<(), ActorError> {
+            ctx.watch_with(&self.target, crate::OnTerminate::StopSelf);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_watch_stop_self() {
+        let bus = EventBus::<TestEvent>::new(1000);
+        let system = ActorSystem::new("test", bus);
+        let target = system
+            .create_actor("target", TestActor::default())
+            .await
+            .unwrap();
+        let watcher = system
+            .create_actor(
+                "watcher",
+                WatchingActor {
+                    target: target.clone(),
+                },
+            )
+            .await
+            .unwrap();
+        let bystander = system
+            .create_actor("bystander", TestActor::default())
+            .await
+            .unwrap();
+
+        system.stop_subtree(target.path()).await;
+        tokio::time::timeout(tokio::time::Duration::from_secs(1), async {
+            while !watcher.is_closed() {
+                tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(!bystander.is_closed());
+        assert!(system
+            .get_actor::<WatchingActor>(watcher.path())
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn actor_ask_timeouts() {
+        let config =
+            SystemConfig::new("test").with_ask_timeout(tokio::time::Duration::from_secs(60));
+        let system = ActorSystem::<TestEvent>::from_config(config);
+        let actor_ref = system
+            .create_actor_with_config(
+                "test-actor",
+                TestActor::default(),
+                ActorConfig::default().with_ask_timeout(tokio::time::Duration::from_millis(50)),
+            )
+            .await
+            .unwrap();
+        actor_ref.suspend().await.unwrap();
+
+        let start = tokio::time::Instant::now();
+        let timeout = actor_ref.ask(TestMessage(1)).await;
+        assert!(matches!(timeout, Err(ActorError::Timeout)));
+        assert!(start.elapsed() < tokio::time::Duration::from_secs(60));
+
+        let short = tokio::time::Duration::from_millis(10);
+        let timeout = actor_ref
+            .ask_with_timeout(TestMessage(1), Some(short))
+            .await;
+        assert!(matches!(timeout, Err(ActorError::Timeout)));
+
+        actor_ref.resume().unwrap();
+        // the asks that timed out are still handled
+        let response = actor_ref.ask_with_timeout(TestMessage(1), None).await;
+        assert_eq!(response.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn actor_drain() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let mut events = system.events();
+        let actor_ref = system
+            .create_actor("test-actor", TestActor::default())
+            .await
+            .unwrap();
+
+        actor_ref.suspend().await.unwrap();
+        for _ in 0..3 {
+            actor_ref.tell(TestMessage(1)).unwrap();
+        }
+        let draining = actor_ref.clone();
+        let drained = tokio::spawn(async move { draining.drain().await });
+        while !actor_ref.is_closed() {
+            tokio::task::yield_now().await;
+        }
+
+        let rejected = actor_ref.tell(TestMessage(1));
+        assert!(matches!(rejected, Err(ActorError::MailboxClosed(_))));
+
+        actor_ref.resume().unwrap();
+        drained.await.unwrap().unwrap();
+        for _ in 0..3 {
+            events.recv().await.unwrap();
+        }
+        assert!(events.try_recv().is_err());
+        assert!(system
+            .get_actor::<TestActor>(actor_ref.path())
+            .await
+            .is_none());
+
+        let stopped = actor_ref.drain().await;
+        assert!(matches!(stopped, Err(ActorError::Terminated(_, _))));
+    }
+
+    #[tokio::test]
+    async fn publish_async() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(2));
+        assert_eq!(
+            system.try_publish(TestEvent("nobody".to_string())).ok(),
+            Some(0)
+        );
+
+        let mut events = system.events();
+        for index in 0..2 {
+            assert_eq!(system.publish_async(TestEvent(index.to_string())).await, 1);
+        }
+        let publisher = system.clone();
+        let publishing =
+            tokio::spawn(async move { publisher.publish_async(TestEvent("2".to_string())).await });
+        tokio::time::sleep(tokio::time::Duration::from_millis(20)).await;
+        assert!(!publishing.is_finished());
+
+        for index in 0..3 {
+            let event = events.recv().await.unwrap();
+            assert_eq!(event.0, index.to_string());
+        }
+        assert_eq!(publishing.await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn group_events() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(10));
+        let mut all = system.events();
+        let first = system.group_events("workers");
+        let second = system.group_events("workers");
+        let other = system.group_events("auditors");
+
+        for index in 0..4 {
+            assert_eq!(
+                system.try_publish(TestEvent(index.to_string())).ok(),
+                Some(3)
+            );
+        }
+
+        let received = vec![
+            first.recv().await.unwrap().0,
+            second.recv().await.unwrap().0,
+            first.recv().await.unwrap().0,
+            second.recv().await.unwrap().0,
+        ];
+        assert_eq!(received, vec!["0", "1", "2", "3"]);
+        for index in 0..4 {
+            assert_eq!(all.recv().await.unwrap().0, index.to_string());
+            assert_eq!(other.recv().await.unwrap().0, index.to_string());
+        }
+
+        drop((first, second));
+        let rejoined = system.group_events("workers");
+        system.publish(TestEvent("4".to_string()));
+        assert_eq!(rejoined.recv().await.unwrap().0, "4");
+    }
+
+    #[derive(Default)]
+    struct SequenceActor {
+        received: Vec<(usize, usize)>,
+    }
+
+    impl Actor<TestEvent> for SequenceActor {}
+
+    #[derive(Clone, Debug)]
+    struct Sequenced {
+        producer: usize,
+        sequence: usize,
+    }
+
+    impl Message for Sequenced {
+        type Response = ();
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, Sequenced> for SequenceActor {
+        async fn handle(&mut self, msg: Sequenced, _ctx: &mut ActorContext<TestEvent>) {
+            self.received.push((msg.producer, msg.sequence));
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct GetSequence;
+
+    impl Message for GetSequence {
+        type Response = Vec<(usize, usize)>;
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, GetSequence> for SequenceActor {
+        async fn handle(
+            &mut self,
+            _msg: GetSequence,
+            _ctx: &mut ActorContext<TestEvent>,
+        ) -> Vec<(usize, usize)> {
+            self.received.clone()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn actor_shared_ref() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let actor_ref = system
+            .create_actor("test-actor", TestActor { counter: 0 })
+            .await
+            .unwrap();
+
+        // Concurrent asks through one reference, without cloning it
+        let (first, second) =
+            tokio::join!(actor_ref.ask(TestMessage(1)), actor_ref.ask(TestMessage(1)));
+        let mut responses = vec![first.unwrap(), second.unwrap()];
+        responses.sort_unstable();
+        assert_eq!(responses, vec![1, 2]);
+
+        let shared = Arc::new(actor_ref);
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let shared = shared.clone();
+                tokio::spawn(async move { shared.ask(TestMessage(1)).await.unwrap() })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(shared.ask(TestMessage(0)).await.unwrap(), 11);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn actor_per_sender_ordering() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let actor_ref = system
+            .create_actor_with_config(
+                "test-actor",
+                SequenceActor::default(),
+                ActorConfig::default().with_mailbox_capacity(2),
+            )
+            .await
+            .unwrap();
+
+        let producers: Vec<_> = (0..4)
+            .map(|producer| {
+                let actor_ref = actor_ref.clone();
+                tokio::spawn(async move {
+                    for sequence in 0..50 {
+                        let msg = Sequenced { producer, sequence };
+                        if sequence % 3 == 0 {
+                            actor_ref.ask(msg).await.unwrap();
+                        } else {
+                            actor_ref.ordered_tell(msg).await.unwrap();
+                        }
+                    }
+                })
+            })
+            .collect();
+        for producer in producers {
+            producer.await.unwrap();
+        }
+
+        let received = actor_ref.ask(GetSequence).await.unwrap();
+        assert_eq!(received.len(), 200);
+        for producer in 0..4 {
+            let sequences: Vec<usize> = received
+                .iter()
+                .filter(|(from, _)| *from == producer)
+                .map(|(_, sequence)| *sequence)
+                .collect();
+            assert_eq!(sequences, (0..50).collect::<Vec<_>>());
+        }
+    }
+
+    #[tokio::test]
+    async fn bulk_operations() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let mut events = system.events();
+        let workers = ActorPath::from("/user/workers");
+        for name in ["1", "2"] {
+            system
+                .create_actor_path(workers.clone() / name, TestActor::default())
+                .await
+                .unwrap();
+        }
+        system
+            .create_actor("other", TestActor::default())
+            .await
+            .unwrap();
+        let full = system
+            .create_actor_path_with_config(
+                workers.clone() / "3",
+                TestActor::default(),
+                ActorConfig::default().with_mailbox_capacity(1),
+            )
+            .await
+            .unwrap();
+        full.suspend().await.unwrap();
+        full.tell(TestMessage(1)).unwrap();
+
+        let result = system
+            .tell_matching::<TestActor, _>("/user/workers/*", TestMessage(1))
+            .await;
+        assert_eq!(
+            result.succeeded,
+            vec![workers.clone() / "1", workers.clone() / "2"]
+        );
+        assert_eq!(result.failed.len(), 1);
+        assert!(
+            matches!(result.failed[0], (ref path, ActorError::MailboxFull) if path == full.path())
+        );
+        for _ in 0..2 {
+            events.recv().await.unwrap();
+        }
+
+        let stopped = system.stop_actors_matching("/user/workers/*").await;
+        assert_eq!(stopped.len(), 3);
+        assert!(full.is_closed());
+        assert!(system
+            .get_actor::<TestActor>(&ActorPath::from("/user/other"))
+            .await
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn registry_events() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let mut events = system.registry_events();
+
+        let actor_ref = system
+            .create_actor("test-actor", OtherActor::default())
+            .await
+            .unwrap();
+        let child_path = actor_ref.path().clone() / "child";
+        while system.get_actor::<TestActor>(&child_path).await.is_none() {
+            tokio::task::yield_now().await;
+        }
+        system.stop_actor(actor_ref.path()).await;
+
+        let mut received = vec![];
+        for _ in 0..4 {
+            received.push(events.recv().await.unwrap());
+        }
+        assert_eq!(
+            received,
+            vec![
+                RegistryEvent::ActorRegistered(actor_ref.path().clone()),
+                RegistryEvent::ActorRegistered(child_path.clone()),
+                RegistryEvent::ActorUnregistered(child_path),
+                RegistryEvent::ActorUnregistered(actor_ref.path().clone()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn actor_ask_error_context() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let actor_ref = system
+            .create_actor_with_config(
+                "test-actor",
+                TestActor::default(),
+                ActorConfig::default().with_ask_timeout(tokio::time::Duration::from_millis(10)),
+            )
+            .await
+            .unwrap();
+
+        let doubled = actor_ref
+            .ask_map(TestMessage(1), |counter| counter * 2)
+            .await;
+        assert_eq!(doubled.unwrap(), 2);
+
+        actor_ref.suspend().await.unwrap();
+        match actor_ref.ask_traced(TestMessage(1)).await {
+            Err(crate::AskError {
+                path,
+                message_type,
+                elapsed,
+                cause,
+            }) => {
+                assert_eq!(&path, actor_ref.path());
+                assert!(message_type.ends_with("TestMessage"));
+                assert!(elapsed >= tokio::time::Duration::from_millis(10));
+                assert!(matches!(cause, ActorError::Timeout));
+            }
+            other => panic!("unexpected ask result: {:?}", other),
+        }
+        // Asks without tracing fail with the error itself
+        let timeout = actor_ref.ask(TestMessage(1)).await;
+        assert!(matches!(timeout, Err(ActorError::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn actor_try_tell_from_thread() {
+        let config = SystemConfig::new("test").with_mailbox_capacity(1);
+        let system = ActorSystem::<TestEvent>::from_config(config);
+        let actor_ref = system
+            .create_actor("test-actor", TestActor::default())
+            .await
+            .unwrap();
+        actor_ref.suspend().await.unwrap();
+
+        let sender = actor_ref.clone();
+        let results = std::thread::spawn(move || {
+            vec![
+                sender.try_tell(TestMessage(1)),
+                sender.try_tell(TestMessage(1)),
+            ]
+        })
+        .join()
+        .unwrap();
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(ActorError::MailboxFull)));
+
+        actor_ref.resume().unwrap();
+        assert_eq!(actor_ref.ask(TestMessage(1)).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn flapping_actors() {
+        let config =
+            SystemConfig::new("test").with_flap_detection(2, tokio::time::Duration::from_secs(60));
+        let system = ActorSystem::<TestEvent>::from_config(config);
+        let mut flapping = system.flapping_actors();
+
+        let actor_ref = system
+            .create_actor("flapping", TestActor::default())
+            .await
+            .unwrap();
+        assert!(system.restart_stats(actor_ref.path()).is_none());
+
+        for _ in 0..2 {
+            system.restart_subtree(actor_ref.path()).await.unwrap();
+        }
+        let stats = system.restart_stats(actor_ref.path()).unwrap();
+        assert_eq!(stats.restarts(), 2);
+        assert!(stats.last_restart().is_some());
+        assert!(flapping.try_recv().is_err());
+
+        system.restart_subtree(actor_ref.path()).await.unwrap();
+        let event = flapping.recv().await.unwrap();
+        assert_eq!(&event.path, actor_ref.path());
+        assert_eq!(event.restarts, 3);
+        assert_eq!(event.within, tokio::time::Duration::from_secs(60));
+
+        // Reported once per crossing of the limit
+        system.restart_subtree(actor_ref.path()).await.unwrap();
+        assert_eq!(
+            system.restart_stats(actor_ref.path()).unwrap().restarts(),
+            4
+        );
+        assert!(flapping.try_recv().is_err());
+    }
+
+    #[derive(Default)]
+    struct BatchWorker {
+        handled: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl Actor<TestEvent> for BatchWorker {}
+
+    #[async_trait]
+    impl Handler<TestEvent, TestMessage> for BatchWorker {
+        async fn handle(&mut self, msg: TestMessage, _ctx: &mut ActorContext<TestEvent>) -> usize {
+            tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+            self.handled.fetch_add(1, Ordering::SeqCst) + msg.0
+        }
+    }
+
+    #[tokio::test]
+    async fn wait_until_idle() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        system.wait_until_idle().await;
+
+        let handled = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        for name in ["slow-1", "slow-2"] {
+            let actor = BatchWorker {
+                handled: handled.clone(),
+            };
+            let actor_ref = system.create_actor(name, actor).await.unwrap();
+            for i in 0..3 {
+                actor_ref.tell(TestMessage(i)).unwrap();
+            }
+        }
+
+        system.wait_until_idle().await;
+        assert_eq!(handled.load(Ordering::SeqCst), 6);
+    }
+
+    /// Asks a [`TestActor`] from within its handler.
+    struct RelayActor {
+        target: ActorRef<TestEvent, TestActor>,
+    }
+
+    impl Actor<TestEvent> for RelayActor {}
+
+    #[async_trait]
+    impl Handler<TestEvent, TestMessage> for RelayActor {
+        async fn handle(&mut self, msg: TestMessage, _ctx: &mut ActorContext<TestEvent>) -> usize {
+            self.target.ask(msg).await.unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn message_policy() {
+        let policy = |delivery: &crate::Delivery<'_>| {
+            delivery.recipient.key() != "guarded"
+                || delivery
+                    .sender
+                    .is_some_and(|sender| sender.key() == "relay")
+        };
+        let config = SystemConfig::new("test").with_message_policy(policy);
+        let system = ActorSystem::<TestEvent>::from_config(config);
+        let dead_letters = system
+            .start_dead_letters(crate::DeadLetterConfig::new(10))
+            .await
+            .unwrap();
+        let guarded = system
+            .create_actor("guarded", TestActor::default())
+            .await
+            .unwrap();
+        let relay = system
+            .create_actor(
+                "relay",
+                RelayActor {
+                    target: guarded.clone(),
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(relay.ask(TestMessage(1)).await.unwrap(), 1);
+        match guarded.ask(TestMessage(2)).await {
+            Err(ActorError::Unauthorized { path, message_type }) => {
+                assert_eq!(&path, guarded.path());
+                assert_eq!(message_type, std::any::type_name::<TestMessage>());
+            }
+            other => panic!("expected an unauthorized ask, got {:?}", other),
+        }
+        assert!(guarded.tell(TestMessage(3)).is_err());
+
+        let letters = dead_letters.ask(crate::GetDeadLetters).await.unwrap();
+        let summary: Vec<_> = letters
+            .iter()
+            .map(|letter| {
+                assert_eq!(letter.recipient(), guarded.path());
+                (letter.payload::<TestMessage>().unwrap().0, letter.reason())
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (2, crate::DeadLetterReason::Unauthorized),
+                (3, crate::DeadLetterReason::Unauthorized),
+            ]
+        );
+    }
+
+    /// Tells who it was asked by, or asks its target to.
+    struct CallerActor {
+        target: Option<ActorRef<TestEvent, CallerActor>>,
+    }
+
+    impl Actor<TestEvent> for CallerActor {}
+
+    #[derive(Clone, Debug)]
+    struct WhoAmI;
+
+    impl Message for WhoAmI {
+        type Response = Option<String>;
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, WhoAmI> for CallerActor {
+        async fn handle(
+            &mut self,
+            msg: WhoAmI,
+            ctx: &mut ActorContext<TestEvent>,
+        ) -> Option<String> {
+            match &self.target {
+                Some(target) => target.ask(msg).await.unwrap(),
+                None => ctx.caller().map(|caller| caller.principal().to_string()),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn caller_propagation() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let back = system
+            .create_actor("back", CallerActor { target: None })
+            .await
+            .unwrap();
+        let front = system
+            .create_actor(
+                "front",
+                CallerActor {
+                    target: Some(back.clone()),
+                },
+            )
+            .await
+            .unwrap();
+
+        let alice = crate::Caller::new("alice").with_attribute("role", "admin");
+        let asked = alice.clone().scope(async {
+            assert_eq!(crate::Caller::current(), Some(alice.clone()));
+            front.ask(WhoAmI).await.unwrap()
+        });
+        assert_eq!(asked.await, Some("alice".to_string()));
+        assert_eq!(front.ask(WhoAmI).await.unwrap(), None);
+        assert_eq!(crate::Caller::current(), None);
+    }
+
+    /// Records the order in which actors started.
+    struct OrderedActor {
+        name: &'static str,
+        started: Arc<std::sync::Mutex<Vec<&'static str>>>,
+    }
+
+    #[async_trait]
+    impl Actor<TestEvent> for OrderedActor {
+        async fn pre_start(
+            &mut self,
+            _ctx: &mut ActorContext<TestEvent>,
+        ) -> Result<(), ActorError> {
+            self.started.lock().unwrap().push(self.name);
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, GetStarts> for OrderedActor {
+        async fn handle(&mut self, _msg: GetStarts, _ctx: &mut ActorContext<TestEvent>) -> usize {
+            self.started.lock().unwrap().len()
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_dependencies() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let started = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let ordered = |name| OrderedActor {
+            name,
+            started: started.clone(),
+        };
+        let db = ActorPath::from("/user/db");
+
+        let config = ActorConfig::default().with_dependency(db.clone());
+        let cache = system
+            .create_actor_with_config("cache", ordered("cache"), config)
+            .await
+            .unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(20)).await;
+        assert!(started.lock().unwrap().is_empty());
+
+        system.create_actor("db", ordered("db")).await.unwrap();
+        assert_eq!(cache.ask(GetStarts).await.unwrap(), 2);
+        assert_eq!(*started.lock().unwrap(), vec!["db", "cache"]);
+
+        let config = ActorConfig::default().with_dependency(ActorPath::from("/user/b"));
+        system
+            .create_actor_with_config("a", ordered("a"), config)
+            .await
+            .unwrap();
+        let config = ActorConfig::default().with_dependency(ActorPath::from("/user/a"));
+        let cycle = system
+            .create_actor_with_config("b", ordered("b"), config)
+            .await;
+        assert!(matches!(cycle, Err(ActorError::CreateError(_))));
+        assert_eq!(
+            system.stop_actors_matching("/user/a").await,
+            vec![ActorPath::from("/user/a")]
+        );
+        assert_eq!(started.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn actor_props() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let config = ActorConfig::default().with_label("role", "counter");
+        let props = Props::new(TestActor::default).with_config(config);
+        let fresh = system
+            .create_actor_from_props("fresh", props.clone())
+            .await
+            .unwrap();
+        let kept = system
+            .create_actor("kept", TestActor::default())
+            .await
+            .unwrap();
+        for actor_ref in [&fresh, &kept] {
+            actor_ref.tell(TestMessage(1)).unwrap();
+            assert_eq!(actor_ref.ask(TestMessage(2)).await.unwrap(), 2);
+        }
+
+        system.restart_subtree(fresh.path()).await.unwrap();
+        system.restart_subtree(kept.path()).await.unwrap();
+        assert_eq!(fresh.ask(TestMessage(3)).await.unwrap(), 1);
+        assert_eq!(kept.ask(TestMessage(3)).await.unwrap(), 3);
+        assert_eq!(
+            system
+                .actors_with_label::<TestActor>("role", "counter")
+                .await,
+            vec![fresh.clone()]
+        );
+    }
+
+    #[tokio::test]
+    async fn event_bus_stats() {
+        let config = SystemConfig::new("test")
+            .with_event_bus_capacity(2)
+            .with_bus_stats_interval(tokio::time::Duration::from_millis(10));
+        let system = ActorSystem::<TestEvent>::from_config(config);
+        let mut reports = system.bus_stats_events();
+
+        system.publish(TestEvent("unheard".to_string()));
+        let _events = system.events();
+        for i in 0..3 {
+            system.publish(TestEvent(i.to_string()));
+        }
+        let stats = system.event_bus_stats();
+        assert_eq!(stats.subscribers, 1);
+        assert_eq!(stats.capacity, 2);
+        assert_eq!(stats.published, 4);
+        assert_eq!(stats.unheard, 1);
+        assert_eq!(stats.overwritten, 1);
+
+        let report = reports.recv().await.unwrap();
+        assert_eq!(report.published, 4);
+        assert!(report.timestamp > stats.timestamp);
+        system.publish(TestEvent("later".to_string()));
+        assert!(system.event_bus_stats().publish_rate(&report) > 0.0);
+    }
+
+    struct StubbornActor;
+
+    #[async_trait]
+    impl Actor<TestEvent> for StubbornActor {
+        async fn post_stop(&mut self, _ctx: &mut ActorContext<TestEvent>) {
+            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn system_shutdown_within() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(10));
+        system
+            .create_actor("test", TestActor::default())
+            .await
+            .unwrap();
+        let stubborn = system
+            .create_actor("stubborn", StubbornActor)
+            .await
+            .unwrap();
+
+        let started = tokio::time::Instant::now();
+        let report = system
+            .shutdown_within(tokio::time::Duration::from_millis(50))
+            .await;
+        assert!(started.elapsed() < tokio::time::Duration::from_secs(1));
+        assert!(!report.is_clean());
+        assert_eq!(report.timed_out, vec![stubborn.path().clone()]);
+        assert!(system.bus.is_closed());
+    }
+
+    #[tokio::test]
+    async fn system_shutdown_within_siblings() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(10));
+        // Stopped after the stubborn actor, as children are stopped before
+        // their parents in reverse order of their paths
+        for name in ["a-idle", "b-idle"] {
+            system
+                .create_actor(name, TestActor::default())
+                .await
+                .unwrap();
+        }
+        let stubborn = system
+            .create_actor("c-stubborn", StubbornActor)
+            .await
+            .unwrap();
+
+        let report = system
+            .shutdown_within(tokio::time::Duration::from_millis(100))
+            .await;
+        assert_eq!(report.timed_out, vec![stubborn.path().clone()]);
+    }
+
+    struct FanOutActor {
+        finished: Arc<AtomicU64>,
+    }
+
+    impl Actor<TestEvent> for FanOutActor {}
+
+    /// Spawns a task per delay, each sleeping for its delay in milliseconds.
+    #[derive(Clone, Debug)]
+    struct FanOut(Vec<u64>);
+
+    impl Message for FanOut {
+        type Response = Result<Vec<u64>, ActorError>;
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, FanOut> for FanOutActor {
+        async fn handle(
+            &mut self,
+            msg: FanOut,
+            ctx: &mut ActorContext<TestEvent>,
+        ) -> Result<Vec<u64>, ActorError> {
+            ctx.scope(|scope| {
+                for delay in msg.0 {
+                    let finished = self.finished.clone();
+                    scope.spawn(async move {
+                        tokio::time::sleep(tokio::time::Duration::from_millis(delay)).await;
+                        finished.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                        delay
+                    });
+                }
+            })
+            .await
+        }
+    }
+
+    #[tokio::test]
+    async fn handler_task_scope() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(10));
+        let finished = Arc::new(AtomicU64::new(0));
+        let actor = FanOutActor {
+            finished: finished.clone(),
+        };
+        let actor_ref = system.create_actor("fan-out", actor).await.unwrap();
+
+        let outputs = actor_ref.ask(FanOut(vec![30, 10, 20])).await.unwrap();
+        assert_eq!(outputs.unwrap(), vec![30, 10, 20]);
+        assert_eq!(finished.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+        // Stopping the actor aborts the tasks of the scope
+        let asking = actor_ref.clone();
+        let pending = tokio::spawn(async move { asking.ask(FanOut(vec![10, 500])).await });
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+        system.stop_actors_matching("/user/fan-out").await;
+        assert!(matches!(
+            pending.await.unwrap().unwrap(),
+            Err(ActorError::Terminated(_, _))
+        ));
+        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        assert_eq!(finished.load(std::sync::atomic::Ordering::SeqCst), 4);
+    }
+
+    #[derive(Clone, Default)]
+    struct YieldRecorder(Arc<std::sync::Mutex<Vec<usize>>>);
+
+    impl crate::MailboxInstrumentation for YieldRecorder {
+        fn yielded(&self, _path: &ActorPath, messages: usize) {
+            self.0.lock().unwrap().push(messages);
+        }
+    }
+
+    struct BusyActor;
+
+    impl Actor<TestEvent> for BusyActor {}
+
+    #[async_trait]
+    impl Handler<TestEvent, TestMessage> for BusyActor {
+        async fn handle(&mut self, msg: TestMessage, _ctx: &mut ActorContext<TestEvent>) -> usize {
+            // Keeps the runner busy without giving the runtime a chance to run
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            msg.0
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_time_slice() {
+        let yields = YieldRecorder::default();
+        let config = SystemConfig::new("test").with_instrumentation(yields.clone());
+        let system = ActorSystem::<TestEvent>::from_config(config);
+        let config = ActorConfig::default().with_time_slice(tokio::time::Duration::from_millis(12));
+        assert_eq!(
+            config.time_slice(),
+            Some(tokio::time::Duration::from_millis(12))
+        );
+        let actor_ref = system
+            .create_actor_with_config("busy", BusyActor, config)
+            .await
+            .unwrap();
+
+        actor_ref.suspend().await.unwrap();
+        for index in 0..8 {
+            actor_ref.tell(TestMessage(index)).unwrap();
+        }
+        actor_ref.resume().unwrap();
+        assert_eq!(actor_ref.ask(TestMessage(8)).await.unwrap(), 8);
+
+        let yields = yields.0.lock().unwrap().clone();
+        assert!(yields.len() >= 2, "yielded {:?}", yields);
+        assert!(yields.iter().all(|&messages| (1..9).contains(&messages)));
+    }
+
+    struct Candidate {
+        leases: Arc<crate::LocalLeases>,
+        leadership: Option<crate::Leadership>,
+    }
+
+    #[async_trait]
+    impl Actor<TestEvent> for Candidate {
+        async fn pre_start(&mut self, ctx: &mut ActorContext<TestEvent>) -> Result<(), ActorError> {
+            let ttl = tokio::time::Duration::from_millis(30);
+            self.leadership = Some(ctx.campaign("duty", self.leases.clone(), ttl));
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct IsLeader;
+
+    impl Message for IsLeader {
+        type Response = bool;
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, IsLeader> for Candidate {
+        async fn handle(&mut self, _msg: IsLeader, _ctx: &mut ActorContext<TestEvent>) -> bool {
+            self.leadership.as_ref().unwrap().is_leader()
+        }
+    }
+
+    #[tokio::test]
+    async fn leader_election() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(10));
+        let mut changes = system.leadership_changes();
+        let leases = Arc::new(crate::LocalLeases::new());
+        let mut candidates = vec![];
+        for name in ["a", "b"] {
+            let candidate = Candidate {
+                leases: leases.clone(),
+                leadership: None,
+            };
+            candidates.push(system.create_actor(name, candidate).await.unwrap());
+        }
+
+        let elected = changes.recv().await.unwrap();
+        assert!(elected.elected);
+        assert_eq!(elected.lease, "duty");
+        let mut leaders = vec![];
+        for candidate in &candidates {
+            if candidate.ask(IsLeader).await.unwrap() {
+                leaders.push(candidate.path().clone());
+            }
+        }
+        assert_eq!(leaders, vec![elected.path.clone()]);
+
+        // The other candidate takes over once the leader stops
+        system.stop_actors_matching(&elected.path.to_string()).await;
+        let resigned = changes.recv().await.unwrap();
+        assert_eq!(
+            (resigned.path.clone(), resigned.elected),
+            (elected.path.clone(), false)
+        );
+        let successor = tokio::time::timeout(tokio::time::Duration::from_secs(1), changes.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(successor.elected);
+        assert_ne!(successor.path, elected.path);
+        assert_eq!(
+            leases.holder("duty"),
+            Some(format!("{}:{}", std::process::id(), successor.path))
+        );
+    }
+
+    struct FinalizingActor {
+        finalized: Arc<AtomicU64>,
+    }
+
+    #[async_trait]
+    impl Actor<TestEvent> for FinalizingActor {
+        async fn pre_start(&mut self, ctx: &mut ActorContext<TestEvent>) -> Result<(), ActorError> {
+            let finalized = self.finalized.clone();
+            ctx.add_finalizer(move || {
+                finalized.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            });
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, TestMessage> for FinalizingActor {
+        async fn handle(&mut self, msg: TestMessage, _ctx: &mut ActorContext<TestEvent>) -> usize {
+            msg.0
+        }
+    }
+
+    fn finalized(finalized: &AtomicU64) -> u64 {
+        finalized.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn actor_finalizers_on_stop() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(10));
+        let count = Arc::new(AtomicU64::new(0));
+        let actor = FinalizingActor {
+            finalized: count.clone(),
+        };
+        let actor_ref = system.create_actor("finalizing", actor).await.unwrap();
+        assert_eq!(actor_ref.ask(TestMessage(1)).await.unwrap(), 1);
+        assert_eq!(finalized(&count), 0);
+
+        system.stop_actors_matching("/user/finalizing").await;
+        assert_eq!(finalized(&count), 1);
+    }
+
+    #[test]
+    fn actor_finalizers_on_abort() {
+        let count = Arc::new(AtomicU64::new(0));
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let actor = FinalizingActor {
+            finalized: count.clone(),
+        };
+        runtime.block_on(async {
+            let system = ActorSystem::new("test", EventBus::<TestEvent>::new(10));
+            let actor_ref = system.create_actor("finalizing", actor).await.unwrap();
+            assert_eq!(actor_ref.ask(TestMessage(1)).await.unwrap(), 1);
+        });
+        assert_eq!(finalized(&count), 0);
+
+        // Shutting down the runtime aborts the runner of the actor
+        drop(runtime);
+        assert_eq!(finalized(&count), 1);
+    }
+
+    #[tokio::test]
+    async fn event_buffering() {
+        let config = SystemConfig::new("test").with_event_buffering(2);
+        let system = ActorSystem::<TestEvent>::from_config(config);
+        for i in 0..3 {
+            assert_eq!(system.try_publish(TestEvent(i.to_string())).ok(), Some(0));
+        }
+
+        let mut first = system.events();
+        let mut second = system.events();
+        assert_eq!(system.try_publish(TestEvent("3".to_string())).ok(), Some(2));
+        for expected in ["1", "2", "3"] {
+            assert_eq!(first.recv().await.unwrap().0, expected);
+        }
+        assert_eq!(second.recv().await.unwrap().0, "3");
+
+        system.shutdown().await;
+        assert!(system.try_publish(TestEvent("4".to_string())).is_err());
+    }
+
+    #[tokio::test]
+    async fn named_subscribers() {
+        let config = SystemConfig::new("test").with_event_bus_capacity(2);
+        let system = ActorSystem::<TestEvent>::from_config(config);
+        let mut audit = system.events_named("audit-writer");
+        let metrics = system.events_named("metrics");
+        assert_eq!(audit.name(), "audit-writer");
+
+        for i in 0..5 {
+            system.publish(TestEvent(i.to_string()));
+        }
+        assert!(matches!(audit.recv().await, Err(EventRecvError::Lagged(3))));
+        assert_eq!(audit.recv().await.unwrap().0, "3");
+        drop(metrics);
+
+        assert_eq!(
+            system.subscriber_stats(),
+            vec![crate::SubscriberStats {
+                name: "audit-writer".to_string(),
+                received: 1,
+                dropped: 3,
+                pending: 1,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn system_shutdown() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let actor_ref = system
+            .create_actor("test-actor", TestActor::default())
+            .await
+            .unwrap();
+        let mut events = system.events();
+        let mut registry = system.registry_events();
+        let mut messages = system.system_messages();
+        actor_ref.ask(TestMessage(1)).await.unwrap();
+
+        system.shutdown().await;
+        assert!(system
+            .get_actor::<TestActor>(actor_ref.path())
+            .await
+            .is_none());
+        assert!(events.recv().await.is_ok());
+        assert!(matches!(events.recv().await, Err(EventRecvError::Closed)));
+        assert!(matches!(
+            registry.recv().await,
+            Ok(RegistryEvent::ActorUnregistered(_))
+        ));
+        assert!(matches!(registry.recv().await, Err(EventRecvError::Closed)));
+        loop {
+            match messages.recv().await {
+                Ok(_) => continue,
+                Err(error) => {
+                    assert!(matches!(error, EventRecvError::Closed));
+                    break;
+                }
+            }
+        }
+        assert!(system.events().recv().await.is_err());
+        assert!(actor_ref.tell(TestMessage(2)).is_err());
+    }
+
+    #[tokio::test]
+    async fn wait_for_stop_all() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let a = system
+            .create_actor("a", TestActor::default())
+            .await
+            .unwrap();
+        let b = system
+            .create_actor("b", TestActor::default())
+            .await
+            .unwrap();
+        let paths = vec![
+            a.path().clone(),
+            b.path().clone(),
+            ActorPath::from("/user/c"),
+        ];
+
+        let stopper = system.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+            stopper.stop_actor(a.path()).await;
+            tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+            stopper.stop_actor(b.path()).await;
+        });
+
+        system.wait_for_stop_all(&paths).await;
+        for path in &paths {
+            assert!(system.get_actor::<TestActor>(path).await.is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_ping() {
+        struct StuckActor;
+
+        impl Actor<TestEvent> for StuckActor {}
+
+        #[async_trait]
+        impl Handler<TestEvent, TestMessage> for StuckActor {
+            async fn handle(
+                &mut self,
+                _msg: TestMessage,
+                _ctx: &mut ActorContext<TestEvent>,
+            ) -> usize {
+                std::future::pending().await
+            }
+        }
+
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let timeout = tokio::time::Duration::from_millis(50);
+
+        let healthy = system
+            .create_actor("healthy", TestActor::default())
+            .await
+            .unwrap();
+        assert!(healthy.ping(timeout).await.is_ok());
+        healthy.suspend().await.unwrap();
+        assert!(healthy.ping(timeout).await.is_ok());
+
+        let stuck = system.create_actor("stuck", StuckActor).await.unwrap();
+        assert!(stuck.ping(timeout).await.is_ok());
+        stuck.tell(TestMessage(1)).unwrap();
+        // Let the actor get stuck handling the message
+        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+        assert!(!stuck.is_closed());
+        assert!(matches!(
+            stuck.ping(timeout).await,
+            Err(ActorError::Timeout)
+        ));
+
+        system.stop_subtree(healthy.path()).await;
+        assert!(matches!(
+            healthy.ping(timeout).await,
+            Err(ActorError::Terminated(_, _))
+        ));
+    }
+
+    #[tokio::test]
+    async fn starvation_warnings() {
+        struct SleepyActor;
+
+        impl Actor<TestEvent> for SleepyActor {}
+
+        #[async_trait]
+        impl Handler<TestEvent, TestMessage> for SleepyActor {
+            async fn handle(
+                &mut self,
+                msg: TestMessage,
+                _ctx: &mut ActorContext<TestEvent>,
+            ) -> usize {
+                tokio::time::sleep(tokio::time::Duration::from_millis(msg.0 as u64)).await;
+                msg.0
+            }
+        }
+
+        let threshold = tokio::time::Duration::from_millis(30);
+        let config = SystemConfig::new("test").with_starvation_threshold(threshold);
+        let system = ActorSystem::<TestEvent>::from_config(config);
+        let mut warnings = system.starvation_warnings();
+        let actor_ref = system.create_actor("sleepy", SleepyActor).await.unwrap();
+
+        // Short handlers with breaks in between are fine
+        for _ in 0..3 {
+            actor_ref.ask(TestMessage(1)).await.unwrap();
+        }
+        assert!(warnings.try_recv().is_err());
+
+        // A single handler is reported while it is still running
+        let ask = tokio::spawn({
+            let actor_ref = actor_ref.clone();
+            async move { actor_ref.ask(TestMessage(200)).await }
+        });
+        let warning = warnings.recv().await.unwrap();
+        assert!(!ask.is_finished());
+        assert_eq!(&warning.path, actor_ref.path());
+        assert_eq!(warning.message_type, std::any::type_name::<TestMessage>());
+        assert_eq!(warning.messages, 1);
+        assert!(warning.elapsed >= threshold);
+        ask.await.unwrap().unwrap();
+        assert!(warnings.try_recv().is_err());
+
+        // As is a run of messages that together take too long
+        for _ in 0..10 {
+            actor_ref.tell(TestMessage(5)).unwrap();
+        }
+        let warning = warnings.recv().await.unwrap();
+        assert!(warning.messages > 1);
+        system.wait_until_idle().await;
+        assert!(warnings.try_recv().is_err());
+    }
+
+    #[derive(Clone, Debug)]
+    struct Reading {
+        sensor: String,
+        value: usize,
+    }
+
+    impl Message for Reading {
+        type Response = ();
+    }
+
+    impl Reading {
+        fn parse(TestEvent(event): TestEvent) -> Option<Reading> {
+            let (sensor, value) = event.split_once(':')?;
+            Some(Reading {
+                sensor: sensor.to_string(),
+                value: value.parse().ok()?,
+            })
+        }
+    }
+
+    #[derive(Default)]
+    struct ReadingRecorder {
+        readings: Arc<std::sync::Mutex<Vec<(String, usize)>>>,
+    }
+
+    impl Actor<TestEvent> for ReadingRecorder {}
+
+    #[async_trait]
+    impl Handler<TestEvent, Reading> for ReadingRecorder {
+        async fn handle(&mut self, msg: Reading, _ctx: &mut ActorContext<TestEvent>) {
+            tokio::time::sleep(tokio::time::Duration::from_millis(20)).await;
+            self.readings.lock().unwrap().push((msg.sensor, msg.value));
+        }
+    }
+
+    #[tokio::test]
+    async fn subscribe_actor() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let delivered = tokio::time::Duration::from_millis(200);
+
+        let sampled = system
+            .create_actor("sampled", TestActor::default())
+            .await
+            .unwrap();
+        // The test actor publishes an event for every message it handles
+        let numbered =
+            |TestEvent(event): TestEvent| event.starts_with("event").then_some(TestMessage(1));
+        let policy = EventPolicy::all().sample_every(3);
+        system.subscribe_actor(&sampled, policy, numbered);
+
+        let limited = system
+            .create_actor("limited", TestActor::default())
+            .await
+            .unwrap();
+        let policy = EventPolicy::all().max_rate(2, tokio::time::Duration::from_secs(60));
+        let subscription = system.subscribe_actor(&limited, policy, numbered);
+
+        for i in 0..9 {
+            system.publish(TestEvent(format!("event {}", i)));
+        }
+        tokio::time::sleep(delivered).await;
+        assert_eq!(sampled.ask(TestMessage(1)).await.unwrap(), 4);
+        assert_eq!(limited.ask(TestMessage(1)).await.unwrap(), 3);
+
+        subscription.cancel();
+        assert!(subscription.is_cancelled());
+
+        let recorder = ReadingRecorder::default();
+        let readings = recorder.readings.clone();
+        let recorder = system.create_actor("recorder", recorder).await.unwrap();
+        let policy = EventPolicy::all().keep_latest_by(|reading: &Reading| reading.sensor.clone());
+        system.subscribe_actor(&recorder, policy, Reading::parse);
+
+        for event in ["a:1", "a:2", "b:1", "a:3", "not a reading"] {
+            system.publish(TestEvent(event.to_string()));
+        }
+        tokio::time::sleep(delivered).await;
+        let expected = vec![
+            ("a".to_string(), 1),
+            ("a".to_string(), 3),
+            ("b".to_string(), 1),
+        ];
+        assert_eq!(*readings.lock().unwrap(), expected);
+
+        // Stopping the actor ends its subscriptions
+        system.stop_subtree(recorder.path()).await;
+        system.publish(TestEvent("a:4".to_string()));
+        tokio::time::sleep(delivered).await;
+        assert_eq!(readings.lock().unwrap().len(), 3);
+    }
+
+    impl crate::ConflationKey for Reading {
+        type Key = String;
+
+        fn conflation_key(&self) -> String {
+            self.sensor.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_tell_latest() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let recorder = ReadingRecorder::default();
+        let readings = recorder.readings.clone();
+        let actor_ref = system.create_actor("recorder", recorder).await.unwrap();
+        let reading = |sensor: &str, value| Reading {
+            sensor: sensor.to_string(),
+            value,
+        };
+
+        actor_ref.suspend().await.unwrap();
+        actor_ref.tell_latest(reading("a", 1)).unwrap();
+        actor_ref.tell_latest(reading("b", 1)).unwrap();
+        actor_ref.tell(reading("a", 10)).unwrap();
+        actor_ref.tell_latest(reading("a", 2)).unwrap();
+        actor_ref.tell_latest(reading("a", 3)).unwrap();
+        actor_ref.tell_latest(reading("b", 2)).unwrap();
+        actor_ref.resume().unwrap();
+        system.wait_until_idle().await;
+
+        let expected = vec![
+            ("a".to_string(), 3),
+            ("b".to_string(), 2),
+            ("a".to_string(), 10),
+        ];
+        assert_eq!(*readings.lock().unwrap(), expected);
+
+        // Once handled, a new message with the same key is queued again
+        actor_ref.tell_latest(reading("a", 4)).unwrap();
+        system.wait_until_idle().await;
+        assert_eq!(readings.lock().unwrap().len(), 4);
+
+        // A message that could not be queued does not linger in its slot
+        system.stop_actors_matching("/user/recorder").await;
+        for _ in 0..2 {
+            assert!(matches!(
+                actor_ref.tell_latest(reading("a", 5)),
+                Err(ActorError::Terminated(_, _))
+            ));
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct Sample {
+        key: u8,
+        bytes: usize,
+    }
+
+    impl Message for Sample {
+        type Response = ();
+
+        fn size_hint(&self) -> usize {
+            self.bytes
+        }
+    }
+
+    impl crate::ConflationKey for Sample {
+        type Key = u8;
+
+        fn conflation_key(&self) -> u8 {
+            self.key
+        }
+    }
+
+    /// The sizes of the samples handled, with their deadlines.
+    type HandledSamples = Arc<std::sync::Mutex<Vec<(usize, Option<Deadline>)>>>;
+
+    #[derive(Default)]
+    struct SampleRecorder {
+        handled: HandledSamples,
+    }
+
+    impl Actor<TestEvent> for SampleRecorder {}
+
+    #[async_trait]
+    impl Handler<TestEvent, Sample> for SampleRecorder {
+        async fn handle(&mut self, msg: Sample, ctx: &mut ActorContext<TestEvent>) {
+            self.handled
+                .lock()
+                .unwrap()
+                .push((msg.bytes, ctx.deadline()));
+        }
+    }
+
+    #[tokio::test]
+    async fn tell_latest_deadline() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let mut messages = system.system_messages();
+        let recorder = SampleRecorder::default();
+        let handled = recorder.handled.clone();
+        let actor_ref = system.create_actor("recorder", recorder).await.unwrap();
+        let sample = |key, bytes| Sample { key, bytes };
+
+        // The deadline of the latest message applies
+        actor_ref.suspend().await.unwrap();
+        let long = Deadline::after(Duration::from_secs(60));
+        let short = Deadline::after(Duration::from_secs(5));
+        long.scope(async { actor_ref.tell_latest(sample(1, 1)) })
+            .await
+            .unwrap();
+        short
+            .scope(async { actor_ref.tell_latest(sample(1, 2)) })
+            .await
+            .unwrap();
+        actor_ref.resume().unwrap();
+        system.wait_until_idle().await;
+        assert_eq!(*handled.lock().unwrap(), vec![(2, Some(short))]);
+
+        // A message whose deadline passed in the mailbox is not handled
+        actor_ref.suspend().await.unwrap();
+        let expiring = Deadline::after(Duration::from_millis(10));
+        expiring
+            .scope(async { actor_ref.tell_latest(sample(1, 3)) })
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        actor_ref.resume().unwrap();
+        system.wait_until_idle().await;
+        assert_eq!(handled.lock().unwrap().len(), 1);
+        let letter = loop {
+            if let SystemMessage::DeadLetter(letter) = messages.recv().await.unwrap() {
+                break letter;
+            }
+        };
+        assert_eq!(letter.reason(), DeadLetterReason::Expired);
+    }
+
+    #[tokio::test]
+    async fn tell_latest_mailbox_bytes() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let config = ActorConfig::default().with_mailbox_bytes(50);
+        let actor_ref = system
+            .create_actor_with_config("recorder", SampleRecorder::default(), config)
+            .await
+            .unwrap();
+        let sample = |key, bytes| Sample { key, bytes };
+
+        actor_ref.suspend().await.unwrap();
+        actor_ref.tell_latest(sample(1, 10)).unwrap();
+        // A larger replacement takes more room in the mailbox
+        actor_ref.tell_latest(sample(1, 45)).unwrap();
+        assert!(matches!(
+            actor_ref.tell(sample(2, 10)),
+            Err(ActorError::MailboxFull)
+        ));
+        // And a smaller one gives it back
+        actor_ref.tell_latest(sample(1, 20)).unwrap();
+        actor_ref.tell(sample(2, 30)).unwrap();
+        actor_ref.resume().unwrap();
+        system.wait_until_idle().await;
+
+        // All room is given back once the messages are handled
+        actor_ref.suspend().await.unwrap();
+        actor_ref.tell(sample(2, 30)).unwrap();
+        actor_ref.tell_latest(sample(1, 20)).unwrap();
+        assert!(matches!(
+            actor_ref.tell(sample(2, 1)),
+            Err(ActorError::MailboxFull)
+        ));
+        actor_ref.resume().unwrap();
+    }
+
+    #[tokio::test]
+    async fn tell_latest_policy() {
+        let policy = |delivery: &crate::Delivery<'_>| {
+            delivery.recipient.key() != "recorder"
+                || delivery
+                    .caller
+                    .is_some_and(|caller| caller.principal() == "alice")
+        };
+        let config = SystemConfig::new("test").with_message_policy(policy);
+        let system = ActorSystem::<TestEvent>::from_config(config);
+        let recorder = ReadingRecorder::default();
+        let readings = recorder.readings.clone();
+        let actor_ref = system.create_actor("recorder", recorder).await.unwrap();
+        let reading = |value| Reading {
+            sensor: "a".to_string(),
+            value,
+        };
+
+        actor_ref.suspend().await.unwrap();
+        let alice = crate::Caller::new("alice");
+        alice
+            .scope(async { actor_ref.tell_latest(reading(1)) })
+            .await
+            .unwrap();
+
+        // A denied sender cannot replace the queued message
+        match actor_ref.tell_latest(reading(2)) {
+            Err(ActorError::Unauthorized { path, .. }) => assert_eq!(&path, actor_ref.path()),
+            other => panic!("expected an unauthorized tell, got {:?}", other),
+        }
+        actor_ref.resume().unwrap();
+        system.wait_until_idle().await;
+        assert_eq!(*readings.lock().unwrap(), vec![("a".to_string(), 1)]);
+    }
+
+    #[derive(Default)]
+    struct Stock {
+        available: usize,
+        reserved: HashMap<crate::TxToken, usize>,
+    }
+
+    impl Actor<TestEvent> for Stock {}
+
+    #[async_trait]
+    impl crate::Participant<TestEvent> for Stock {
+        type Request = usize;
+        type Output = usize;
+
+        async fn prepare(
+            &mut self,
+            token: crate::TxToken,
+            amount: usize,
+            _ctx: &mut ActorContext<TestEvent>,
+        ) -> Result<(), ActorError> {
+            if amount > self.available {
+                return Err(ActorError::CreateError("out of stock".to_string()));
+            }
+            self.available -= amount;
+            self.reserved.insert(token, amount);
+            Ok(())
+        }
+
+        async fn commit(
+            &mut self,
+            token: crate::TxToken,
+            _ctx: &mut ActorContext<TestEvent>,
+        ) -> Result<usize, ActorError> {
+            self.reserved
+                .remove(&token)
+                .ok_or(ActorError::TransactionAborted(token))
+        }
+
+        async fn abort(&mut self, token: crate::TxToken, _ctx: &mut ActorContext<TestEvent>) {
+            self.available += self.reserved.remove(&token).unwrap_or_default();
+        }
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, TestMessage> for Stock {
+        async fn handle(&mut self, _msg: TestMessage, _ctx: &mut ActorContext<TestEvent>) -> usize {
+            self.available
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_transactions() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let stock = Stock {
+            available: 10,
+            ..Default::default()
+        };
+        let stock = system.create_actor("stock", stock).await.unwrap();
+        let timeout = tokio::time::Duration::from_secs(60);
+
+        let transaction = stock.prepare(3, timeout).await.unwrap();
+        assert_eq!(stock.ask(TestMessage(0)).await.unwrap(), 7);
+        assert_eq!(transaction.commit().await.unwrap(), 3);
+        assert_eq!(stock.ask(TestMessage(0)).await.unwrap(), 7);
+
+        assert!(stock.prepare(8, timeout).await.is_err());
+
+        let transaction = stock.prepare(5, timeout).await.unwrap();
+        assert_eq!(stock.ask(TestMessage(0)).await.unwrap(), 2);
+        transaction.abort();
+        assert_eq!(stock.ask(TestMessage(0)).await.unwrap(), 7);
+
+        // Dropped transactions are aborted
+        drop(stock.prepare(5, timeout).await.unwrap());
+        assert_eq!(stock.ask(TestMessage(0)).await.unwrap(), 7);
+
+        // As are transactions that are not committed in time
+        let transaction = stock
+            .prepare(5, tokio::time::Duration::from_millis(10))
+            .await
+            .unwrap();
+        let token = transaction.token();
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+        assert_eq!(stock.ask(TestMessage(0)).await.unwrap(), 7);
+        match transaction.commit().await {
+            Err(ActorError::TransactionAborted(aborted)) => assert_eq!(aborted, token),
+            other => panic!(
+                "Expected an aborted transaction, got {:?}",
+                other.map(|_| ())
+            ),
+        }
+    }
+
+    #[derive(Default)]
+    struct TickCounter {
+        ticks: usize,
+    }
+
+    #[async_trait]
+    impl Actor<TestEvent> for TickCounter {
+        async fn pre_start(&mut self, ctx: &mut ActorContext<TestEvent>) -> Result<(), ActorError> {
+            let ticks = |TestEvent(event): TestEvent| (event == "tick").then_some(TestMessage(1));
+            ctx.subscribe::<Self, _, _>(EventPolicy::all(), ticks)
+                .await?;
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, TestMessage> for TickCounter {
+        async fn handle(&mut self, msg: TestMessage, _ctx: &mut ActorContext<TestEvent>) -> usize {
+            self.ticks += msg.0;
+            self.ticks
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct Unsubscribe;
+
+    impl Message for Unsubscribe {
+        type Response = ();
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, Unsubscribe> for TickCounter {
+        async fn handle(&mut self, _msg: Unsubscribe, ctx: &mut ActorContext<TestEvent>) {
+            ctx.unsubscribe();
+        }
+    }
+
+    #[tokio::test]
+    async fn context_subscriptions() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let delivered = tokio::time::Duration::from_millis(50);
+        let subscribers = system.bus.receiver_count();
+
+        let counter = system
+            .create_actor_ready("ticks", TickCounter::default())
+            .await
+            .unwrap();
+        assert_eq!(system.bus.receiver_count(), subscribers + 1);
+        system.publish(TestEvent("tick".to_string()));
+        system.publish(TestEvent("tock".to_string()));
+        tokio::time::sleep(delivered).await;
+        assert_eq!(counter.ask(TestMessage(0)).await.unwrap(), 1);
+
+        counter.ask(Unsubscribe).await.unwrap();
+        tokio::time::sleep(delivered).await;
+        assert_eq!(system.bus.receiver_count(), subscribers);
+        system.publish(TestEvent("tick".to_string()));
+        tokio::time::sleep(delivered).await;
+        assert_eq!(counter.ask(TestMessage(0)).await.unwrap(), 1);
 
-    use crate::actor::{Actor, ActorContext, Handler, Message};
-    use async_trait::async_trait;
-    use thiserror::Error;
+        // Subscriptions end when the actor stops
+        let counter = system
+            .create_actor_ready("more-ticks", TickCounter::default())
+            .await
+            .unwrap();
+        assert_eq!(system.bus.receiver_count(), subscribers + 1);
+        system.stop_subtree(counter.path()).await;
+        tokio::time::sleep(delivered).await;
+        assert_eq!(system.bus.receiver_count(), subscribers);
 
-    use super::*;
+        // A context can only subscribe its own actor type
+        struct Mistaken;
 
-    #[derive(Clone, Debug)]
-    struct TestEvent(String);
+        #[async_trait]
+        impl Actor<TestEvent> for Mistaken {
+            async fn pre_start(
+                &mut self,
+                ctx: &mut ActorContext<TestEvent>,
+            ) -> Result<(), ActorError> {
+                ctx.subscribe::<TickCounter, _, _>(EventPolicy::all(), |_| Some(TestMessage(1)))
+                    .await?;
+                Ok(())
+            }
+        }
 
-    impl SystemEvent for TestEvent {}
+        let mistaken = system.create_actor_ready("mistaken", Mistaken).await;
+        assert!(matches!(mistaken, Err(ActorError::NotFound(_))));
+    }
 
-    #[derive(Default)]
-    struct TestActor {
-        counter: usize,
+    struct FlakyConnector {
+        failures: usize,
+        healthy: Arc<std::sync::atomic::AtomicBool>,
     }
 
     #[async_trait]
-    impl Actor<TestEvent> for TestActor {
-        async fn pre_start(
+    impl crate::Connector<TestEvent> for FlakyConnector {
+        type Connection = Arc<std::sync::atomic::AtomicBool>;
+
+        async fn connect(
             &mut self,
             _ctx: &mut ActorContext<TestEvent>,
-        ) -> Result<(), ActorError> {
-            log::debug!("Starting actor TestActor!");
-            Ok(())
+        ) -> Result<Self::Connection, ActorError> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(ActorError::CreateError("refused".to_string()));
+            }
+            self.healthy.store(true, Ordering::SeqCst);
+            Ok(self.healthy.clone())
         }
 
-        async fn post_stop(&mut self, _ctx: &mut ActorContext<TestEvent>) {
-            log::debug!("Stopped actor TestActor!");
+        async fn is_healthy(&mut self, connection: &mut Self::Connection) -> bool {
+            connection.load(Ordering::SeqCst)
         }
     }
 
     #[derive(Clone, Debug)]
-    struct TestMessage(usize);
+    struct Double(usize);
 
-    impl Message for TestMessage {
-        type Response = usize;
+    impl Message for Double {
+        type Response = Result<usize, ActorError>;
     }
 
-    impl SystemEvent for TestMessage {}
+    impl crate::ConnectionRequest for Double {}
 
     #[async_trait]
-    impl Handler<TestEvent, TestMessage> for TestActor {
-        async fn handle(&mut self, msg: TestMessage, ctx: &mut ActorContext<TestEvent>) -> usize {
-            log::debug!("received message! {:?}", &msg);
-            self.counter += 1;
-            log::debug!("counter is now {}", &self.counter);
-            log::debug!("{} on system {}", &ctx.path, ctx.system.name());
-            ctx.system
-                .publish(TestEvent("Message received!".to_string()));
-            self.counter
+    impl crate::Serve<TestEvent, Double> for FlakyConnector {
+        async fn serve(
+            &mut self,
+            _connection: &mut Self::Connection,
+            msg: Double,
+            _ctx: &mut ActorContext<TestEvent>,
+        ) -> Result<usize, ActorError> {
+            Ok(msg.0 * 2)
         }
     }
 
-    #[derive(Default, Clone)]
-    struct OtherActor {
-        message: String,
-        child: Option<ActorRef<TestEvent, TestActor>>,
-    }
+    #[tokio::test]
+    async fn managed_connection() {
+        use crate::{ConnectionState::*, ManagedConnection, SupervisionPolicy, WhileDisconnected};
 
-    #[async_trait]
-    impl Actor<TestEvent> for OtherActor {
-        async fn pre_start(&mut self, ctx: &mut ActorContext<TestEvent>) -> Result<(), ActorError> {
-            log::debug!("OtherActor initial message: {}", &self.message);
-            let child = TestActor { counter: 0 };
-            self.child = ctx.create_child("child", child).await.ok();
-            Ok(())
-        }
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(10));
+        let mut changes = system.connection_changes();
+        let healthy = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let connector = FlakyConnector {
+            failures: 2,
+            healthy: healthy.clone(),
+        };
+        let backoff = SupervisionPolicy::FixedInterval {
+            max_retries: 3,
+            interval: Duration::from_millis(5),
+        };
+        let managed = ManagedConnection::new(connector).with_backoff(backoff);
+        let actor = system.create_actor("database", managed).await.unwrap();
 
-        async fn post_stop(&mut self, _ctx: &mut ActorContext<TestEvent>) {
-            log::debug!("OtherActor stopped.");
+        // Connecting fails at the start and once more while the request waits
+        assert_eq!(actor.ask(Double(2)).await.unwrap().unwrap(), 4);
+        healthy.store(false, Ordering::SeqCst);
+        assert_eq!(actor.ask(Double(3)).await.unwrap().unwrap(), 6);
+
+        let mut states = Vec::new();
+        while let Ok(Ok(change)) =
+            tokio::time::timeout(Duration::from_millis(50), changes.recv()).await
+        {
+            assert_eq!(&change.path, actor.path());
+            states.push(change.state);
         }
+        assert_eq!(
+            states,
+            vec![
+                Connecting,
+                Disconnected,
+                Connecting,
+                Disconnected,
+                Connecting,
+                Connected,
+                Disconnected,
+                Connecting,
+                Connected
+            ]
+        );
+
+        // Rejected requests do not wait for the backoff
+        let connector = FlakyConnector {
+            failures: 2,
+            healthy: Default::default(),
+        };
+        let backoff = SupervisionPolicy::FixedInterval {
+            max_retries: 3,
+            interval: Duration::from_secs(3600),
+        };
+        let managed = ManagedConnection::new(connector)
+            .with_backoff(backoff)
+            .with_disconnected_policy(WhileDisconnected::Reject);
+        let actor = system.create_actor("cache", managed).await.unwrap();
+        assert!(matches!(
+            actor.ask(Double(2)).await.unwrap(),
+            Err(ActorError::CreateError(_))
+        ));
+        assert!(matches!(
+            actor.ask(Double(2)).await.unwrap(),
+            Err(ActorError::Disconnected(_))
+        ));
     }
 
+    struct BudgetActor;
+
+    impl Actor<TestEvent> for BudgetActor {}
+
     #[derive(Clone, Debug)]
-    struct OtherMessage(String);
+    struct Budget;
 
-    impl Message for OtherMessage {
-        type Response = String;
+    impl Message for Budget {
+        type Response = Option<Duration>;
     }
 
     #[async_trait]
-    impl Handler<TestEvent, OtherMessage> for OtherActor {
-        async fn handle(&mut self, msg: OtherMessage, ctx: &mut ActorContext<TestEvent>) -> String {
-            log::debug!("OtherActor received message! {:?}", &msg);
-            log::debug!("original message is {}", &self.message);
-            self.message = msg.0;
-            log::debug!("message is now {}", &self.message);
-            log::debug!("{} on system {}", &ctx.path, ctx.system.name());
-            ctx.system
-                .publish(TestEvent("Received message!".to_string()));
-            self.message.clone()
+    impl Handler<TestEvent, Budget> for BudgetActor {
+        async fn handle(
+            &mut self,
+            _msg: Budget,
+            ctx: &mut ActorContext<TestEvent>,
+        ) -> Option<Duration> {
+            ctx.remaining_time()
         }
     }
 
-    #[tokio::test]
-    async fn actor_create() {
-        if std::env::var("RUST_LOG").is_err() {
-            std::env::set_var("RUST_LOG", "trace");
-        }
-        let _ = env_logger::builder().is_test(true).try_init();
-
-        let actor = TestActor { counter: 0 };
-        let msg = TestMessage(10);
+    #[derive(Clone, Debug)]
+    struct RelayBudget(ActorRef<TestEvent, BudgetActor>);
 
-        let bus = EventBus::<TestEvent>::new(1000);
-        let system = ActorSystem::new("test", bus);
-        let actor_ref = system.create_actor("test-actor", actor).await.unwrap();
-        let result = actor_ref.ask(msg).await.unwrap();
+    impl Message for RelayBudget {
+        type Response = Option<Duration>;
+    }
 
-        assert_eq!(result, 1);
+    #[async_trait]
+    impl Handler<TestEvent, RelayBudget> for BudgetActor {
+        async fn handle(
+            &mut self,
+            msg: RelayBudget,
+            ctx: &mut ActorContext<TestEvent>,
+        ) -> Option<Duration> {
+            ctx.ask(&msg.0, Budget).await.unwrap()
+        }
     }
 
-    #[derive(Debug, Error)]
-    #[error("custom error")]
-    struct CustomError(String);
+    #[derive(Clone, Debug)]
+    struct Nap(Duration);
 
-    fn create_other(message: String) -> OtherActor {
-        OtherActor {
-            message,
-            child: None,
+    impl Message for Nap {
+        type Response = ();
+    }
+
+    #[async_trait]
+    impl Handler<TestEvent, Nap> for BudgetActor {
+        async fn handle(&mut self, msg: Nap, _ctx: &mut ActorContext<TestEvent>) {
+            tokio::time::sleep(msg.0).await;
         }
     }
 
     #[tokio::test]
-    async fn actor_get_or_create() {
-        if std::env::var("RUST_LOG").is_err() {
-            std::env::set_var("RUST_LOG", "trace");
-        }
-        let _ = env_logger::builder().is_test(true).try_init();
+    async fn message_deadline() {
+        use crate::Deadline;
 
-        let bus = EventBus::<TestEvent>::new(1000);
-        let system = ActorSystem::new("test", bus);
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let mut messages = system.system_messages();
+        let front = system.create_actor("front", BudgetActor).await.unwrap();
+        let back = system.create_actor("back", BudgetActor).await.unwrap();
+        assert_eq!(front.ask(Budget).await.unwrap(), None);
 
-        let initial_message = "hello world!".to_string();
-        let actor_fn = || create_other(initial_message);
-        let actor_ref = system
-            .get_or_create_actor("test-actor", actor_fn)
+        // The deadline follows the message through nested asks
+        let deadline = Deadline::after(Duration::from_secs(5));
+        let remaining = deadline
+            .scope(front.ask(RelayBudget(back.clone())))
             .await
+            .unwrap()
             .unwrap();
+        assert!(remaining > Duration::from_secs(4) && remaining <= Duration::from_secs(5));
 
-        let msg = OtherMessage("Updated message.".to_string());
-        let result = actor_ref.ask(msg).await.unwrap();
-
-        assert_eq!(result, "Updated message.".to_string());
+        // Messages whose deadline passes in the mailbox are not handled
+        back.tell(Nap(Duration::from_millis(50))).unwrap();
+        let deadline = Deadline::after(Duration::from_millis(10));
+        let expired = deadline.scope(back.ask(Budget)).await;
+        assert!(matches!(expired, Err(ActorError::Timeout)));
+        let letter = loop {
+            match messages.recv().await.unwrap() {
+                SystemMessage::DeadLetter(letter) => break letter,
+                _ => continue,
+            }
+        };
+        assert_eq!(letter.reason(), DeadLetterReason::Expired);
+        assert_eq!(letter.recipient(), back.path());
+        assert!(letter.payload::<Budget>().is_some());
     }
 
     #[tokio::test]
-    async fn actor_stop() {
-        if std::env::var("RUST_LOG").is_err() {
-            std::env::set_var("RUST_LOG", "trace");
-        }
-        let _ = env_logger::builder().is_test(true).try_init();
-
-        let actor = TestActor { counter: 0 };
-        let msg = TestMessage(10);
-
-        let bus = EventBus::<TestEvent>::new(1000);
-        let system = ActorSystem::new("test", bus);
-
-        {
-            let actor_ref = system.create_actor("test-actor", actor).await.unwrap();
-            let result = actor_ref.ask(msg).await.unwrap();
+    async fn system_request() {
+        #[derive(Clone, Debug)]
+        struct Square(u64);
 
-            assert_eq!(result, 1);
+        let system = ActorSystem::<TestEvent>::from_config(
+            SystemConfig::new("test").with_ask_timeout(Duration::from_millis(50)),
+        );
+        let error = system.request::<Square, u64>(Square(3)).await.unwrap_err();
+        assert!(matches!(error, ActorError::NoResponder(_)));
 
-            system.stop_actor(actor_ref.path()).await;
-        }
+        // One responder ignores the requests, the other answers them
+        let mut silent = system.responder::<Square, u64>();
+        let mut squares = system.responder::<Square, u64>();
+        tokio::spawn(async move {
+            while let Ok(request) = squares.recv().await {
+                let square = request.request().0 * request.request().0;
+                assert!(request.respond(square));
+                assert!(!request.respond(square));
+            }
+        });
+        assert_eq!(system.request::<Square, u64>(Square(3)).await.unwrap(), 9);
+        assert_eq!(system.request::<Square, u64>(Square(4)).await.unwrap(), 16);
+        let first = silent.recv().await.unwrap();
+        let second = silent.recv().await.unwrap();
+        assert_ne!(first.correlation_id(), second.correlation_id());
+        assert!(!first.is_pending());
 
-        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+        // Requests of another response type go to other responders
+        let _strings = system.responder::<Square, String>();
+        let error = system
+            .request_with_timeout::<Square, String>(Square(5), Duration::from_millis(10))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ActorError::Timeout));
     }
 
     #[tokio::test]
-    async fn actor_events() {
-        if std::env::var("RUST_LOG").is_err() {
-            std::env::set_var("RUST_LOG", "trace");
+    async fn ask_all_quorum() {
+        use crate::{ask_all, Fastest, FirstSuccess, Fold, Majority};
+
+        #[derive(Clone, Debug)]
+        struct Read;
+
+        impl Message for Read {
+            type Response = u64;
         }
-        let _ = env_logger::builder().is_test(true).try_init();
 
-        let actor = TestActor { counter: 0 };
-        let msg = TestMessage(10);
+        struct Replica(Option<u64>);
 
-        let bus = EventBus::<TestEvent>::new(1000);
-        let system = ActorSystem::new("test", bus);
-        let actor_ref = system.create_actor("test-actor", actor).await.unwrap();
+        impl Actor<TestEvent> for Replica {}
 
-        let mut events = system.events();
-        tokio::spawn(async move {
-            loop {
-                match events.recv().await {
-                    Ok(event) => println!("Received event! {:?}", event),
-                    Err(err) => println!("Error receivng event!!! {:?}", err),
+        #[async_trait]
+        impl Handler<TestEvent, Read> for Replica {
+            async fn handle(&mut self, _msg: Read, _ctx: &mut ActorContext<TestEvent>) -> u64 {
+                match self.0 {
+                    Some(version) => version,
+                    None => std::future::pending().await,
                 }
             }
-        });
+        }
 
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        let system = ActorSystem::<TestEvent>::from_config(
+            SystemConfig::new("test").with_ask_timeout(Duration::from_millis(50)),
+        );
+        let mut replicas = Vec::new();
+        for (name, version) in [("a", Some(7)), ("b", Some(7)), ("c", Some(6)), ("d", None)] {
+            replicas.push(system.create_actor(name, Replica(version)).await.unwrap());
+        }
 
-        let result = actor_ref.ask(msg).await.unwrap();
+        let healthy = &replicas[..3];
+        assert_eq!(ask_all(healthy, Read, Majority::new()).await.unwrap(), 7);
+        let first = ask_all(&replicas, Read, FirstSuccess::new()).await.unwrap();
+        assert!(first == 7 || first == 6);
+        let mut fastest = ask_all(&replicas, Read, Fastest::new(3)).await.unwrap();
+        fastest.sort_unstable();
+        assert_eq!(fastest, vec![6, 7, 7]);
+        let error = ask_all(&replicas, Read, Fastest::new(4)).await.unwrap_err();
+        assert!(matches!(
+            error,
+            ActorError::NoQuorum {
+                asked: 4,
+                needed: 4
+            }
+        ));
 
-        assert_eq!(result, 1);
+        // The stuck replica times out, and is counted by the fold
+        let (sum, failed) = ask_all(
+            &replicas,
+            Read,
+            Fold::new((0, 0), |(sum, failed), version| match version {
+                Ok(version) => (sum + version, failed),
+                Err(_) => (sum, failed + 1),
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!((sum, failed), (20, 1));
+
+        // Without a majority agreeing, the quorum fails
+        let split = &replicas[1..];
+        let error = ask_all(split, Read, Majority::new()).await.unwrap_err();
+        assert!(matches!(
+            error,
+            ActorError::NoQuorum {
+                asked: 3,
+                needed: 2
+            }
+        ));
+        let error = ask_all(&replicas[3..], Read, FirstSuccess::new())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ActorError::Timeout));
     }
 
     #[tokio::test]
-    async fn actor_get() {
-        if std::env::var("RUST_LOG").is_err() {
-            std::env::set_var("RUST_LOG", "trace");
-        }
-        let _ = env_logger::builder().is_test(true).try_init();
+    async fn supervision_group() {
+        use crate::GroupStrategy;
 
-        let actor = TestActor { counter: 0 };
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let mut events = system.supervision_group_events();
+        let first = system
+            .create_actor("first", TestActor::default())
+            .await
+            .unwrap();
+        let second = system
+            .create_actor("second", TestActor::default())
+            .await
+            .unwrap();
+        let panicking = system
+            .create_actor("panicking", PanickingActor)
+            .await
+            .unwrap();
 
-        let bus = EventBus::<TestEvent>::new(1000);
-        let system = ActorSystem::new("test", bus);
-        let original = system.create_actor("test-actor", actor).await.unwrap();
+        let group = system.supervision_group("shared", GroupStrategy::RestartAll);
+        group.join(&first);
+        group.join(&second);
+        group.join(&panicking);
+        assert!(panicking.ask(TestMessage(1)).await.is_err());
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.group, "shared");
+        assert_eq!(&event.failed, panicking.path());
+        assert_eq!(event.strategy, GroupStrategy::RestartAll);
+        assert_eq!(
+            event.members,
+            vec![first.path().clone(), second.path().clone()]
+        );
+        assert_eq!(system.restart_stats(first.path()).unwrap().restarts(), 1);
+        assert_eq!(system.restart_stats(second.path()).unwrap().restarts(), 1);
+        assert!(first.ask(TestMessage(2)).await.is_ok());
 
-        if let Some(actor_ref) = system.get_actor::<TestActor>(original.path()).await {
-            let msg = TestMessage(10);
-            let result = actor_ref.ask(msg).await.unwrap();
-            assert_eq!(result, 1);
-        } else {
-            panic!("It should have retrieved the actor!")
-        }
+        // Joining another group leaves the previous one
+        let stopping = system.supervision_group("stopping", GroupStrategy::StopAll);
+        stopping.join(&first);
+        assert_eq!(
+            group.members(),
+            vec![second.path().clone(), panicking.path().clone()]
+        );
+        let panicking = system
+            .create_actor("panicking", PanickingActor)
+            .await
+            .unwrap();
+        stopping.join(&panicking);
+        assert!(panicking.ask(TestMessage(3)).await.is_err());
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.group, "stopping");
+        assert_eq!(event.members, vec![first.path().clone()]);
+        assert!(system.get_actor::<TestActor>(first.path()).await.is_none());
+        assert!(system.get_actor::<TestActor>(second.path()).await.is_some());
+    }
 
-        if let Some(actor_ref) = system.get_actor::<OtherActor>(original.path()).await {
-            let msg = OtherMessage("Hello world!".to_string());
-            let result = actor_ref.ask(msg).await.unwrap();
-            println!("Result is: {}", result);
-            panic!("It should not go here!");
+    #[tokio::test]
+    async fn replay_dead_letters() {
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let dead_letters = system
+            .start_dead_letters(crate::DeadLetterConfig::new(10))
+            .await
+            .unwrap();
+        let first = system
+            .create_actor("first", TestActor::default())
+            .await
+            .unwrap();
+        let second = system
+            .create_actor("second", TestActor::default())
+            .await
+            .unwrap();
+        system.stop_subtree(first.path()).await;
+        system.stop_subtree(second.path()).await;
+        for index in 0..3 {
+            assert!(first.tell(TestMessage(index)).is_err());
+        }
+        assert!(second.tell(TestMessage(3)).is_err());
+        while dead_letters.ask(crate::GetDeadLetters).await.unwrap().len() < 4 {
+            tokio::task::yield_now().await;
         }
 
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        // Only the letters of the running recipient matching the filter
+        let first = system
+            .create_actor("first", TestActor::default())
+            .await
+            .unwrap();
+        let replay =
+            crate::ReplayDeadLetters::<TestActor, TestMessage>::new().with_filter(|letter| {
+                letter
+                    .payload::<TestMessage>()
+                    .is_some_and(|message| message.0 > 0)
+            });
+        assert_eq!(dead_letters.ask(replay).await.unwrap(), 2);
+        assert_eq!(first.ask(TestMessage(4)).await.unwrap(), 3);
+
+        let left: Vec<_> = dead_letters
+            .ask(crate::GetDeadLetters)
+            .await
+            .unwrap()
+            .iter()
+            .map(|letter| letter.payload::<TestMessage>().unwrap().0)
+            .collect();
+        assert_eq!(left, vec![0, 3]);
     }
 
     #[tokio::test]
-    async fn actor_parent_child() {
-        if std::env::var("RUST_LOG").is_err() {
-            std::env::set_var("RUST_LOG", "trace");
-        }
-        let _ = env_logger::builder().is_test(true).try_init();
+    async fn ask_hedged_backups() {
+        use crate::ask_hedged;
 
-        let actor = OtherActor {
-            message: "Initial".to_string(),
-            child: None,
-        };
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let stuck = system
+            .create_actor("stuck", TestActor::default())
+            .await
+            .unwrap();
+        let backup = system
+            .create_actor("backup", TestActor::default())
+            .await
+            .unwrap();
+        let stopped = system
+            .create_actor("stopped", TestActor::default())
+            .await
+            .unwrap();
+        system.stop_subtree(stopped.path()).await;
 
-        let bus = EventBus::<TestEvent>::new(1000);
-        let system = ActorSystem::new("test", bus);
+        // The backup answers once the suspended primary did not in time
+        stuck.suspend().await.unwrap();
+        let hedge_after = Duration::from_millis(10);
+        let replicas = vec![stuck.clone(), backup.clone()];
+        assert_eq!(
+            ask_hedged(&replicas, TestMessage(1), hedge_after)
+                .await
+                .unwrap(),
+            1
+        );
 
-        {
-            let actor_ref = system.create_actor("test-actor", actor).await.unwrap();
-            let msg = OtherMessage("new message!".to_string());
-            let result = actor_ref.ask(msg).await.unwrap();
-            assert_eq!(result, "new message!".to_string());
+        // A failed ask moves on to the next actor right away
+        let replicas = vec![stopped.clone(), backup.clone()];
+        let hedge_after = Duration::from_secs(60);
+        let response = tokio::time::timeout(
+            Duration::from_secs(5),
+            ask_hedged(&replicas, TestMessage(2), hedge_after),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.unwrap(), 2);
 
-            tokio::time::sleep(tokio::time::Duration::from_millis(2000)).await;
-            system.stop_actor(actor_ref.path()).await;
-        }
+        let replicas = vec![stopped.clone()];
+        assert!(ask_hedged(&replicas, TestMessage(3), hedge_after)
+            .await
+            .is_err());
+        let none: Vec<ActorRef<TestEvent, TestActor>> = Vec::new();
+        assert!(ask_hedged(&none, TestMessage(4), hedge_after)
+            .await
+            .is_err());
+    }
 
-        tokio::time::sleep(tokio::time::Duration::from_millis(2000)).await;
-        let actors = system.actors.read().await;
-        for actor in actors.keys() {
-            println!("Still active!: {:?}", actor);
-        }
-        assert_eq!(actors.len(), 0);
+    #[tokio::test]
+    async fn seeded_id_generator() {
+        let ids = |seed| async move {
+            let config = SystemConfig::new("test").with_id_generator(SeededIds::new(seed));
+            let system = ActorSystem::<TestEvent>::from_config(config);
+            system
+                .create_anonymous_actor(TestActor::default())
+                .await
+                .unwrap()
+        };
+        let actor = ids(42).await;
+        assert!(actor.path().key().starts_with(ANONYMOUS_PREFIX));
+        let same_seed = ids(42).await;
+        assert_eq!(same_seed.path(), actor.path());
+        // References to actors of different systems never compare equal
+        assert_ne!(same_seed.incarnation(), actor.incarnation());
+        assert_ne!(same_seed, actor);
+        assert_ne!(ids(43).await.path(), actor.path());
+
+        // Without a generator the ids are counters
+        let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+        let first = system
+            .create_anonymous_actor(TestActor::default())
+            .await
+            .unwrap();
+        let second = system
+            .create_anonymous_actor(TestActor::default())
+            .await
+            .unwrap();
+        assert_eq!(first.path().key(), format!("{}0", ANONYMOUS_PREFIX));
+        assert_eq!(second.path().key(), format!("{}1", ANONYMOUS_PREFIX));
+        assert!(second.incarnation() > first.incarnation());
     }
 }
@@ -0,0 +1,361 @@
+//! A harness for behavior tests of actors. Requires the `testkit` feature.
+//!
+//! A [`Scenario`] drives the actor under test itself: it feeds the actor an
+//! ordered script of messages, advances the virtual time in between, and
+//! gives access to the state of the actor and the events it published.
+//! Messages the actor sends to other actors are captured by [`Probe`]s. For
+//! example:
+//! ```
+//! # use tiny_tokio_actor::{*, testkit::*};
+//! # use std::time::Duration;
+//! #[derive(Clone, Debug, PartialEq)]
+//! struct Alarm(u64);
+//!
+//! impl SystemEvent for Alarm {}
+//!
+//! impl Message for Alarm {
+//!     type Response = ();
+//! }
+//!
+//! impl ProbeMessage for Alarm {}
+//!
+//! #[derive(Clone, Debug)]
+//! struct Reading(u64);
+//!
+//! impl Message for Reading {
+//!     type Response = ();
+//! }
+//!
+//! struct Thermostat {
+//!     pager: ActorRef<Alarm, Probe<Alarm>>,
+//!     readings: usize,
+//! }
+//!
+//! impl Actor<Alarm> for Thermostat {}
+//!
+//! #[async_trait]
+//! impl Handler<Alarm, Reading> for Thermostat {
+//!     async fn handle(&mut self, msg: Reading, ctx: &mut ActorContext<Alarm>) {
+//!         self.readings += 1;
+//!         if msg.0 > 30 {
+//!             // Debounce, the reading might be a glitch
+//!             tokio::time::sleep(Duration::from_secs(60)).await;
+//!             ctx.system.publish(Alarm(msg.0));
+//!             self.pager.tell(Alarm(msg.0)).unwrap();
+//!         }
+//!     }
+//! }
+//!
+//! #[tokio::main(flavor = "current_thread", start_paused = true)]
+//! async fn main() -> Result<(), ActorError> {
+//!     let system = ActorSystem::new("test", EventBus::<Alarm>::new(100));
+//!     let pager = Probe::new();
+//!     let thermostat = Thermostat {
+//!         pager: system.create_actor("pager", pager.clone()).await?,
+//!         readings: 0,
+//!     };
+//!
+//!     let mut scenario = Scenario::start(&system, "thermostat", thermostat).await?;
+//!     scenario.send_all(vec![Reading(21), Reading(35)]).await;
+//!     scenario.advance(Duration::from_secs(60)).await;
+//!
+//!     assert_eq!(scenario.state().readings, 2);
+//!     assert_eq!(scenario.events(), vec![Alarm(35)]);
+//!     assert_eq!(pager.expect(1).await, vec![Alarm(35)]);
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Virtual time requires a paused clock, e.g. through
+//! `#[tokio::test(start_paused = true)]`. A paused clock also jumps ahead
+//! whenever the runtime has nothing else to do, so a handler sleeping like
+//! the one above does not slow down the test.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    ids::IdKind, system::validate_name, Actor, ActorContext, ActorError, ActorPath, ActorSystem,
+    EventReceiver, Handler, Message, SystemEvent,
+};
+
+/// Drives an actor through a script of messages, see the [module
+/// documentation](self).
+///
+/// The actor is not registered on the actor system, as it has no mailbox:
+/// every message is handled right away by the [`Scenario::send()`] that
+/// feeds it. It can still create children, publish events and send messages
+/// to other actors through its context.
+pub struct Scenario<E: SystemEvent, A: Actor<E>> {
+    actor: A,
+    ctx: ActorContext<E>,
+    events: EventReceiver<E>,
+}
+
+impl<E: SystemEvent, A: Actor<E>> Scenario<E, A> {
+    /// Starts the actor under the '/user' path of the actor system, running
+    /// its `pre_start`.
+    pub async fn start(
+        system: &ActorSystem<E>,
+        name: &str,
+        mut actor: A,
+    ) -> Result<Self, ActorError> {
+        validate_name(name)?;
+        let path = ActorPath::from("/user") / name;
+        let events = system.events();
+        let mut ctx = ActorContext::new(
+            path,
+            system.next_id(IdKind::Incarnation),
+            CancellationToken::new(),
+            system.clone(),
+        );
+        actor.pre_start(&mut ctx).await?;
+        Ok(Scenario { actor, ctx, events })
+    }
+
+    /// Handles the message, returning the response of the actor.
+    pub async fn send<M>(&mut self, msg: M) -> M::Response
+    where
+        A: Handler<E, M>,
+        M: Message,
+    {
+        self.actor.handle(msg, &mut self.ctx).await
+    }
+
+    /// Handles the messages in order, returning the responses of the actor.
+    pub async fn send_all<M, I>(&mut self, msgs: I) -> Vec<M::Response>
+    where
+        A: Handler<E, M>,
+        M: Message,
+        I: IntoIterator<Item = M>,
+    {
+        let mut responses = Vec::new();
+        for msg in msgs {
+            responses.push(self.send(msg).await);
+        }
+        responses
+    }
+
+    /// Advances the paused clock, firing the timers that become due in
+    /// order, so work that repeats on a timer runs as often as it would in
+    /// real time. Without a paused clock this waits for real.
+    pub async fn advance(&mut self, duration: Duration) {
+        // A paused clock jumps from timer to timer while the runtime is idle
+        tokio::time::sleep(duration).await;
+        // Let the tasks woken up by the last timers run their course
+        for _ in 0..16 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    /// The actor under test
+    pub fn state(&self) -> &A {
+        &self.actor
+    }
+
+    /// The actor under test, e.g. to set up its state
+    pub fn state_mut(&mut self) -> &mut A {
+        &mut self.actor
+    }
+
+    /// The context the actor handles its messages with
+    pub fn context(&mut self) -> &mut ActorContext<E> {
+        &mut self.ctx
+    }
+
+    /// The events published on the actor system since the last call, by any
+    /// actor. Events the bus overwrote before they were taken are skipped.
+    pub fn events(&mut self) -> Vec<E> {
+        let mut events = Vec::new();
+        loop {
+            match self.events.try_recv() {
+                Ok(event) => events.push(event),
+                Err(tokio::sync::broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(_) => return events,
+            }
+        }
+    }
+
+    /// Stops the actor, running its `post_stop`, and returns it.
+    pub async fn stop(mut self) -> A {
+        self.ctx.cancellation_token().cancel();
+        self.actor.post_stop(&mut self.ctx).await;
+        self.actor
+    }
+}
+
+/// A message without a response that a [`Probe`] can capture.
+pub trait ProbeMessage: Message<Response = ()> {}
+
+/// An actor capturing the messages of type `M` it receives, standing in for
+/// the actors the actor under test sends messages to. Clones share the
+/// captured messages, so keep a clone to inspect them once the probe was
+/// created on the actor system.
+pub struct Probe<M> {
+    received: Arc<Mutex<Vec<M>>>,
+    arrived: Arc<Notify>,
+}
+
+impl<M> Clone for Probe<M> {
+    fn clone(&self) -> Self {
+        Probe {
+            received: self.received.clone(),
+            arrived: self.arrived.clone(),
+        }
+    }
+}
+
+impl<M> Default for Probe<M> {
+    fn default() -> Self {
+        Probe {
+            received: Default::default(),
+            arrived: Default::default(),
+        }
+    }
+}
+
+impl<M: Clone> Probe<M> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The messages received so far, in the order they arrived
+    pub fn received(&self) -> Vec<M> {
+        self.received.lock().unwrap().clone()
+    }
+
+    /// Waits until at least `count` messages arrived, returning all of them.
+    /// Use a timeout to fail a test that would otherwise wait forever, or
+    /// rely on the paused clock of the test to jump ahead.
+    pub async fn expect(&self, count: usize) -> Vec<M> {
+        loop {
+            let arrived = self.arrived.notified();
+            {
+                let received = self.received.lock().unwrap();
+                if received.len() >= count {
+                    return received.clone();
+                }
+            }
+            arrived.await;
+        }
+    }
+
+    /// Forgets the messages received so far.
+    pub fn clear(&self) {
+        self.received.lock().unwrap().clear();
+    }
+}
+
+impl<E: SystemEvent, M: Send + 'static> Actor<E> for Probe<M> {}
+
+#[async_trait]
+impl<E, M> Handler<E, M> for Probe<M>
+where
+    E: SystemEvent,
+    M: ProbeMessage,
+{
+    async fn handle(&mut self, msg: M, _ctx: &mut ActorContext<E>) {
+        self.received.lock().unwrap().push(msg);
+        self.arrived.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::EventBus;
+
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Tick(u64);
+
+    impl SystemEvent for Tick {}
+
+    impl Message for Tick {
+        type Response = ();
+    }
+
+    impl ProbeMessage for Tick {}
+
+    #[derive(Clone, Debug)]
+    struct Start;
+
+    impl Message for Start {
+        type Response = usize;
+    }
+
+    /// Sends a tick to its target every second, once started.
+    struct Ticker {
+        target: crate::ActorRef<Tick, Probe<Tick>>,
+        started: usize,
+        stopped: bool,
+    }
+
+    #[async_trait]
+    impl Actor<Tick> for Ticker {
+        async fn post_stop(&mut self, _ctx: &mut ActorContext<Tick>) {
+            self.stopped = true;
+        }
+    }
+
+    #[async_trait]
+    impl Handler<Tick, Start> for Ticker {
+        async fn handle(&mut self, _msg: Start, ctx: &mut ActorContext<Tick>) -> usize {
+            self.started += 1;
+            let target = self.target.clone();
+            let stopped = ctx.cancellation_token().clone();
+            tokio::spawn(async move {
+                let mut count = 0;
+                while !stopped.is_cancelled() {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    count += 1;
+                    target.tell(Tick(count)).unwrap_or_default();
+                }
+            });
+            ctx.system.publish(Tick(0));
+            self.started
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn scenario() {
+        let system = ActorSystem::new("test", EventBus::<Tick>::new(100));
+        let probe = Probe::new();
+        let target = system.create_actor("probe", probe.clone()).await.unwrap();
+        let ticker = |target| Ticker {
+            target,
+            started: 0,
+            stopped: false,
+        };
+        assert!(
+            Scenario::start(&system, "no/ticker", ticker(target.clone()))
+                .await
+                .is_err()
+        );
+
+        let mut scenario = Scenario::start(&system, "ticker", ticker(target))
+            .await
+            .unwrap();
+        assert_eq!(scenario.context().path, ActorPath::from("/user/ticker"));
+        assert_eq!(scenario.send(Start).await, 1);
+        assert_eq!(scenario.events(), vec![Tick(0)]);
+        assert!(scenario.events().is_empty());
+        assert!(probe.received().is_empty());
+
+        scenario.advance(Duration::from_millis(2500)).await;
+        assert_eq!(probe.received(), vec![Tick(1), Tick(2)]);
+        probe.clear();
+        assert_eq!(probe.expect(1).await, vec![Tick(3)]);
+
+        let ticker = scenario.stop().await;
+        assert_eq!(ticker.started, 1);
+        assert!(ticker.stopped);
+    }
+}
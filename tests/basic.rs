@@ -2,6 +2,7 @@ use tiny_tokio_actor::*;
 
 // The event message you may want to publish to the
 // system event bus.
+#[allow(dead_code)]
 #[derive(Clone, Debug)]
 struct TestEvent(String);
 
@@ -18,6 +19,7 @@ impl Actor<TestEvent> for TestActor {}
 
 // The message the actor will expect. It must
 // derive Clone. Debug is not required.
+#[allow(dead_code)]
 #[derive(Clone, Debug)]
 struct TestMessage(String);
 
@@ -0,0 +1,124 @@
+use std::{
+    sync::{Mutex, Once},
+    time::Duration,
+};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use tiny_tokio_actor::*;
+
+// Collects the lines logged by the actors. This test runs in its own process,
+// so it can install the logger without racing the other tests.
+struct Lines(Mutex<Vec<(Level, String)>>);
+
+impl Log for Lines {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        self.0
+            .lock()
+            .unwrap()
+            .push((record.level(), record.args().to_string()));
+    }
+
+    fn flush(&self) {}
+}
+
+static LINES: Lines = Lines(Mutex::new(Vec::new()));
+
+fn lines(filter: &str) -> Vec<(Level, String)> {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        log::set_logger(&LINES).unwrap();
+        log::set_max_level(LevelFilter::Trace);
+    });
+    LINES
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, line)| line.contains(filter))
+        .cloned()
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+struct TestEvent;
+
+impl SystemEvent for TestEvent {}
+
+#[derive(Clone, Debug)]
+struct Report(Level);
+
+impl Message for Report {
+    type Response = ();
+}
+
+struct Reporter;
+
+impl Actor<TestEvent> for Reporter {}
+
+#[async_trait]
+impl Handler<TestEvent, Report> for Reporter {
+    async fn handle(&mut self, msg: Report, ctx: &mut ActorContext<TestEvent>) {
+        let line = format!("{} report of {}", msg.0, ctx.path);
+        ctx.log_throttled(&format!("{}", msg.0), msg.0, line);
+    }
+}
+
+#[tokio::test]
+async fn log_throttled_respects_actor_log_level() {
+    lines("");
+
+    let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+    let quiet = system
+        .create_actor_with_config(
+            "quiet",
+            Reporter,
+            ActorConfig::default().with_log_level(LevelFilter::Warn),
+        )
+        .await
+        .unwrap();
+    let chatty = system.create_actor("chatty", Reporter).await.unwrap();
+
+    for actor in [&quiet, &chatty] {
+        actor.ask(Report(Level::Info)).await.unwrap();
+        actor.ask(Report(Level::Warn)).await.unwrap();
+    }
+
+    assert_eq!(
+        lines(" report of "),
+        [
+            (Level::Warn, "WARN report of /user/quiet".to_string()),
+            (Level::Info, "INFO report of /user/chatty".to_string()),
+            (Level::Warn, "WARN report of /user/chatty".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn failed_asks_are_logged() {
+    lines("");
+
+    let system = ActorSystem::new("test", EventBus::<TestEvent>::new(1000));
+    let slow = system
+        .create_actor_with_config(
+            "slow",
+            Reporter,
+            ActorConfig::default().with_ask_timeout(Duration::from_millis(10)),
+        )
+        .await
+        .unwrap();
+    slow.suspend().await.unwrap();
+
+    let timeout = slow.ask(Report(Level::Info)).await;
+    assert!(matches!(timeout, Err(ActorError::Timeout)));
+
+    let logged = lines("to actor '/user/slow' failed after");
+    assert_eq!(logged.len(), 1);
+    let (level, line) = &logged[0];
+    assert_eq!(*level, Level::Warn);
+    assert!(line.contains("::Report"));
+    assert!(line.ends_with(": Ask timed out"));
+}
@@ -1,6 +1,7 @@
 use tiny_tokio_actor::*;
 
 // Define the system event bus message
+#[allow(dead_code)]
 #[derive(Clone, Debug)]
 struct TestEvent(String);
 
@@ -26,6 +27,7 @@ impl Actor<TestEvent> for TestActor {
 }
 
 // Define a message the actor will handle
+#[allow(dead_code)]
 #[derive(Clone, Debug)]
 struct TestMessage(String);
 
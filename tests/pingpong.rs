@@ -1,5 +1,6 @@
 use tiny_tokio_actor::*;
 
+#[allow(dead_code)]
 #[derive(Clone, Debug)]
 struct EventMessage(String);
 